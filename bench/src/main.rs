@@ -1,13 +1,57 @@
+mod report;
+mod topology;
+
+use clap::Parser;
 use env_logger::Builder;
 use futures::future::join_all;
-use std::str::FromStr;
+use rand::{rngs::StdRng, SeedableRng};
+use report::Report;
+use std::{path::PathBuf, str::FromStr};
+use topology::{ScoreDistribution, Topology};
 
 use eigen_trust::{Keypair, LevelFilter, Multiaddr, Node};
 use eigen_trust_circuit::utils::read_params;
-use rand::Rng;
 
-const INTERVAL: u64 = 60 * 6;
-const NUM_CONNECTIONS: usize = 4;
+/// Run a local network of nodes, and measure it.
+#[derive(Parser, Debug)]
+struct Args {
+	/// The number of nodes.
+	#[clap(long, default_value_t = 5)]
+	peers: usize,
+	/// How the nodes are connected.
+	#[clap(long, arg_enum, default_value = "full-mesh")]
+	topology: Topology,
+	/// The number of neighbors of each node, in the random regular topology.
+	#[clap(long, default_value_t = 2)]
+	degree: usize,
+	/// The epoch interval, in seconds.
+	#[clap(long, default_value_t = 60 * 6)]
+	interval: u64,
+	/// The number of epochs to run for.
+	#[clap(long, default_value_t = 10)]
+	epochs: u32,
+	/// How the local scores towards the neighbors are drawn.
+	#[clap(long, arg_enum, default_value = "uniform")]
+	scores: ScoreDistribution,
+	/// The maximum local score.
+	#[clap(long, default_value_t = 100)]
+	max_score: u32,
+	/// The port of the first node. The rest of the nodes use the next ports.
+	#[clap(long, default_value_t = 58400)]
+	start_port: u16,
+	/// Path to the KZG params.
+	#[clap(long, default_value = "./data/params-18.bin")]
+	params: String,
+	/// The seed of the topology and the scores, so the runs can be repeated.
+	#[clap(long, default_value_t = 0)]
+	seed: u64,
+	/// Write the report of the run, per node and per epoch, as JSON.
+	#[clap(long)]
+	report: Option<PathBuf>,
+	/// Write the report of the run, per node and per epoch, as CSV.
+	#[clap(long)]
+	csv: Option<PathBuf>,
+}
 
 pub fn init_logger() {
 	let mut builder = Builder::from_default_env();
@@ -20,58 +64,89 @@ pub fn init_logger() {
 
 #[tokio::main]
 async fn main() {
+	let args = Args::parse();
 	init_logger();
 
+	let rng = &mut StdRng::seed_from_u64(args.seed);
+	let topology = args
+		.topology
+		.neighbors(args.peers, args.degree, rng)
+		.expect("The topology can't be built with these peers and degree");
+
 	let mut local_keys = Vec::new();
 	let mut local_addresses = Vec::new();
-	let mut bootstrap_nodes = Vec::new();
-	let starting_port = 58400;
-
-	for i in 0..(NUM_CONNECTIONS + 1) {
-		let local_key = Keypair::generate_secp256k1();
-		let peer_id = local_key.public().to_peer_id();
-		let addr = format!("/ip4/127.0.0.1/tcp/{}", starting_port + i);
-		let local_address = Multiaddr::from_str(&addr).unwrap();
-
-		local_keys.push(local_key);
-		local_addresses.push(local_address.clone());
-		bootstrap_nodes.push((peer_id, local_address));
+	for i in 0..args.peers {
+		let port = usize::from(args.start_port) + i;
+		let addr = format!("/ip4/127.0.0.1/tcp/{}", port);
+		local_keys.push(Keypair::generate_secp256k1());
+		local_addresses.push(Multiaddr::from_str(&addr).unwrap());
 	}
 
-	let params = read_params("./data/params-18.bin");
+	let params = read_params(&args.params);
 
 	let mut tasks = Vec::new();
-	for i in 0..(NUM_CONNECTIONS + 1) {
+	for (i, neighbors) in topology.into_iter().enumerate() {
 		let local_key = local_keys[i].clone();
 		let local_address = local_addresses[i].clone();
-		let bootstrap_nodes = bootstrap_nodes.clone();
+		// The nodes dial their neighbors as the bootstrap nodes.
+		let bootstrap_nodes: Vec<_> = neighbors
+			.iter()
+			.map(|&j| {
+				(
+					local_keys[j].public().to_peer_id(),
+					local_addresses[j].clone(),
+				)
+			})
+			.collect();
+		let scores: Vec<u32> = neighbors
+			.iter()
+			.map(|_| args.scores.sample(args.max_score, rng))
+			.collect();
 		let params = params.clone();
+		let interval = args.interval;
+		let epochs = args.epochs;
 
 		let join_handle = tokio::spawn(async move {
 			let mut node = Node::new(
 				local_key,
 				local_address,
 				bootstrap_nodes.clone(),
-				INTERVAL,
+				interval,
 				params,
 			)
 			.unwrap();
 
 			let peer = node.get_peer_mut();
-			for (peer_id, ..) in bootstrap_nodes {
-				let random_score: u32 = rand::thread_rng().gen_range(0..100);
-				peer.set_score(peer_id, random_score);
+			for ((peer_id, ..), score) in bootstrap_nodes.into_iter().zip(scores) {
+				peer.set_score(peer_id, score);
 			}
 
-			node.main_loop(Some(10)).await.unwrap();
+			let mut events = node.subscribe();
+			node.main_loop(Some(epochs)).await.unwrap();
+
+			// The node still holds the sender, so this stops once the events run out.
+			let mut collected = Vec::new();
+			while let Ok(Some(event)) = events.try_next() {
+				collected.push(event);
+			}
+			collected
 		});
 		tasks.push(join_handle);
 	}
 
-	let _ = join_all(tasks)
-		.await
-		.iter()
-		.map(|r| r.as_ref().unwrap())
-		.collect::<Vec<_>>();
-	println!("Done");
+	let mut report = Report::default();
+	for (i, result) in join_all(tasks).await.into_iter().enumerate() {
+		let peer_id = local_keys[i].public().to_peer_id();
+		report.add_node(i, peer_id, result.unwrap());
+	}
+
+	report.print_summary();
+	if let Some(path) = &args.report {
+		report.write_json(path).unwrap();
+		println!("Report written to {:?}", path);
+	}
+	if let Some(path) = &args.csv {
+		report.write_csv(path).unwrap();
+		println!("CSV report written to {:?}", path);
+	}
 }
@@ -0,0 +1,305 @@
+//! The report of a run, aggregated from the events of the nodes, per node and
+//! per epoch.
+
+use serde::Serialize;
+use std::{
+	cmp::Ordering,
+	collections::BTreeMap,
+	fs,
+	io::{self, Write},
+	path::Path,
+};
+
+use eigen_trust::{NodeEvent, PeerId};
+
+/// What happened at one node during one epoch.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct EpochRecord {
+	/// The index of the node.
+	pub node: usize,
+	/// The PeerId of the node.
+	pub peer_id: String,
+	/// The epoch.
+	pub epoch: u64,
+	/// The global trust score of the node at the start of the epoch.
+	pub score: Option<f64>,
+	/// The proving times of the opinions sent to the neighbors.
+	pub proof_ms: Vec<u64>,
+	/// The verification times of the opinions received from the neighbors.
+	pub verification_ms: Vec<u64>,
+	/// The number of received opinions that failed the verification.
+	pub invalid: usize,
+	/// The times between the requests for the opinions and the responses.
+	pub latency_ms: Vec<u64>,
+	/// The number of failed requests.
+	pub failures: usize,
+}
+
+/// The records of all the nodes.
+#[derive(Serialize, Debug, Default)]
+pub struct Report {
+	/// The records, ordered by the node, and then the epoch.
+	pub records: Vec<EpochRecord>,
+}
+
+impl Report {
+	/// Add the events of a node. The failed requests count towards the epoch
+	/// that was running when they failed.
+	pub fn add_node(&mut self, node: usize, peer_id: PeerId, events: Vec<NodeEvent>) {
+		let base = EpochRecord {
+			node,
+			peer_id: peer_id.to_base58(),
+			..EpochRecord::default()
+		};
+		let mut records: BTreeMap<u64, EpochRecord> = BTreeMap::new();
+
+		let mut current_epoch = 0;
+		for event in events {
+			match event {
+				NodeEvent::EpochStarted { epoch, score } => {
+					current_epoch = epoch.0;
+					record(&mut records, &base, epoch.0).score = Some(score);
+				},
+				NodeEvent::OpinionProven {
+					epoch, duration_ms, ..
+				} => record(&mut records, &base, epoch.0)
+					.proof_ms
+					.push(duration_ms),
+				NodeEvent::OpinionVerified {
+					epoch,
+					valid,
+					duration_ms,
+					..
+				} => {
+					let record = record(&mut records, &base, epoch.0);
+					record.verification_ms.push(duration_ms);
+					if !valid {
+						record.invalid += 1;
+					}
+				},
+				NodeEvent::OpinionReceived {
+					epoch, latency_ms, ..
+				} => record(&mut records, &base, epoch.0)
+					.latency_ms
+					.push(latency_ms),
+				NodeEvent::RequestFailed { .. } => {
+					record(&mut records, &base, current_epoch).failures += 1
+				},
+				_ => {},
+			}
+		}
+
+		self.records.extend(records.into_values());
+	}
+
+	/// Write the report as JSON.
+	pub fn write_json(&self, path: &Path) -> io::Result<()> {
+		let json = serde_json::to_string_pretty(self)?;
+		fs::write(path, json)
+	}
+
+	/// Write the report as CSV, one row per node and epoch, with the means of
+	/// the times.
+	pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+		let mut file = fs::File::create(path)?;
+		writeln!(
+			file,
+			"node,peer_id,epoch,score,proofs,proof_ms_mean,verifications,invalid,\
+			 verification_ms_mean,responses,latency_ms_mean,failures"
+		)?;
+		for r in &self.records {
+			writeln!(
+				file,
+				"{},{},{},{},{},{},{},{},{},{},{},{}",
+				r.node,
+				r.peer_id,
+				r.epoch,
+				r.score.map(|s| s.to_string()).unwrap_or_default(),
+				r.proof_ms.len(),
+				format_mean(&r.proof_ms),
+				r.verification_ms.len(),
+				r.invalid,
+				format_mean(&r.verification_ms),
+				r.latency_ms.len(),
+				format_mean(&r.latency_ms),
+				r.failures,
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Print the summary statistics of the whole run.
+	pub fn print_summary(&self) {
+		let collect = |f: fn(&EpochRecord) -> &Vec<u64>| -> Vec<f64> {
+			self.records
+				.iter()
+				.flat_map(|r| f(r).iter().map(|&v| v as f64))
+				.collect()
+		};
+		let scores: Vec<f64> = self.records.iter().filter_map(|r| r.score).collect();
+		let invalid: usize = self.records.iter().map(|r| r.invalid).sum();
+		let failures: usize = self.records.iter().map(|r| r.failures).sum();
+
+		println!(
+			"{:<20} {:>6} {:>10} {:>10} {:>10} {:>10} {:>10}",
+			"", "count", "min", "mean", "p50", "p95", "max"
+		);
+		print_row("score", Summary::of(scores));
+		print_row("proof (ms)", Summary::of(collect(|r| &r.proof_ms)));
+		print_row(
+			"verification (ms)",
+			Summary::of(collect(|r| &r.verification_ms)),
+		);
+		print_row("latency (ms)", Summary::of(collect(|r| &r.latency_ms)));
+		println!("Invalid opinions: {}", invalid);
+		println!("Failed requests: {}", failures);
+	}
+}
+
+/// The record of the epoch, added if there is none yet.
+fn record<'a>(
+	records: &'a mut BTreeMap<u64, EpochRecord>,
+	base: &EpochRecord,
+	epoch: u64,
+) -> &'a mut EpochRecord {
+	records.entry(epoch).or_insert_with(|| EpochRecord {
+		epoch,
+		..base.clone()
+	})
+}
+
+/// The summary statistics of a set of values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+	/// The number of values.
+	pub count: usize,
+	pub min: f64,
+	pub mean: f64,
+	/// The median.
+	pub p50: f64,
+	/// The 95th percentile.
+	pub p95: f64,
+	pub max: f64,
+}
+
+impl Summary {
+	/// Summarize the values. Returns `None` if there are none.
+	pub fn of(mut values: Vec<f64>) -> Option<Self> {
+		if values.is_empty() {
+			return None;
+		}
+		values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+		let count = values.len();
+		Some(Self {
+			count,
+			min: values[0],
+			mean: values.iter().sum::<f64>() / count as f64,
+			p50: percentile(&values, 50),
+			p95: percentile(&values, 95),
+			max: values[count - 1],
+		})
+	}
+}
+
+/// The nearest-rank percentile of the sorted values.
+fn percentile(sorted: &[f64], p: usize) -> f64 {
+	let rank = (p * sorted.len() + 99) / 100;
+	sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_mean(values: &[u64]) -> String {
+	if values.is_empty() {
+		return String::new();
+	}
+	let mean = values.iter().sum::<u64>() as f64 / values.len() as f64;
+	format!("{:.1}", mean)
+}
+
+fn print_row(name: &str, summary: Option<Summary>) {
+	match summary {
+		Some(s) => println!(
+			"{:<20} {:>6} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+			name, s.count, s.min, s.mean, s.p50, s.p95, s.max
+		),
+		None => println!("{:<20} {:>6}", name, 0),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use eigen_trust::{Epoch, Keypair};
+
+	#[test]
+	fn should_summarize() {
+		assert_eq!(Summary::of(Vec::new()), None);
+
+		let values = (1..=20).rev().map(f64::from).collect();
+		let summary = Summary::of(values).unwrap();
+		assert_eq!(summary.count, 20);
+		assert_eq!(summary.min, 1.);
+		assert_eq!(summary.mean, 10.5);
+		assert_eq!(summary.p50, 10.);
+		assert_eq!(summary.p95, 19.);
+		assert_eq!(summary.max, 20.);
+
+		let summary = Summary::of(vec![7.]).unwrap();
+		assert_eq!((summary.p50, summary.p95), (7., 7.));
+	}
+
+	#[test]
+	fn should_aggregate_events() {
+		let peer_id = Keypair::generate_secp256k1().public().to_peer_id();
+		let neighbor = Keypair::generate_secp256k1().public().to_peer_id();
+		let events = vec![
+			NodeEvent::RequestFailed { peer_id: neighbor },
+			NodeEvent::EpochStarted {
+				epoch: Epoch(1),
+				score: 0.5,
+			},
+			NodeEvent::OpinionProven {
+				peer_id: neighbor,
+				epoch: Epoch(1),
+				duration_ms: 100,
+			},
+			NodeEvent::OpinionReceived {
+				peer_id: neighbor,
+				epoch: Epoch(1),
+				latency_ms: 120,
+			},
+			NodeEvent::OpinionVerified {
+				peer_id: neighbor,
+				epoch: Epoch(1),
+				valid: false,
+				duration_ms: 10,
+			},
+			NodeEvent::RequestFailed { peer_id: neighbor },
+		];
+
+		let mut report = Report::default();
+		report.add_node(3, peer_id, events);
+
+		let base = EpochRecord {
+			node: 3,
+			peer_id: peer_id.to_base58(),
+			..EpochRecord::default()
+		};
+		assert_eq!(report.records, vec![
+			EpochRecord {
+				epoch: 0,
+				failures: 1,
+				..base.clone()
+			},
+			EpochRecord {
+				epoch: 1,
+				score: Some(0.5),
+				proof_ms: vec![100],
+				verification_ms: vec![10],
+				invalid: 1,
+				latency_ms: vec![120],
+				failures: 1,
+				..base
+			},
+		]);
+	}
+}
@@ -0,0 +1,172 @@
+//! The topologies of the benchmarked network, and the distributions of the
+//! local scores.
+
+use clap::ArgEnum;
+use rand::{seq::SliceRandom, Rng};
+use std::collections::BTreeSet;
+
+/// The number of attempts at pairing the nodes into a random regular graph,
+/// before giving up.
+const MAX_PAIRING_ATTEMPTS: usize = 1000;
+
+/// How the nodes are connected.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+	/// Every node is connected to every other node.
+	FullMesh,
+	/// Every node is connected to the previous and the next node.
+	Ring,
+	/// Every node is connected to `degree` random nodes.
+	RandomRegular,
+}
+
+impl Topology {
+	/// The neighbors of each of the `n` nodes, as indices. Returns `None` if
+	/// the topology can't be built, e.g. a random regular graph with an odd
+	/// number of stubs.
+	pub fn neighbors<R: Rng>(
+		&self,
+		n: usize,
+		degree: usize,
+		rng: &mut R,
+	) -> Option<Vec<Vec<usize>>> {
+		match self {
+			Topology::FullMesh => Some(
+				(0..n)
+					.map(|i| (0..n).filter(|&j| j != i).collect())
+					.collect(),
+			),
+			Topology::Ring => {
+				let neighbors = (0..n)
+					.map(|i| {
+						let set: BTreeSet<usize> = [(i + n - 1) % n, (i + 1) % n]
+							.into_iter()
+							.filter(|&j| j != i)
+							.collect();
+						set.into_iter().collect()
+					})
+					.collect();
+				Some(neighbors)
+			},
+			Topology::RandomRegular => random_regular(n, degree, rng),
+		}
+	}
+}
+
+/// Pair the stubs of the nodes at random, until there are no loops and no
+/// duplicate edges.
+fn random_regular<R: Rng>(n: usize, degree: usize, rng: &mut R) -> Option<Vec<Vec<usize>>> {
+	if degree >= n || (n * degree) % 2 != 0 {
+		return None;
+	}
+	let mut stubs: Vec<usize> = (0..n).flat_map(|i| vec![i; degree]).collect();
+	'attempts: for _ in 0..MAX_PAIRING_ATTEMPTS {
+		stubs.shuffle(rng);
+		let mut neighbors = vec![BTreeSet::new(); n];
+		for pair in stubs.chunks(2) {
+			let (a, b) = (pair[0], pair[1]);
+			if a == b || !neighbors[a].insert(b) {
+				continue 'attempts;
+			}
+			neighbors[b].insert(a);
+		}
+		return Some(
+			neighbors
+				.into_iter()
+				.map(|set| set.into_iter().collect())
+				.collect(),
+		);
+	}
+	None
+}
+
+/// How the local scores towards the neighbors are drawn.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreDistribution {
+	/// Every neighbor gets the maximum score.
+	Constant,
+	/// Uniform between 1 and the maximum score.
+	Uniform,
+	/// Most neighbors get low scores, and a few get high ones.
+	PowerLaw,
+}
+
+impl ScoreDistribution {
+	/// Draw a score, between 1 and `max`.
+	pub fn sample<R: Rng>(&self, max: u32, rng: &mut R) -> u32 {
+		let max = max.max(1);
+		match self {
+			ScoreDistribution::Constant => max,
+			ScoreDistribution::Uniform => rng.gen_range(1..=max),
+			ScoreDistribution::PowerLaw => {
+				let u: f64 = rng.gen_range(0.0..1.0);
+				let score = (f64::from(max) * u.powi(3)).ceil();
+				// The score is within (0, max], so the cast doesn't truncate.
+				(score as u32).clamp(1, max)
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rand::{rngs::StdRng, SeedableRng};
+
+	fn is_symmetric(neighbors: &[Vec<usize>]) -> bool {
+		neighbors
+			.iter()
+			.enumerate()
+			.all(|(i, ns)| ns.iter().all(|&j| j != i && neighbors[j].contains(&i)))
+	}
+
+	#[test]
+	fn should_build_topologies() {
+		let rng = &mut StdRng::seed_from_u64(1);
+
+		let mesh = Topology::FullMesh.neighbors(5, 0, rng).unwrap();
+		assert!(mesh.iter().all(|ns| ns.len() == 4));
+		assert!(is_symmetric(&mesh));
+
+		let ring = Topology::Ring.neighbors(5, 0, rng).unwrap();
+		assert_eq!(ring[0], vec![1, 4]);
+		assert!(ring.iter().all(|ns| ns.len() == 2));
+		assert!(is_symmetric(&ring));
+		// Two nodes are only connected once.
+		assert_eq!(Topology::Ring.neighbors(2, 0, rng).unwrap(), vec![
+			vec![1],
+			vec![0]
+		]);
+
+		let random = Topology::RandomRegular.neighbors(10, 3, rng).unwrap();
+		assert!(random.iter().all(|ns| ns.len() == 3));
+		assert!(is_symmetric(&random));
+
+		// Odd number of stubs, or a degree that is too high.
+		assert!(Topology::RandomRegular.neighbors(5, 3, rng).is_none());
+		assert!(Topology::RandomRegular.neighbors(4, 4, rng).is_none());
+	}
+
+	#[test]
+	fn should_be_reproducible() {
+		let a = Topology::RandomRegular.neighbors(20, 4, &mut StdRng::seed_from_u64(7));
+		let b = Topology::RandomRegular.neighbors(20, 4, &mut StdRng::seed_from_u64(7));
+		assert_eq!(a, b);
+	}
+
+	#[test]
+	fn should_sample_scores_in_range() {
+		let rng = &mut StdRng::seed_from_u64(1);
+		for dist in [
+			ScoreDistribution::Constant,
+			ScoreDistribution::Uniform,
+			ScoreDistribution::PowerLaw,
+		] {
+			for _ in 0..100 {
+				let score = dist.sample(100, rng);
+				assert!((1..=100).contains(&score));
+			}
+		}
+		assert_eq!(ScoreDistribution::Constant.sample(7, rng), 7);
+	}
+}
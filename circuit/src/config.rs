@@ -0,0 +1,25 @@
+//! Type-level configuration of the EigenTrust circuit. It bundles the sizes
+//! and the primitives that have to match between the provers and the
+//! verifiers of a network, so they are chosen once, with a single type.
+
+use crate::hasher::Hasher;
+use halo2wrong::halo2::arithmetic::{CurveAffine, FieldExt};
+use std::fmt::Debug;
+
+/// The configuration of a deployment of the circuit.
+pub trait CircuitConfig: Clone + Debug + PartialEq + Eq {
+	/// Maximum number of neighbors of a peer. This is also the number of the
+	/// opinions summed in the circuit.
+	const MAX_NEIGHBORS: usize;
+	/// The scale of the fixed point scores. The local scores a peer gives to
+	/// its neighbors sum to it. Must fit into
+	/// [`SCORE_BITS`](crate::SCORE_BITS) bits.
+	const SCALE: u64;
+	/// The native field of the circuit.
+	type Scalar: FieldExt;
+	/// The curve of the signatures verified in the circuit. The circuit only
+	/// verifies ECDSA, so the scheme itself is not configurable.
+	type SignatureCurve: CurveAffine;
+	/// The hash of the signed opinion messages.
+	type MessageHasher: Hasher<Self::Scalar, 5>;
+}
@@ -0,0 +1,334 @@
+//! The module for the circuit proving the whole EigenTrust convergence:
+//! `t^(k+1) = (1 - a) * C^T * t^(k) + a * p`, over a bounded number of
+//! iterations.
+//!
+//! All values are fixed-point numbers, scaled by `scale`. No division is done
+//! inside the circuit, so the scale of the scores grows with every iteration:
+//! after `k` iterations the scores are scaled by `scale^(2k + 1)`. The scale
+//! and the number of iterations must be picked so the values stay below the
+//! field modulus.
+//!
+//! The opinions are range checked to [`SCORE_BITS`] bits, so a row can't sum
+//! to the scale by wrapping around the field, and only the salted commitment
+//! to them is public, with the final scores.
+
+use crate::{
+	gadgets::{
+		commitment::{commit, CommitmentChip},
+		lt::LessThanChip,
+	},
+	hasher::Hasher,
+	SCORE_BITS,
+};
+use halo2wrong::halo2::{
+	arithmetic::FieldExt,
+	circuit::{Layouter, SimpleFloorPlanner},
+	plonk::{Circuit, ConstraintSystem, Error},
+};
+use maingate::{MainGate, MainGateConfig, MainGateInstructions, RegionCtx, UnassignedValue};
+use std::marker::PhantomData;
+
+/// The halo2 columns config for the convergence circuit.
+#[derive(Clone, Debug)]
+pub struct ConvergenceConfig {
+	main_gate_config: MainGateConfig,
+}
+
+/// The circuit proving `ITER` iterations of the EigenTrust algorithm for a
+/// network of `N` peers, with the commitment to the opinions hashed with `H`.
+#[derive(Clone)]
+pub struct ConvergenceCircuit<F: FieldExt, const N: usize, const ITER: usize, H>
+where
+	H: Hasher<F, 5>,
+{
+	/// Local trust matrix, where `ops[i][j]` is the scaled opinion of peer `i`
+	/// towards peer `j`. Every row must sum up to `scale`.
+	ops: [[Option<F>; N]; N],
+	/// The salt of the commitment to the opinions.
+	salt: Option<F>,
+	/// Scaled pre-trust scores.
+	pre_trust: [F; N],
+	/// Scaled pre-trust weight `a`.
+	pre_trust_weight: F,
+	/// The scale of the fixed-point values.
+	scale: F,
+	_hasher: PhantomData<H>,
+}
+
+impl<F: FieldExt, const N: usize, const ITER: usize, H> ConvergenceCircuit<F, N, ITER, H>
+where
+	H: Hasher<F, 5>,
+{
+	/// Create a new ConvergenceCircuit.
+	pub fn new(
+		ops: [[F; N]; N],
+		salt: F,
+		pre_trust: [F; N],
+		pre_trust_weight: F,
+		scale: F,
+	) -> Self {
+		Self {
+			ops: ops.map(|row| row.map(Some)),
+			salt: Some(salt),
+			pre_trust,
+			pre_trust_weight,
+			scale,
+			_hasher: PhantomData,
+		}
+	}
+}
+
+impl<F: FieldExt, const N: usize, const ITER: usize, H> Circuit<F>
+	for ConvergenceCircuit<F, N, ITER, H>
+where
+	H: Hasher<F, 5>,
+{
+	type Config = ConvergenceConfig;
+	type FloorPlanner = SimpleFloorPlanner;
+
+	fn without_witnesses(&self) -> Self {
+		Self {
+			ops: [[None; N]; N],
+			salt: None,
+			pre_trust: self.pre_trust,
+			pre_trust_weight: self.pre_trust_weight,
+			scale: self.scale,
+			_hasher: PhantomData,
+		}
+	}
+
+	/// Make the circuit config.
+	fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+		let main_gate_config = MainGate::<F>::configure(meta);
+		ConvergenceConfig { main_gate_config }
+	}
+
+	/// Synthesize the circuit.
+	fn synthesize(
+		&self,
+		config: Self::Config,
+		mut layouter: impl Layouter<F>,
+	) -> Result<(), Error> {
+		let main_gate = MainGate::<F>::new(config.main_gate_config.clone());
+		let lt_chip = LessThanChip::<F, SCORE_BITS>::new(config.main_gate_config.clone());
+		let commitment_chip = CommitmentChip::<F, H>::new(config.main_gate_config);
+
+		let (commitment, scores) = layouter.assign_region(
+			|| "convergence",
+			|mut region| {
+				let position = &mut 0;
+				let ctx = &mut RegionCtx::new(&mut region, position);
+
+				let scale = main_gate.assign_constant(ctx, self.scale)?;
+				let one_minus_a =
+					main_gate.assign_constant(ctx, self.scale - self.pre_trust_weight)?;
+
+				// Assign the opinions, and check that every row sums up to the scale.
+				// In range, the opinions of a row can't wrap around the field.
+				let mut ops = Vec::new();
+				for i in 0..N {
+					let mut row = Vec::new();
+					let mut sum = main_gate.assign_constant(ctx, F::zero())?;
+					for j in 0..N {
+						let unassigned_op = UnassignedValue::from(self.ops[i][j]);
+						let op = main_gate.assign_value(ctx, &unassigned_op)?;
+						lt_chip.assert_in_range(ctx, &op)?;
+						sum = main_gate.add(ctx, &sum, &op)?;
+						row.push(op);
+					}
+					main_gate.assert_equal(ctx, &sum, &scale)?;
+					ops.push(row);
+				}
+
+				// Commit to the opinions, row by row.
+				let salt = main_gate.assign_value(ctx, &UnassignedValue::from(self.salt))?;
+				let flat_ops: Vec<_> = ops.iter().flatten().cloned().collect();
+				let commitment = commitment_chip.commit(ctx, &salt, &flat_ops)?;
+
+				// Starting from the pre-trusted scores.
+				let mut scores = Vec::new();
+				for i in 0..N {
+					scores.push(main_gate.assign_constant(ctx, self.pre_trust[i])?);
+				}
+
+				// The pre-trust term has to be scaled up to the scale of the scores.
+				let mut pre_trust_scale = self.scale;
+				for _ in 0..ITER {
+					let mut next_scores = Vec::new();
+					for i in 0..N {
+						// sum = c_1i * t_1 + ... + c_ni * t_n
+						let mut sum = main_gate.assign_constant(ctx, F::zero())?;
+						for j in 0..N {
+							let term = main_gate.mul(ctx, &ops[j][i], &scores[j])?;
+							sum = main_gate.add(ctx, &sum, &term)?;
+						}
+						// t_i = (1 - a) * sum + a * p_i
+						let weighted_sum = main_gate.mul(ctx, &sum, &one_minus_a)?;
+						let pre_trust_term =
+							self.pre_trust_weight * self.pre_trust[i] * pre_trust_scale;
+						let pre_trust = main_gate.assign_constant(ctx, pre_trust_term)?;
+						next_scores.push(main_gate.add(ctx, &weighted_sum, &pre_trust)?);
+					}
+					scores = next_scores;
+					pre_trust_scale = pre_trust_scale * self.scale * self.scale;
+				}
+
+				Ok((commitment, scores))
+			},
+		)?;
+
+		// Constrain the commitment to the opinions and the final scores to public
+		// inputs.
+		main_gate.expose_public(layouter.namespace(|| "ops"), commitment, 0)?;
+		for (i, score) in scores.into_iter().enumerate() {
+			main_gate.expose_public(layouter.namespace(|| "score"), score, i + 1)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Native implementation of the same computation as the circuit, used to
+/// calculate the expected final scores.
+pub fn native_convergence<F: FieldExt, const N: usize>(
+	ops: [[F; N]; N],
+	pre_trust: [F; N],
+	pre_trust_weight: F,
+	scale: F,
+	iterations: usize,
+) -> [F; N] {
+	let one_minus_a = scale - pre_trust_weight;
+	let mut scores = pre_trust;
+	let mut pre_trust_scale = scale;
+	for _ in 0..iterations {
+		let mut next_scores = [F::zero(); N];
+		for i in 0..N {
+			let mut sum = F::zero();
+			for j in 0..N {
+				sum += ops[j][i] * scores[j];
+			}
+			next_scores[i] = sum * one_minus_a + pre_trust_weight * pre_trust[i] * pre_trust_scale;
+		}
+		scores = next_scores;
+		pre_trust_scale = pre_trust_scale * scale * scale;
+	}
+	scores
+}
+
+/// The salted commitment to the opinions, row by row, the same as in the
+/// circuit.
+pub fn commit_ops<F: FieldExt, H: Hasher<F, 5>, const N: usize>(ops: &[[F; N]; N], salt: F) -> F {
+	let flat_ops: Vec<F> = ops.iter().flatten().cloned().collect();
+	commit::<F, H>(salt, &flat_ops)
+}
+
+/// Public inputs of the circuit: the commitment to the opinions, followed by
+/// the final scores.
+pub fn public_inputs<F: FieldExt, const N: usize>(ops_commitment: F, scores: &[F; N]) -> Vec<F> {
+	let mut pub_ins = vec![ops_commitment];
+	pub_ins.extend(scores.iter());
+	pub_ins
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{hasher::PoseidonHasher, poseidon::params::Params5x5Bn254};
+	use halo2wrong::curves::bn256::Fr;
+	use maingate::halo2::dev::MockProver;
+
+	const N: usize = 3;
+	const ITER: usize = 3;
+
+	type Hasher5x5 = PoseidonHasher<Params5x5Bn254>;
+	type TestCircuit = ConvergenceCircuit<Fr, N, ITER, Hasher5x5>;
+
+	fn test_inputs() -> ([[Fr; N]; N], [Fr; N], Fr, Fr) {
+		let scale = Fr::from_u128(1000);
+		let ops = [[0, 600, 400], [500, 0, 500], [900, 100, 0]].map(|row| row.map(Fr::from_u128));
+		let pre_trust = [500, 500, 0].map(Fr::from_u128);
+		let pre_trust_weight = Fr::from_u128(200);
+		(ops, pre_trust, pre_trust_weight, scale)
+	}
+
+	fn salt() -> Fr {
+		Fr::from_u128(7)
+	}
+
+	#[test]
+	fn test_convergence_verify() {
+		let k = 15;
+		let (ops, pre_trust, pre_trust_weight, scale) = test_inputs();
+		let scores = native_convergence(ops, pre_trust, pre_trust_weight, scale, ITER);
+
+		let circuit = TestCircuit::new(ops, salt(), pre_trust, pre_trust_weight, scale);
+		let commitment = commit_ops::<Fr, Hasher5x5, N>(&ops, salt());
+		let pub_ins = public_inputs(commitment, &scores);
+
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		assert_eq!(prover.verify(), Ok(()));
+	}
+
+	#[test]
+	fn test_convergence_wrong_scores() {
+		let k = 15;
+		let (ops, pre_trust, pre_trust_weight, scale) = test_inputs();
+		let mut scores = native_convergence(ops, pre_trust, pre_trust_weight, scale, ITER);
+		scores[0] += Fr::one();
+
+		let circuit = TestCircuit::new(ops, salt(), pre_trust, pre_trust_weight, scale);
+		let commitment = commit_ops::<Fr, Hasher5x5, N>(&ops, salt());
+		let pub_ins = public_inputs(commitment, &scores);
+
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+
+	#[test]
+	fn test_convergence_wrong_commitment() {
+		let k = 15;
+		let (ops, pre_trust, pre_trust_weight, scale) = test_inputs();
+		let scores = native_convergence(ops, pre_trust, pre_trust_weight, scale, ITER);
+
+		let circuit = TestCircuit::new(ops, salt(), pre_trust, pre_trust_weight, scale);
+		// Committed with a different salt.
+		let commitment = commit_ops::<Fr, Hasher5x5, N>(&ops, Fr::from_u128(8));
+		let pub_ins = public_inputs(commitment, &scores);
+
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+
+	#[test]
+	fn test_convergence_unnormalized_opinions() {
+		let k = 15;
+		let (mut ops, pre_trust, pre_trust_weight, scale) = test_inputs();
+		// Peer 0 gives out more than 100% of its trust.
+		ops[0][1] = Fr::from_u128(700);
+		let scores = native_convergence(ops, pre_trust, pre_trust_weight, scale, ITER);
+
+		let circuit = TestCircuit::new(ops, salt(), pre_trust, pre_trust_weight, scale);
+		let commitment = commit_ops::<Fr, Hasher5x5, N>(&ops, salt());
+		let pub_ins = public_inputs(commitment, &scores);
+
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+
+	#[test]
+	fn test_convergence_negative_opinions() {
+		let k = 15;
+		let (mut ops, pre_trust, pre_trust_weight, scale) = test_inputs();
+		// Peer 0 still gives out 100% of its trust, with a negative opinion.
+		ops[0][1] = Fr::from_u128(1100);
+		ops[0][2] = -Fr::from_u128(100);
+		let scores = native_convergence(ops, pre_trust, pre_trust_weight, scale, ITER);
+
+		let circuit = TestCircuit::new(ops, salt(), pre_trust, pre_trust_weight, scale);
+		let commitment = commit_ops::<Fr, Hasher5x5, N>(&ops, salt());
+		let pub_ins = public_inputs(commitment, &scores);
+
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+}
@@ -0,0 +1,325 @@
+//! EdDSA signature verification over the BabyJubJub curve. The curve is
+//! defined over the scalar field of Bn254, so its arithmetic is native to the
+//! circuit, which makes the verification much cheaper than ECDSA over
+//! secp256k1. The gadget stands on its own: the opinions are still signed and
+//! proven with ECDSA, see [`EigenTrustCircuit`](crate::EigenTrustCircuit).
+
+pub mod native;
+
+use crate::{
+	gadgets::bits::ToBitsChip,
+	poseidon::{chip::PoseidonChip, params::Params5x5Bn254},
+};
+use halo2wrong::{
+	curves::bn256::Fr,
+	halo2::{arithmetic::FieldExt, plonk::Error},
+};
+use maingate::{
+	AssignedCondition, AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx,
+	UnassignedValue,
+};
+use native::{from_limbs, Point, A, D, SUBORDER, SUBORDER_BITS};
+
+/// Number of bits of the challenge hash.
+pub(crate) const HASH_BITS: usize = 254;
+
+/// BabyJubJub point assigned in the circuit.
+#[derive(Clone, Debug)]
+pub struct AssignedPoint {
+	/// X coordinate.
+	pub x: AssignedValue<Fr>,
+	/// Y coordinate.
+	pub y: AssignedValue<Fr>,
+}
+
+/// Chip for verifying EdDSA signatures inside a circuit.
+pub struct EddsaChip {
+	main_gate: MainGate<Fr>,
+	bits_chip: ToBitsChip<Fr>,
+	poseidon: PoseidonChip<Fr, 5, Params5x5Bn254>,
+}
+
+impl EddsaChip {
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			bits_chip: ToBitsChip::new(main_gate_config.clone()),
+			poseidon: PoseidonChip::new(main_gate_config),
+		}
+	}
+
+	/// Assign a point, and check that it is on the curve.
+	pub fn assign_point(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		point: Option<Point>,
+	) -> Result<AssignedPoint, Error> {
+		let x = self
+			.main_gate
+			.assign_value(ctx, &UnassignedValue::from(point.map(|p| p.x)))?;
+		let y = self
+			.main_gate
+			.assign_value(ctx, &UnassignedValue::from(point.map(|p| p.y)))?;
+		let point = AssignedPoint { x, y };
+		self.assert_on_curve(ctx, &point)?;
+		Ok(point)
+	}
+
+	/// Constrain the curve equation: `a*x^2 + y^2 = 1 + d*x^2*y^2`.
+	pub fn assert_on_curve(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		point: &AssignedPoint,
+	) -> Result<(), Error> {
+		let a = self.main_gate.assign_constant(ctx, Fr::from_u128(A))?;
+		let d = self.main_gate.assign_constant(ctx, Fr::from_u128(D))?;
+		let one = self.main_gate.assign_constant(ctx, Fr::one())?;
+
+		let x2 = self.main_gate.mul(ctx, &point.x, &point.x)?;
+		let y2 = self.main_gate.mul(ctx, &point.y, &point.y)?;
+		let ax2 = self.main_gate.mul(ctx, &a, &x2)?;
+		let left = self.main_gate.add(ctx, &ax2, &y2)?;
+
+		let x2y2 = self.main_gate.mul(ctx, &x2, &y2)?;
+		let dx2y2 = self.main_gate.mul(ctx, &d, &x2y2)?;
+		let right = self.main_gate.add(ctx, &one, &dx2y2)?;
+
+		self.main_gate.assert_equal(ctx, &left, &right)
+	}
+
+	/// Constrain the addition of two points.
+	pub fn add(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		p: &AssignedPoint,
+		q: &AssignedPoint,
+	) -> Result<AssignedPoint, Error> {
+		let a = self.main_gate.assign_constant(ctx, Fr::from_u128(A))?;
+		let d = self.main_gate.assign_constant(ctx, Fr::from_u128(D))?;
+		let one = self.main_gate.assign_constant(ctx, Fr::one())?;
+
+		let x1y2 = self.main_gate.mul(ctx, &p.x, &q.y)?;
+		let y1x2 = self.main_gate.mul(ctx, &p.y, &q.x)?;
+		let x1x2 = self.main_gate.mul(ctx, &p.x, &q.x)?;
+		let y1y2 = self.main_gate.mul(ctx, &p.y, &q.y)?;
+		let x1x2y1y2 = self.main_gate.mul(ctx, &x1x2, &y1y2)?;
+		let t = self.main_gate.mul(ctx, &d, &x1x2y1y2)?;
+
+		// x3 = (x1*y2 + y1*x2) / (1 + d*x1*x2*y1*y2)
+		let x_num = self.main_gate.add(ctx, &x1y2, &y1x2)?;
+		let x_den = self.main_gate.add(ctx, &one, &t)?;
+		let x = self.main_gate.div_unsafe(ctx, &x_num, &x_den)?;
+
+		// y3 = (y1*y2 - a*x1*x2) / (1 - d*x1*x2*y1*y2)
+		let ax1x2 = self.main_gate.mul(ctx, &a, &x1x2)?;
+		let y_num = self.main_gate.sub(ctx, &y1y2, &ax1x2)?;
+		let y_den = self.main_gate.sub(ctx, &one, &t)?;
+		let y = self.main_gate.div_unsafe(ctx, &y_num, &y_den)?;
+
+		Ok(AssignedPoint { x, y })
+	}
+
+	/// Constrain the scalar multiplication of a point, where the scalar is
+	/// given as little-endian bits.
+	pub fn mul_scalar(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		point: &AssignedPoint,
+		bits: &[AssignedCondition<Fr>],
+	) -> Result<AssignedPoint, Error> {
+		let mut acc = AssignedPoint {
+			x: self.main_gate.assign_constant(ctx, Fr::zero())?,
+			y: self.main_gate.assign_constant(ctx, Fr::one())?,
+		};
+		let mut base = point.clone();
+		for bit in bits {
+			let sum = self.add(ctx, &acc, &base)?;
+			acc = AssignedPoint {
+				x: self.main_gate.select(ctx, &sum.x, &acc.x, bit)?,
+				y: self.main_gate.select(ctx, &sum.y, &acc.y, bit)?,
+			};
+			base = self.add(ctx, &base, &base)?;
+		}
+		Ok(acc)
+	}
+
+	/// Constrain the signature verification: `s * B8 == R + H(R, A, m) * A`.
+	pub fn verify(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		big_r: &AssignedPoint,
+		s: &AssignedValue<Fr>,
+		pk: &AssignedPoint,
+		m: &AssignedValue<Fr>,
+	) -> Result<(), Error> {
+		let b8 = Point::b8();
+		let b8 = AssignedPoint {
+			x: self.main_gate.assign_constant(ctx, b8.x)?,
+			y: self.main_gate.assign_constant(ctx, b8.y)?,
+		};
+
+		let inputs = [
+			big_r.x.clone(),
+			big_r.y.clone(),
+			pk.x.clone(),
+			pk.y.clone(),
+			m.clone(),
+		];
+		let h = self.poseidon.permute(ctx, &inputs)?[0].clone();
+
+		// Decomposing `s` into `SUBORDER_BITS` bits checks that it can't wrap
+		// around the field. `s < SUBORDER` holds when `SUBORDER - 1 - s` also
+		// fits into them, since it would wrap around the field otherwise.
+		let s_bits = self.bits_chip.to_bits_le(ctx, s, SUBORDER_BITS)?;
+		let max_s = from_limbs(&SUBORDER) - Fr::one();
+		let max_s = self.main_gate.assign_constant(ctx, max_s)?;
+		let s_gap = self.main_gate.sub(ctx, &max_s, s)?;
+		self.bits_chip.to_bits_le(ctx, &s_gap, SUBORDER_BITS)?;
+		let h_bits = self.bits_chip.to_bits_le(ctx, &h, HASH_BITS)?;
+
+		let left = self.mul_scalar(ctx, &b8, &s_bits)?;
+		let h_pk = self.mul_scalar(ctx, pk, &h_bits)?;
+		let right = self.add(ctx, big_r, &h_pk)?;
+
+		self.main_gate.assert_equal(ctx, &left.x, &right.x)?;
+		self.main_gate.assert_equal(ctx, &left.y, &right.y)?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::halo2::{
+		circuit::{Layouter, SimpleFloorPlanner},
+		dev::MockProver,
+		plonk::{Circuit, ConstraintSystem},
+	};
+	use native::{sign, SecretKey, Signature};
+	use rand::thread_rng;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		sig: Option<Signature>,
+		pk: Option<Point>,
+		m: Option<Fr>,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				sig: None,
+				pk: None,
+				m: None,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let eddsa = EddsaChip::new(config);
+
+			let m = layouter.assign_region(
+				|| "eddsa",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+
+					let big_r = eddsa.assign_point(ctx, self.sig.map(|sig| sig.big_r))?;
+					let s = main_gate
+						.assign_value(ctx, &UnassignedValue::from(self.sig.map(|sig| sig.s)))?;
+					let pk = eddsa.assign_point(ctx, self.pk)?;
+					let m = main_gate.assign_value(ctx, &UnassignedValue::from(self.m))?;
+
+					eddsa.verify(ctx, &big_r, &s, &pk, &m)?;
+					Ok(m)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "m"), m, 0)?;
+
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_eddsa_chip() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public();
+		let m = Fr::from_u128(123456789);
+		let sig = sign(&sk, m);
+
+		let circuit = TestCircuit {
+			sig: Some(sig),
+			pk: Some(pk.0),
+			m: Some(m),
+		};
+		let k = 16;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![m]]).unwrap();
+		assert_eq!(prover.verify(), Ok(()));
+	}
+
+	#[test]
+	fn test_eddsa_chip_non_canonical_s() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public();
+		let order = from_limbs(&SUBORDER);
+		// Look for a signature where `s + SUBORDER` still fits into
+		// `SUBORDER_BITS` bits, so only the comparison with the order can
+		// reject it.
+		let (m, mut sig) = (0..)
+			.map(|i| {
+				let m = Fr::from_u128(i);
+				(m, sign(&sk, m))
+			})
+			.find(|(_, sig)| {
+				native::to_bits(&(sig.s + order))[SUBORDER_BITS..]
+					.iter()
+					.all(|b| !b)
+			})
+			.unwrap();
+		sig.s += order;
+
+		let circuit = TestCircuit {
+			sig: Some(sig),
+			pk: Some(pk.0),
+			m: Some(m),
+		};
+		let k = 16;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![m]]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+
+	#[test]
+	fn test_eddsa_chip_wrong_message() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public();
+		let m = Fr::from_u128(123456789);
+		let sig = sign(&sk, m);
+
+		let wrong_m = m + Fr::one();
+		let circuit = TestCircuit {
+			sig: Some(sig),
+			pk: Some(pk.0),
+			m: Some(wrong_m),
+		};
+		let k = 16;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![wrong_m]]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+}
@@ -0,0 +1,332 @@
+//! Native implementation of the BabyJubJub curve and EdDSA signatures over it,
+//! using Poseidon as the hash function.
+
+use crate::poseidon::{
+	params::{hex_to_field, Params5x5Bn254},
+	Poseidon,
+};
+use halo2wrong::{
+	curves::bn256::Fr,
+	halo2::arithmetic::{Field, FieldExt},
+};
+use rand::Rng;
+
+type Poseidon5x5 = Poseidon<Fr, 5, Params5x5Bn254>;
+
+/// The `a` coefficient of the curve equation: `a*x^2 + y^2 = 1 + d*x^2*y^2`.
+pub const A: u128 = 168700;
+/// The `d` coefficient of the curve equation: `a*x^2 + y^2 = 1 + d*x^2*y^2`.
+pub const D: u128 = 168696;
+/// Order of the prime subgroup generated by `B8`, as little-endian limbs.
+pub const SUBORDER: [u64; 4] = [
+	0x677297dc392126f1,
+	0xab3eedb83920ee0a,
+	0x370a08b6d0302b0b,
+	0x060c89ce5c263405,
+];
+/// Number of bits of the subgroup order.
+pub const SUBORDER_BITS: usize = 251;
+
+/// Point on the BabyJubJub curve, in affine coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Point {
+	/// X coordinate.
+	pub x: Fr,
+	/// Y coordinate.
+	pub y: Fr,
+}
+
+impl Point {
+	/// The neutral element of the curve.
+	pub fn identity() -> Self {
+		Self {
+			x: Fr::zero(),
+			y: Fr::one(),
+		}
+	}
+
+	/// The generator of the prime subgroup.
+	pub fn b8() -> Self {
+		Self {
+			x: hex_to_field("0x0bb77a6ad63e739b4eacb2e09d6277c12ab8d8010534e0b62893f3f6bb957051"),
+			y: hex_to_field("0x25797203f7a0b24925572e1cd16bf9edfce0051fb9e133774b3c257a872d7d8b"),
+		}
+	}
+
+	/// Checks whether the point satisfies the curve equation.
+	pub fn is_on_curve(&self) -> bool {
+		let x2 = self.x.square();
+		let y2 = self.y.square();
+		let a = Fr::from_u128(A);
+		let d = Fr::from_u128(D);
+		a * x2 + y2 == Fr::one() + d * x2 * y2
+	}
+
+	/// Adds two points. The addition law is complete, so it also works for
+	/// doubling and the identity.
+	pub fn add(&self, other: &Self) -> Self {
+		let a = Fr::from_u128(A);
+		let d = Fr::from_u128(D);
+		let t = d * self.x * other.x * self.y * other.y;
+		let x_num = self.x * other.y + self.y * other.x;
+		let y_num = self.y * other.y - a * self.x * other.x;
+		// The denominators are never zero for points on the curve.
+		let x_den = (Fr::one() + t).invert().unwrap_or(Fr::zero());
+		let y_den = (Fr::one() - t).invert().unwrap_or(Fr::zero());
+		Self {
+			x: x_num * x_den,
+			y: y_num * y_den,
+		}
+	}
+
+	/// Multiplies the point by a scalar, using double-and-add.
+	pub fn mul_scalar(&self, scalar: &Fr) -> Self {
+		let mut acc = Self::identity();
+		let mut base = *self;
+		for bit in to_bits(scalar) {
+			if bit {
+				acc = acc.add(&base);
+			}
+			base = base.add(&base);
+		}
+		acc
+	}
+}
+
+/// Secret key, a scalar smaller than the subgroup order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecretKey(pub(crate) Fr);
+
+impl SecretKey {
+	/// Generate a random secret key.
+	pub fn random<R: Rng>(rng: &mut R) -> Self {
+		let mut bytes = [0u8; 64];
+		rng.fill(&mut bytes[..]);
+		Self(reduce_bytes(&bytes))
+	}
+
+	/// Derive the secret key from arbitrary bytes, e.g. an existing secret
+	/// key of another scheme.
+	pub fn from_bytes(bytes: &[u8]) -> Self {
+		Self(reduce_bytes(bytes))
+	}
+
+	/// Returns the public key.
+	pub fn public(&self) -> PublicKey {
+		PublicKey(Point::b8().mul_scalar(&self.0))
+	}
+}
+
+/// Public key, a point in the prime subgroup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey(pub Point);
+
+/// EdDSA signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+	/// The commitment to the nonce: `R = r * B8`.
+	pub big_r: Point,
+	/// The response: `s = r + H(R, A, m) * sk`.
+	pub s: Fr,
+}
+
+/// The challenge hash `H(R, A, m)`.
+pub fn challenge(big_r: &Point, pk: &PublicKey, m: Fr) -> Fr {
+	let inputs = [big_r.x, big_r.y, pk.0.x, pk.0.y, m];
+	Poseidon5x5::new(inputs).permute()[0]
+}
+
+/// Sign the message. The nonce is derived from the secret key and the
+/// message, so no randomness is needed.
+pub fn sign(sk: &SecretKey, m: Fr) -> Signature {
+	let nonce_inputs = [sk.0, m, Fr::zero(), Fr::zero(), Fr::zero()];
+	let nonce_hash = Poseidon5x5::new(nonce_inputs).permute()[0];
+	let r = reduce_bytes(&nonce_hash.to_bytes());
+	let big_r = Point::b8().mul_scalar(&r);
+
+	let pk = sk.public();
+	let h = challenge(&big_r, &pk, m);
+	// s = r + h * sk (mod l)
+	let h_sk = mul_limbs(&to_limbs(&h), &to_limbs(&sk.0));
+	let s = reduce_limbs(&add_limbs(&h_sk, &to_limbs(&r)));
+
+	Signature {
+		big_r,
+		s: from_limbs(&s),
+	}
+}
+
+/// Verify the signature: `s * B8 == R + H(R, A, m) * A`.
+pub fn verify(sig: &Signature, pk: &PublicKey, m: Fr) -> bool {
+	if !is_canonical(&sig.s) || !sig.big_r.is_on_curve() || !pk.0.is_on_curve() {
+		return false;
+	}
+
+	let h = challenge(&sig.big_r, pk, m);
+	let left = Point::b8().mul_scalar(&sig.s);
+	let right = sig.big_r.add(&pk.0.mul_scalar(&h));
+	left == right
+}
+
+/// Little-endian bits of the field element.
+pub fn to_bits(f: &Fr) -> Vec<bool> {
+	let bytes = f.to_bytes();
+	let mut bits = Vec::new();
+	for byte in bytes {
+		for i in 0..8 {
+			bits.push((byte >> i) & 1 == 1);
+		}
+	}
+	bits
+}
+
+/// Checks whether the scalar is smaller than the subgroup order.
+pub(crate) fn is_canonical(f: &Fr) -> bool {
+	let limbs = to_limbs(f);
+	!geq(&limbs, &SUBORDER)
+}
+
+/// Reduce little-endian bytes modulo the subgroup order.
+pub(crate) fn reduce_bytes(bytes: &[u8]) -> Fr {
+	let mut limbs = vec![0u64; (bytes.len() + 7) / 8];
+	for (i, byte) in bytes.iter().enumerate() {
+		limbs[i / 8] |= u64::from(*byte) << ((i % 8) * 8);
+	}
+	from_limbs(&reduce_limbs(&limbs))
+}
+
+/// Reduce a little-endian number modulo the subgroup order, one bit at a time.
+pub(crate) fn reduce_limbs(limbs: &[u64]) -> [u64; 4] {
+	let mut res = [0u64; 4];
+	for i in (0..limbs.len() * 64).rev() {
+		let bit = (limbs[i / 64] >> (i % 64)) & 1;
+		// res < l < 2^251, so shifting can't overflow.
+		for j in (1..4).rev() {
+			res[j] = (res[j] << 1) | (res[j - 1] >> 63);
+		}
+		res[0] = (res[0] << 1) | bit;
+		if geq(&res, &SUBORDER) {
+			res = sub(&res, &SUBORDER);
+		}
+	}
+	res
+}
+
+/// Checks whether `a >= b`.
+pub(crate) fn geq(a: &[u64; 4], b: &[u64; 4]) -> bool {
+	for i in (0..4).rev() {
+		if a[i] != b[i] {
+			return a[i] > b[i];
+		}
+	}
+	true
+}
+
+/// Computes `a - b`, assuming `a >= b`.
+pub(crate) fn sub(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+	let mut res = [0u64; 4];
+	let mut borrow = false;
+	for i in 0..4 {
+		let (diff, b1) = a[i].overflowing_sub(b[i]);
+		let (diff, b2) = diff.overflowing_sub(u64::from(borrow));
+		res[i] = diff;
+		borrow = b1 || b2;
+	}
+	res
+}
+
+/// Computes the full 512-bit product of two 256-bit numbers.
+pub(crate) fn mul_limbs(a: &[u64; 4], b: &[u64; 4]) -> [u64; 8] {
+	let mut res = [0u64; 8];
+	for i in 0..4 {
+		let mut carry = 0u128;
+		for j in 0..4 {
+			let cur = u128::from(res[i + j]) + u128::from(a[i]) * u128::from(b[j]) + carry;
+			res[i + j] = cur as u64;
+			carry = cur >> 64;
+		}
+		res[i + 4] = carry as u64;
+	}
+	res
+}
+
+/// Adds a 256-bit number to a 512-bit number. The product of two scalars is
+/// far from overflowing 512 bits, so the carry is dropped.
+pub(crate) fn add_limbs(a: &[u64; 8], b: &[u64; 4]) -> [u64; 8] {
+	let mut res = *a;
+	let mut carry = 0u128;
+	for i in 0..8 {
+		let b_i = if i < 4 { u128::from(b[i]) } else { 0 };
+		let cur = u128::from(res[i]) + b_i + carry;
+		res[i] = cur as u64;
+		carry = cur >> 64;
+	}
+	res
+}
+
+pub(crate) fn to_limbs(f: &Fr) -> [u64; 4] {
+	let bytes = f.to_bytes();
+	let mut limbs = [0u64; 4];
+	for i in 0..4 {
+		let mut limb_bytes = [0u8; 8];
+		limb_bytes.copy_from_slice(&bytes[i * 8..(i + 1) * 8]);
+		limbs[i] = u64::from_le_bytes(limb_bytes);
+	}
+	limbs
+}
+
+pub(crate) fn from_limbs(limbs: &[u64; 4]) -> Fr {
+	let mut bytes = [0u8; 32];
+	for i in 0..4 {
+		bytes[i * 8..(i + 1) * 8].copy_from_slice(&limbs[i].to_le_bytes());
+	}
+	// Values reduced modulo the subgroup order always fit into the field.
+	Fr::from_bytes(&bytes).unwrap_or(Fr::zero())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rand::thread_rng;
+
+	#[test]
+	fn test_b8_in_subgroup() {
+		let b8 = Point::b8();
+		assert!(b8.is_on_curve());
+		assert!(b8 != Point::identity());
+
+		let order = from_limbs(&SUBORDER);
+		assert_eq!(b8.mul_scalar(&order), Point::identity());
+	}
+
+	#[test]
+	fn test_sign_verify() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public();
+		let m = Fr::from_u128(123456789);
+
+		let sig = sign(&sk, m);
+		assert!(verify(&sig, &pk, m));
+
+		// Wrong message.
+		assert!(!verify(&sig, &pk, m + Fr::one()));
+
+		// Wrong public key.
+		let other_pk = SecretKey::random(rng).public();
+		assert!(!verify(&sig, &other_pk, m));
+	}
+
+	#[test]
+	fn test_reject_non_canonical_s() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public();
+		let m = Fr::from_u128(42);
+
+		let mut sig = sign(&sk, m);
+		// s + l is an equivalent scalar, but must not be accepted.
+		sig.s += from_limbs(&SUBORDER);
+		assert!(!verify(&sig, &pk, m));
+	}
+}
@@ -0,0 +1,145 @@
+//! Bit decomposition of field elements.
+
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{
+	AssignedCondition, AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx,
+};
+
+/// Chip for decomposing a value into `n` constrained bits. The decomposition
+/// also proves that the value is smaller than `2^n`.
+pub struct ToBitsChip<F: FieldExt> {
+	main_gate: MainGate<F>,
+}
+
+impl<F: FieldExt> ToBitsChip<F> {
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config),
+		}
+	}
+
+	/// Decompose `x` into `n` bits, starting from the least significant one.
+	pub fn to_bits_le(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+		n: usize,
+	) -> Result<Vec<AssignedCondition<F>>, Error> {
+		self.main_gate.to_bits(ctx, x, n)
+	}
+
+	/// Decompose `x` into `n` bits, starting from the most significant one.
+	pub fn to_bits_be(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+		n: usize,
+	) -> Result<Vec<AssignedCondition<F>>, Error> {
+		let mut bits = self.to_bits_le(ctx, x, n)?;
+		bits.reverse();
+		Ok(bits)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const BITS: usize = 4;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		x: Option<Fr>,
+		big_endian: bool,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				x: None,
+				big_endian: self.big_endian,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let bits_chip = ToBitsChip::new(config);
+
+			let bits = layouter.assign_region(
+				|| "to_bits",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let x = main_gate.assign_value(ctx, &UnassignedValue::from(self.x))?;
+					let bits = if self.big_endian {
+						bits_chip.to_bits_be(ctx, &x, BITS)?
+					} else {
+						bits_chip.to_bits_le(ctx, &x, BITS)?
+					};
+
+					let zero = main_gate.assign_constant(ctx, Fr::zero())?;
+					let one = main_gate.assign_constant(ctx, Fr::one())?;
+					let mut values = Vec::new();
+					for bit in &bits {
+						values.push(main_gate.select(ctx, &one, &zero, bit)?);
+					}
+					Ok(values)
+				},
+			)?;
+
+			for (i, bit) in bits.into_iter().enumerate() {
+				main_gate.expose_public(layouter.namespace(|| "bit"), bit, i)?;
+			}
+
+			Ok(())
+		}
+	}
+
+	fn run(x: u128, big_endian: bool, bits: [u128; BITS]) -> bool {
+		let circuit = TestCircuit {
+			x: Some(Fr::from_u128(x)),
+			big_endian,
+		};
+		let pub_ins = bits.map(Fr::from_u128).to_vec();
+		let k = 7;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_to_bits() {
+		assert!(run(0b1011, false, [1, 1, 0, 1]));
+		assert!(run(0b1011, true, [1, 0, 1, 1]));
+		assert!(run(0, true, [0, 0, 0, 0]));
+		assert!(run(15, false, [1, 1, 1, 1]));
+	}
+
+	#[test]
+	fn test_to_bits_wrong() {
+		assert!(!run(0b1011, false, [1, 0, 1, 1]));
+		assert!(!run(0b1011, true, [1, 1, 0, 1]));
+		// Doesn't fit into the bits.
+		assert!(!run(16, false, [0, 0, 0, 0]));
+	}
+}
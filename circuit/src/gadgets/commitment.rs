@@ -0,0 +1,166 @@
+//! Salted commitment to a list of values, by chaining the hashes of the chunks
+//! of 4 values: `acc_{k+1} = H(acc_k, v_4k, .., v_4k+3)`, starting from the
+//! salt. The last chunk is padded with zeros. The salt hides the values when
+//! they are easy to guess, like the public keys of the peers of a network.
+
+use crate::hasher::Hasher;
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+use std::marker::PhantomData;
+
+/// Compute the commitment natively, the same as [`CommitmentChip::commit`].
+pub fn commit<F: FieldExt, H: Hasher<F, 5>>(salt: F, values: &[F]) -> F {
+	let mut acc = salt;
+	for chunk in values.chunks(4) {
+		let mut inputs = [F::zero(); 5];
+		inputs[0] = acc;
+		inputs[1..1 + chunk.len()].copy_from_slice(chunk);
+		acc = H::hash(inputs);
+	}
+	acc
+}
+
+/// Chip for committing to a list of values.
+pub struct CommitmentChip<F: FieldExt, H>
+where
+	H: Hasher<F, 5>,
+{
+	main_gate: MainGate<F>,
+	main_gate_config: MainGateConfig,
+	_hasher: PhantomData<H>,
+}
+
+impl<F: FieldExt, H> CommitmentChip<F, H>
+where
+	H: Hasher<F, 5>,
+{
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			main_gate_config,
+			_hasher: PhantomData,
+		}
+	}
+
+	/// Constrain the commitment to the values, with the salt.
+	pub fn commit(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		salt: &AssignedValue<F>,
+		values: &[AssignedValue<F>],
+	) -> Result<AssignedValue<F>, Error> {
+		let zero = self.main_gate.assign_constant(ctx, F::zero())?;
+		let mut acc = salt.clone();
+		for chunk in values.chunks(4) {
+			let mut inputs = [(); 5].map(|_| zero.clone());
+			inputs[0] = acc;
+			for (input, value) in inputs[1..].iter_mut().zip(chunk) {
+				*input = value.clone();
+			}
+			acc = H::hash_assigned(&self.main_gate_config, ctx, &inputs)?;
+		}
+		Ok(acc)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		hasher::PoseidonHasher, poseidon::params::Params5x5Bn254, test_utils::is_satisfied,
+	};
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			arithmetic::Field,
+			circuit::{Layouter, SimpleFloorPlanner},
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const N: usize = 6;
+
+	type Hasher5x5 = PoseidonHasher<Params5x5Bn254>;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		salt: Option<Fr>,
+		values: [Option<Fr>; N],
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				salt: None,
+				values: [None; N],
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let chip = CommitmentChip::<Fr, Hasher5x5>::new(config);
+
+			let commitment = layouter.assign_region(
+				|| "commitment",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let salt = main_gate.assign_value(ctx, &UnassignedValue::from(self.salt))?;
+					let values = self
+						.values
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					chip.commit(ctx, &salt, &values)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "commitment"), commitment, 0)?;
+
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_commit_native() {
+		let salt = Fr::from_u128(7);
+		let values = [1, 2, 3, 4, 5, 6].map(Fr::from_u128);
+		let first = Hasher5x5::hash([salt, values[0], values[1], values[2], values[3]]);
+		let expected = Hasher5x5::hash([first, values[4], values[5], Fr::zero(), Fr::zero()]);
+		assert_eq!(commit::<Fr, Hasher5x5>(salt, &values), expected);
+
+		// The commitment depends on the salt, and on the order of the values.
+		assert_ne!(commit::<Fr, Hasher5x5>(Fr::zero(), &values), expected);
+		let mut swapped = values;
+		swapped.swap(0, 1);
+		assert_ne!(commit::<Fr, Hasher5x5>(salt, &swapped), expected);
+	}
+
+	#[test]
+	fn test_commitment_chip() {
+		let salt = Fr::from_u128(7);
+		let values = [1, 2, 3, 4, 5, 6].map(Fr::from_u128);
+		let circuit = TestCircuit {
+			salt: Some(salt),
+			values: values.map(Some),
+		};
+		let commitment = commit::<Fr, Hasher5x5>(salt, &values);
+		assert!(is_satisfied(14, &circuit, vec![commitment]));
+
+		// Another salt gives another commitment.
+		let other = commit::<Fr, Hasher5x5>(salt + Fr::one(), &values);
+		assert!(!is_satisfied(14, &circuit, vec![other]));
+	}
+}
@@ -0,0 +1,219 @@
+//! Zero check of a field element.
+//!
+//! The prover supplies the output `out` and the inverse `inv` of the input
+//! `x`, and the chip constrains:
+//! - `x * inv = 1 - out`
+//! - `x * out = 0`
+//!
+//! When `x` is not zero, the second constraint forces `out = 0`, and then the
+//! first one forces `inv` to be the inverse of `x`. When `x` is zero, the first
+//! constraint forces `out = 1`, whatever `inv` is. So the output is always a
+//! bit, and no choice of the witnesses can flip it.
+
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{
+	AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx, UnassignedValue,
+};
+
+/// Chip for checking whether a value is zero.
+pub struct IsZeroChip<F: FieldExt> {
+	main_gate: MainGate<F>,
+}
+
+impl<F: FieldExt> IsZeroChip<F> {
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config),
+		}
+	}
+
+	/// The witnesses for the input `x`: the output, and the inverse of `x`, or
+	/// zero if there is none.
+	pub fn witness(x: F) -> (F, F) {
+		let inv = x.invert().unwrap_or(F::zero());
+		let out = if x == F::zero() { F::one() } else { F::zero() };
+		(out, inv)
+	}
+
+	/// Returns 1 if `x` is zero, and 0 otherwise.
+	pub fn is_zero(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let witness = x.value().map(Self::witness);
+		self.is_zero_with_witness(ctx, x, witness.map(|w| w.0), witness.map(|w| w.1))
+	}
+
+	/// Constrain the claimed output and inverse of `x`, and return the output.
+	/// Fails to be satisfied if the witnesses are not consistent with `x`.
+	pub fn is_zero_with_witness(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+		out: Option<F>,
+		inv: Option<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let out = self
+			.main_gate
+			.assign_value(ctx, &UnassignedValue::from(out))?;
+		let inv = self
+			.main_gate
+			.assign_value(ctx, &UnassignedValue::from(inv))?;
+
+		// x * inv = 1 - out
+		let one = self.main_gate.assign_constant(ctx, F::one())?;
+		let x_inv = self.main_gate.mul(ctx, x, &inv)?;
+		let one_minus_out = self.main_gate.sub(ctx, &one, &out)?;
+		self.main_gate.assert_equal(ctx, &x_inv, &one_minus_out)?;
+
+		// x * out = 0
+		let x_out = self.main_gate.mul(ctx, x, &out)?;
+		self.main_gate.assert_zero(ctx, &x_out)?;
+
+		Ok(out)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_utils::is_satisfied;
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			arithmetic::Field,
+			circuit::{Layouter, SimpleFloorPlanner},
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use rand::thread_rng;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		x: Option<Fr>,
+		out: Option<Fr>,
+		inv: Option<Fr>,
+	}
+
+	impl TestCircuit {
+		fn new(x: Fr) -> Self {
+			let (out, inv) = IsZeroChip::witness(x);
+			Self {
+				x: Some(x),
+				out: Some(out),
+				inv: Some(inv),
+			}
+		}
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				x: None,
+				out: None,
+				inv: None,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let chip = IsZeroChip::new(config);
+
+			let out = layouter.assign_region(
+				|| "is_zero",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let x = main_gate.assign_value(ctx, &UnassignedValue::from(self.x))?;
+					chip.is_zero_with_witness(ctx, &x, self.out, self.inv)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "out"), out, 0)?;
+
+			Ok(())
+		}
+	}
+
+	fn run(x: Fr, out: Fr) -> bool {
+		is_satisfied(5, &TestCircuit::new(x), vec![out])
+	}
+
+	#[test]
+	fn test_is_zero() {
+		assert!(run(Fr::zero(), Fr::one()));
+		assert!(run(Fr::one(), Fr::zero()));
+		assert!(run(-Fr::one(), Fr::zero()));
+		assert!(!run(Fr::zero(), Fr::zero()));
+		assert!(!run(Fr::one(), Fr::one()));
+	}
+
+	#[test]
+	fn test_is_zero_random() {
+		let rng = &mut thread_rng();
+		for _ in 0..10 {
+			let x = Fr::random(&mut *rng);
+			let (out, inv) = IsZeroChip::witness(x);
+			assert_eq!(out, Fr::zero());
+			assert_eq!(x * inv, Fr::one());
+
+			assert!(run(x, Fr::zero()));
+			assert!(!run(x, Fr::one()));
+		}
+	}
+
+	#[test]
+	fn test_is_zero_wrong_output() {
+		// The output can't be flipped for a zero input, whatever the inverse.
+		let circuit = TestCircuit {
+			out: Some(Fr::zero()),
+			..TestCircuit::new(Fr::zero())
+		};
+		assert!(!is_satisfied(5, &circuit, vec![Fr::zero()]));
+		let circuit = TestCircuit {
+			out: Some(Fr::zero()),
+			inv: Some(Fr::one()),
+			..TestCircuit::new(Fr::zero())
+		};
+		assert!(!is_satisfied(5, &circuit, vec![Fr::zero()]));
+
+		// Nor for a non zero one.
+		let circuit = TestCircuit {
+			out: Some(Fr::one()),
+			..TestCircuit::new(Fr::from_u128(5))
+		};
+		assert!(!is_satisfied(5, &circuit, vec![Fr::one()]));
+	}
+
+	#[test]
+	fn test_is_zero_wrong_inverse() {
+		let rng = &mut thread_rng();
+		let x = Fr::random(&mut *rng);
+		let circuit = TestCircuit {
+			inv: Some(Fr::random(&mut *rng)),
+			..TestCircuit::new(x)
+		};
+		assert!(!is_satisfied(5, &circuit, vec![Fr::zero()]));
+
+		// An output that isn't a bit.
+		let circuit = TestCircuit {
+			out: Some(Fr::from_u128(2)),
+			inv: Some(Fr::zero()),
+			..TestCircuit::new(Fr::zero())
+		};
+		assert!(!is_satisfied(5, &circuit, vec![Fr::from_u128(2)]));
+	}
+}
@@ -0,0 +1,244 @@
+//! Proof that the local scores a peer hands out, `c_ij` over all of its
+//! neighbors `j`, sum to exactly the scale. Every opinion of the same epoch
+//! carries the same public commitment to the scores, and reveals only the
+//! score at its own slot, so a peer can't give out more than the whole scale
+//! by telling each neighbor a different story. The commitment is salted, so
+//! it doesn't reveal the scores.
+//!
+//! The scores are range checked to `BITS` bits, and the number of the scores
+//! times `2^BITS` must be below the field size, so the sum can't wrap around
+//! the field.
+
+use super::{commitment::CommitmentChip, is_zero::IsZeroChip, lt::LessThanChip};
+use crate::hasher::Hasher;
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+
+/// Chip for the local scores of the neighbors.
+pub struct LocalScoresChip<F: FieldExt, const BITS: usize, H>
+where
+	H: Hasher<F, 5>,
+{
+	main_gate: MainGate<F>,
+	lt_chip: LessThanChip<F, BITS>,
+	is_zero_chip: IsZeroChip<F>,
+	commitment_chip: CommitmentChip<F, H>,
+}
+
+impl<F: FieldExt, const BITS: usize, H> LocalScoresChip<F, BITS, H>
+where
+	H: Hasher<F, 5>,
+{
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			lt_chip: LessThanChip::new(main_gate_config.clone()),
+			is_zero_chip: IsZeroChip::new(main_gate_config.clone()),
+			commitment_chip: CommitmentChip::new(main_gate_config),
+		}
+	}
+
+	/// Constrain the scores to sum to `scale`, and return the commitment to
+	/// them with the salt, the same as
+	/// [`commit`](super::commitment::commit).
+	pub fn commit(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		salt: &AssignedValue<F>,
+		scores: &[AssignedValue<F>],
+		scale: F,
+	) -> Result<AssignedValue<F>, Error> {
+		let mut sum = self.main_gate.assign_constant(ctx, F::zero())?;
+		for score in scores {
+			self.lt_chip.assert_in_range(ctx, score)?;
+			sum = self.main_gate.add(ctx, &sum, score)?;
+		}
+		let scale = self.main_gate.assign_constant(ctx, scale)?;
+		self.main_gate.assert_equal(ctx, &sum, &scale)?;
+
+		self.commitment_chip.commit(ctx, salt, scores)
+	}
+
+	/// Return the score at the slot `index`. Fails if the index is not one of
+	/// the slots.
+	pub fn score_at(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		scores: &[AssignedValue<F>],
+		index: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let zero = self.main_gate.assign_constant(ctx, F::zero())?;
+		let one = self.main_gate.assign_constant(ctx, F::one())?;
+
+		let mut score = zero.clone();
+		let mut matches = zero;
+		for (i, score_i) in scores.iter().enumerate() {
+			let slot = self
+				.main_gate
+				.assign_constant(ctx, F::from_u128(i as u128))?;
+			let diff = self.main_gate.sub(ctx, index, &slot)?;
+			let is_slot = self.is_zero_chip.is_zero(ctx, &diff)?;
+			let selected = self.main_gate.mul(ctx, score_i, &is_slot)?;
+			score = self.main_gate.add(ctx, &score, &selected)?;
+			matches = self.main_gate.add(ctx, &matches, &is_slot)?;
+		}
+		// Exactly one of the slots is selected.
+		self.main_gate.assert_equal(ctx, &matches, &one)?;
+
+		Ok(score)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		gadgets::commitment::commit, hasher::PoseidonHasher, poseidon::params::Params5x5Bn254,
+		test_utils::is_satisfied,
+	};
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			arithmetic::Field,
+			circuit::{Layouter, SimpleFloorPlanner},
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const N: usize = 6;
+	const BITS: usize = 16;
+	const SCALE: u128 = 1000;
+
+	type Hasher5x5 = PoseidonHasher<Params5x5Bn254>;
+	type TestChip = LocalScoresChip<Fr, BITS, Hasher5x5>;
+
+	fn salt() -> Fr {
+		Fr::from_u128(7)
+	}
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		salt: Option<Fr>,
+		scores: [Option<Fr>; N],
+		index: Option<Fr>,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				salt: None,
+				scores: [None; N],
+				index: None,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let chip = TestChip::new(config);
+
+			let (commitment, index, score) = layouter.assign_region(
+				|| "local_scores",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let scores = self
+						.scores
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					let index = main_gate.assign_value(ctx, &UnassignedValue::from(self.index))?;
+					let salt = main_gate.assign_value(ctx, &UnassignedValue::from(self.salt))?;
+					let commitment = chip.commit(ctx, &salt, &scores, Fr::from_u128(SCALE))?;
+					let score = chip.score_at(ctx, &scores, &index)?;
+					Ok((commitment, index, score))
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "commitment"), commitment, 0)?;
+			main_gate.expose_public(layouter.namespace(|| "index"), index, 1)?;
+			main_gate.expose_public(layouter.namespace(|| "score"), score, 2)?;
+
+			Ok(())
+		}
+	}
+
+	fn run(scores: [Fr; N], index: usize, pub_ins: [Fr; 3]) -> bool {
+		let circuit = TestCircuit {
+			salt: Some(salt()),
+			scores: scores.map(Some),
+			index: Some(Fr::from_u128(index as u128)),
+		};
+		is_satisfied(14, &circuit, pub_ins.to_vec())
+	}
+
+	fn valid_pub_ins(scores: [Fr; N], index: usize) -> [Fr; 3] {
+		[
+			commit::<Fr, Hasher5x5>(salt(), &scores),
+			Fr::from_u128(index as u128),
+			scores[index],
+		]
+	}
+
+	#[test]
+	fn test_local_scores() {
+		let scores = [100, 200, 300, 400, 0, 0].map(Fr::from_u128);
+		for index in [0, 3, 5] {
+			assert!(run(scores, index, valid_pub_ins(scores, index)));
+		}
+	}
+
+	#[test]
+	fn test_local_scores_wrong_sum() {
+		// Less than the scale.
+		let scores = [100, 200, 300, 300, 0, 0].map(Fr::from_u128);
+		assert!(!run(scores, 0, valid_pub_ins(scores, 0)));
+		// More than the scale.
+		let scores = [100, 200, 300, 500, 0, 0].map(Fr::from_u128);
+		assert!(!run(scores, 0, valid_pub_ins(scores, 0)));
+		// A score that wraps around the field, to make up for a bigger one.
+		let mut scores = [100, 200, 300, 400, 0, 0].map(Fr::from_u128);
+		scores[0] += Fr::one();
+		scores[1] = -Fr::one();
+		scores[2] += Fr::from_u128(200);
+		assert!(!run(scores, 0, valid_pub_ins(scores, 0)));
+	}
+
+	#[test]
+	fn test_local_scores_wrong_public_inputs() {
+		let scores = [100, 200, 300, 400, 0, 0].map(Fr::from_u128);
+
+		// Commitment to other scores.
+		let other = [400, 300, 200, 100, 0, 0].map(Fr::from_u128);
+		let mut pub_ins = valid_pub_ins(scores, 0);
+		pub_ins[0] = commit::<Fr, Hasher5x5>(salt(), &other);
+		assert!(!run(scores, 0, pub_ins));
+
+		// The same scores with another salt.
+		let mut pub_ins = valid_pub_ins(scores, 0);
+		pub_ins[0] = commit::<Fr, Hasher5x5>(Fr::zero(), &scores);
+		assert!(!run(scores, 0, pub_ins));
+
+		// Claiming the biggest score for another slot.
+		let mut pub_ins = valid_pub_ins(scores, 0);
+		pub_ins[2] = scores[3];
+		assert!(!run(scores, 0, pub_ins));
+
+		// Slot out of range.
+		let mut pub_ins = valid_pub_ins(scores, 0);
+		pub_ins[1] = Fr::from_u128(N as u128);
+		pub_ins[2] = Fr::zero();
+		assert!(!run(scores, N, pub_ins));
+	}
+}
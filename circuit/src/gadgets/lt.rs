@@ -0,0 +1,170 @@
+//! Less-than comparator, built on a bit decomposition range check.
+
+use super::bits::ToBitsChip;
+use halo2wrong::halo2::{
+	arithmetic::{Field, FieldExt},
+	plonk::Error,
+};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+
+/// Chip for comparing values smaller than `2^BITS`.
+pub struct LessThanChip<F: FieldExt, const BITS: usize> {
+	main_gate: MainGate<F>,
+	bits_chip: ToBitsChip<F>,
+}
+
+impl<F: FieldExt, const BITS: usize> LessThanChip<F, BITS> {
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			bits_chip: ToBitsChip::new(main_gate_config),
+		}
+	}
+
+	/// Constrain `x < 2^BITS`, by decomposing it into `BITS` bits.
+	pub fn assert_in_range(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+	) -> Result<(), Error> {
+		self.bits_chip.to_bits_le(ctx, x, BITS)?;
+		Ok(())
+	}
+
+	/// Returns the assigned bit that is set when `x >= y`.
+	/// Since both values are in the range, `x + 2^BITS - y` fits into
+	/// `BITS + 1` bits, and its top bit is set exactly when `x >= y`.
+	fn geq_bit(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+		y: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		self.assert_in_range(ctx, x)?;
+		self.assert_in_range(ctx, y)?;
+
+		let shift = F::from_u128(2).pow_vartime(&[BITS as u64]);
+		let shift = self.main_gate.assign_constant(ctx, shift)?;
+		let x_shifted = self.main_gate.add(ctx, x, &shift)?;
+		let diff = self.main_gate.sub(ctx, &x_shifted, y)?;
+		let bits = self.bits_chip.to_bits_le(ctx, &diff, BITS + 1)?;
+
+		let zero = self.main_gate.assign_constant(ctx, F::zero())?;
+		let one = self.main_gate.assign_constant(ctx, F::one())?;
+		self.main_gate.select(ctx, &one, &zero, &bits[BITS])
+	}
+
+	/// Returns 1 if `x < y`, and 0 otherwise.
+	pub fn less_than(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+		y: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let geq = self.geq_bit(ctx, x, y)?;
+		let one = self.main_gate.assign_constant(ctx, F::one())?;
+		self.main_gate.sub(ctx, &one, &geq)
+	}
+
+	/// Constrain `x < y`.
+	pub fn assert_less_than(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+		y: &AssignedValue<F>,
+	) -> Result<(), Error> {
+		let geq = self.geq_bit(ctx, x, y)?;
+		self.main_gate.assert_zero(ctx, &geq)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const BITS: usize = 8;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		x: Option<Fr>,
+		y: Option<Fr>,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self { x: None, y: None }
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let lt_chip = LessThanChip::<Fr, BITS>::new(config);
+
+			let res = layouter.assign_region(
+				|| "less_than",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let x = main_gate.assign_value(ctx, &UnassignedValue::from(self.x))?;
+					let y = main_gate.assign_value(ctx, &UnassignedValue::from(self.y))?;
+					lt_chip.less_than(ctx, &x, &y)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "res"), res, 0)?;
+
+			Ok(())
+		}
+	}
+
+	fn run(x: u128, y: u128, res: u128) -> bool {
+		let circuit = TestCircuit {
+			x: Some(Fr::from_u128(x)),
+			y: Some(Fr::from_u128(y)),
+		};
+		let k = 9;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![Fr::from_u128(res)]]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_less_than() {
+		assert!(run(3, 5, 1));
+		assert!(run(0, 255, 1));
+		assert!(run(5, 3, 0));
+		assert!(run(5, 5, 0));
+		assert!(run(255, 0, 0));
+	}
+
+	#[test]
+	fn test_less_than_wrong_result() {
+		assert!(!run(3, 5, 0));
+		assert!(!run(5, 3, 1));
+	}
+
+	#[test]
+	fn test_less_than_out_of_range() {
+		assert!(!run(256, 5, 0));
+		assert!(!run(3, 256, 1));
+	}
+}
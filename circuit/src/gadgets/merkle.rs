@@ -0,0 +1,297 @@
+//! Merkle tree over a pluggable hash function, and the chip for verifying the
+//! inclusion proofs inside a circuit.
+
+use super::bits::ToBitsChip;
+use crate::hasher::Hasher;
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+use std::marker::PhantomData;
+
+/// Hash a pair of nodes into their parent.
+pub fn hash_pair<F: FieldExt, const WIDTH: usize, H>(left: F, right: F) -> F
+where
+	H: Hasher<F, WIDTH>,
+{
+	let mut inputs = [F::zero(); WIDTH];
+	inputs[0] = left;
+	inputs[1] = right;
+	H::hash(inputs)
+}
+
+/// Compute the root natively, from the leaf at `index` and the siblings on
+/// its path, the same as [`MerkleChip::compute_root`].
+pub fn compute_root<F: FieldExt, const WIDTH: usize, H>(leaf: F, index: usize, siblings: &[F]) -> F
+where
+	H: Hasher<F, WIDTH>,
+{
+	let mut index = index;
+	let mut node = leaf;
+	for sibling in siblings {
+		node = if index & 1 == 1 {
+			hash_pair::<F, WIDTH, H>(*sibling, node)
+		} else {
+			hash_pair::<F, WIDTH, H>(node, *sibling)
+		};
+		index >>= 1;
+	}
+	node
+}
+
+/// Native Merkle tree of a fixed depth. Missing leaves are filled with zeros.
+pub struct MerkleTree<F: FieldExt, const WIDTH: usize, H>
+where
+	H: Hasher<F, WIDTH>,
+{
+	/// All the levels of the tree, from the leaves up to the root.
+	levels: Vec<Vec<F>>,
+	_hasher: PhantomData<H>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, H> MerkleTree<F, WIDTH, H>
+where
+	H: Hasher<F, WIDTH>,
+{
+	/// Build the tree of the given depth. Returns `None` if there are more
+	/// leaves than the tree can hold.
+	pub fn new(leaves: Vec<F>, depth: usize) -> Option<Self> {
+		let size = 1 << depth;
+		if leaves.len() > size {
+			return None;
+		}
+
+		let mut level = leaves;
+		level.resize(size, F::zero());
+		let mut levels = vec![level.clone()];
+		for _ in 0..depth {
+			level = level
+				.chunks(2)
+				.map(|pair| hash_pair::<F, WIDTH, H>(pair[0], pair[1]))
+				.collect();
+			levels.push(level.clone());
+		}
+
+		Some(Self {
+			levels,
+			_hasher: PhantomData,
+		})
+	}
+
+	/// The root of the tree.
+	pub fn root(&self) -> F {
+		self.levels[self.levels.len() - 1][0]
+	}
+
+	/// The siblings on the path from the leaf at `index` to the root.
+	pub fn path(&self, index: usize) -> Option<Vec<F>> {
+		if index >= self.levels[0].len() {
+			return None;
+		}
+
+		let mut index = index;
+		let mut siblings = Vec::new();
+		for level in &self.levels[..self.levels.len() - 1] {
+			siblings.push(level[index ^ 1]);
+			index >>= 1;
+		}
+		Some(siblings)
+	}
+}
+
+/// Chip for verifying Merkle inclusion proofs.
+pub struct MerkleChip<F: FieldExt, const WIDTH: usize, H>
+where
+	H: Hasher<F, WIDTH>,
+{
+	main_gate: MainGate<F>,
+	bits_chip: ToBitsChip<F>,
+	main_gate_config: MainGateConfig,
+	_hasher: PhantomData<H>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, H> MerkleChip<F, WIDTH, H>
+where
+	H: Hasher<F, WIDTH>,
+{
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			bits_chip: ToBitsChip::new(main_gate_config.clone()),
+			main_gate_config,
+			_hasher: PhantomData,
+		}
+	}
+
+	/// Constrain the hash of a pair of nodes.
+	pub fn hash_pair(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		left: &AssignedValue<F>,
+		right: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let zero = self.main_gate.assign_constant(ctx, F::zero())?;
+		let mut inputs = [(); WIDTH].map(|_| zero.clone());
+		inputs[0] = left.clone();
+		inputs[1] = right.clone();
+		H::hash_assigned(&self.main_gate_config, ctx, &inputs)
+	}
+
+	/// Compute the root from the leaf at `index` and the siblings on its path.
+	/// The depth of the tree is the number of siblings. The caller must
+	/// constrain the result against the committed root.
+	pub fn compute_root(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		leaf: &AssignedValue<F>,
+		index: &AssignedValue<F>,
+		siblings: &[AssignedValue<F>],
+	) -> Result<AssignedValue<F>, Error> {
+		// Bit `i` of the index is set when the node on level `i` is a right child.
+		let index_bits = self.bits_chip.to_bits_le(ctx, index, siblings.len())?;
+
+		let mut node = leaf.clone();
+		for (sibling, bit) in siblings.iter().zip(index_bits.iter()) {
+			let left = self.main_gate.select(ctx, sibling, &node, bit)?;
+			let right = self.main_gate.select(ctx, &node, sibling, bit)?;
+			node = self.hash_pair(ctx, &left, &right)?;
+		}
+		Ok(node)
+	}
+
+	/// Constrain that the leaf at `index` is a member of the tree with `root`.
+	pub fn verify_inclusion(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		root: &AssignedValue<F>,
+		leaf: &AssignedValue<F>,
+		index: &AssignedValue<F>,
+		siblings: &[AssignedValue<F>],
+	) -> Result<(), Error> {
+		let computed_root = self.compute_root(ctx, leaf, index, siblings)?;
+		self.main_gate.assert_equal(ctx, &computed_root, root)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{hasher::PoseidonHasher, poseidon::params::Params3x3Bn254};
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const DEPTH: usize = 3;
+
+	type TestHasher = PoseidonHasher<Params3x3Bn254>;
+	type TestTree = MerkleTree<Fr, 3, TestHasher>;
+	type TestMerkleChip = MerkleChip<Fr, 3, TestHasher>;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		leaf: Option<Fr>,
+		index: Option<Fr>,
+		siblings: [Option<Fr>; DEPTH],
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				leaf: None,
+				index: None,
+				siblings: [None; DEPTH],
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let merkle_chip = TestMerkleChip::new(config);
+
+			let root = layouter.assign_region(
+				|| "merkle",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let leaf = main_gate.assign_value(ctx, &UnassignedValue::from(self.leaf))?;
+					let index = main_gate.assign_value(ctx, &UnassignedValue::from(self.index))?;
+					let siblings = self
+						.siblings
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					merkle_chip.compute_root(ctx, &leaf, &index, &siblings)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "root"), root, 0)?;
+
+			Ok(())
+		}
+	}
+
+	fn test_tree() -> TestTree {
+		let leaves = (1..7).map(Fr::from_u128).collect();
+		TestTree::new(leaves, DEPTH).unwrap()
+	}
+
+	fn run(leaf: Fr, index: usize, siblings: Vec<Fr>, root: Fr) -> bool {
+		let siblings: [Fr; DEPTH] = siblings.try_into().unwrap();
+		let circuit = TestCircuit {
+			leaf: Some(leaf),
+			index: Some(Fr::from_u128(index as u128)),
+			siblings: siblings.map(Some),
+		};
+		let k = 15;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![root]]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_merkle_tree_native() {
+		let tree = test_tree();
+		let path = tree.path(4).unwrap();
+		assert_eq!(path.len(), DEPTH);
+		assert_eq!(path[0], Fr::from_u128(6));
+
+		let root = compute_root::<_, 3, TestHasher>(Fr::from_u128(5), 4, &path);
+		assert_eq!(root, tree.root());
+		let root = compute_root::<_, 3, TestHasher>(Fr::from_u128(5), 5, &path);
+		assert_ne!(root, tree.root());
+
+		assert!(tree.path(8).is_none());
+		assert!(TestTree::new(vec![Fr::zero(); 9], DEPTH).is_none());
+	}
+
+	#[test]
+	fn test_merkle_inclusion() {
+		let tree = test_tree();
+		let root = tree.root();
+		assert!(run(Fr::from_u128(5), 4, tree.path(4).unwrap(), root));
+		assert!(run(Fr::from_u128(2), 1, tree.path(1).unwrap(), root));
+	}
+
+	#[test]
+	fn test_merkle_non_member() {
+		let tree = test_tree();
+		let root = tree.root();
+		// Wrong leaf.
+		assert!(!run(Fr::from_u128(7), 4, tree.path(4).unwrap(), root));
+		// Wrong index.
+		assert!(!run(Fr::from_u128(5), 5, tree.path(4).unwrap(), root));
+	}
+}
@@ -0,0 +1,38 @@
+//! Reusable gadgets, built on top of the MainGate.
+
+pub mod bits;
+pub mod commitment;
+pub mod is_zero;
+pub mod local_scores;
+pub mod lt;
+pub mod merkle;
+pub mod non_membership;
+pub mod normalize;
+pub mod threshold;
+pub mod weighted_sum;
+
+use halo2wrong::{curves::group::ff::PrimeField, halo2::arithmetic::FieldExt};
+
+/// Returns the lower 128 bits of the field element. Used for native
+/// computations on values that are known to be small.
+pub fn to_u128<F: FieldExt>(f: &F) -> u128 {
+	let repr = f.to_repr();
+	let mut bytes = [0u8; 16];
+	bytes.copy_from_slice(&repr.as_ref()[..16]);
+	u128::from_le_bytes(bytes)
+}
+
+/// Convert an element of a foreign field into the native one, reducing it if
+/// it doesn't fit.
+pub fn to_native<N: PrimeField, F: FieldExt>(x: &N) -> F {
+	let repr = x.to_repr();
+	let mut bytes = [0u8; 64];
+	bytes[..repr.as_ref().len()].copy_from_slice(repr.as_ref());
+	F::from_bytes_wide(&bytes)
+}
+
+/// Returns `ceil(log2(n))`, the number of extra bits taken by the sum of `n`
+/// values, over the bits of each value.
+pub const fn ceil_log2(n: usize) -> usize {
+	(usize::BITS - n.saturating_sub(1).leading_zeros()) as usize
+}
@@ -0,0 +1,286 @@
+//! Non-membership proofs, against a set committed as a sorted Merkle tree.
+//!
+//! The leaves of the tree are the members of the set in increasing order,
+//! surrounded by the sentinels `0` and `2^BITS - 1`. A value is not in the set
+//! when there are two adjacent leaves `low < value < high`.
+
+use super::{
+	lt::LessThanChip,
+	merkle::{MerkleChip, MerkleTree},
+	to_u128,
+};
+use crate::hasher::Hasher;
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+
+/// Witness for the non-membership of a value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NonMembershipWitness<F: FieldExt> {
+	/// Index of the lower neighbor.
+	pub index: usize,
+	/// The closest member below the value.
+	pub low: F,
+	/// Merkle path of the lower neighbor.
+	pub low_path: Vec<F>,
+	/// The closest member above the value.
+	pub high: F,
+	/// Merkle path of the upper neighbor.
+	pub high_path: Vec<F>,
+}
+
+/// Native set committed as a sorted Merkle tree.
+pub struct SortedSet<F: FieldExt, const WIDTH: usize, H, const BITS: usize>
+where
+	H: Hasher<F, WIDTH>,
+{
+	leaves: Vec<F>,
+	tree: MerkleTree<F, WIDTH, H>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, H, const BITS: usize> SortedSet<F, WIDTH, H, BITS>
+where
+	H: Hasher<F, WIDTH>,
+{
+	/// Build the set in a tree of the given depth. Returns `None` if the
+	/// members don't fit into the tree, or are outside of the range. The
+	/// native set supports `BITS` up to 127.
+	pub fn new(members: Vec<F>, depth: usize) -> Option<Self> {
+		let max = (1u128 << BITS) - 1;
+		let mut members: Vec<u128> = members.iter().map(to_u128).collect();
+		members.sort_unstable();
+		members.dedup();
+		if members.iter().any(|&member| member == 0 || member >= max) {
+			return None;
+		}
+
+		let mut leaves = vec![F::zero()];
+		leaves.extend(members.into_iter().map(F::from_u128));
+		leaves.push(F::from_u128(max));
+
+		let tree = MerkleTree::new(leaves.clone(), depth)?;
+		Some(Self { leaves, tree })
+	}
+
+	/// The root of the tree.
+	pub fn root(&self) -> F {
+		self.tree.root()
+	}
+
+	/// Finds the witness for the non-membership of the value. Returns `None`
+	/// if the value is in the set, or outside of the range.
+	pub fn witness(&self, value: F) -> Option<NonMembershipWitness<F>> {
+		let value_u128 = to_u128(&value);
+		let index = self
+			.leaves
+			.windows(2)
+			.position(|pair| to_u128(&pair[0]) < value_u128 && value_u128 < to_u128(&pair[1]))?;
+
+		Some(NonMembershipWitness {
+			index,
+			low: self.leaves[index],
+			low_path: self.tree.path(index)?,
+			high: self.leaves[index + 1],
+			high_path: self.tree.path(index + 1)?,
+		})
+	}
+}
+
+/// Chip for proving that a value is not a member of a committed set.
+pub struct NonMembershipChip<F: FieldExt, const WIDTH: usize, H, const BITS: usize>
+where
+	H: Hasher<F, WIDTH>,
+{
+	main_gate: MainGate<F>,
+	merkle_chip: MerkleChip<F, WIDTH, H>,
+	lt_chip: LessThanChip<F, BITS>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, H, const BITS: usize> NonMembershipChip<F, WIDTH, H, BITS>
+where
+	H: Hasher<F, WIDTH>,
+{
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			merkle_chip: MerkleChip::new(main_gate_config.clone()),
+			lt_chip: LessThanChip::new(main_gate_config),
+		}
+	}
+
+	/// Constrain that `value` is not in the set with the `root`, given the
+	/// adjacent leaves `low` at `index` and `high` at `index + 1`.
+	#[allow(clippy::too_many_arguments)]
+	pub fn verify_non_membership(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		root: &AssignedValue<F>,
+		value: &AssignedValue<F>,
+		index: &AssignedValue<F>,
+		low: &AssignedValue<F>,
+		low_siblings: &[AssignedValue<F>],
+		high: &AssignedValue<F>,
+		high_siblings: &[AssignedValue<F>],
+	) -> Result<(), Error> {
+		let one = self.main_gate.assign_constant(ctx, F::one())?;
+		let next_index = self.main_gate.add(ctx, index, &one)?;
+
+		self.merkle_chip
+			.verify_inclusion(ctx, root, low, index, low_siblings)?;
+		self.merkle_chip
+			.verify_inclusion(ctx, root, high, &next_index, high_siblings)?;
+
+		self.lt_chip.assert_less_than(ctx, low, value)?;
+		self.lt_chip.assert_less_than(ctx, value, high)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{hasher::PoseidonHasher, poseidon::params::Params3x3Bn254};
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const DEPTH: usize = 3;
+	const BITS: usize = 8;
+
+	type TestHasher = PoseidonHasher<Params3x3Bn254>;
+	type TestSet = SortedSet<Fr, 3, TestHasher, BITS>;
+	type TestChip = NonMembershipChip<Fr, 3, TestHasher, BITS>;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		value: Option<Fr>,
+		witness: Option<NonMembershipWitness<Fr>>,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				value: None,
+				witness: None,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let chip = TestChip::new(config);
+
+			let root = layouter.assign_region(
+				|| "non_membership",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let mut assign =
+						|val: Option<Fr>| main_gate.assign_value(ctx, &UnassignedValue::from(val));
+
+					let w = self.witness.as_ref();
+					let value = assign(self.value)?;
+					let index = assign(w.map(|w| Fr::from_u128(w.index as u128)))?;
+					let low = assign(w.map(|w| w.low))?;
+					let high = assign(w.map(|w| w.high))?;
+					let mut low_siblings = Vec::new();
+					let mut high_siblings = Vec::new();
+					for i in 0..DEPTH {
+						low_siblings.push(assign(w.map(|w| w.low_path[i]))?);
+						high_siblings.push(assign(w.map(|w| w.high_path[i]))?);
+					}
+					// The root is recomputed from the lower neighbor, and
+					// exposed for the verifier to compare.
+					let root = chip
+						.merkle_chip
+						.compute_root(ctx, &low, &index, &low_siblings)?;
+
+					chip.verify_non_membership(
+						ctx,
+						&root,
+						&value,
+						&index,
+						&low,
+						&low_siblings,
+						&high,
+						&high_siblings,
+					)?;
+					Ok(root)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "root"), root, 0)?;
+
+			Ok(())
+		}
+	}
+
+	fn test_set() -> TestSet {
+		let members = [30, 10, 20].map(Fr::from_u128).to_vec();
+		TestSet::new(members, DEPTH).unwrap()
+	}
+
+	fn run(value: Fr, witness: NonMembershipWitness<Fr>, root: Fr) -> bool {
+		let circuit = TestCircuit {
+			value: Some(value),
+			witness: Some(witness),
+		};
+		let k = 16;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![root]]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_sorted_set_native() {
+		let set = test_set();
+		let witness = set.witness(Fr::from_u128(15)).unwrap();
+		assert_eq!(witness.index, 1);
+		assert_eq!(witness.low, Fr::from_u128(10));
+		assert_eq!(witness.high, Fr::from_u128(20));
+
+		let witness = set.witness(Fr::from_u128(31)).unwrap();
+		assert_eq!(witness.high, Fr::from_u128(255));
+
+		assert!(set.witness(Fr::from_u128(20)).is_none());
+		assert!(set.witness(Fr::from_u128(255)).is_none());
+		assert!(TestSet::new(vec![Fr::from_u128(255)], DEPTH).is_none());
+	}
+
+	#[test]
+	fn test_non_membership() {
+		let set = test_set();
+		let value = Fr::from_u128(15);
+		let witness = set.witness(value).unwrap();
+		assert!(run(value, witness, set.root()));
+	}
+
+	#[test]
+	fn test_non_membership_of_member() {
+		let set = test_set();
+		// Try to pass off a member, using the witness of the range above it.
+		let witness = set.witness(Fr::from_u128(25)).unwrap();
+		assert!(!run(Fr::from_u128(20), witness, set.root()));
+
+		// Or by skipping over it.
+		let mut witness = set.witness(Fr::from_u128(15)).unwrap();
+		let skipping = set.witness(Fr::from_u128(25)).unwrap();
+		witness.high = skipping.high;
+		witness.high_path = skipping.high_path;
+		assert!(!run(Fr::from_u128(20), witness, set.root()));
+	}
+}
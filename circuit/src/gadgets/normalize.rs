@@ -0,0 +1,218 @@
+//! Fixed-point normalization of scores: `c_i = s_i * scale / sum(s_j)`.
+//!
+//! The division is proven with a remainder: `s_i * scale = c_i * sum + r_i`,
+//! where `r_i < sum`. All the scores, and the normalized values, must be
+//! smaller than `2^BITS`, so their sum and the remainders are smaller than
+//! `2^(BITS + ceil(log2(N)))`. `2 * BITS + log2(N)` must stay well below the
+//! field size, so none of the products can wrap around the field.
+
+use super::{bits::ToBitsChip, ceil_log2, lt::LessThanChip, to_u128};
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{
+	AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx, UnassignedValue,
+};
+
+/// Chip for proving the normalization of `N` raw scores.
+pub struct NormalizeChip<F: FieldExt, const N: usize, const BITS: usize> {
+	main_gate: MainGate<F>,
+	bits_chip: ToBitsChip<F>,
+	lt_chip: LessThanChip<F, BITS>,
+}
+
+impl<F: FieldExt, const N: usize, const BITS: usize> NormalizeChip<F, N, BITS> {
+	/// Number of bits of the sum of the scores.
+	const SUM_BITS: usize = BITS + ceil_log2(N);
+
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			bits_chip: ToBitsChip::new(main_gate_config.clone()),
+			lt_chip: LessThanChip::new(main_gate_config),
+		}
+	}
+
+	/// Native normalization, rounding down. Returns the normalized scores and
+	/// the remainders of the divisions.
+	pub fn normalize_native(scores: [F; N], scale: F) -> ([F; N], [F; N]) {
+		let scores = scores.map(|s| to_u128(&s));
+		let scale = to_u128(&scale);
+		let sum: u128 = scores.iter().sum();
+
+		let mut normalized = [F::zero(); N];
+		let mut remainders = [F::zero(); N];
+		if sum == 0 {
+			return (normalized, remainders);
+		}
+		for i in 0..N {
+			let scaled = scores[i] * scale;
+			normalized[i] = F::from_u128(scaled / sum);
+			remainders[i] = F::from_u128(scaled % sum);
+		}
+		(normalized, remainders)
+	}
+
+	/// Constrain the normalization of the scores, and return the normalized
+	/// values. The sum of the scores must not be zero.
+	pub fn normalize(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		scores: &[AssignedValue<F>; N],
+		score_values: [Option<F>; N],
+		scale: F,
+	) -> Result<[AssignedValue<F>; N], Error> {
+		let witness = score_values
+			.try_map(|s| s)
+			.map(|scores| Self::normalize_native(scores, scale));
+
+		let scale = self.main_gate.assign_constant(ctx, scale)?;
+		let mut sum = self.main_gate.assign_constant(ctx, F::zero())?;
+		for score in scores {
+			self.lt_chip.assert_in_range(ctx, score)?;
+			sum = self.main_gate.add(ctx, &sum, score)?;
+		}
+
+		let one = self.main_gate.assign_constant(ctx, F::one())?;
+		let max_r = self.main_gate.sub(ctx, &sum, &one)?;
+
+		let mut normalized = scores.clone();
+		for i in 0..N {
+			let c_i = witness.map(|(c, _)| c[i]);
+			let r_i = witness.map(|(_, r)| r[i]);
+			let c_i = self
+				.main_gate
+				.assign_value(ctx, &UnassignedValue::from(c_i))?;
+			let r_i = self
+				.main_gate
+				.assign_value(ctx, &UnassignedValue::from(r_i))?;
+
+			// s_i * scale = c_i * sum + r_i
+			let lhs = self.main_gate.mul(ctx, &scores[i], &scale)?;
+			let c_sum = self.main_gate.mul(ctx, &c_i, &sum)?;
+			let rhs = self.main_gate.add(ctx, &c_sum, &r_i)?;
+			self.main_gate.assert_equal(ctx, &lhs, &rhs)?;
+
+			// r_i < sum, since both r_i and sum - 1 - r_i fit into the bits
+			// of the sum, and c_i fits into the range. The sum is not range
+			// checked to BITS, since it can take up to SUM_BITS bits.
+			let r_gap = self.main_gate.sub(ctx, &max_r, &r_i)?;
+			self.bits_chip.to_bits_le(ctx, &r_i, Self::SUM_BITS)?;
+			self.bits_chip.to_bits_le(ctx, &r_gap, Self::SUM_BITS)?;
+			self.lt_chip.assert_in_range(ctx, &c_i)?;
+
+			normalized[i] = c_i;
+		}
+
+		Ok(normalized)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+
+	const N: usize = 3;
+	const BITS: usize = 16;
+	const SCALE: u128 = 1000;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		scores: [Option<Fr>; N],
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self { scores: [None; N] }
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let normalize_chip = NormalizeChip::<Fr, N, BITS>::new(config);
+
+			let normalized = layouter.assign_region(
+				|| "normalize",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let scores = self
+						.scores
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					normalize_chip.normalize(ctx, &scores, self.scores, Fr::from_u128(SCALE))
+				},
+			)?;
+
+			for i in 0..N {
+				main_gate.expose_public(layouter.namespace(|| "c"), normalized[i].clone(), i)?;
+			}
+
+			Ok(())
+		}
+	}
+
+	fn run(scores: [u128; N], normalized: [u128; N]) -> bool {
+		let circuit = TestCircuit {
+			scores: scores.map(|s| Some(Fr::from_u128(s))),
+		};
+		let pub_ins = normalized.map(Fr::from_u128).to_vec();
+		let k = 10;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_normalize_native() {
+		let scores = [1, 2, 3].map(Fr::from_u128);
+		let (c, r) = NormalizeChip::<Fr, N, BITS>::normalize_native(scores, Fr::from_u128(SCALE));
+		assert_eq!(c, [166, 333, 500].map(Fr::from_u128));
+		assert_eq!(r, [4, 2, 0].map(Fr::from_u128));
+	}
+
+	#[test]
+	fn test_normalize() {
+		assert!(run([1, 2, 3], [166, 333, 500]));
+		assert!(run([5, 0, 5], [500, 0, 500]));
+	}
+
+	#[test]
+	fn test_normalize_sum_above_range() {
+		// Each score fits into BITS, but their sum doesn't.
+		assert!(run([40000, 40000, 40000], [333, 333, 333]));
+		assert!(!run([40000, 40000, 40000], [334, 333, 333]));
+		assert!(!run([40000, 40000, 40000], [332, 333, 333]));
+	}
+
+	#[test]
+	fn test_normalize_wrong_values() {
+		// Rounding up is not allowed.
+		assert!(!run([1, 2, 3], [167, 333, 500]));
+		// Neither is rounding down by more than one unit.
+		assert!(!run([1, 2, 3], [165, 333, 500]));
+		// Nor handing out more than the scale.
+		assert!(!run([1, 2, 3], [500, 333, 500]));
+	}
+
+	#[test]
+	fn test_normalize_zero_sum() {
+		assert!(!run([0, 0, 0], [0, 0, 0]));
+	}
+}
@@ -0,0 +1,138 @@
+//! Threshold comparison: proves whether a score reaches a public threshold,
+//! without revealing the score itself.
+
+use super::lt::LessThanChip;
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+
+/// Chip for comparing scores smaller than `2^BITS` against a threshold.
+pub struct ThresholdChip<F: FieldExt, const BITS: usize> {
+	main_gate: MainGate<F>,
+	lt_chip: LessThanChip<F, BITS>,
+}
+
+impl<F: FieldExt, const BITS: usize> ThresholdChip<F, BITS> {
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			lt_chip: LessThanChip::new(main_gate_config),
+		}
+	}
+
+	/// Returns 1 if `score >= threshold`, and 0 otherwise. The caller should
+	/// expose the threshold, and the result, as public inputs.
+	pub fn is_above(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		score: &AssignedValue<F>,
+		threshold: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let below = self.lt_chip.less_than(ctx, score, threshold)?;
+		let one = self.main_gate.assign_constant(ctx, F::one())?;
+		self.main_gate.sub(ctx, &one, &below)
+	}
+
+	/// Constrain `score >= threshold`.
+	pub fn assert_above(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		score: &AssignedValue<F>,
+		threshold: &AssignedValue<F>,
+	) -> Result<(), Error> {
+		let below = self.lt_chip.less_than(ctx, score, threshold)?;
+		self.main_gate.assert_zero(ctx, &below)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const BITS: usize = 8;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		score: Option<Fr>,
+		threshold: Option<Fr>,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				score: None,
+				threshold: None,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let threshold_chip = ThresholdChip::<Fr, BITS>::new(config);
+
+			let (threshold, res) = layouter.assign_region(
+				|| "threshold",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let score = main_gate.assign_value(ctx, &UnassignedValue::from(self.score))?;
+					let threshold =
+						main_gate.assign_value(ctx, &UnassignedValue::from(self.threshold))?;
+					let res = threshold_chip.is_above(ctx, &score, &threshold)?;
+					Ok((threshold, res))
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "threshold"), threshold, 0)?;
+			main_gate.expose_public(layouter.namespace(|| "res"), res, 1)?;
+
+			Ok(())
+		}
+	}
+
+	fn run(score: u128, threshold: u128, res: u128) -> bool {
+		let circuit = TestCircuit {
+			score: Some(Fr::from_u128(score)),
+			threshold: Some(Fr::from_u128(threshold)),
+		};
+		let pub_ins = vec![Fr::from_u128(threshold), Fr::from_u128(res)];
+		let k = 9;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![pub_ins]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_threshold() {
+		assert!(run(200, 100, 1));
+		assert!(run(100, 100, 1));
+		assert!(run(99, 100, 0));
+		assert!(run(0, 255, 0));
+	}
+
+	#[test]
+	fn test_threshold_wrong_result() {
+		assert!(!run(200, 100, 0));
+		assert!(!run(99, 100, 1));
+		// A score out of the range can't pass as a large one.
+		assert!(!run(256, 100, 1));
+	}
+}
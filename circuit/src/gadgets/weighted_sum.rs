@@ -0,0 +1,202 @@
+//! Overflow-safe weighted sum of fixed-point values: `sum(w_i * v_i)`.
+//!
+//! The weights and the values are range checked to `BITS` bits, and every
+//! product to `2 * BITS` bits. Since both operands are below `2^BITS`, a
+//! product can't wrap around the field as long as `2 * BITS + log2(N)` is
+//! below the field size, so neither can the sum. The result is then exactly
+//! the integer weighted sum, and is smaller than `N * 2^(2 * BITS)`.
+
+use super::{bits::ToBitsChip, ceil_log2, lt::LessThanChip, to_u128};
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+
+/// Chip for the weighted sum of `N` values.
+pub struct WeightedSumChip<F: FieldExt, const N: usize, const BITS: usize> {
+	main_gate: MainGate<F>,
+	bits_chip: ToBitsChip<F>,
+	lt_chip: LessThanChip<F, BITS>,
+}
+
+impl<F: FieldExt, const N: usize, const BITS: usize> WeightedSumChip<F, N, BITS> {
+	/// Number of bits of the products.
+	const PRODUCT_BITS: usize = 2 * BITS;
+
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			bits_chip: ToBitsChip::new(main_gate_config.clone()),
+			lt_chip: LessThanChip::new(main_gate_config),
+		}
+	}
+
+	/// Native weighted sum. Returns `None` if any of the operands don't fit
+	/// into `BITS` bits, the same as the circuit would fail on, or if the sum
+	/// doesn't fit into 128 bits.
+	pub fn weighted_sum_native(weights: [F; N], values: [F; N]) -> Option<F> {
+		let max = 1u128.checked_shl(BITS as u32)?;
+		let mut sum = 0u128;
+		for i in 0..N {
+			let weight = to_u128(&weights[i]);
+			let value = to_u128(&values[i]);
+			if weight >= max || value >= max {
+				return None;
+			}
+			sum = sum.checked_add(weight.checked_mul(value)?)?;
+		}
+		Some(F::from_u128(sum))
+	}
+
+	/// Constrain the weighted sum, and return it.
+	pub fn weighted_sum(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		weights: &[AssignedValue<F>; N],
+		values: &[AssignedValue<F>; N],
+	) -> Result<AssignedValue<F>, Error> {
+		// Neither the products nor their sum may wrap around the field.
+		if Self::PRODUCT_BITS + ceil_log2(N) >= F::NUM_BITS as usize {
+			return Err(Error::Synthesis);
+		}
+
+		let mut sum = self.main_gate.assign_constant(ctx, F::zero())?;
+		for i in 0..N {
+			self.lt_chip.assert_in_range(ctx, &weights[i])?;
+			self.lt_chip.assert_in_range(ctx, &values[i])?;
+			let product = self.main_gate.mul(ctx, &weights[i], &values[i])?;
+			self.bits_chip
+				.to_bits_le(ctx, &product, Self::PRODUCT_BITS)?;
+			sum = self.main_gate.add(ctx, &sum, &product)?;
+		}
+		Ok(sum)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	const N: usize = 3;
+	const BITS: usize = 16;
+
+	type TestChip = WeightedSumChip<Fr, N, BITS>;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		weights: [Option<Fr>; N],
+		values: [Option<Fr>; N],
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				weights: [None; N],
+				values: [None; N],
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let chip = TestChip::new(config);
+
+			let sum = layouter.assign_region(
+				|| "weighted_sum",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let weights = self
+						.weights
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					let values = self
+						.values
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					chip.weighted_sum(ctx, &weights, &values)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "sum"), sum, 0)?;
+
+			Ok(())
+		}
+	}
+
+	fn run(weights: [u128; N], values: [u128; N], sum: Fr) -> bool {
+		let circuit = TestCircuit {
+			weights: weights.map(|w| Some(Fr::from_u128(w))),
+			values: values.map(|v| Some(Fr::from_u128(v))),
+		};
+		let k = 9;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![sum]]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_weighted_sum_native() {
+		let weights = [100, 200, 300].map(Fr::from_u128);
+		let values = [1, 2, 3].map(Fr::from_u128);
+		let sum = TestChip::weighted_sum_native(weights, values);
+		assert_eq!(sum, Some(Fr::from_u128(1400)));
+
+		let values = [1, 2, 65536].map(Fr::from_u128);
+		assert_eq!(TestChip::weighted_sum_native(weights, values), None);
+	}
+
+	#[test]
+	fn test_weighted_sum() {
+		assert!(run([100, 200, 300], [1, 2, 3], Fr::from_u128(1400)));
+		assert!(run([65535, 0, 1], [1, 7, 65535], Fr::from_u128(131070)));
+	}
+
+	#[test]
+	fn test_weighted_sum_large_operands() {
+		// The products take up to 32 bits, above the range of the operands.
+		let weights = [65535, 40000, 300];
+		let values = [65535, 50000, 300];
+		let sum = 65535 * 65535 + 40000 * 50000 + 300 * 300;
+		let native =
+			TestChip::weighted_sum_native(weights.map(Fr::from_u128), values.map(Fr::from_u128));
+		assert_eq!(native, Some(Fr::from_u128(sum)));
+		assert!(run(weights, values, Fr::from_u128(sum)));
+		assert!(!run(weights, values, Fr::from_u128(sum - 1)));
+	}
+
+	#[test]
+	fn test_weighted_sum_overflow() {
+		// The value 65536 doesn't fit into 16 bits.
+		assert!(!run(
+			[100, 200, 300],
+			[1, 2, 65536],
+			Fr::from_u128(19661300)
+		));
+		// A value that wraps around the field, so the product looks small.
+		let minus_one = -Fr::one();
+		let circuit = TestCircuit {
+			weights: [Some(Fr::one()); N],
+			values: [Some(minus_one), Some(Fr::one()), Some(Fr::one())],
+		};
+		let prover = MockProver::<Fr>::run(9, &circuit, vec![vec![Fr::one()]]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+}
@@ -0,0 +1,159 @@
+//! A common interface over the hash functions, so the message hashes and the
+//! commitments can use either Poseidon or Rescue-Prime. Rescue-Prime needs
+//! far fewer rounds, which makes it cheaper to prove, while its inverse S-box
+//! makes Poseidon much cheaper to compute natively.
+
+use crate::{
+	poseidon::{chip::PoseidonChip, params::RoundParams, Poseidon},
+	rescue_prime::{chip::RescuePrimeChip, params::RescuePrimeParams, RescuePrime},
+};
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGateConfig, RegionCtx};
+use std::marker::PhantomData;
+
+/// Hash function of `WIDTH` field elements into one, which can be computed
+/// both natively and inside the circuit.
+pub trait Hasher<F: FieldExt, const WIDTH: usize> {
+	/// Compute the hash natively.
+	fn hash(inputs: [F; WIDTH]) -> F;
+
+	/// Constrain the hash of the assigned inputs.
+	fn hash_assigned(
+		main_gate_config: &MainGateConfig,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		inputs: &[AssignedValue<F>; WIDTH],
+	) -> Result<AssignedValue<F>, Error>;
+}
+
+/// Hashing with the Poseidon permutation, with the parameters `P`.
+pub struct PoseidonHasher<P>(PhantomData<P>);
+
+impl<F: FieldExt, const WIDTH: usize, P> Hasher<F, WIDTH> for PoseidonHasher<P>
+where
+	P: RoundParams<F, WIDTH>,
+{
+	fn hash(inputs: [F; WIDTH]) -> F {
+		Poseidon::<F, WIDTH, P>::new(inputs).permute()[0]
+	}
+
+	fn hash_assigned(
+		main_gate_config: &MainGateConfig,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		inputs: &[AssignedValue<F>; WIDTH],
+	) -> Result<AssignedValue<F>, Error> {
+		let chip = PoseidonChip::<F, WIDTH, P>::new(main_gate_config.clone());
+		let out = chip.permute(ctx, inputs)?;
+		Ok(out[0].clone())
+	}
+}
+
+/// Hashing with the Rescue-Prime permutation, with the parameters `P`.
+pub struct RescuePrimeHasher<P>(PhantomData<P>);
+
+impl<F: FieldExt, const WIDTH: usize, P> Hasher<F, WIDTH> for RescuePrimeHasher<P>
+where
+	P: RescuePrimeParams<F, WIDTH>,
+{
+	fn hash(inputs: [F; WIDTH]) -> F {
+		RescuePrime::<F, WIDTH, P>::new(inputs).permute()[0]
+	}
+
+	fn hash_assigned(
+		main_gate_config: &MainGateConfig,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		inputs: &[AssignedValue<F>; WIDTH],
+	) -> Result<AssignedValue<F>, Error> {
+		let chip = RescuePrimeChip::<F, WIDTH, P>::new(main_gate_config.clone());
+		let out = chip.permute(ctx, inputs)?;
+		Ok(out[0].clone())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{poseidon::params::Params5x5Bn254, rescue_prime::params::RescuePrime5Bn254};
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::{MainGate, MainGateInstructions, UnassignedValue};
+
+	struct TestCircuit<H: Hasher<Fr, 5>> {
+		inputs: [Option<Fr>; 5],
+		_hasher: PhantomData<H>,
+	}
+
+	impl<H: Hasher<Fr, 5>> TestCircuit<H> {
+		fn new(inputs: [Fr; 5]) -> Self {
+			Self {
+				inputs: inputs.map(Some),
+				_hasher: PhantomData,
+			}
+		}
+	}
+
+	impl<H: Hasher<Fr, 5>> Circuit<Fr> for TestCircuit<H> {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				inputs: [None; 5],
+				_hasher: PhantomData,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+
+			let out = layouter.assign_region(
+				|| "hash",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let inputs = self
+						.inputs
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					H::hash_assigned(&config, ctx, &inputs)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "out"), out, 0)?;
+
+			Ok(())
+		}
+	}
+
+	fn run<H: Hasher<Fr, 5>>(inputs: [Fr; 5]) -> bool {
+		let circuit = TestCircuit::<H>::new(inputs);
+		let out = H::hash(inputs);
+		let k = 14;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![out]]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_hashers() {
+		let inputs = [0, 1, 2, 3, 4].map(Fr::from_u128);
+		assert!(run::<PoseidonHasher<Params5x5Bn254>>(inputs));
+		assert!(run::<RescuePrimeHasher<RescuePrime5Bn254>>(inputs));
+		assert_ne!(
+			PoseidonHasher::<Params5x5Bn254>::hash(inputs),
+			RescuePrimeHasher::<RescuePrime5Bn254>::hash(inputs)
+		);
+	}
+}
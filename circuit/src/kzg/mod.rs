@@ -0,0 +1,308 @@
+//! In-circuit batching of KZG openings over Bn254. The pairings can't be
+//! computed inside the circuit, so the chip only constrains the accumulator of
+//! the openings (see [`native`]). The final pairing check is left to the
+//! verifier of the circuit.
+//!
+//! The opinion proofs are not folded with this chip: the halo2 verifier keeps
+//! the pairing check of a proof to itself, so there are no openings to feed
+//! in. The proofs of many epochs are only batch verified, see
+//! [`verify_batch`](crate::utils::verify_batch).
+
+pub mod native;
+
+use crate::{BIT_LEN_LIMB, NUMBER_OF_LIMBS};
+use ecc::{maingate::RegionCtx, AssignedPoint, EccConfig, GeneralEccChip};
+use halo2wrong::{
+	curves::{
+		bn256::{Fq, Fr, G1Affine, G1},
+		group::{Curve, Group},
+	},
+	halo2::plonk::Error,
+};
+use integer::{AssignedInteger, IntegerInstructions, Range};
+use native::KzgOpening;
+
+/// Bn254 point, assigned with non-native coordinates.
+pub type AssignedG1 = AssignedPoint<Fq, Fr, NUMBER_OF_LIMBS, BIT_LEN_LIMB>;
+/// Bn254 scalar, assigned as a non-native integer.
+pub type AssignedScalar = AssignedInteger<Fr, Fr, NUMBER_OF_LIMBS, BIT_LEN_LIMB>;
+
+/// KZG opening assigned in the circuit.
+#[derive(Clone, Debug)]
+pub struct AssignedKzgOpening {
+	/// The commitment to the polynomial.
+	pub commitment: AssignedG1,
+	/// The point of the evaluation.
+	pub point: AssignedScalar,
+	/// The claimed evaluation.
+	pub value: AssignedScalar,
+	/// The commitment to the quotient.
+	pub proof: AssignedG1,
+}
+
+/// Accumulator assigned in the circuit.
+#[derive(Clone, Debug)]
+pub struct AssignedKzgAccumulator {
+	/// Accumulated proofs.
+	pub lhs: AssignedG1,
+	/// Accumulated right hand sides.
+	pub rhs: AssignedG1,
+}
+
+/// Chip for accumulating KZG openings inside a circuit.
+pub struct KzgChip {
+	ecc_chip: GeneralEccChip<G1Affine, Fr, NUMBER_OF_LIMBS, BIT_LEN_LIMB>,
+	window_size: usize,
+}
+
+impl KzgChip {
+	/// Create a new chip, with the window size of the multi-scalar
+	/// multiplications.
+	pub fn new(ecc_config: EccConfig, window_size: usize) -> Self {
+		Self {
+			ecc_chip: GeneralEccChip::new(ecc_config),
+			window_size,
+		}
+	}
+
+	/// Assign the auxiliary points of the multi-scalar multiplications, for
+	/// accumulating `num_openings` openings. Must be called once, before the
+	/// openings are accumulated.
+	pub fn assign_aux(
+		&mut self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		aux_generator: Option<G1Affine>,
+		num_openings: usize,
+	) -> Result<(), Error> {
+		self.ecc_chip.assign_aux_generator(ctx, aux_generator)?;
+		self.ecc_chip
+			.assign_aux(ctx, self.window_size, num_openings)?;
+		self.ecc_chip
+			.assign_aux(ctx, self.window_size, 2 * num_openings + 1)?;
+		Ok(())
+	}
+
+	/// Assign a scalar.
+	pub fn assign_scalar(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		scalar: Option<Fr>,
+	) -> Result<AssignedScalar, Error> {
+		let unassigned = self.ecc_chip.new_unassigned_scalar(scalar);
+		self.ecc_chip
+			.scalar_field_chip()
+			.assign_integer(ctx, unassigned, Range::Remainder)
+	}
+
+	/// Assign an opening. The points are checked to be on the curve.
+	pub fn assign_opening(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		opening: Option<KzgOpening>,
+	) -> Result<AssignedKzgOpening, Error> {
+		Ok(AssignedKzgOpening {
+			commitment: self
+				.ecc_chip
+				.assign_point(ctx, opening.map(|o| o.commitment))?,
+			point: self.assign_scalar(ctx, opening.map(|o| o.point))?,
+			value: self.assign_scalar(ctx, opening.map(|o| o.value))?,
+			proof: self.ecc_chip.assign_point(ctx, opening.map(|o| o.proof))?,
+		})
+	}
+
+	/// Constrain the accumulation of the openings with the powers of the
+	/// challenge `r`, the same as [`native::accumulate`]. The challenge must be
+	/// bound to the openings by the caller, e.g. as a hash of them.
+	pub fn accumulate(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		openings: &[AssignedKzgOpening],
+		r: &AssignedScalar,
+	) -> Result<AssignedKzgAccumulator, Error> {
+		let scalar_chip = self.ecc_chip.scalar_field_chip();
+
+		let mut lhs_pairs = Vec::new();
+		let mut rhs_pairs = Vec::new();
+		let mut value: Option<AssignedScalar> = None;
+		let mut r_i = r.clone();
+		for (i, opening) in openings.iter().enumerate() {
+			if i > 0 {
+				r_i = scalar_chip.mul(ctx, &r_i, r)?;
+			}
+			let z_r_i = scalar_chip.mul(ctx, &opening.point, &r_i)?;
+			let v_r_i = scalar_chip.mul(ctx, &opening.value, &r_i)?;
+			value = Some(match value {
+				Some(value) => scalar_chip.add(ctx, &value, &v_r_i)?,
+				None => v_r_i,
+			});
+
+			lhs_pairs.push((opening.proof.clone(), r_i.clone()));
+			rhs_pairs.push((opening.commitment.clone(), r_i.clone()));
+			rhs_pairs.push((opening.proof.clone(), z_r_i));
+		}
+		let value = value.ok_or(Error::Synthesis)?;
+
+		// - sum(r^i * v_i) * G
+		let g = self
+			.ecc_chip
+			.assign_constant(ctx, G1::generator().to_affine())?;
+		let neg_value = scalar_chip.neg(ctx, &value)?;
+		rhs_pairs.push((g, neg_value));
+
+		let lhs = self
+			.ecc_chip
+			.mul_batch_1d_horizontal(ctx, lhs_pairs, self.window_size)?;
+		let rhs = self
+			.ecc_chip
+			.mul_batch_1d_horizontal(ctx, rhs_pairs, self.window_size)?;
+
+		Ok(AssignedKzgAccumulator { lhs, rhs })
+	}
+
+	/// Constrain the accumulators to be equal.
+	pub fn assert_equal(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		a: &AssignedKzgAccumulator,
+		b: &AssignedKzgAccumulator,
+	) -> Result<(), Error> {
+		self.ecc_chip.assert_equal(ctx, &a.lhs, &b.lhs)?;
+		self.ecc_chip.assert_equal(ctx, &a.rhs, &b.rhs)
+	}
+
+	/// Assign an accumulator, computed outside of the circuit.
+	pub fn assign_accumulator(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		acc: Option<native::KzgAccumulator>,
+	) -> Result<AssignedKzgAccumulator, Error> {
+		Ok(AssignedKzgAccumulator {
+			lhs: self.ecc_chip.assign_point(ctx, acc.map(|a| a.lhs))?,
+			rhs: self.ecc_chip.assign_point(ctx, acc.map(|a| a.rhs))?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{test_utils::is_satisfied, EigenTrustConfig, DEFAULT_WINDOW_SIZE};
+	use halo2wrong::{
+		curves::bn256::G2,
+		halo2::{
+			arithmetic::Field,
+			circuit::{Layouter, SimpleFloorPlanner},
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::{MainGate, RangeChip};
+	use native::{accumulate, decide, test::random_opening, KzgAccumulator};
+	use rand::thread_rng;
+
+	const OPENINGS: usize = 2;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		openings: [Option<KzgOpening>; OPENINGS],
+		r: Option<Fr>,
+		acc: Option<KzgAccumulator>,
+		aux_generator: Option<G1Affine>,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = EigenTrustConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				openings: [None; OPENINGS],
+				r: None,
+				acc: None,
+				aux_generator: None,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			let (rns_base, rns_scalar) =
+				GeneralEccChip::<G1Affine, Fr, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
+			let main_gate_config = MainGate::<Fr>::configure(meta);
+			let mut overflow_bit_lengths: Vec<usize> = vec![];
+			overflow_bit_lengths.extend(rns_base.overflow_lengths());
+			overflow_bit_lengths.extend(rns_scalar.overflow_lengths());
+			let range_config =
+				RangeChip::<Fr>::configure(meta, &main_gate_config, overflow_bit_lengths);
+			EigenTrustConfig {
+				main_gate_config,
+				range_config,
+			}
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let mut kzg_chip = KzgChip::new(
+				EccConfig::new(config.range_config.clone(), config.main_gate_config.clone()),
+				DEFAULT_WINDOW_SIZE,
+			);
+
+			layouter.assign_region(
+				|| "assign_aux",
+				|mut region| {
+					let offset = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, offset);
+					kzg_chip.assign_aux(ctx, self.aux_generator, OPENINGS)
+				},
+			)?;
+
+			layouter.assign_region(
+				|| "kzg_accumulate",
+				|mut region| {
+					let offset = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, offset);
+					let mut openings = Vec::new();
+					for opening in self.openings {
+						openings.push(kzg_chip.assign_opening(ctx, opening)?);
+					}
+					let r = kzg_chip.assign_scalar(ctx, self.r)?;
+					let acc = kzg_chip.accumulate(ctx, &openings, &r)?;
+
+					let expected = kzg_chip.assign_accumulator(ctx, self.acc)?;
+					kzg_chip.assert_equal(ctx, &acc, &expected)
+				},
+			)?;
+
+			config.config_range(&mut layouter)?;
+
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_kzg_accumulate() {
+		let rng = &mut thread_rng();
+		let s = Fr::random(&mut *rng);
+		let openings = [(); OPENINGS].map(|_| random_opening(s));
+		let r = Fr::random(&mut *rng);
+		let acc = accumulate(&openings, r);
+		assert!(decide(&acc, &(G2::generator() * s).to_affine()));
+
+		let aux_generator = (G1::generator() * Fr::random(&mut *rng)).to_affine();
+		let circuit = TestCircuit {
+			openings: openings.map(Some),
+			r: Some(r),
+			acc: Some(acc),
+			aux_generator: Some(aux_generator),
+		};
+		assert!(is_satisfied(20, &circuit, vec![]));
+
+		// The accumulator of other openings is rejected.
+		let other_openings = [(); OPENINGS].map(|_| random_opening(s));
+		let circuit = TestCircuit {
+			acc: Some(accumulate(&other_openings, r)),
+			..circuit
+		};
+		assert!(!is_satisfied(20, &circuit, vec![]));
+	}
+}
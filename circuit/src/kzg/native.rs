@@ -0,0 +1,113 @@
+//! Native batching of KZG openings over Bn254.
+//!
+//! A single opening proves `p(z) = v` for the commitment `C = [p(s)]`, with
+//! the proof `W = [(p(s) - v) / (s - z)]`. It holds when
+//! `e(W, [s]_2) == e(C - v * G + z * W, [1]_2)`. Many openings are combined
+//! with the powers of a random challenge `r` into a single pair of points, the
+//! accumulator, so only one pairing check is needed at the end.
+
+use halo2wrong::{
+	curves::{
+		bn256::{Bn256, Fr, G1Affine, G2Affine, G1, G2},
+		group::{Curve, Group},
+		pairing::Engine,
+	},
+	halo2::arithmetic::Field,
+};
+
+/// Opening of a committed polynomial at a single point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KzgOpening {
+	/// The commitment to the polynomial: `C = [p(s)]`.
+	pub commitment: G1Affine,
+	/// The point of the evaluation: `z`.
+	pub point: Fr,
+	/// The claimed evaluation: `v = p(z)`.
+	pub value: Fr,
+	/// The commitment to the quotient: `W = [(p(s) - v) / (s - z)]`.
+	pub proof: G1Affine,
+}
+
+/// The accumulated openings, valid when `e(lhs, [s]_2) == e(rhs, [1]_2)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KzgAccumulator {
+	/// Accumulated proofs: `sum(r^i * W_i)`.
+	pub lhs: G1Affine,
+	/// Accumulated right hand sides: `sum(r^i * (C_i - v_i * G + z_i * W_i))`.
+	pub rhs: G1Affine,
+}
+
+/// Combine the openings with the powers of the challenge, starting from `r`.
+/// The challenge must be derived from all the openings, otherwise invalid
+/// openings can cancel each other out.
+pub fn accumulate(openings: &[KzgOpening], r: Fr) -> KzgAccumulator {
+	let mut lhs = G1::identity();
+	let mut rhs = G1::identity();
+	let mut value = Fr::zero();
+	let mut r_i = r;
+	for opening in openings {
+		lhs += opening.proof * r_i;
+		rhs += opening.commitment * r_i;
+		rhs += opening.proof * (opening.point * r_i);
+		value += opening.value * r_i;
+		r_i *= r;
+	}
+	rhs -= G1::generator() * value;
+
+	KzgAccumulator {
+		lhs: lhs.to_affine(),
+		rhs: rhs.to_affine(),
+	}
+}
+
+/// Decide the accumulator with the pairing check, given `[s]_2` from the
+/// trusted setup.
+pub fn decide(acc: &KzgAccumulator, s_g2: &G2Affine) -> bool {
+	let g2 = G2::generator().to_affine();
+	Bn256::pairing(&acc.lhs, s_g2) == Bn256::pairing(&acc.rhs, &g2)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+	use super::*;
+	use rand::thread_rng;
+
+	/// Open the linear polynomial `p(x) = a + b * x` at a random point, with
+	/// the trapdoor `s`. The quotient is the constant `b`.
+	pub(crate) fn random_opening(s: Fr) -> KzgOpening {
+		let rng = &mut thread_rng();
+		let a = Fr::random(&mut *rng);
+		let b = Fr::random(&mut *rng);
+		let z = Fr::random(&mut *rng);
+		let g = G1::generator();
+		KzgOpening {
+			commitment: (g * (a + b * s)).to_affine(),
+			point: z,
+			value: a + b * z,
+			proof: (g * b).to_affine(),
+		}
+	}
+
+	#[test]
+	fn test_accumulate_decide() {
+		let rng = &mut thread_rng();
+		let s = Fr::random(&mut *rng);
+		let s_g2 = (G2::generator() * s).to_affine();
+		let openings: Vec<KzgOpening> = (0..3).map(|_| random_opening(s)).collect();
+
+		let acc = accumulate(&openings, Fr::random(&mut *rng));
+		assert!(decide(&acc, &s_g2));
+	}
+
+	#[test]
+	fn test_wrong_value() {
+		let rng = &mut thread_rng();
+		let s = Fr::random(&mut *rng);
+		let s_g2 = (G2::generator() * s).to_affine();
+		let mut openings: Vec<KzgOpening> = (0..3).map(|_| random_opening(s)).collect();
+		openings[1].value += Fr::one();
+
+		let acc = accumulate(&openings, Fr::random(&mut *rng));
+		assert!(!decide(&acc, &s_g2));
+	}
+}
@@ -1,13 +1,34 @@
 //! The module for the main EigenTrust circuit.
 
-#![feature(array_try_map)]
+#![feature(array_try_map, once_cell)]
 #![allow(clippy::needless_range_loop)]
 
+pub mod config;
+pub mod convergence;
 pub mod ecdsa;
+pub mod eddsa;
+pub mod gadgets;
+pub mod hasher;
+pub mod kzg;
+pub mod multi_epoch;
 pub mod poseidon;
+pub mod rescue_prime;
+pub mod schnorr;
+pub mod test_utils;
 pub mod utils;
 
-use crate::ecdsa::SigData;
+use crate::{
+	config::CircuitConfig,
+	ecdsa::SigData,
+	gadgets::{
+		commitment::{commit, CommitmentChip},
+		is_zero::IsZeroChip,
+		local_scores::LocalScoresChip,
+		to_native,
+	},
+	hasher::{Hasher, PoseidonHasher},
+	poseidon::params::Params9x9Bn254,
+};
 use ::ecdsa::ecdsa::{AssignedEcdsaSig, AssignedPublicKey, EcdsaChip};
 use ecc::{maingate::RegionCtx, EccConfig, GeneralEccChip};
 pub use halo2wrong;
@@ -18,14 +39,48 @@ use halo2wrong::halo2::{
 };
 use integer::{IntegerInstructions, Range, NUMBER_OF_LOOKUP_LIMBS};
 use maingate::{
-	MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions,
-	UnassignedValue,
+	AssignedCondition, MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig,
+	RangeInstructions, UnassignedValue,
 };
 use std::marker::PhantomData;
 
 // Constants for halo2wrong
 const BIT_LEN_LIMB: usize = 68;
 const NUMBER_OF_LIMBS: usize = 4;
+/// The default window size of the scalar multiplication in the ECDSA
+/// verification.
+pub const DEFAULT_WINDOW_SIZE: usize = 2;
+/// The largest window size of the scalar multiplication. The table of a window
+/// has `2^window_size` points, so the larger ones cost more rows than they
+/// save in additions.
+pub const MAX_WINDOW_SIZE: usize = 8;
+/// The number of public inputs of the circuit: `[op_v, r, s, m_hash, pk_x,
+/// pk_y, peers, scores, index_v]`.
+pub const NUM_PUBLIC_INPUTS: usize = 9;
+/// The number of bits of the local scores, enough for the scale of any config.
+pub const SCORE_BITS: usize = 32;
+
+/// The hash used for compressing the public inputs into a single instance.
+pub type PublicInputHasher = PoseidonHasher<Params9x9Bn254>;
+
+/// Hash the public inputs into the single instance, exposed by the circuit
+/// with the compressed public inputs.
+pub fn compress_public_inputs<N: FieldExt>(pub_ins: [N; NUM_PUBLIC_INPUTS]) -> N
+where
+	PublicInputHasher: Hasher<N, 9>,
+{
+	let mut inputs = [N::zero(); 9];
+	inputs[..NUM_PUBLIC_INPUTS].copy_from_slice(&pub_ins);
+	PublicInputHasher::hash(inputs)
+}
+
+/// Check that the window size of the scalar multiplication is supported.
+pub(crate) fn check_window_size(window_size: usize) -> Result<(), Error> {
+	if window_size == 0 || window_size > MAX_WINDOW_SIZE {
+		return Err(Error::Synthesis);
+	}
+	Ok(())
+}
 
 /// The halo2 columns config for the main circuit.
 #[derive(Clone, Debug)]
@@ -45,50 +100,154 @@ impl EigenTrustConfig {
 	}
 }
 
-/// The EigenTrust main circuit.
+/// The scalar field of the signature curve of the config.
+type SignatureScalar<C> = <<C as CircuitConfig>::SignatureCurve as CurveAffine>::ScalarExt;
+
+/// The EigenTrust main circuit. The number of the neighbors, and the curve of
+/// the signatures are taken from the config.
 #[derive(Clone)]
-pub struct EigenTrustCircuit<E: CurveAffine, N: FieldExt, const SIZE: usize> {
+pub struct EigenTrustCircuit<C: CircuitConfig> {
 	/// Public key of the prover.
-	pubkey_i: Option<E>,
+	pubkey_i: Option<C::SignatureCurve>,
 	/// Signature by the prover over the message: sig_i.m_hash.
-	sig_i: Option<SigData<E::ScalarExt>>,
+	sig_i: Option<SigData<SignatureScalar<C>>>,
 	/// Opinions of peers j to the peer i (the prover).
-	op_ji: [Option<N>; SIZE],
-	/// Opinon from peer i (the prover) to the peer v (the verifyer).
-	c_v: Option<N>,
+	op_ji: Vec<Option<C::Scalar>>,
+	/// Public keys of the peers j, as field elements. Zero marks an empty
+	/// neighbor slot, whose opinion is left out of the sum.
+	pubkey_ji: Vec<Option<C::Scalar>>,
+	/// Salt of the public commitment to the keys of the peers j.
+	peers_salt: Option<C::Scalar>,
+	/// Local scores given by peer i (the prover) to its neighbors, summing to
+	/// `C::SCALE`.
+	scores: Vec<Option<C::Scalar>>,
+	/// Salt of the public commitment to the local scores.
+	scores_salt: Option<C::Scalar>,
+	/// Slot of the peer v (the verifier) in the local scores. Its score is the
+	/// one the opinion is weighted with.
+	index_v: Option<C::Scalar>,
 	/// Min score of the peers.
-	min_score: N,
+	min_score: C::Scalar,
 	// Range chip values
-	aux_generator: Option<E>,
+	aux_generator: Option<C::SignatureCurve>,
 	window_size: usize,
-	_marker: PhantomData<N>,
+	/// Whether the public inputs are hashed into a single instance.
+	compressed: bool,
+	/// Whether the signature is left to the verifier, instead of being
+	/// verified in the circuit.
+	native_signature: bool,
+	_config: PhantomData<C>,
 }
 
-impl<E: CurveAffine, N: FieldExt, const SIZE: usize> EigenTrustCircuit<E, N, SIZE> {
-	/// Create a new EigenTrustCircuit.
+impl<C: CircuitConfig> EigenTrustCircuit<C> {
+	/// Create a new EigenTrustCircuit, with the local scores of the peer i and
+	/// the slot of the peer v in them. Panics if the number of the opinions or
+	/// of the scores is not `C::MAX_NEIGHBORS`.
 	pub fn new(
-		pubkey_i: E,
-		sig_i: SigData<E::ScalarExt>,
-		op_ji: [N; SIZE],
-		c_v: N,
-		min_score: N,
-		aux_generator: E,
+		pubkey_i: C::SignatureCurve,
+		sig_i: SigData<SignatureScalar<C>>,
+		op_ji: Vec<C::Scalar>,
+		scores: Vec<C::Scalar>,
+		index_v: usize,
+		min_score: C::Scalar,
+		aux_generator: C::SignatureCurve,
 	) -> Self {
+		assert_eq!(op_ji.len(), C::MAX_NEIGHBORS, "wrong number of opinions");
+		assert_eq!(scores.len(), C::MAX_NEIGHBORS, "wrong number of scores");
 		Self {
 			pubkey_i: Some(pubkey_i),
 			sig_i: Some(sig_i),
-			op_ji: op_ji.map(|c| Some(c)),
-			c_v: Some(c_v),
-
+			op_ji: op_ji.into_iter().map(Some).collect(),
+			pubkey_ji: vec![Some(C::Scalar::one()); C::MAX_NEIGHBORS],
+			peers_salt: Some(C::Scalar::zero()),
+			scores: scores.into_iter().map(Some).collect(),
+			scores_salt: Some(C::Scalar::zero()),
+			index_v: Some(C::Scalar::from_u128(index_v as u128)),
 			min_score,
 			aux_generator: Some(aux_generator),
-			window_size: 2,
-			_marker: PhantomData,
+			window_size: DEFAULT_WINDOW_SIZE,
+			compressed: false,
+			native_signature: false,
+			_config: PhantomData,
 		}
 	}
+
+	/// Set the window size of the scalar multiplication in the ECDSA
+	/// verification. Larger windows trade bigger tables for fewer point
+	/// additions, so the signature verification takes fewer rows. The degree
+	/// of the params can't drop below 18 though, since the range table of the
+	/// limbs takes `2^17` rows, see [`Self::with_native_signature`] for
+	/// smaller ones. The proving key must be generated with the same window
+	/// size. Fails if the window size is not between 1 and
+	/// [`MAX_WINDOW_SIZE`].
+	pub fn with_window_size(mut self, window_size: usize) -> Result<Self, Error> {
+		check_window_size(window_size)?;
+		self.window_size = window_size;
+		Ok(self)
+	}
+
+	/// Set the public keys of the neighbors, as field elements, with zero in
+	/// the empty slots, and the salt of their commitment. By default all the
+	/// slots are taken, with the key one, and the salt is zero. Panics if the
+	/// number of the keys is not `C::MAX_NEIGHBORS`.
+	pub fn with_neighbors(mut self, pubkey_ji: Vec<C::Scalar>, salt: C::Scalar) -> Self {
+		assert_eq!(
+			pubkey_ji.len(),
+			C::MAX_NEIGHBORS,
+			"wrong number of neighbors"
+		);
+		self.pubkey_ji = pubkey_ji.into_iter().map(Some).collect();
+		self.peers_salt = Some(salt);
+		self
+	}
+
+	/// The commitment to the keys of the neighbors, exposed as the public
+	/// input `peers`. Returns `None` without the witnesses.
+	pub fn peers_commitment(&self) -> Option<C::Scalar> {
+		let pubkey_ji: Option<Vec<C::Scalar>> = self.pubkey_ji.iter().cloned().collect();
+		Some(commit::<_, C::MessageHasher>(self.peers_salt?, &pubkey_ji?))
+	}
+
+	/// Set the salt of the commitment to the local scores. By default it is
+	/// zero.
+	pub fn with_scores_salt(mut self, salt: C::Scalar) -> Self {
+		self.scores_salt = Some(salt);
+		self
+	}
+
+	/// The commitment to the local scores, exposed as the public input
+	/// `scores`. Returns `None` without the witnesses.
+	pub fn scores_commitment(&self) -> Option<C::Scalar> {
+		let scores: Option<Vec<C::Scalar>> = self.scores.iter().cloned().collect();
+		Some(commit::<_, C::MessageHasher>(self.scores_salt?, &scores?))
+	}
+
+	/// Expose the hash of the public inputs as the only instance, instead of
+	/// the public inputs themselves. The verifier recomputes the hash with
+	/// [`compress_public_inputs`]. The proving key must be generated with the
+	/// same setting.
+	pub fn with_compressed_public_inputs(mut self) -> Self {
+		self.compressed = true;
+		self
+	}
+
+	/// Leave the signature of the peer i to the verifier, instead of verifying
+	/// it in the circuit. The signature and the public key are still the
+	/// public inputs `r`, `s`, `m_hash`, `pk_x` and `pk_y`, so the verifier
+	/// has to check them with [`crate::ecdsa::verify_signature`] along with the
+	/// proof. Without the non-native arithmetic there is no range table of
+	/// the limbs, and the circuit fits in params of degree below 18. The
+	/// proving key must be generated with the same setting.
+	pub fn with_native_signature(mut self) -> Self {
+		self.native_signature = true;
+		self
+	}
 }
 
-impl<E: CurveAffine, N: FieldExt, const SIZE: usize> Circuit<N> for EigenTrustCircuit<E, N, SIZE> {
+impl<C: CircuitConfig> Circuit<C::Scalar> for EigenTrustCircuit<C>
+where
+	PublicInputHasher: Hasher<C::Scalar, 9>,
+{
 	type Config = EigenTrustConfig;
 	type FloorPlanner = SimpleFloorPlanner;
 
@@ -96,24 +255,32 @@ impl<E: CurveAffine, N: FieldExt, const SIZE: usize> Circuit<N> for EigenTrustCi
 		Self {
 			pubkey_i: None,
 			sig_i: None,
-			op_ji: [None; SIZE],
-			c_v: None,
+			op_ji: vec![None; C::MAX_NEIGHBORS],
+			pubkey_ji: vec![None; C::MAX_NEIGHBORS],
+			peers_salt: None,
+			scores: vec![None; C::MAX_NEIGHBORS],
+			scores_salt: None,
+			index_v: None,
 
 			min_score: self.min_score,
 			aux_generator: None,
 			window_size: self.window_size,
-			_marker: PhantomData,
+			compressed: self.compressed,
+			native_signature: self.native_signature,
+			_config: PhantomData,
 		}
 	}
 
 	/// Make the circuit config.
-	fn configure(meta: &mut ConstraintSystem<N>) -> Self::Config {
-		let (rns_base, rns_scalar) = GeneralEccChip::<E, N, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
-		let main_gate_config = MainGate::<N>::configure(meta);
+	fn configure(meta: &mut ConstraintSystem<C::Scalar>) -> Self::Config {
+		let (rns_base, rns_scalar) =
+			GeneralEccChip::<C::SignatureCurve, C::Scalar, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
+		let main_gate_config = MainGate::<C::Scalar>::configure(meta);
 		let mut overflow_bit_lengths: Vec<usize> = vec![];
 		overflow_bit_lengths.extend(rns_base.overflow_lengths());
 		overflow_bit_lengths.extend(rns_scalar.overflow_lengths());
-		let range_config = RangeChip::<N>::configure(meta, &main_gate_config, overflow_bit_lengths);
+		let range_config =
+			RangeChip::<C::Scalar>::configure(meta, &main_gate_config, overflow_bit_lengths);
 		EigenTrustConfig {
 			main_gate_config,
 			range_config,
@@ -124,47 +291,92 @@ impl<E: CurveAffine, N: FieldExt, const SIZE: usize> Circuit<N> for EigenTrustCi
 	fn synthesize(
 		&self,
 		config: Self::Config,
-		mut layouter: impl Layouter<N>,
+		mut layouter: impl Layouter<C::Scalar>,
 	) -> Result<(), Error> {
-		let mut ecc_chip = GeneralEccChip::<E, N, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::new(
-			EccConfig::new(config.range_config.clone(), config.main_gate_config.clone()),
-		);
+		let mut ecc_chip =
+			GeneralEccChip::<C::SignatureCurve, C::Scalar, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::new(
+				EccConfig::new(config.range_config.clone(), config.main_gate_config.clone()),
+			);
 		let scalar_chip = ecc_chip.scalar_field_chip();
-		let main_gate = MainGate::<N>::new(config.main_gate_config.clone());
+		let main_gate = MainGate::<C::Scalar>::new(config.main_gate_config.clone());
+		let is_zero_chip = IsZeroChip::<C::Scalar>::new(config.main_gate_config.clone());
+		let commitment_chip =
+			CommitmentChip::<C::Scalar, C::MessageHasher>::new(config.main_gate_config.clone());
+		let local_scores_chip = LocalScoresChip::<C::Scalar, SCORE_BITS, C::MessageHasher>::new(
+			config.main_gate_config.clone(),
+		);
+		// The scores must be able to add up to the scale.
+		if C::SCALE >> SCORE_BITS != 0 {
+			return Err(Error::Synthesis);
+		}
 
 		// Set up the Ecc chip
-		layouter.assign_region(
-			|| "assign_aux",
-			|mut region| {
-				let offset = &mut 0;
-				let ctx = &mut RegionCtx::new(&mut region, offset);
-
-				ecc_chip.assign_aux_generator(ctx, self.aux_generator)?;
-				ecc_chip.assign_aux(ctx, self.window_size, 1)?;
-				Ok(())
-			},
-		)?;
+		if !self.native_signature {
+			layouter.assign_region(
+				|| "assign_aux",
+				|mut region| {
+					let offset = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, offset);
+
+					ecc_chip.assign_aux_generator(ctx, self.aux_generator)?;
+					ecc_chip.assign_aux(ctx, self.window_size, 1)?;
+					Ok(())
+				},
+			)?;
+		}
 
-		// Calculate the opinion towards peer v.
-		let op_v = layouter.assign_region(
+		// Calculate the opinion towards peer v, and commit to the peers j and to
+		// the local scores.
+		let (op_v, peers, scores, index_v) = layouter.assign_region(
 			|| "t_i",
 			|mut region| {
 				let position = &mut 0;
 				let ctx = &mut RegionCtx::new(&mut region, position);
-				let unassigned_op_jis = self.op_ji.map(UnassignedValue::from);
-				let unassigned_c_v = UnassignedValue::from(self.c_v);
-
-				let assigned_op_jis =
-					unassigned_op_jis.try_map(|val| main_gate.assign_value(ctx, &val))?;
-
-				let assigned_c_v = main_gate.assign_value(ctx, &unassigned_c_v)?;
 
+				let assigned_op_jis = self
+					.op_ji
+					.iter()
+					.map(|val| main_gate.assign_value(ctx, &UnassignedValue::from(*val)))
+					.collect::<Result<Vec<_>, Error>>()?;
+
+				// The score of the peer v is taken from the committed scores, which
+				// add up to the scale.
+				let assigned_scores = self
+					.scores
+					.iter()
+					.map(|val| main_gate.assign_value(ctx, &UnassignedValue::from(*val)))
+					.collect::<Result<Vec<_>, Error>>()?;
+				let scores_salt =
+					main_gate.assign_value(ctx, &UnassignedValue::from(self.scores_salt))?;
+				let scale = C::Scalar::from_u128(u128::from(C::SCALE));
+				let scores =
+					local_scores_chip.commit(ctx, &scores_salt, &assigned_scores, scale)?;
+				let index_v = main_gate.assign_value(ctx, &UnassignedValue::from(self.index_v))?;
+				let assigned_c_v = local_scores_chip.score_at(ctx, &assigned_scores, &index_v)?;
+
+				let assigned_pubkey_jis = self
+					.pubkey_ji
+					.iter()
+					.map(|val| main_gate.assign_value(ctx, &UnassignedValue::from(*val)))
+					.collect::<Result<Vec<_>, Error>>()?;
+				// The keys of the peers are bound to the public commitment, so the
+				// prover can't empty the slots it doesn't want to count.
+				let peers_salt =
+					main_gate.assign_value(ctx, &UnassignedValue::from(self.peers_salt))?;
+				let peers = commitment_chip.commit(ctx, &peers_salt, &assigned_pubkey_jis)?;
+
+				let zero = main_gate.assign_constant(ctx, C::Scalar::zero())?;
 				let min_score = main_gate.assign_constant(ctx, self.min_score)?;
-				let mut sum = main_gate.assign_constant(ctx, N::zero())?;
-				// Calculate the sum of all opinions.
+				let mut sum = zero.clone();
+				// Calculate the sum of the opinions of the taken slots.
 				// t_i = op_1i + ... + op_nij
-				for i in 0..SIZE {
-					sum = main_gate.add(ctx, &sum, &assigned_op_jis[i])?;
+				for i in 0..C::MAX_NEIGHBORS {
+					// The slot is empty exactly when the public key is zero. The output
+					// of the zero check is constrained to a bit, so it is the flag.
+					let empty: AssignedCondition<_> =
+						is_zero_chip.is_zero(ctx, &assigned_pubkey_jis[i])?.into();
+					let op_ji = main_gate.select(ctx, &zero, &assigned_op_jis[i], &empty)?;
+					sum = main_gate.add(ctx, &sum, &op_ji)?;
 				}
 				// t_i = min_score + t_i
 				let t_i = main_gate.add(ctx, &sum, &min_score)?;
@@ -172,54 +384,115 @@ impl<E: CurveAffine, N: FieldExt, const SIZE: usize> Circuit<N> for EigenTrustCi
 				// op_v = t_i * c_v
 				let op = main_gate.mul(ctx, &t_i, &assigned_c_v)?;
 
-				Ok(op)
+				Ok((op, peers, scores, index_v))
 			},
 		)?;
 
-		let ecdsa_chip = EcdsaChip::new(ecc_chip.clone());
-
-		// Verify the ecdsa signature.
-		let (r, s, m_hash, pk) = layouter.assign_region(
-			|| "sig_i_verify",
-			|mut region| {
-				let offset = &mut 0;
-				let ctx = &mut RegionCtx::new(&mut region, offset);
-
-				let unassigned_r = ecc_chip.new_unassigned_scalar(self.sig_i.map(|s| s.r));
-				let unassigned_s = ecc_chip.new_unassigned_scalar(self.sig_i.map(|s| s.s));
-				let unassigned_m_hash =
-					ecc_chip.new_unassigned_scalar(self.sig_i.map(|s| s.m_hash));
-
-				let assigned_r = scalar_chip.assign_integer(ctx, unassigned_r, Range::Remainder)?;
-				let assigned_s = scalar_chip.assign_integer(ctx, unassigned_s, Range::Remainder)?;
-				let assigned_m_hash =
-					scalar_chip.assign_integer(ctx, unassigned_m_hash, Range::Remainder)?;
-
-				let pk_in_circuit = ecc_chip.assign_point(ctx, self.pubkey_i)?;
-
-				let sig = AssignedEcdsaSig {
-					r: assigned_r.clone(),
-					s: assigned_s.clone(),
-				};
-				let assigned_pk = AssignedPublicKey {
-					point: pk_in_circuit.clone(),
-				};
-
-				ecdsa_chip.verify(ctx, &sig, &assigned_pk, &assigned_m_hash)?;
-
-				Ok((assigned_r, assigned_s, assigned_m_hash, pk_in_circuit))
-			},
-		)?;
+		let (r, s, m_hash, pk_x, pk_y) = if self.native_signature {
+			// The signature is checked by the verifier, so it is only assigned to
+			// be exposed.
+			layouter.assign_region(
+				|| "sig_i",
+				|mut region| {
+					let offset = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, offset);
+
+					let coordinates = self.pubkey_i.and_then(|pk| Option::from(pk.coordinates()));
+					let values = [
+						self.sig_i.map(|s| to_native(&s.r)),
+						self.sig_i.map(|s| to_native(&s.s)),
+						self.sig_i.map(|s| to_native(&s.m_hash)),
+						coordinates.map(|c| to_native(c.x())),
+						coordinates.map(|c| to_native(c.y())),
+					];
+					let [r, s, m_hash, pk_x, pk_y] = values
+						.try_map(|val| main_gate.assign_value(ctx, &UnassignedValue::from(val)))?;
+					Ok((r, s, m_hash, pk_x, pk_y))
+				},
+			)?
+		} else {
+			let ecdsa_chip = EcdsaChip::new(ecc_chip.clone());
+
+			// Verify the ecdsa signature.
+			let sig = layouter.assign_region(
+				|| "sig_i_verify",
+				|mut region| {
+					let offset = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, offset);
+
+					let unassigned_r = ecc_chip.new_unassigned_scalar(self.sig_i.map(|s| s.r));
+					let unassigned_s = ecc_chip.new_unassigned_scalar(self.sig_i.map(|s| s.s));
+					let unassigned_m_hash =
+						ecc_chip.new_unassigned_scalar(self.sig_i.map(|s| s.m_hash));
+
+					let assigned_r =
+						scalar_chip.assign_integer(ctx, unassigned_r, Range::Remainder)?;
+					let assigned_s =
+						scalar_chip.assign_integer(ctx, unassigned_s, Range::Remainder)?;
+					let assigned_m_hash =
+						scalar_chip.assign_integer(ctx, unassigned_m_hash, Range::Remainder)?;
+
+					let pk_in_circuit = ecc_chip.assign_point(ctx, self.pubkey_i)?;
+
+					let sig = AssignedEcdsaSig {
+						r: assigned_r.clone(),
+						s: assigned_s.clone(),
+					};
+					let assigned_pk = AssignedPublicKey {
+						point: pk_in_circuit.clone(),
+					};
+
+					ecdsa_chip.verify(ctx, &sig, &assigned_pk, &assigned_m_hash)?;
+
+					Ok((
+						assigned_r.native(),
+						assigned_s.native(),
+						assigned_m_hash.native(),
+						pk_in_circuit.get_x().native(),
+						pk_in_circuit.get_y().native(),
+					))
+				},
+			)?;
+
+			config.config_range(&mut layouter)?;
+			sig
+		};
 
-		config.config_range(&mut layouter)?;
+		if self.compressed {
+			// Constrain the hash of the values to the only public input.
+			let instance = layouter.assign_region(
+				|| "compress_public_inputs",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let inputs = [
+						op_v.clone(),
+						r.clone(),
+						s.clone(),
+						m_hash.clone(),
+						pk_x.clone(),
+						pk_y.clone(),
+						peers.clone(),
+						scores.clone(),
+						index_v.clone(),
+					];
+					PublicInputHasher::hash_assigned(&config.main_gate_config, ctx, &inputs)
+				},
+			)?;
+			main_gate.expose_public(layouter.namespace(|| "public_inputs"), instance, 0)?;
+			return Ok(());
+		}
 
 		// Constrain the values to public inputs.
 		main_gate.expose_public(layouter.namespace(|| "op_v"), op_v, 0)?;
-		main_gate.expose_public(layouter.namespace(|| "r"), r.native(), 1)?;
-		main_gate.expose_public(layouter.namespace(|| "s"), s.native(), 2)?;
-		main_gate.expose_public(layouter.namespace(|| "m_hash"), m_hash.native(), 3)?;
-		main_gate.expose_public(layouter.namespace(|| "pk_x"), pk.get_x().native(), 4)?;
-		main_gate.expose_public(layouter.namespace(|| "pk_y"), pk.get_y().native(), 5)?;
+		main_gate.expose_public(layouter.namespace(|| "r"), r, 1)?;
+		main_gate.expose_public(layouter.namespace(|| "s"), s, 2)?;
+		main_gate.expose_public(layouter.namespace(|| "m_hash"), m_hash, 3)?;
+		main_gate.expose_public(layouter.namespace(|| "pk_x"), pk_x, 4)?;
+		main_gate.expose_public(layouter.namespace(|| "pk_y"), pk_y, 5)?;
+		main_gate.expose_public(layouter.namespace(|| "peers"), peers, 6)?;
+		main_gate.expose_public(layouter.namespace(|| "scores"), scores, 7)?;
+		main_gate.expose_public(layouter.namespace(|| "index_v"), index_v, 8)?;
 
 		Ok(())
 	}
@@ -228,25 +501,30 @@ impl<E: CurveAffine, N: FieldExt, const SIZE: usize> Circuit<N> for EigenTrustCi
 #[cfg(test)]
 mod test {
 	use super::*;
-	use crate::ecdsa::{generate_signature, Keypair};
+	use crate::ecdsa::{generate_signature, verify_signature, Keypair};
 	use halo2wrong::{
 		curves::{
 			bn256::{Bn256, Fr},
 			group::{Curve, Group},
 			secp256k1::{Fq, Secp256k1Affine as Secp256},
 		},
-		halo2::arithmetic::CurveAffine,
+		halo2::arithmetic::{CurveAffine, Field},
 	};
 	use maingate::halo2::dev::MockProver;
 	use rand::thread_rng;
+	use test_utils::{eigen_trust_public_inputs, is_satisfied, mock_prove, TestConfig};
 	use utils::{generate_params, prove_and_verify};
 
 	const SIZE: usize = 12;
-
-	fn to_wide(p: [u8; 32]) -> [u8; 64] {
-		let mut res = [0u8; 64];
-		res[..32].copy_from_slice(&p[..]);
-		res
+	const SCALE: u64 = <TestConfig<SIZE> as CircuitConfig>::SCALE;
+
+	/// The local scores with `c_v` in the first slot, and the rest of the
+	/// scale in the second one.
+	fn local_scores(c_v: u64) -> Vec<Fr> {
+		let mut scores = vec![Fr::zero(); SIZE];
+		scores[0] = Fr::from(c_v);
+		scores[1] = Fr::from(SCALE - c_v);
+		scores
 	}
 
 	#[test]
@@ -262,35 +540,26 @@ mod test {
 		let sig_i = generate_signature(pair_i, m_hash, &mut rng).unwrap();
 
 		// Data from neighbors of i
-		let op_ji = [(); SIZE].map(|_| Fr::from_u128(1));
-		let c_v = Fr::from_u128(1);
+		let op_ji = vec![Fr::from_u128(1); SIZE];
+		let scores = local_scores(1);
 
 		// Aux generator
 		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
 		let min_score = Fr::from_u128(1);
-		let eigen_trust = EigenTrustCircuit::<_, _, SIZE>::new(
+		let eigen_trust = EigenTrustCircuit::<TestConfig<SIZE>>::new(
 			pubkey_i,
 			sig_i,
 			op_ji,
-			c_v,
+			scores,
+			0,
 			min_score,
 			aux_generator,
 		);
 
+		let peers = eigen_trust.peers_commitment().unwrap();
+		let scores = eigen_trust.scores_commitment().unwrap();
 		let op = Fr::from_u128(SIZE as u128) + min_score;
-		let r = Fr::from_bytes_wide(&to_wide(sig_i.r.to_bytes()));
-		let s = Fr::from_bytes_wide(&to_wide(sig_i.s.to_bytes()));
-		let m_hash = Fr::from_bytes_wide(&to_wide(sig_i.m_hash.to_bytes()));
-		let pk_ix = Fr::from_bytes_wide(&to_wide(pubkey_i.x.to_bytes()));
-		let pk_iy = Fr::from_bytes_wide(&to_wide(pubkey_i.y.to_bytes()));
-
-		let mut pub_ins = Vec::new();
-		pub_ins.push(op);
-		pub_ins.push(r);
-		pub_ins.push(s);
-		pub_ins.push(m_hash);
-		pub_ins.push(pk_ix);
-		pub_ins.push(pk_iy);
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 0).to_vec();
 
 		let prover = match MockProver::<Fr>::run(k, &eigen_trust, vec![pub_ins]) {
 			Ok(prover) => prover,
@@ -300,6 +569,212 @@ mod test {
 		assert_eq!(prover.verify(), Ok(()));
 	}
 
+	#[test]
+	fn test_eigen_trust_verify_window_size() {
+		let k = 18;
+		let mut rng = thread_rng();
+
+		let m_hash = Fq::from_u128(12342);
+
+		let pair_i = Keypair::<Secp256>::new(&mut rng);
+		let pubkey_i = pair_i.public().to_owned();
+		let sig_i = generate_signature(pair_i, m_hash, &mut rng).unwrap();
+
+		let op_ji = vec![Fr::from_u128(1); SIZE];
+		let scores = local_scores(1);
+
+		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
+		let min_score = Fr::from_u128(1);
+		let eigen_trust = EigenTrustCircuit::<TestConfig<SIZE>>::new(
+			pubkey_i,
+			sig_i,
+			op_ji,
+			scores,
+			0,
+			min_score,
+			aux_generator,
+		)
+		.with_window_size(4)
+		.unwrap();
+		assert_eq!(eigen_trust.without_witnesses().window_size, 4);
+
+		let peers = eigen_trust.peers_commitment().unwrap();
+		let scores = eigen_trust.scores_commitment().unwrap();
+		let op = Fr::from_u128(SIZE as u128) + min_score;
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 0).to_vec();
+
+		let prover = match MockProver::<Fr>::run(k, &eigen_trust, vec![pub_ins]) {
+			Ok(prover) => prover,
+			Err(e) => panic!("{}", e),
+		};
+
+		assert_eq!(prover.verify(), Ok(()));
+	}
+
+	#[test]
+	fn test_eigen_trust_invalid_window_size() {
+		let mut rng = thread_rng();
+		let min_score = Fr::from_u128(1);
+		for window_size in [0, MAX_WINDOW_SIZE + 1] {
+			let eigen_trust = utils::random_circuit::<TestConfig<SIZE>, _>(min_score, &mut rng);
+			assert!(matches!(
+				eigen_trust.with_window_size(window_size),
+				Err(Error::Synthesis)
+			));
+			assert!(
+				utils::random_circuit_with_window_size::<TestConfig<SIZE>, _>(
+					min_score,
+					window_size,
+					&mut rng
+				)
+				.is_err()
+			);
+		}
+	}
+
+	#[test]
+	fn test_eigen_trust_verify_empty_slots() {
+		let k = 18;
+		let mut rng = thread_rng();
+
+		let m_hash = Fq::from_u128(12342);
+
+		let pair_i = Keypair::<Secp256>::new(&mut rng);
+		let pubkey_i = pair_i.public().to_owned();
+		let sig_i = generate_signature(pair_i, m_hash, &mut rng).unwrap();
+
+		let op_ji = vec![Fr::from_u128(1); SIZE];
+		let scores = local_scores(1);
+		// Only the first 4 slots are taken.
+		let mut pubkey_ji = vec![Fr::zero(); SIZE];
+		for pk in pubkey_ji.iter_mut().take(4) {
+			*pk = Fr::random(&mut rng);
+		}
+
+		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
+		let min_score = Fr::from_u128(1);
+		let eigen_trust = EigenTrustCircuit::<TestConfig<SIZE>>::new(
+			pubkey_i,
+			sig_i,
+			op_ji,
+			scores,
+			0,
+			min_score,
+			aux_generator,
+		)
+		.with_neighbors(pubkey_ji, Fr::random(&mut rng));
+
+		let peers = eigen_trust.peers_commitment().unwrap();
+		let scores = eigen_trust.scores_commitment().unwrap();
+		let op = Fr::from_u128(4) + min_score;
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 0).to_vec();
+		assert_eq!(mock_prove(k, &eigen_trust, pub_ins.clone()), Ok(()));
+
+		// Counting the opinions of the empty slots is rejected.
+		let mut pub_ins = pub_ins;
+		pub_ins[0] = Fr::from_u128(SIZE as u128) + min_score;
+		assert!(!is_satisfied(k, &eigen_trust, pub_ins.clone()));
+
+		// So is emptying the slots behind the back of the commitment.
+		let all_empty = eigen_trust
+			.clone()
+			.with_neighbors(vec![Fr::zero(); SIZE], Fr::zero());
+		pub_ins[0] = min_score;
+		assert!(!is_satisfied(k, &all_empty, pub_ins));
+	}
+
+	#[test]
+	fn test_eigen_trust_verify_local_scores() {
+		let k = 18;
+		let mut rng = thread_rng();
+
+		let m_hash = Fq::from_u128(12342);
+
+		let pair_i = Keypair::<Secp256>::new(&mut rng);
+		let pubkey_i = pair_i.public().to_owned();
+		let sig_i = generate_signature(pair_i, m_hash, &mut rng).unwrap();
+
+		let op_ji = vec![Fr::from_u128(1); SIZE];
+		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
+		let min_score = Fr::from_u128(1);
+		let circuit = |scores: Vec<Fr>, index_v| {
+			EigenTrustCircuit::<TestConfig<SIZE>>::new(
+				pubkey_i,
+				sig_i,
+				op_ji.clone(),
+				scores,
+				index_v,
+				min_score,
+				aux_generator,
+			)
+			.with_scores_salt(Fr::from_u128(7))
+		};
+		let t_i = Fr::from_u128(SIZE as u128) + min_score;
+
+		// The opinion is weighted with the score at the slot of the peer v.
+		let eigen_trust = circuit(local_scores(1), 1);
+		let peers = eigen_trust.peers_commitment().unwrap();
+		let scores = eigen_trust.scores_commitment().unwrap();
+		let op = t_i * Fr::from(SCALE - 1);
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 1);
+		assert_eq!(mock_prove(k, &eigen_trust, pub_ins.to_vec()), Ok(()));
+
+		// Claiming another slot is rejected.
+		let mut other_slot = pub_ins;
+		other_slot[8] = Fr::zero();
+		assert!(!is_satisfied(k, &eigen_trust, other_slot.to_vec()));
+
+		// So are the scores that add up to more than the scale.
+		let mut too_much = local_scores(1);
+		too_much[2] = Fr::from(SCALE);
+		let eigen_trust = circuit(too_much, 2);
+		let scores = eigen_trust.scores_commitment().unwrap();
+		let op = t_i * Fr::from(SCALE);
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 2);
+		assert!(!is_satisfied(k, &eigen_trust, pub_ins.to_vec()));
+	}
+
+	#[test]
+	fn test_eigen_trust_verify_compressed() {
+		let k = 18;
+		let mut rng = thread_rng();
+
+		let m_hash = Fq::from_u128(12342);
+
+		let pair_i = Keypair::<Secp256>::new(&mut rng);
+		let pubkey_i = pair_i.public().to_owned();
+		let sig_i = generate_signature(pair_i, m_hash, &mut rng).unwrap();
+
+		let op_ji = vec![Fr::from_u128(1); SIZE];
+		let scores = local_scores(1);
+
+		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
+		let min_score = Fr::from_u128(1);
+		let eigen_trust = EigenTrustCircuit::<TestConfig<SIZE>>::new(
+			pubkey_i,
+			sig_i,
+			op_ji,
+			scores,
+			0,
+			min_score,
+			aux_generator,
+		)
+		.with_compressed_public_inputs();
+
+		let peers = eigen_trust.peers_commitment().unwrap();
+		let scores = eigen_trust.scores_commitment().unwrap();
+		let op = Fr::from_u128(SIZE as u128) + min_score;
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 0);
+		let instance = compress_public_inputs(pub_ins);
+		assert_eq!(mock_prove(k, &eigen_trust, vec![instance]), Ok(()));
+
+		// The hash of any other opinion is rejected.
+		let mut pub_ins = pub_ins;
+		pub_ins[0] += Fr::one();
+		let instance = compress_public_inputs(pub_ins);
+		assert!(!is_satisfied(k, &eigen_trust, vec![instance]));
+	}
+
 	#[test]
 	fn test_eigen_trust_production_prove_verify() {
 		let k = 18;
@@ -313,35 +788,72 @@ mod test {
 		let sig_i = generate_signature(pair_i, m_hash, &mut rng).unwrap();
 
 		// Data from neighbors of i
-		let op_ji = [(); SIZE].map(|_| Fr::from_u128(1));
-		let c_v = Fr::from_u128(1);
+		let op_ji = vec![Fr::from_u128(1); SIZE];
+		let scores = local_scores(1);
 
 		// Aux generator
 		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
 		let min_score = Fr::from_u128(1);
-		let eigen_trust = EigenTrustCircuit::<_, _, SIZE>::new(
+		let eigen_trust = EigenTrustCircuit::<TestConfig<SIZE>>::new(
 			pubkey_i,
 			sig_i,
 			op_ji,
-			c_v,
+			scores,
+			0,
 			min_score,
 			aux_generator,
 		);
 
+		let peers = eigen_trust.peers_commitment().unwrap();
+		let scores = eigen_trust.scores_commitment().unwrap();
 		let op = Fr::from_u128(SIZE as u128) + min_score;
-		let r = Fr::from_bytes_wide(&to_wide(sig_i.r.to_bytes()));
-		let s = Fr::from_bytes_wide(&to_wide(sig_i.s.to_bytes()));
-		let m_hash = Fr::from_bytes_wide(&to_wide(sig_i.m_hash.to_bytes()));
-		let pk_ix = Fr::from_bytes_wide(&to_wide(pubkey_i.x.to_bytes()));
-		let pk_iy = Fr::from_bytes_wide(&to_wide(pubkey_i.y.to_bytes()));
-
-		let mut pub_ins = Vec::new();
-		pub_ins.push(op);
-		pub_ins.push(r);
-		pub_ins.push(s);
-		pub_ins.push(m_hash);
-		pub_ins.push(pk_ix);
-		pub_ins.push(pk_iy);
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 0).to_vec();
+
+		let params = generate_params(k);
+		prove_and_verify::<Bn256, _, _>(params, eigen_trust, &[&pub_ins[..]], &mut rng).unwrap();
+	}
+
+	#[test]
+	fn test_eigen_trust_native_signature_prove_verify() {
+		let k = 16;
+		let mut rng = thread_rng();
+
+		let m_hash = Fq::from_u128(12342);
+
+		let pair_i = Keypair::<Secp256>::new(&mut rng);
+		let pubkey_i = pair_i.public().to_owned();
+		let sig_i = generate_signature(pair_i, m_hash, &mut rng).unwrap();
+
+		let op_ji = vec![Fr::from_u128(1); SIZE];
+		let scores = local_scores(1);
+
+		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
+		let min_score = Fr::from_u128(1);
+		let eigen_trust = EigenTrustCircuit::<TestConfig<SIZE>>::new(
+			pubkey_i,
+			sig_i,
+			op_ji,
+			scores,
+			0,
+			min_score,
+			aux_generator,
+		)
+		.with_native_signature();
+		assert!(eigen_trust.without_witnesses().native_signature);
+
+		let peers = eigen_trust.peers_commitment().unwrap();
+		let scores = eigen_trust.scores_commitment().unwrap();
+		let op = Fr::from_u128(SIZE as u128) + min_score;
+		let pub_ins = eigen_trust_public_inputs(op, &sig_i, &pubkey_i, peers, scores, 0).to_vec();
+
+		// The verifier checks the signature on its own.
+		assert!(verify_signature(&sig_i, &pubkey_i));
+		assert_eq!(mock_prove(k, &eigen_trust, pub_ins.clone()), Ok(()));
+
+		// The exposed signature is still bound to the witness.
+		let mut wrong_pub_ins = pub_ins.clone();
+		wrong_pub_ins[2] += Fr::one();
+		assert!(!is_satisfied(k, &eigen_trust, wrong_pub_ins));
 
 		let params = generate_params(k);
 		prove_and_verify::<Bn256, _, _>(params, eigen_trust, &[&pub_ins[..]], &mut rng).unwrap();
@@ -0,0 +1,285 @@
+//! The module for the circuit proving the opinions of a peer over many
+//! consecutive epochs in a single proof.
+//!
+//! The public key of the prover is assigned once, and every signature is
+//! verified against it. For each epoch the opinion is calculated the same as
+//! in the [`EigenTrustCircuit`](crate::EigenTrustCircuit): `op_v = (min_score +
+//! sum(op_ji)) * c_v`.
+
+use crate::{
+	check_window_size, ecdsa::SigData, EigenTrustConfig, BIT_LEN_LIMB, DEFAULT_WINDOW_SIZE,
+	NUMBER_OF_LIMBS,
+};
+use ::ecdsa::ecdsa::{AssignedEcdsaSig, AssignedPublicKey, EcdsaChip};
+use ecc::{maingate::RegionCtx, EccConfig, GeneralEccChip};
+use halo2wrong::halo2::{
+	arithmetic::{CurveAffine, FieldExt},
+	circuit::{Layouter, SimpleFloorPlanner},
+	plonk::{Circuit, ConstraintSystem, Error},
+};
+use integer::{IntegerInstructions, Range};
+use maingate::{MainGate, MainGateInstructions, RangeChip, UnassignedValue};
+use std::marker::PhantomData;
+
+/// The number of public inputs of every epoch: `[op_v, r, s, m_hash]`.
+pub const EPOCH_PUBLIC_INPUTS: usize = 4;
+
+/// The circuit proving `EPOCHS` opinions of the same peer.
+#[derive(Clone)]
+pub struct MultiEpochCircuit<E: CurveAffine, N: FieldExt, const SIZE: usize, const EPOCHS: usize> {
+	/// Public key of the prover.
+	pubkey_i: Option<E>,
+	/// Signatures by the prover, one for each epoch.
+	sigs_i: [Option<SigData<E::ScalarExt>>; EPOCHS],
+	/// Opinions of peers j to the peer i (the prover), for each epoch.
+	op_ji: [[Option<N>; SIZE]; EPOCHS],
+	/// Opinons from peer i (the prover) to the peer v (the verifyer), for each
+	/// epoch.
+	c_v: [Option<N>; EPOCHS],
+	/// Min score of the peers.
+	min_score: N,
+	// Range chip values
+	aux_generator: Option<E>,
+	window_size: usize,
+	_marker: PhantomData<N>,
+}
+
+impl<E: CurveAffine, N: FieldExt, const SIZE: usize, const EPOCHS: usize>
+	MultiEpochCircuit<E, N, SIZE, EPOCHS>
+{
+	/// Create a new MultiEpochCircuit.
+	pub fn new(
+		pubkey_i: E,
+		sigs_i: [SigData<E::ScalarExt>; EPOCHS],
+		op_ji: [[N; SIZE]; EPOCHS],
+		c_v: [N; EPOCHS],
+		min_score: N,
+		aux_generator: E,
+	) -> Self {
+		Self {
+			pubkey_i: Some(pubkey_i),
+			sigs_i: sigs_i.map(Some),
+			op_ji: op_ji.map(|ops| ops.map(Some)),
+			c_v: c_v.map(Some),
+			min_score,
+			aux_generator: Some(aux_generator),
+			window_size: DEFAULT_WINDOW_SIZE,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Set the window size of the scalar multiplication in the ECDSA
+	/// verification. Fails if it is not between 1 and `MAX_WINDOW_SIZE`.
+	pub fn with_window_size(mut self, window_size: usize) -> Result<Self, Error> {
+		check_window_size(window_size)?;
+		self.window_size = window_size;
+		Ok(self)
+	}
+}
+
+impl<E: CurveAffine, N: FieldExt, const SIZE: usize, const EPOCHS: usize> Circuit<N>
+	for MultiEpochCircuit<E, N, SIZE, EPOCHS>
+{
+	type Config = EigenTrustConfig;
+	type FloorPlanner = SimpleFloorPlanner;
+
+	fn without_witnesses(&self) -> Self {
+		Self {
+			pubkey_i: None,
+			sigs_i: [None; EPOCHS],
+			op_ji: [[None; SIZE]; EPOCHS],
+			c_v: [None; EPOCHS],
+			min_score: self.min_score,
+			aux_generator: None,
+			window_size: self.window_size,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Make the circuit config.
+	fn configure(meta: &mut ConstraintSystem<N>) -> Self::Config {
+		let (rns_base, rns_scalar) = GeneralEccChip::<E, N, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
+		let main_gate_config = MainGate::<N>::configure(meta);
+		let mut overflow_bit_lengths: Vec<usize> = vec![];
+		overflow_bit_lengths.extend(rns_base.overflow_lengths());
+		overflow_bit_lengths.extend(rns_scalar.overflow_lengths());
+		let range_config = RangeChip::<N>::configure(meta, &main_gate_config, overflow_bit_lengths);
+		EigenTrustConfig {
+			main_gate_config,
+			range_config,
+		}
+	}
+
+	/// Synthesize the circuit.
+	fn synthesize(
+		&self,
+		config: Self::Config,
+		mut layouter: impl Layouter<N>,
+	) -> Result<(), Error> {
+		let mut ecc_chip = GeneralEccChip::<E, N, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::new(
+			EccConfig::new(config.range_config.clone(), config.main_gate_config.clone()),
+		);
+		let scalar_chip = ecc_chip.scalar_field_chip();
+		let main_gate = MainGate::<N>::new(config.main_gate_config.clone());
+
+		// Set up the Ecc chip
+		layouter.assign_region(
+			|| "assign_aux",
+			|mut region| {
+				let offset = &mut 0;
+				let ctx = &mut RegionCtx::new(&mut region, offset);
+
+				ecc_chip.assign_aux_generator(ctx, self.aux_generator)?;
+				ecc_chip.assign_aux(ctx, self.window_size, 1)?;
+				Ok(())
+			},
+		)?;
+
+		// Calculate the opinions towards peer v, for every epoch.
+		let ops_v = layouter.assign_region(
+			|| "t_i",
+			|mut region| {
+				let position = &mut 0;
+				let ctx = &mut RegionCtx::new(&mut region, position);
+
+				let min_score = main_gate.assign_constant(ctx, self.min_score)?;
+				let mut ops_v = Vec::new();
+				for epoch in 0..EPOCHS {
+					let assigned_op_jis = self.op_ji[epoch]
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					let assigned_c_v =
+						main_gate.assign_value(ctx, &UnassignedValue::from(self.c_v[epoch]))?;
+
+					// t_i = min_score + op_1i + ... + op_ni
+					let mut t_i = min_score.clone();
+					for op_ji in &assigned_op_jis {
+						t_i = main_gate.add(ctx, &t_i, op_ji)?;
+					}
+					// op_v = t_i * c_v
+					ops_v.push(main_gate.mul(ctx, &t_i, &assigned_c_v)?);
+				}
+
+				Ok(ops_v)
+			},
+		)?;
+
+		let ecdsa_chip = EcdsaChip::new(ecc_chip.clone());
+
+		// Verify all the signatures against the same public key.
+		let (sigs, pk) = layouter.assign_region(
+			|| "sigs_i_verify",
+			|mut region| {
+				let offset = &mut 0;
+				let ctx = &mut RegionCtx::new(&mut region, offset);
+
+				let pk_in_circuit = ecc_chip.assign_point(ctx, self.pubkey_i)?;
+				let assigned_pk = AssignedPublicKey {
+					point: pk_in_circuit.clone(),
+				};
+
+				let mut sigs = Vec::new();
+				for sig_i in self.sigs_i {
+					let unassigned_r = ecc_chip.new_unassigned_scalar(sig_i.map(|s| s.r));
+					let unassigned_s = ecc_chip.new_unassigned_scalar(sig_i.map(|s| s.s));
+					let unassigned_m_hash = ecc_chip.new_unassigned_scalar(sig_i.map(|s| s.m_hash));
+
+					let assigned_r =
+						scalar_chip.assign_integer(ctx, unassigned_r, Range::Remainder)?;
+					let assigned_s =
+						scalar_chip.assign_integer(ctx, unassigned_s, Range::Remainder)?;
+					let assigned_m_hash =
+						scalar_chip.assign_integer(ctx, unassigned_m_hash, Range::Remainder)?;
+
+					let sig = AssignedEcdsaSig {
+						r: assigned_r.clone(),
+						s: assigned_s.clone(),
+					};
+					ecdsa_chip.verify(ctx, &sig, &assigned_pk, &assigned_m_hash)?;
+
+					sigs.push((assigned_r, assigned_s, assigned_m_hash));
+				}
+
+				Ok((sigs, pk_in_circuit))
+			},
+		)?;
+
+		config.config_range(&mut layouter)?;
+
+		// Constrain the public key, followed by the values of every epoch.
+		main_gate.expose_public(layouter.namespace(|| "pk_x"), pk.get_x().native(), 0)?;
+		main_gate.expose_public(layouter.namespace(|| "pk_y"), pk.get_y().native(), 1)?;
+		for (epoch, (op_v, (r, s, m_hash))) in ops_v.into_iter().zip(sigs).enumerate() {
+			let row = 2 + epoch * EPOCH_PUBLIC_INPUTS;
+			main_gate.expose_public(layouter.namespace(|| "op_v"), op_v, row)?;
+			main_gate.expose_public(layouter.namespace(|| "r"), r.native(), row + 1)?;
+			main_gate.expose_public(layouter.namespace(|| "s"), s.native(), row + 2)?;
+			main_gate.expose_public(layouter.namespace(|| "m_hash"), m_hash.native(), row + 3)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		ecdsa::{generate_signature, Keypair},
+		gadgets::to_native,
+		test_utils::{is_satisfied, mock_prove},
+	};
+	use halo2wrong::curves::{
+		bn256::Fr,
+		group::{Curve, Group},
+		secp256k1::{Fq, Secp256k1Affine as Secp256},
+	};
+	use rand::thread_rng;
+
+	const SIZE: usize = 4;
+	const EPOCHS: usize = 2;
+
+	#[test]
+	fn test_multi_epoch_verify() {
+		let k = 19;
+		let mut rng = thread_rng();
+
+		let pair_i = Keypair::<Secp256>::new(&mut rng);
+		let pubkey_i = pair_i.public().to_owned();
+		let sigs_i = [1u128, 2].map(|epoch| {
+			let m_hash = Fq::from_u128(epoch);
+			generate_signature(pair_i, m_hash, &mut rng).unwrap()
+		});
+
+		let op_ji = [[1u128; SIZE], [2; SIZE]].map(|ops| ops.map(Fr::from_u128));
+		let c_v = [1u128, 3].map(Fr::from_u128);
+		let min_score = Fr::from_u128(1);
+
+		let aux_generator = <Secp256 as CurveAffine>::CurveExt::random(&mut rng).to_affine();
+		let circuit = MultiEpochCircuit::<_, _, SIZE, EPOCHS>::new(
+			pubkey_i,
+			sigs_i,
+			op_ji,
+			c_v,
+			min_score,
+			aux_generator,
+		);
+
+		let ops_v = [Fr::from_u128(5), Fr::from_u128(27)];
+		let mut pub_ins: Vec<Fr> = vec![to_native(&pubkey_i.x), to_native(&pubkey_i.y)];
+		for (op_v, sig) in ops_v.iter().zip(sigs_i) {
+			pub_ins.extend([
+				*op_v,
+				to_native(&sig.r),
+				to_native(&sig.s),
+				to_native(&sig.m_hash),
+			]);
+		}
+
+		assert_eq!(mock_prove(k, &circuit, pub_ins.clone()), Ok(()));
+
+		// Swapping the opinions of the epochs breaks the proof.
+		pub_ins.swap(2, 2 + EPOCH_PUBLIC_INPUTS);
+		assert!(!is_satisfied(k, &circuit, pub_ins));
+	}
+}
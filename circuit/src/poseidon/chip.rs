@@ -0,0 +1,270 @@
+//! In-circuit Poseidon permutation, built on top of the MainGate.
+
+use super::params::RoundParams;
+use halo2wrong::halo2::{arithmetic::FieldExt, plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+use std::marker::PhantomData;
+
+/// Chip for constraining the Poseidon permutation inside a circuit.
+pub struct PoseidonChip<F: FieldExt, const WIDTH: usize, P>
+where
+	P: RoundParams<F, WIDTH>,
+{
+	main_gate: MainGate<F>,
+	_params: PhantomData<P>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, P> PoseidonChip<F, WIDTH, P>
+where
+	P: RoundParams<F, WIDTH>,
+{
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		PoseidonChip {
+			main_gate: MainGate::new(main_gate_config),
+			_params: PhantomData,
+		}
+	}
+
+	/// Apply round constants to the state.
+	fn apply_round_constants(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		state: &[AssignedValue<F>; WIDTH],
+		round_consts: &[F; WIDTH],
+	) -> Result<[AssignedValue<F>; WIDTH], Error> {
+		let mut next_state = state.clone();
+		for i in 0..WIDTH {
+			let round_const = self.main_gate.assign_constant(ctx, round_consts[i])?;
+			next_state[i] = self.main_gate.add(ctx, &state[i], &round_const)?;
+		}
+		Ok(next_state)
+	}
+
+	/// Apply mds matrix to the state.
+	fn apply_mds(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		state: &[AssignedValue<F>; WIDTH],
+		mds: &[[F; WIDTH]; WIDTH],
+	) -> Result<[AssignedValue<F>; WIDTH], Error> {
+		let mut new_state = state.clone();
+		for i in 0..WIDTH {
+			let mut sum = self.main_gate.assign_constant(ctx, F::zero())?;
+			for j in 0..WIDTH {
+				let mds_ij = self.main_gate.assign_constant(ctx, mds[i][j])?;
+				let m_product = self.main_gate.mul(ctx, &state[j], &mds_ij)?;
+				sum = self.main_gate.add(ctx, &sum, &m_product)?;
+			}
+			new_state[i] = sum;
+		}
+		Ok(new_state)
+	}
+
+	/// Constrain the Poseidon permutation of the inputs, and return the
+	/// resulting state.
+	pub fn permute(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		inputs: &[AssignedValue<F>; WIDTH],
+	) -> Result<[AssignedValue<F>; WIDTH], Error> {
+		let full_rounds = P::full_rounds();
+		let half_full_rounds = full_rounds / 2;
+		let partial_rounds = P::partial_rounds();
+		let mds = P::mds();
+		let round_constants = P::round_constants();
+		let total_count = P::round_constants_count();
+
+		let first_round_end = half_full_rounds * WIDTH;
+		let first_round_constants = &round_constants[0..first_round_end];
+
+		let second_round_end = first_round_end + partial_rounds * WIDTH;
+		let second_round_constants = &round_constants[first_round_end..second_round_end];
+
+		let third_round_constants = &round_constants[second_round_end..total_count];
+
+		let mut state = inputs.clone();
+		for round in 0..half_full_rounds {
+			let round_consts = P::load_round_constants(round, first_round_constants);
+			state = self.apply_round_constants(ctx, &state, &round_consts)?;
+			for i in 0..WIDTH {
+				state[i] = P::sbox_asgn(&self.main_gate, ctx, &state[i])?;
+			}
+			state = self.apply_mds(ctx, &state, &mds)?;
+		}
+
+		for round in 0..partial_rounds {
+			let round_consts = P::load_round_constants(round, second_round_constants);
+			state = self.apply_round_constants(ctx, &state, &round_consts)?;
+			state[0] = P::sbox_asgn(&self.main_gate, ctx, &state[0])?;
+			state = self.apply_mds(ctx, &state, &mds)?;
+		}
+
+		for round in 0..half_full_rounds {
+			let round_consts = P::load_round_constants(round, third_round_constants);
+			state = self.apply_round_constants(ctx, &state, &round_consts)?;
+			for i in 0..WIDTH {
+				state[i] = P::sbox_asgn(&self.main_gate, ctx, &state[i])?;
+			}
+			state = self.apply_mds(ctx, &state, &mds)?;
+		}
+
+		Ok(state)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::poseidon::{
+		params::{hex_to_field, Params3x3Bn254, Params5x5Bn254},
+		Poseidon,
+	};
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+	use maingate::UnassignedValue;
+
+	type TestPoseidonChip = PoseidonChip<Fr, 5, Params5x5Bn254>;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		inputs: [Option<Fr>; 5],
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self { inputs: [None; 5] }
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let poseidon = TestPoseidonChip::new(config);
+
+			let outputs = layouter.assign_region(
+				|| "poseidon",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let inputs = self
+						.inputs
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					poseidon.permute(ctx, &inputs)
+				},
+			)?;
+
+			for i in 0..5 {
+				main_gate.expose_public(layouter.namespace(|| "out"), outputs[i].clone(), i)?;
+			}
+
+			Ok(())
+		}
+	}
+
+	#[derive(Clone)]
+	struct TestCircuit3x3 {
+		inputs: [Option<Fr>; 3],
+	}
+
+	impl Circuit<Fr> for TestCircuit3x3 {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self { inputs: [None; 3] }
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let poseidon = PoseidonChip::<Fr, 3, Params3x3Bn254>::new(config);
+
+			let outputs = layouter.assign_region(
+				|| "poseidon",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let inputs = self
+						.inputs
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					poseidon.permute(ctx, &inputs)
+				},
+			)?;
+
+			for i in 0..3 {
+				main_gate.expose_public(layouter.namespace(|| "out"), outputs[i].clone(), i)?;
+			}
+
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_poseidon_chip_5x5() {
+		let inputs: [Fr; 5] = [0, 1, 2, 3, 4].map(Fr::from_u128);
+		let outputs = Poseidon::<Fr, 5, Params5x5Bn254>::new(inputs).permute();
+		assert_eq!(
+			outputs[0],
+			hex_to_field("0x299c867db6c1fdd79dcefa40e4510b9837e60ebb1ce0663dbaa525df65250465")
+		);
+
+		let circuit = TestCircuit {
+			inputs: inputs.map(Some),
+		};
+		let k = 14;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![outputs.to_vec()]).unwrap();
+		assert_eq!(prover.verify(), Ok(()));
+	}
+
+	#[test]
+	fn test_poseidon_chip_wrong_output() {
+		let inputs: [Fr; 5] = [0, 1, 2, 3, 4].map(Fr::from_u128);
+		let mut outputs = Poseidon::<Fr, 5, Params5x5Bn254>::new(inputs).permute();
+		outputs[0] += Fr::one();
+
+		let circuit = TestCircuit {
+			inputs: inputs.map(Some),
+		};
+		let k = 14;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![outputs.to_vec()]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+
+	#[test]
+	fn test_poseidon_chip_3x3() {
+		let inputs: [Fr; 3] = [0, 1, 2].map(Fr::from_u128);
+		let outputs = Poseidon::<Fr, 3, Params3x3Bn254>::new(inputs).permute();
+
+		let circuit = TestCircuit3x3 {
+			inputs: inputs.map(Some),
+		};
+		let k = 13;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![outputs.to_vec()]).unwrap();
+		assert_eq!(prover.verify(), Ok(()));
+	}
+}
@@ -1,5 +1,6 @@
 //! Poseidon hash function implementation.
 
+pub mod chip;
 pub mod params;
 
 use halo2wrong::halo2::arithmetic::FieldExt;
@@ -104,7 +105,7 @@ where
 #[cfg(test)]
 mod test {
 	use super::*;
-	use crate::poseidon::params::{hex_to_field, Params5x5Bn254};
+	use crate::poseidon::params::{hex_to_field, Params3x3Bn254, Params5x5Bn254, Params9x9Bn254};
 	use halo2wrong::curves::bn256::Fr;
 
 	type TestPoseidon = Poseidon<Fr, 5, Params5x5Bn254>;
@@ -135,4 +136,42 @@ mod test {
 
 		assert_eq!(out, outputs);
 	}
+
+	#[test]
+	fn test_native_poseidon_3x3() {
+		let inputs: [Fr; 3] = [0, 1, 2].map(Fr::from_u128);
+
+		let outputs: [Fr; 3] = [
+			"0x115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a",
+			"0x0fca49b798923ab0239de1c9e7a4a9a2210312b6a2f616d18b5a87f9b628ae29",
+			"0x0e7ae82e40091e63cbd4f16a6d16310b3729d4b6e138fcf54110e2867045a30c",
+		]
+		.map(|n| hex_to_field(n));
+
+		let poseidon = Poseidon::<Fr, 3, Params3x3Bn254>::new(inputs);
+
+		assert_eq!(poseidon.permute(), outputs);
+	}
+
+	#[test]
+	fn test_native_poseidon_9x9() {
+		let inputs: [Fr; 9] = [0, 1, 2, 3, 4, 5, 6, 7, 8].map(Fr::from_u128);
+
+		let outputs: [Fr; 9] = [
+			"0x2921ab9bd0140cbc98e40395c0fefb40337a4d54fbbecd9a4d43b3d8d0c4d8d1",
+			"0x0f4bef710c430ccf4b066245ebda76ec4c571816b5766bffbe64dfcef83ad9ee",
+			"0x29ae93298f7f5ac359eed2a1b4fe0b8605e6caf86a2952ddc353edee612f431b",
+			"0x0100596375fcd85a397fabfef5af0a64caac9fa4206e3825651c96c00221ad89",
+			"0x0f007579146e6d18785d8edd07bfc2ff49ff194bb40da0bfad0fd77239d41104",
+			"0x21b31b3be4a08e10a24e2d327ea64077fb18dc9428fa04e30faf543a5cad6c41",
+			"0x032589fca1f1eb8f5c617c7256ae25221ed6cb7272b9ce1ff0f4cfb89050e601",
+			"0x1e51f0950c8b317a62bb43b082347bdb2b83deb856dabc1cdbbb7569c0e81955",
+			"0x2c8e23a3569963447e55619f1d1462f63ea2e40d3d405c18bbf394f13c253749",
+		]
+		.map(|n| hex_to_field(n));
+
+		let poseidon = Poseidon::<Fr, 9, Params9x9Bn254>::new(inputs);
+
+		assert_eq!(poseidon.permute(), outputs);
+	}
 }
@@ -0,0 +1,216 @@
+//! In-circuit Rescue-Prime permutation, built on top of the MainGate.
+
+use super::params::RescuePrimeParams;
+use halo2wrong::halo2::{
+	arithmetic::{Field, FieldExt},
+	plonk::Error,
+};
+use maingate::{
+	AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx, UnassignedValue,
+};
+use std::marker::PhantomData;
+
+/// Chip for constraining the Rescue-Prime permutation inside a circuit.
+pub struct RescuePrimeChip<F: FieldExt, const WIDTH: usize, P>
+where
+	P: RescuePrimeParams<F, WIDTH>,
+{
+	main_gate: MainGate<F>,
+	_params: PhantomData<P>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, P> RescuePrimeChip<F, WIDTH, P>
+where
+	P: RescuePrimeParams<F, WIDTH>,
+{
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		RescuePrimeChip {
+			main_gate: MainGate::new(main_gate_config),
+			_params: PhantomData,
+		}
+	}
+
+	/// Apply round constants to the state.
+	fn apply_round_constants(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		state: &[AssignedValue<F>; WIDTH],
+		round_consts: &[F; WIDTH],
+	) -> Result<[AssignedValue<F>; WIDTH], Error> {
+		let mut next_state = state.clone();
+		for i in 0..WIDTH {
+			let round_const = self.main_gate.assign_constant(ctx, round_consts[i])?;
+			next_state[i] = self.main_gate.add(ctx, &state[i], &round_const)?;
+		}
+		Ok(next_state)
+	}
+
+	/// Apply mds matrix to the state.
+	fn apply_mds(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		state: &[AssignedValue<F>; WIDTH],
+		mds: &[[F; WIDTH]; WIDTH],
+	) -> Result<[AssignedValue<F>; WIDTH], Error> {
+		let mut new_state = state.clone();
+		for i in 0..WIDTH {
+			let mut sum = self.main_gate.assign_constant(ctx, F::zero())?;
+			for j in 0..WIDTH {
+				let mds_ij = self.main_gate.assign_constant(ctx, mds[i][j])?;
+				let m_product = self.main_gate.mul(ctx, &state[j], &mds_ij)?;
+				sum = self.main_gate.add(ctx, &sum, &m_product)?;
+			}
+			new_state[i] = sum;
+		}
+		Ok(new_state)
+	}
+
+	/// Constrain `x^5`.
+	fn sbox(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let x2 = self.main_gate.mul(ctx, x, x)?;
+		let x4 = self.main_gate.mul(ctx, &x2, &x2)?;
+		self.main_gate.mul(ctx, &x4, x)
+	}
+
+	/// Constrain the inverse S-box. The root is computed outside of the
+	/// circuit, and constrained by raising it back to the 5th power, which is
+	/// much cheaper than the exponentiation itself.
+	fn inverse_sbox(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		x: &AssignedValue<F>,
+	) -> Result<AssignedValue<F>, Error> {
+		let alpha_inv = P::alpha_inv();
+		let root = x.value().map(|x| x.pow_vartime(&alpha_inv));
+		let root = self
+			.main_gate
+			.assign_value(ctx, &UnassignedValue::from(root))?;
+		let root_5 = self.sbox(ctx, &root)?;
+		self.main_gate.assert_equal(ctx, &root_5, x)?;
+		Ok(root)
+	}
+
+	/// Constrain the Rescue-Prime permutation of the inputs, and return the
+	/// resulting state.
+	pub fn permute(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, F>,
+		inputs: &[AssignedValue<F>; WIDTH],
+	) -> Result<[AssignedValue<F>; WIDTH], Error> {
+		let mds = P::mds();
+		let round_constants = P::round_constants();
+
+		let mut state = inputs.clone();
+		for round in 0..P::rounds() {
+			for i in 0..WIDTH {
+				state[i] = self.sbox(ctx, &state[i])?;
+			}
+			state = self.apply_mds(ctx, &state, &mds)?;
+			let round_consts = P::load_round_constants(2 * round, &round_constants);
+			state = self.apply_round_constants(ctx, &state, &round_consts)?;
+
+			for i in 0..WIDTH {
+				state[i] = self.inverse_sbox(ctx, &state[i])?;
+			}
+			state = self.apply_mds(ctx, &state, &mds)?;
+			let round_consts = P::load_round_constants(2 * round + 1, &round_constants);
+			state = self.apply_round_constants(ctx, &state, &round_consts)?;
+		}
+
+		Ok(state)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::rescue_prime::{params::RescuePrime5Bn254, RescuePrime};
+	use halo2wrong::{
+		curves::bn256::Fr,
+		halo2::{
+			circuit::{Layouter, SimpleFloorPlanner},
+			dev::MockProver,
+			plonk::{Circuit, ConstraintSystem},
+		},
+	};
+
+	type TestRescuePrimeChip = RescuePrimeChip<Fr, 5, RescuePrime5Bn254>;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		inputs: [Option<Fr>; 5],
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self { inputs: [None; 5] }
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let rescue = TestRescuePrimeChip::new(config);
+
+			let outputs = layouter.assign_region(
+				|| "rescue_prime",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+					let inputs = self
+						.inputs
+						.map(UnassignedValue::from)
+						.try_map(|val| main_gate.assign_value(ctx, &val))?;
+					rescue.permute(ctx, &inputs)
+				},
+			)?;
+
+			for i in 0..5 {
+				main_gate.expose_public(layouter.namespace(|| "out"), outputs[i].clone(), i)?;
+			}
+
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_rescue_prime_chip_5() {
+		let inputs: [Fr; 5] = [0, 1, 2, 3, 4].map(Fr::from_u128);
+		let outputs = RescuePrime::<Fr, 5, RescuePrime5Bn254>::new(inputs).permute();
+
+		let circuit = TestCircuit {
+			inputs: inputs.map(Some),
+		};
+		let k = 13;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![outputs.to_vec()]).unwrap();
+		assert_eq!(prover.verify(), Ok(()));
+	}
+
+	#[test]
+	fn test_rescue_prime_chip_wrong_output() {
+		let inputs: [Fr; 5] = [0, 1, 2, 3, 4].map(Fr::from_u128);
+		let mut outputs = RescuePrime::<Fr, 5, RescuePrime5Bn254>::new(inputs).permute();
+		outputs[0] += Fr::one();
+
+		let circuit = TestCircuit {
+			inputs: inputs.map(Some),
+		};
+		let k = 13;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![outputs.to_vec()]).unwrap();
+		assert!(prover.verify().is_err());
+	}
+}
@@ -0,0 +1,110 @@
+//! Rescue-Prime hash function implementation.
+
+pub mod chip;
+pub mod params;
+
+use halo2wrong::halo2::arithmetic::{Field, FieldExt};
+use params::RescuePrimeParams;
+use std::marker::PhantomData;
+
+/// Rescue-Prime struct with generic field type and width of the state.
+pub struct RescuePrime<F: FieldExt, const WIDTH: usize, P>
+where
+	P: RescuePrimeParams<F, WIDTH>,
+{
+	/// Input to the hash function.
+	inputs: [F; WIDTH],
+	_params: PhantomData<P>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, P> RescuePrime<F, WIDTH, P>
+where
+	P: RescuePrimeParams<F, WIDTH>,
+{
+	/// Rescue-Prime constructor.
+	pub fn new(inputs: [F; WIDTH]) -> Self {
+		RescuePrime {
+			inputs,
+			_params: PhantomData,
+		}
+	}
+
+	/// Apply round constants to the state.
+	fn apply_round_constants(state: &[F; WIDTH], round_consts: &[F; WIDTH]) -> [F; WIDTH] {
+		let mut next_state = [F::zero(); WIDTH];
+		for i in 0..WIDTH {
+			next_state[i] = state[i] + round_consts[i];
+		}
+		next_state
+	}
+
+	/// Apply mds matrix to the state.
+	fn apply_mds(state: &[F; WIDTH], mds: &[[F; WIDTH]; WIDTH]) -> [F; WIDTH] {
+		let mut new_state = [F::zero(); WIDTH];
+		for i in 0..WIDTH {
+			for j in 0..WIDTH {
+				new_state[i] += state[j] * mds[i][j];
+			}
+		}
+		new_state
+	}
+
+	/// Run the Rescue-Prime permutation.
+	pub fn permute(&self) -> [F; WIDTH] {
+		let mds = P::mds();
+		let round_constants = P::round_constants();
+		let alpha_inv = P::alpha_inv();
+
+		let mut state = self.inputs;
+		for round in 0..P::rounds() {
+			state = state.map(|x| x.pow_vartime(&[5]));
+			state = Self::apply_mds(&state, &mds);
+			let round_consts = P::load_round_constants(2 * round, &round_constants);
+			state = Self::apply_round_constants(&state, &round_consts);
+
+			state = state.map(|x| x.pow_vartime(&alpha_inv));
+			state = Self::apply_mds(&state, &mds);
+			let round_consts = P::load_round_constants(2 * round + 1, &round_constants);
+			state = Self::apply_round_constants(&state, &round_consts);
+		}
+
+		state
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::poseidon::params::hex_to_field;
+	use halo2wrong::curves::bn256::Fr;
+	use params::RescuePrime5Bn254;
+
+	type TestRescuePrime = RescuePrime<Fr, 5, RescuePrime5Bn254>;
+
+	#[test]
+	fn test_native_rescue_prime_5() {
+		let inputs: [Fr; 5] = [0, 1, 2, 3, 4].map(Fr::from_u128);
+
+		// Computed with the reference permutation, using the parameters
+		// from `scripts/rescue_prime_params.py`.
+		let outputs: [Fr; 5] = [
+			"0x2cd8fc0509157f1faf9f730aeb40f6eb745872e281675e39a54b12f60b504dae",
+			"0x2888c57a46a166001bd4d59c6ee0996a952fd7321fd1f79dacb31b4729977743",
+			"0x1fd755076447d23085a92725eb0e9009678be249c091feb5a534fef9a6424402",
+			"0x2ee747db42419d1dff124db129c597a15857404c519a595ade6ab8c83fe3fa8f",
+			"0x0c3600f7a922d59314a27b26130c152b346c1f4372e9deef67acd6be36b5cd3d",
+		]
+		.map(|n| hex_to_field(n));
+
+		let rescue = TestRescuePrime::new(inputs);
+
+		assert_eq!(rescue.permute(), outputs);
+	}
+
+	#[test]
+	fn test_inverse_sbox() {
+		let x = Fr::from_u128(123456789);
+		let y = x.pow_vartime(&RescuePrime5Bn254::alpha_inv());
+		assert_eq!(y.pow_vartime(&[5]), x);
+	}
+}
@@ -0,0 +1,196 @@
+//! A module for defining pre-computed Rescue-Prime parameters. The parameters
+//! are generated with `scripts/rescue_prime_params.py`.
+
+use crate::poseidon::params::hex_to_field;
+use halo2wrong::{curves::bn256::Fr, halo2::arithmetic::FieldExt};
+
+/// A trait for implementing Rescue-Prime parameters for specific curves and
+/// widths. The S-box is `x^5`, and its inverse is `x^ALPHA_INV`.
+pub trait RescuePrimeParams<F: FieldExt, const WIDTH: usize> {
+	/// Get the number of rounds.
+	fn rounds() -> usize;
+	/// Get the inverse of 5, modulo the order of the multiplicative group,
+	/// as little-endian limbs.
+	fn alpha_inv() -> [u64; 4];
+	/// Get the round constants, two sets per round.
+	fn round_constants() -> Vec<F> {
+		let round_constants: Vec<F> = Self::round_constants_raw()
+			.iter()
+			.map(|x| hex_to_field(x))
+			.collect();
+		assert_eq!(round_constants.len(), 2 * Self::rounds() * WIDTH);
+		round_constants
+	}
+	/// Load the round constants with the given index into a buffer with a
+	/// size of the state.
+	fn load_round_constants(index: usize, round_consts: &[F]) -> [F; WIDTH] {
+		let mut result = [F::zero(); WIDTH];
+		for i in 0..WIDTH {
+			result[i] = round_consts[index * WIDTH + i];
+		}
+		result
+	}
+	/// Get the MDS matrix.
+	fn mds() -> [[F; WIDTH]; WIDTH] {
+		Self::mds_raw().map(|row| row.map(|item| hex_to_field(item)))
+	}
+	/// Get the raw round constants -- the hex representation.
+	fn round_constants_raw() -> Vec<&'static str>;
+	/// Get the mds matrix raw -- the hex representation.
+	fn mds_raw() -> [[&'static str; WIDTH]; WIDTH];
+}
+
+/// The parameters for width = 5, capacity = 1, curve = Bn254, at the 128-bit
+/// security level.
+pub struct RescuePrime5Bn254;
+
+impl RescuePrimeParams<Fr, 5> for RescuePrime5Bn254 {
+	fn rounds() -> usize {
+		9
+	}
+
+	fn alpha_inv() -> [u64; 4] {
+		[
+			0xcfe7f7a98ccccccd,
+			0x535cb9d394945a0d,
+			0x93736af8679aad17,
+			0x26b6a528b427b354,
+		]
+	}
+
+	fn round_constants_raw() -> Vec<&'static str> {
+		[
+			"0x17da3d2b361cb0f798b206de10b9863774ab15469fdac3e0752dc5a00a341b28",
+			"0x1eb22ca4a666148be7e9af480cc37a7a2c04a42e2a0c09909bafcced16b473ea",
+			"0x23d6d9dddbb5be55bb3ba56db362ffb8d6dfa759a5b67d8aadfd4459324cc205",
+			"0x177d37af3422963cd4000ac910618108d084a860a8f7ca4ae624de507722c5e9",
+			"0x068d3041c72a1898a1a03403f384364952668c5fa60df85c8536f5ecd0732418",
+			"0x1bff6adc782502fbabfe0e8d6e3a6384cbb3bc9fc6d19978b37b89f2216e34af",
+			"0x12d558d4e0b7a866370e55d218815d5ddc2400915756a957f8d30c8f10b3d7a3",
+			"0x1101e4d506082561f35818270f91be4d8c5360599a496bb30e4cd5a746f1388f",
+			"0x2e31acc401b7edcbc0a88561081d59a0f853bd09df09f82160f32207ebab3809",
+			"0x2ee6e1049639847f31ecbed4b4ae3af7942e3527bc110711c5f838b1f6621629",
+			"0x2bd459ad4cb8d3b9f3a971e43583e6d0ca6e453451d4ab173a5f588bf76e5a5a",
+			"0x2cd01e3dc70e39ad2eb24292768bd7a2f9d26e524a1b974e29ead6dec80b905c",
+			"0x25e0a3f20c99442d2a7f890c9925b215541df91ab0c6f5b24737e2bf239510ba",
+			"0x24b4129dad2e8dd3db19737f1878cce4ed41c018746a9f32c4796a79e3bd8e82",
+			"0x2abcae4ee4a54bb69b8514ed11605b68b25b2d3f823807d2d1e4a464f9a6d4b7",
+			"0x09ffbdae6d2c7eac21b6b259a558b1efac61f64a43b23741483f110fe03de2d7",
+			"0x2265b479eeb0fe8533d84e0ba17ed8f387d213c111808eaed90528da107f7e06",
+			"0x2245e3a2b832bc9a6e6a69854bf512d5f816c877c8fdd893e00f8fc4407404f0",
+			"0x177fd7f2765716842928b8ed06bbaf8daa38772ba6f504db61768953cb89ff72",
+			"0x25abed20fb0838654f16830cfc3e80bbd877ff4382c24b080f5374956be78108",
+			"0x2d6ec57fc9cf1db7cf4bba6268e1e64a311f8a348639abc61736ad5d61477c5f",
+			"0x16ff349b9a1f2f44ad55fc37c3de12add23dc1e08d086867a34057eee4ee3255",
+			"0x033a4584ab5512f10060d1afc702c089a37d3493065b6ba1d41cef79033d375d",
+			"0x1be256ec7458c687c68851695b903986b63c683bdd8ce81555020b5e0a2d247a",
+			"0x123fc05e88543c929d924bdceba14c8b90ef73fd336375d53c6fd1e59c0ddb61",
+			"0x1ed7e7105a33e53ab1f67782ee7f7c7005039b80980eaa75c19066281198d7ef",
+			"0x1b7963cec2138cac8d6c89d9476fb40a9fe7787dc38001b7fe37f1a63886367f",
+			"0x190cc6460ce712bd32a484b741f05c63fbcbf6de54d84ac11b0a2dd181ae9d0a",
+			"0x21a168bad5da0cd998cb6b2b67053e8c9c7f50993d9f54c20c97acc46f6e7f44",
+			"0x084c9cdf8e2b9a80ca4249bc205716bdc84625ea74456d48cb8a9886e6086348",
+			"0x2cbbbb5846f0c24091c44cd5f5d0112b8d4e54c4b66f4411c7e01552ad729284",
+			"0x111b991907dd83bb4b827a549ce1a044048282a3dfc013a89bd701ee291c3d5c",
+			"0x0e6ec890564868a6bce2e4e336af7bc0e29188813a16eae21654e7174b63f7bf",
+			"0x0970ef930aae4dd74283b212d6301d670af3712186519f9863d9be410d05aab5",
+			"0x2da4823c251ce6173b8cf422f38f9dc0bddce401ea9bc0fad7305b100e347834",
+			"0x0e1f08603ca71a70cbfd4640fb4ad971a76917e9d930d5825e94cb5bf60c8c89",
+			"0x2851c946728552a12b8cec2b6a3f26f465684713a4b8d2b893de09be937a8c43",
+			"0x22a34ac8c8ebba8523ecb06adfbcd4697c0f1f3de6820f31c11d0bb1133c1ce0",
+			"0x165b7a1ef8d794cb6a7ec4e177970f2f719568591ed627155b4b3f6abf5b8d9d",
+			"0x23e150a37baae4a1ee2d1ed2965131b8e26fbfc2627b6baf2dc8a43129cabb32",
+			"0x11a84312f38cf77be11f43ad8593a946f24d4e255b5641f218466c20e277a74b",
+			"0x284126f8ee35fa9a911a43c31688f2965ee49fc1bec3960bdc5de4bbd2c12c4b",
+			"0x24e8495eba647903b892340557405933c16833f55d32c06fafb62f9cfc9e2f00",
+			"0x06ebc1692538e3cbef4029eb7185fc1da78925a1e7f37569f5bedcafbd53d7fc",
+			"0x2f9a140b6105bd87c9aa6261d1abd09370690f4fa9903c6bb154be91233160a4",
+			"0x000eefedab129fdefb1e02601c4c77f25d20e7b913b0d530ddcc2fa9feba5be4",
+			"0x2a11200fc92b1cfb16a3d9a68d03a0cd4b137c2d53cca49e500ef333a67aaeee",
+			"0x2a35cdda9bb61e668cede46351eeb6d64881723ca872f5c0dbb0c904c8f7cbc2",
+			"0x16a2ebb7ad586a5cd3bb0a3e9d078a57539619cc1f9d61e0f791658541993813",
+			"0x2912934e0bdc8e965eb3528bea98505de3614491c2e97e6c486b8fbf45d33e9a",
+			"0x2d07a10320b3f8be3520624c0cf67d2637bfe54c406e09de2bd1f0f60a0cc3f0",
+			"0x09d5e7c5e1e6f4fd7f79516a456a2ec3df17f56651598cf1dd7b5cba68197068",
+			"0x0178d98c4a6c3c2eb99ba338f0caa41583128c9620377640e526564d62d090ca",
+			"0x0610c1181ef7ff7b0814c9433550b9705a1e836ea9fd5bd32dbe91982f3b89a0",
+			"0x09377de6eccdec78fa68974b15feb3a1f0bd7b6cdceee348896d6c533cfaa6c7",
+			"0x1bfbce8f9bbf17badca95ba94290cf4452be414c1c4d58a11a59823c23e456d8",
+			"0x0a013feeba2a27e2eb854b5815d7715cbf6b45c6b625e3662806260318fc247d",
+			"0x18a436719f35ec05ca6c9b7bbd622a81ba8f107fbf54a902b9bd0b9f864b463f",
+			"0x20ee0b9549bb2e5d63de27a50bc9b6278f1027ddec0004ad778b558baddbd30c",
+			"0x0c27aa5ef7286b70e536bc8beea805aafb5f0768c7839c415acf99066f682ea4",
+			"0x28c0dfcc390157eea4ca4116acae55084ce061d21cda3844f396b18fceaf7686",
+			"0x0693ce8454c51a634cf5efd18535d2aa1642d9fc9c7193204a905ee7db96a08d",
+			"0x17fce57d0dbc0d3518e1138d76696f78d5ce5320303cf1c6b2fca4665bd03374",
+			"0x2eb77c99e1658f9415afd57d5b6aaa9fa6696c696d114c9d7e6f0464c27ff5f4",
+			"0x266f9bd53b5a2d2812bcc810fbf6be9ba8751a46ee7436f9a63b70c12591a4e9",
+			"0x1f10f582f39d78b21d080135e4dfa2b4094929a6739cc135918f0b14ffadef27",
+			"0x01dda5cb44b14ef4fdce1efaf49b78a109f08565383fcd671fe39336e980147d",
+			"0x2e56f719efb57b67a7d35459211a888ffaa021d7f13aeee37ea328cfd44a3db9",
+			"0x04c9355e8fd216d6755a6c38e4a6a67879d2db87a8e2268fe7296c28949a285e",
+			"0x1f3f62e6aaf9972474ebd5eadf1ac71c796bd6a900ad033b6b5ef812cd2578b3",
+			"0x0bd4b76a83b61c499dab7c9996ec763565c62fa1408adea3ee31a0c65491b848",
+			"0x113326a470234f8657ff2ff1784aadc07887518fcd9f72766f0d17ede35da478",
+			"0x30321f50e804a0758a3bf61d0096c763eb040fcd91f52502724e2b5c41c1ec8d",
+			"0x15eb8e47d1b13f850fa9d95e992e1e591eccf46c91e3a0eb9f320d558ae6877d",
+			"0x21be537487693fc68617933057eb52ddd603e27ad1eea31e9b173ddbbce168e8",
+			"0x21b47fe71d3df0da8606757e83511a251d8340265a2fc2cedbf8c1b404bd9b70",
+			"0x1c68d5bdf878dc38f92a2af2d3ef2e0a4f08096c3ff90dcb617b55a2fd1f3ddb",
+			"0x074e5e6a0d9937d117307fc51bf5c42cacb7577dcc4b966a3f945be88ee82217",
+			"0x264c9b931dcf33fddbf4fe9aeaae7a7910aa1b965885b17689b6da90f1e0bb5c",
+			"0x09341a544aeae88a06e1aef383a21f56f200c76753d138a25d16d20a02bcc302",
+			"0x01d0cfe64af9a105a716cbdb1cadbf4f003335cef189db78a60bf966c636a819",
+			"0x1f0daa4ac243b09a74c1a08c19c325573539c1c961ff7dc4f6a419f5fb7fee6a",
+			"0x1b1b0a2f9feaf99d03908f6814e7c4a37f0eeba46f0463409725e24bb72d1ae5",
+			"0x277b7c924473fc400d608d46dad2fdc7d7536e1a9dfae2f7b59bb9a6363cfed5",
+			"0x057f9c986d9f8f26066552c2ef48e7ccb259c49a1cbb1238cc93f36542adce45",
+			"0x2a39c1ecc71c04a0969f8e954ac18eb270d6584c13b2298a93b10667fe123df2",
+			"0x18106476683f638596d5adeece8089e231c32349719daeca210ef459b2b2ea92",
+			"0x2a0310874fd0ce9f8298a4e1afdbfec5c810881e2fdc3dc0d7d0992dabd4288e",
+			"0x217d9f7ad3d96a041c127331abc9818ad218811c7ceb99f1af198767b09ba5d0",
+			"0x1eacbaf590deb53f07ffbdbf2bb74a5861249f824b3177d60438aa413dc8555f",
+		]
+		.to_vec()
+	}
+
+	fn mds_raw() -> [[&'static str; 5]; 5] {
+		[
+			[
+				"0x00000000000000000000000000000000000000000000000000000000009502f9",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593ef45cb8c",
+				"0x000000000000000000000000000000000000000000000000000000000026bb0a",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593effe7367",
+				"0x000000000000000000000000000000000000000000000000000000000000030d",
+			],
+			[
+				"0x00000000000000000000000000000000000000000000000000000001c69a11a5",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f591b882fa09",
+				"0x00000000000000000000000000000000000000000000000000000000756e690d",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593eb6cc939",
+				"0x000000000000000000000000000000000000000000000000000000000007c20f",
+			],
+			[
+				"0x0000000000000000000000000000000000000000000000000000048409cade97",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143e1eff120bd54cb",
+				"0x0000000000000000000000000000000000000000000000000000012a41cb839e",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f58860947808",
+				"0x000000000000000000000000000000000000000000000000000000001317d0fb",
+			],
+			[
+				"0x000000000000000000000000000000000000000000000000000b1d1563e33a23",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143d416e093175fe1",
+				"0x0000000000000000000000000000000000000000000000000002ddd95a94d798",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709143e1d929de3388a1",
+				"0x0000000000000000000000000000000000000000000000000000002eb03d05c6",
+			],
+			[
+				"0x0000000000000000000000000000000000000000000000001b2d1e4e41a42996",
+				"0x30644e72e131a029b85045b68181585d2833e84879b9709121f7753030f75ea6",
+				"0x000000000000000000000000000000000000000000000000070266eaf4153b9c",
+				"0x30644e72e131a029b85045b68181585d2833e84879b97091439c7eb8e0f1167d",
+				"0x00000000000000000000000000000000000000000000000000007205985e25ae",
+			],
+		]
+	}
+}
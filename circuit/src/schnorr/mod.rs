@@ -0,0 +1,183 @@
+//! Schnorr signature verification over the BabyJubJub curve, with Poseidon as
+//! the challenge hash. The signature carries the challenge instead of the
+//! nonce commitment, so the verifier only hashes the recovered commitment,
+//! and doesn't need to assign or check a point from the signer.
+//!
+//! Like the EdDSA gadget, it is not used for the opinions, which are still
+//! signed and proven with ECDSA. It is meant for the circuits built on top of
+//! this crate.
+
+pub mod native;
+
+use crate::{
+	eddsa::{native::SUBORDER_BITS, AssignedPoint, EddsaChip, HASH_BITS},
+	gadgets::bits::ToBitsChip,
+	poseidon::{chip::PoseidonChip, params::Params5x5Bn254},
+};
+use halo2wrong::{curves::bn256::Fr, halo2::plonk::Error};
+use maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RegionCtx};
+
+/// Chip for verifying Schnorr signatures inside a circuit.
+pub struct SchnorrChip {
+	main_gate: MainGate<Fr>,
+	eddsa: EddsaChip,
+	bits_chip: ToBitsChip<Fr>,
+	poseidon: PoseidonChip<Fr, 5, Params5x5Bn254>,
+}
+
+impl SchnorrChip {
+	/// Create a new chip.
+	pub fn new(main_gate_config: MainGateConfig) -> Self {
+		Self {
+			main_gate: MainGate::new(main_gate_config.clone()),
+			eddsa: EddsaChip::new(main_gate_config.clone()),
+			bits_chip: ToBitsChip::new(main_gate_config.clone()),
+			poseidon: PoseidonChip::new(main_gate_config),
+		}
+	}
+
+	/// Constrain the signature verification: `H(s * B8 + e * A, A, m) == e`.
+	/// The public key must be assigned with `assign_public_key`, which checks
+	/// that it is on the curve.
+	pub fn verify(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		e: &AssignedValue<Fr>,
+		s: &AssignedValue<Fr>,
+		pk: &AssignedPoint,
+		m: &AssignedValue<Fr>,
+	) -> Result<(), Error> {
+		let b8 = native::Point::b8();
+		let b8 = AssignedPoint {
+			x: self.main_gate.assign_constant(ctx, b8.x)?,
+			y: self.main_gate.assign_constant(ctx, b8.y)?,
+		};
+
+		// Decomposing `s` into `SUBORDER_BITS` bits also checks that it can't
+		// wrap around the field.
+		let s_bits = self.bits_chip.to_bits_le(ctx, s, SUBORDER_BITS)?;
+		let e_bits = self.bits_chip.to_bits_le(ctx, e, HASH_BITS)?;
+
+		let s_b8 = self.eddsa.mul_scalar(ctx, &b8, &s_bits)?;
+		let e_pk = self.eddsa.mul_scalar(ctx, pk, &e_bits)?;
+		let big_r = self.eddsa.add(ctx, &s_b8, &e_pk)?;
+
+		let inputs = [big_r.x, big_r.y, pk.x.clone(), pk.y.clone(), m.clone()];
+		let h = self.poseidon.permute(ctx, &inputs)?[0].clone();
+		self.main_gate.assert_equal(ctx, &h, e)
+	}
+
+	/// Assign a public key, and check that it is on the curve.
+	pub fn assign_public_key(
+		&self,
+		ctx: &mut RegionCtx<'_, '_, Fr>,
+		pk: Option<native::Point>,
+	) -> Result<AssignedPoint, Error> {
+		self.eddsa.assign_point(ctx, pk)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::eddsa::native::{Point, SecretKey};
+	use halo2wrong::halo2::{
+		arithmetic::{Field, FieldExt},
+		circuit::{Layouter, SimpleFloorPlanner},
+		dev::MockProver,
+		plonk::{Circuit, ConstraintSystem},
+	};
+	use maingate::UnassignedValue;
+	use native::{sign, Signature};
+	use rand::thread_rng;
+
+	#[derive(Clone)]
+	struct TestCircuit {
+		sig: Option<Signature>,
+		pk: Option<Point>,
+		m: Option<Fr>,
+	}
+
+	impl Circuit<Fr> for TestCircuit {
+		type Config = MainGateConfig;
+		type FloorPlanner = SimpleFloorPlanner;
+
+		fn without_witnesses(&self) -> Self {
+			Self {
+				sig: None,
+				pk: None,
+				m: None,
+			}
+		}
+
+		fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+			MainGate::<Fr>::configure(meta)
+		}
+
+		fn synthesize(
+			&self,
+			config: Self::Config,
+			mut layouter: impl Layouter<Fr>,
+		) -> Result<(), Error> {
+			let main_gate = MainGate::<Fr>::new(config.clone());
+			let schnorr = SchnorrChip::new(config);
+
+			let m = layouter.assign_region(
+				|| "schnorr",
+				|mut region| {
+					let position = &mut 0;
+					let ctx = &mut RegionCtx::new(&mut region, position);
+
+					let e = main_gate
+						.assign_value(ctx, &UnassignedValue::from(self.sig.map(|sig| sig.e)))?;
+					let s = main_gate
+						.assign_value(ctx, &UnassignedValue::from(self.sig.map(|sig| sig.s)))?;
+					let pk = schnorr.assign_public_key(ctx, self.pk)?;
+					let m = main_gate.assign_value(ctx, &UnassignedValue::from(self.m))?;
+
+					schnorr.verify(ctx, &e, &s, &pk, &m)?;
+					Ok(m)
+				},
+			)?;
+
+			main_gate.expose_public(layouter.namespace(|| "m"), m, 0)?;
+
+			Ok(())
+		}
+	}
+
+	fn run(sig: Signature, pk: Point, m: Fr) -> bool {
+		let circuit = TestCircuit {
+			sig: Some(sig),
+			pk: Some(pk),
+			m: Some(m),
+		};
+		let k = 16;
+		let prover = MockProver::<Fr>::run(k, &circuit, vec![vec![m]]).unwrap();
+		prover.verify().is_ok()
+	}
+
+	#[test]
+	fn test_schnorr_chip() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let m = Fr::from_u128(123456789);
+		let sig = sign(&sk, m);
+		assert!(run(sig, sk.public().0, m));
+	}
+
+	#[test]
+	fn test_schnorr_chip_wrong_signature() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public().0;
+		let m = Fr::from_u128(123456789);
+		let sig = sign(&sk, m);
+
+		assert!(!run(sig, pk, m + Fr::one()));
+
+		let mut wrong_sig = sig;
+		wrong_sig.s += Fr::one();
+		assert!(!run(wrong_sig, pk, m));
+	}
+}
@@ -0,0 +1,118 @@
+//! Native Schnorr signatures over the BabyJubJub curve, using Poseidon as the
+//! challenge hash.
+
+use crate::{
+	eddsa::native::{
+		add_limbs, from_limbs, is_canonical, mul_limbs, reduce_bytes, reduce_limbs, sub, to_limbs,
+		Point, PublicKey, SecretKey, SUBORDER,
+	},
+	poseidon::{params::Params5x5Bn254, Poseidon},
+};
+use halo2wrong::{curves::bn256::Fr, halo2::arithmetic::Field};
+
+type Poseidon5x5 = Poseidon<Fr, 5, Params5x5Bn254>;
+
+/// Schnorr signature. Unlike EdDSA, the nonce commitment is not part of the
+/// signature, it is recovered by the verifier from the challenge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+	/// The challenge: `e = H(R, A, m)`.
+	pub e: Fr,
+	/// The response: `s = r - e * sk`.
+	pub s: Fr,
+}
+
+/// The challenge hash `H(R, A, m)`.
+pub fn challenge(big_r: &Point, pk: &PublicKey, m: Fr) -> Fr {
+	let inputs = [big_r.x, big_r.y, pk.0.x, pk.0.y, m];
+	Poseidon5x5::new(inputs).permute()[0]
+}
+
+/// Sign the message. The nonce is derived from the secret key and the
+/// message, domain separated from the EdDSA nonce of the same key.
+pub fn sign(sk: &SecretKey, m: Fr) -> Signature {
+	let nonce_inputs = [sk.0, m, Fr::one(), Fr::zero(), Fr::zero()];
+	let nonce_hash = Poseidon5x5::new(nonce_inputs).permute()[0];
+	let r = reduce_bytes(&nonce_hash.to_bytes());
+	let big_r = Point::b8().mul_scalar(&r);
+
+	let pk = sk.public();
+	let e = challenge(&big_r, &pk, m);
+	// s = r - e * sk = r + (l - e * sk mod l) (mod l)
+	let e_sk = reduce_limbs(&mul_limbs(&to_limbs(&e), &to_limbs(&sk.0)));
+	let neg_e_sk = if e_sk == [0; 4] {
+		e_sk
+	} else {
+		sub(&SUBORDER, &e_sk)
+	};
+	let mut r_wide = [0u64; 8];
+	r_wide[..4].copy_from_slice(&to_limbs(&r));
+	let s = reduce_limbs(&add_limbs(&r_wide, &neg_e_sk));
+
+	Signature {
+		e,
+		s: from_limbs(&s),
+	}
+}
+
+/// Verify the signature: `H(s * B8 + e * A, A, m) == e`.
+pub fn verify(sig: &Signature, pk: &PublicKey, m: Fr) -> bool {
+	if !is_canonical(&sig.s) || !pk.0.is_on_curve() {
+		return false;
+	}
+
+	let big_r = Point::b8().mul_scalar(&sig.s).add(&pk.0.mul_scalar(&sig.e));
+	challenge(&big_r, pk, m) == sig.e
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::halo2::arithmetic::FieldExt;
+	use rand::thread_rng;
+
+	#[test]
+	fn test_sign_verify() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public();
+		let m = Fr::from_u128(123456789);
+
+		let sig = sign(&sk, m);
+		assert!(verify(&sig, &pk, m));
+
+		// Wrong message.
+		assert!(!verify(&sig, &pk, m + Fr::one()));
+
+		// Wrong public key.
+		let other_pk = SecretKey::random(rng).public();
+		assert!(!verify(&sig, &other_pk, m));
+	}
+
+	#[test]
+	fn test_reject_non_canonical_s() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let pk = sk.public();
+		let m = Fr::from_u128(42);
+
+		let mut sig = sign(&sk, m);
+		sig.s += from_limbs(&SUBORDER);
+		assert!(!verify(&sig, &pk, m));
+	}
+
+	#[test]
+	fn test_nonce_differs_from_eddsa() {
+		let rng = &mut thread_rng();
+		let sk = SecretKey::random(rng);
+		let m = Fr::from_u128(7);
+
+		// Reusing the EdDSA nonce for a Schnorr signature would leak the key.
+		let eddsa_sig = crate::eddsa::native::sign(&sk, m);
+		let sig = sign(&sk, m);
+		let big_r = Point::b8()
+			.mul_scalar(&sig.s)
+			.add(&sk.public().0.mul_scalar(&sig.e));
+		assert_ne!(big_r, eddsa_sig.big_r);
+	}
+}
@@ -0,0 +1,131 @@
+//! Helpers for testing circuits: running the mock prover, caching the
+//! parameters and keys of real proofs, and building the public inputs.
+
+use crate::{
+	config::CircuitConfig, ecdsa::SigData, gadgets::to_native, hasher::PoseidonHasher,
+	poseidon::params::Params5x5Bn254, utils::keygen, NUM_PUBLIC_INPUTS,
+};
+use halo2wrong::{
+	curves::{
+		bn256::{Bn256, Fr, G1Affine},
+		secp256k1::Secp256k1Affine,
+	},
+	halo2::{
+		arithmetic::{CurveAffine, FieldExt},
+		dev::{MockProver, VerifyFailure},
+		plonk::{Circuit, ProvingKey},
+		poly::kzg::commitment::ParamsKZG,
+	},
+};
+use std::{collections::HashMap, lazy::SyncLazy, sync::Mutex};
+
+/// Parameters generated so far, keyed by `k`. Generating them is the slowest
+/// part of most tests, so they are shared between the tests of one binary.
+static PARAMS: SyncLazy<Mutex<HashMap<u32, ParamsKZG<Bn256>>>> =
+	SyncLazy::new(|| Mutex::new(HashMap::new()));
+
+/// Circuit config for the tests, with `N` neighbors, ECDSA over secp256k1,
+/// and Poseidon as the message hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestConfig<const N: usize>;
+
+impl<const N: usize> CircuitConfig for TestConfig<N> {
+	type MessageHasher = PoseidonHasher<Params5x5Bn254>;
+	type Scalar = Fr;
+	type SignatureCurve = Secp256k1Affine;
+
+	const MAX_NEIGHBORS: usize = N;
+	const SCALE: u64 = 100_000_000;
+}
+
+/// Run the mock prover with a single instance column, and return the
+/// verification failures. Panics if the circuit can't be synthesized.
+pub fn mock_prove<F: FieldExt, C: Circuit<F>>(
+	k: u32,
+	circuit: &C,
+	pub_ins: Vec<F>,
+) -> Result<(), Vec<VerifyFailure>> {
+	let prover = match MockProver::<F>::run(k, circuit, vec![pub_ins]) {
+		Ok(prover) => prover,
+		Err(e) => panic!("{}", e),
+	};
+	prover.verify()
+}
+
+/// Checks whether the circuit is satisfied by the public inputs.
+pub fn is_satisfied<F: FieldExt, C: Circuit<F>>(k: u32, circuit: &C, pub_ins: Vec<F>) -> bool {
+	mock_prove(k, circuit, pub_ins).is_ok()
+}
+
+/// Parameters for the polynomial degree `k`, generated on the first use.
+pub fn params(k: u32) -> ParamsKZG<Bn256> {
+	let mut cache = PARAMS.lock().unwrap_or_else(|e| e.into_inner());
+	cache
+		.entry(k)
+		.or_insert_with(|| ParamsKZG::<Bn256>::new(k))
+		.clone()
+}
+
+/// Parameters and the proving key of the circuit, for the degree `k`.
+pub fn proving_key<C: Circuit<Fr>>(
+	k: u32,
+	circuit: &C,
+) -> (ParamsKZG<Bn256>, ProvingKey<G1Affine>) {
+	let params = params(k);
+	let pk = keygen(&params, circuit).unwrap();
+	(params, pk)
+}
+
+/// Write an array of 32 elements into an array of 64 elements.
+pub fn to_wide(p: [u8; 32]) -> [u8; 64] {
+	let mut res = [0u8; 64];
+	res[..32].copy_from_slice(&p[..]);
+	res
+}
+
+/// The public inputs of the `EigenTrustCircuit`, in the order they are
+/// exposed: `[op_v, r, s, m_hash, pk_x, pk_y, peers, scores, index_v]`.
+pub fn eigen_trust_public_inputs<N: CurveAffine, F: FieldExt>(
+	op_v: F,
+	sig: &SigData<N::ScalarExt>,
+	pk: &N,
+	peers: F,
+	scores: F,
+	index_v: usize,
+) -> [F; NUM_PUBLIC_INPUTS] {
+	let coords = pk.coordinates().unwrap();
+	[
+		op_v,
+		to_native(&sig.r),
+		to_native(&sig.s),
+		to_native(&sig.m_hash),
+		to_native(coords.x()),
+		to_native(coords.y()),
+		peers,
+		scores,
+		F::from_u128(index_v as u128),
+	]
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use halo2wrong::{curves::secp256k1::Fq, halo2::arithmetic::Field};
+
+	#[test]
+	fn test_to_native() {
+		let x = Fq::from_u128(123456789);
+		assert_eq!(to_native::<_, Fr>(&x), Fr::from_u128(123456789));
+
+		// The modulus of secp256k1 is bigger, so the top values wrap around.
+		let minus_one = -Fq::one();
+		let expected = Fr::from_bytes_wide(&to_wide(minus_one.to_bytes()));
+		assert_eq!(to_native::<_, Fr>(&minus_one), expected);
+	}
+
+	#[test]
+	fn test_params_cached() {
+		params(4);
+		assert!(PARAMS.lock().unwrap().contains_key(&4));
+	}
+}
@@ -2,17 +2,18 @@
 //! proofs, etc.
 
 use crate::{
+	config::CircuitConfig,
 	ecdsa::{generate_signature, Keypair},
 	EigenTrustCircuit,
 };
 use halo2wrong::{
 	curves::{
-		group::{Curve, Group},
+		group::{ff::PrimeField, Curve, Group},
 		pairing::{Engine, MultiMillerLoop},
 		CurveAffine,
 	},
 	halo2::{
-		arithmetic::Field,
+		arithmetic::{Field, FieldExt},
 		plonk::{
 			create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, Error, ProvingKey,
 			VerifyingKey,
@@ -32,7 +33,20 @@ use halo2wrong::{
 	},
 };
 use rand::Rng;
-use std::{fmt::Debug, fs::write, io::Read, time::Instant};
+use std::{
+	fmt::Debug,
+	fs::write,
+	io::{self, Read},
+	time::Instant,
+};
+
+/// Version of the encoding of the keys and the proofs. Bumped on every change
+/// of the layout, so the old encodings are rejected instead of misread.
+pub const ENCODING_VERSION: u8 = 1;
+/// Magic bytes of an encoded verifying key.
+const VK_MAGIC: &[u8; 4] = b"ETVK";
+/// Magic bytes of an encoded proof.
+const PROOF_MAGIC: &[u8; 4] = b"ETPF";
 
 /// Generate parameters with polynomial degere = `k`.
 pub fn generate_params<E: MultiMillerLoop + Debug>(k: u32) -> ParamsKZG<E> {
@@ -54,31 +68,42 @@ pub fn read_params<E: MultiMillerLoop + Debug>(path: &str) -> ParamsKZG<E> {
 	ParamsKZG::<E>::read(&mut &buffer[..]).unwrap()
 }
 
+/// Make a new circuit with the inputs being random values, and a custom window
+/// size of the ECDSA scalar multiplication. Fails if the window size is out of
+/// the supported range.
+pub fn random_circuit_with_window_size<C: CircuitConfig, R: Rng + Clone>(
+	min_score: C::Scalar,
+	window_size: usize,
+	rng: &mut R,
+) -> Result<EigenTrustCircuit<C>, Error> {
+	random_circuit::<C, R>(min_score, rng).with_window_size(window_size)
+}
+
 /// Make a new circuit with the inputs being random values.
-pub fn random_circuit<
-	E: MultiMillerLoop + Debug,
-	N: CurveAffine,
-	R: Rng + Clone,
-	const SIZE: usize,
->(
-	min_score: E::Scalar,
+pub fn random_circuit<C: CircuitConfig, R: Rng + Clone>(
+	min_score: C::Scalar,
 	rng: &mut R,
-) -> EigenTrustCircuit<N, <E as Engine>::Scalar, SIZE> {
-	let m_hash = N::ScalarExt::random(rng.clone());
+) -> EigenTrustCircuit<C> {
+	let m_hash = <C::SignatureCurve as CurveAffine>::ScalarExt::random(rng.clone());
 
 	// Data for prover
-	let pair_i = Keypair::<N>::new(rng);
+	let pair_i = Keypair::<C::SignatureCurve>::new(rng);
 	let pubkey_i = pair_i.public().to_owned();
 	let sig_i = generate_signature(pair_i, m_hash, rng).unwrap();
 
 	// Data from neighbors of i
-	let op_ji = [(); SIZE].map(|_| E::Scalar::random(rng.clone()));
-	let op_v = E::Scalar::random(rng.clone());
+	let op_ji = (0..C::MAX_NEIGHBORS)
+		.map(|_| C::Scalar::random(rng.clone()))
+		.collect();
+	// All of the scale goes to the first neighbor.
+	let mut scores = vec![C::Scalar::zero(); C::MAX_NEIGHBORS];
+	scores[0] = C::Scalar::from_u128(u128::from(C::SCALE));
 
 	// Aux generator
-	let aux_generator = N::CurveExt::random(rng.clone()).to_affine();
+	let aux_generator =
+		<C::SignatureCurve as CurveAffine>::CurveExt::random(rng.clone()).to_affine();
 
-	EigenTrustCircuit::<_, _, SIZE>::new(pubkey_i, sig_i, op_ji, op_v, min_score, aux_generator)
+	EigenTrustCircuit::new(pubkey_i, sig_i, op_ji, scores, 0, min_score, aux_generator)
 }
 
 /// Proving/verifying key generation.
@@ -147,6 +172,39 @@ pub fn verify<E: MultiMillerLoop + Debug, R: Rng + Clone>(
 	Ok(finalize_verify(output))
 }
 
+/// Batch verification of many proofs of the same circuit. Each proof is still
+/// read and checked against its own transcript, only the final pairing
+/// checks are combined into one, with random coefficients. This saves a
+/// pairing per proof, but the cost still grows with the size of the batch,
+/// and nothing is produced that can be stored or verified in place of the
+/// proofs. This is not an accumulation of the proofs: all of them have to be
+/// kept and passed in again to be verified later.
+pub fn verify_batch<E: MultiMillerLoop + Debug, R: Rng + Clone>(
+	params: &ParamsKZG<E>,
+	pub_inps: &[&[&[<KZGCommitmentScheme<E> as CommitmentScheme>::Scalar]]],
+	proofs: &[&[u8]],
+	vk: &VerifyingKey<E::G1Affine>,
+	rng: &mut R,
+) -> Result<bool, Error> {
+	if pub_inps.len() != proofs.len() {
+		return Ok(false);
+	}
+
+	let mut strategy = BatchVerifier::<E, R>::new(params, rng.clone());
+	for (pub_inp, proof) in pub_inps.iter().zip(proofs) {
+		let mut transcript = Blake2bRead::<_, E::G1Affine, Challenge255<_>>::init(*proof);
+		strategy = verify_proof::<KZGCommitmentScheme<E>, _, _, VerifierSHPLONK<E>, _, _>(
+			params,
+			vk,
+			strategy,
+			&[pub_inp],
+			&mut transcript,
+		)?;
+	}
+
+	Ok(finalize_verify(strategy))
+}
+
 /// Helper function for doing proof and verification at the same time.
 pub fn prove_and_verify<E: MultiMillerLoop + Debug, C: Circuit<E::Scalar>, R: Rng + Clone>(
 	params: ParamsKZG<E>,
@@ -163,3 +221,216 @@ pub fn prove_and_verify<E: MultiMillerLoop + Debug, C: Circuit<E::Scalar>, R: Rn
 
 	Ok(res)
 }
+
+fn invalid_data(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Check the magic bytes and the version of the encoding, and return the rest
+/// of the bytes.
+fn read_header<'a>(bytes: &'a [u8], magic: &[u8; 4]) -> io::Result<&'a [u8]> {
+	if bytes.len() < 5 || &bytes[..4] != magic {
+		return Err(invalid_data("unknown encoding"));
+	}
+	if bytes[4] != ENCODING_VERSION {
+		return Err(invalid_data("unsupported encoding version"));
+	}
+	Ok(&bytes[5..])
+}
+
+/// Encode the verifying key: the magic bytes `ETVK`, the version byte, and
+/// the key as written by halo2, with the curve points in their compressed
+/// encoding.
+pub fn vk_to_bytes<E: MultiMillerLoop + Debug>(vk: &VerifyingKey<E::G1Affine>) -> Vec<u8> {
+	let mut bytes = VK_MAGIC.to_vec();
+	bytes.push(ENCODING_VERSION);
+	// Writing into a vector can't fail.
+	vk.write(&mut bytes).unwrap();
+	bytes
+}
+
+/// Decode the verifying key of the circuit `C`, encoded with [`vk_to_bytes`].
+pub fn vk_from_bytes<E: MultiMillerLoop + Debug, C: Circuit<E::Scalar>>(
+	bytes: &[u8],
+	params: &ParamsKZG<E>,
+) -> io::Result<VerifyingKey<E::G1Affine>> {
+	let mut body = read_header(bytes, VK_MAGIC)?;
+	VerifyingKey::read::<_, C>(&mut body, params)
+}
+
+/// Encode the proving key. The proving key is fully determined by the
+/// verifying key and the circuit, so only the verifying key is encoded.
+pub fn pk_to_bytes<E: MultiMillerLoop + Debug>(pk: &ProvingKey<E::G1Affine>) -> Vec<u8> {
+	vk_to_bytes::<E>(pk.get_vk())
+}
+
+/// Decode the proving key, encoded with [`pk_to_bytes`], by deriving it from
+/// the verifying key and the circuit.
+pub fn pk_from_bytes<E: MultiMillerLoop + Debug, C: Circuit<E::Scalar>>(
+	bytes: &[u8],
+	params: &ParamsKZG<E>,
+	circuit: &C,
+) -> io::Result<ProvingKey<E::G1Affine>> {
+	let vk = vk_from_bytes::<E, C>(bytes, params)?;
+	keygen_pk::<KZGCommitmentScheme<E>, _>(params, vk, circuit)
+		.map_err(|_| invalid_data("proving key doesn't match the circuit"))
+}
+
+/// Encode the proof together with its public inputs: the magic bytes `ETPF`,
+/// the version byte, the number of public inputs as a little-endian `u32`,
+/// the public inputs as 32 byte little-endian field elements, and the proof
+/// transcript.
+pub fn proof_to_bytes<F: FieldExt>(pub_ins: &[F], proof: &[u8]) -> Vec<u8> {
+	let mut bytes = PROOF_MAGIC.to_vec();
+	bytes.push(ENCODING_VERSION);
+	bytes.extend((pub_ins.len() as u32).to_le_bytes());
+	for pub_in in pub_ins {
+		bytes.extend(pub_in.to_repr().as_ref());
+	}
+	bytes.extend(proof);
+	bytes
+}
+
+/// Decode the public inputs and the proof, encoded with [`proof_to_bytes`].
+pub fn proof_from_bytes<F: FieldExt>(bytes: &[u8]) -> io::Result<(Vec<F>, Vec<u8>)> {
+	let body = read_header(bytes, PROOF_MAGIC)?;
+	if body.len() < 4 {
+		return Err(invalid_data("missing the number of public inputs"));
+	}
+	let mut len_bytes = [0u8; 4];
+	len_bytes.copy_from_slice(&body[..4]);
+	let len = u32::from_le_bytes(len_bytes) as usize;
+
+	let mut repr = F::Repr::default();
+	let size = repr.as_ref().len();
+	let body = &body[4..];
+	if body.len() < len * size {
+		return Err(invalid_data("missing public inputs"));
+	}
+
+	let mut pub_ins = Vec::new();
+	for chunk in body[..len * size].chunks(size) {
+		repr.as_mut().copy_from_slice(chunk);
+		let pub_in: Option<F> = F::from_repr(repr).into();
+		pub_ins.push(pub_in.ok_or_else(|| invalid_data("public input not in the field"))?);
+	}
+
+	Ok((pub_ins, body[len * size..].to_vec()))
+}
+
+/// Encode the public inputs and the proof as the calldata of a generated
+/// Solidity verifier: every public input as a 32 byte big-endian word, in the
+/// order they are exposed, followed by the proof transcript. The verifier
+/// only accepts the proofs made with the transcript it was generated for.
+pub fn encode_calldata<F: FieldExt>(pub_ins: &[F], proof: &[u8]) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	for pub_in in pub_ins {
+		let mut word = [0u8; 32];
+		let repr = pub_in.to_repr();
+		for (byte, repr_byte) in word.iter_mut().rev().zip(repr.as_ref()) {
+			*byte = *repr_byte;
+		}
+		bytes.extend(word);
+	}
+	bytes.extend(proof);
+	bytes
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		convergence::{commit_ops, native_convergence, public_inputs, ConvergenceCircuit},
+		hasher::PoseidonHasher,
+		poseidon::params::Params5x5Bn254,
+	};
+	use halo2wrong::curves::bn256::{Bn256, Fr};
+	use rand::thread_rng;
+
+	type Hasher5x5 = PoseidonHasher<Params5x5Bn254>;
+	type TestCircuit = ConvergenceCircuit<Fr, 2, 1, Hasher5x5>;
+
+	fn test_circuit() -> (TestCircuit, Vec<Fr>) {
+		let scale = Fr::from_u128(10);
+		let ops = [[0, 10], [10, 0]].map(|row| row.map(Fr::from_u128));
+		let pre_trust = [5, 5].map(Fr::from_u128);
+		let pre_trust_weight = Fr::from_u128(2);
+		let scores = native_convergence(ops, pre_trust, pre_trust_weight, scale, 1);
+		let salt = Fr::from_u128(7);
+		let circuit = TestCircuit::new(ops, salt, pre_trust, pre_trust_weight, scale);
+		let commitment = commit_ops::<Fr, Hasher5x5, 2>(&ops, salt);
+		(circuit, public_inputs(commitment, &scores))
+	}
+
+	#[test]
+	fn test_keys_round_trip() {
+		let params = generate_params::<Bn256>(13);
+		let (circuit, pub_ins) = test_circuit();
+		let pk = keygen(&params, &circuit).unwrap();
+
+		let vk_bytes = vk_to_bytes::<Bn256>(pk.get_vk());
+		let vk = vk_from_bytes::<Bn256, TestCircuit>(&vk_bytes, &params).unwrap();
+		assert_eq!(vk_to_bytes::<Bn256>(&vk), vk_bytes);
+
+		let pk_bytes = pk_to_bytes::<Bn256>(&pk);
+		let decoded_pk = pk_from_bytes(&pk_bytes, &params, &circuit).unwrap();
+
+		// A proof made with the decoded key verifies with the original one.
+		let rng = &mut thread_rng();
+		let proof = prove(&params, circuit, &[&pub_ins], &decoded_pk, rng).unwrap();
+		assert!(verify(&params, &[&pub_ins], &proof, &vk, rng).unwrap());
+	}
+
+	#[test]
+	fn test_proof_round_trip() {
+		let pub_ins = [1, 2, 3].map(Fr::from_u128).to_vec();
+		let proof = vec![7u8; 100];
+		let bytes = proof_to_bytes(&pub_ins, &proof);
+		assert_eq!(&bytes[..4], b"ETPF");
+		assert_eq!(bytes[4], ENCODING_VERSION);
+		assert_eq!(&bytes[5..9], &[3, 0, 0, 0]);
+		assert_eq!(bytes[9], 1);
+
+		let (decoded_pub_ins, decoded_proof) = proof_from_bytes::<Fr>(&bytes).unwrap();
+		assert_eq!(decoded_pub_ins, pub_ins);
+		assert_eq!(decoded_proof, proof);
+	}
+
+	#[test]
+	fn test_encode_calldata() {
+		let pub_ins = [Fr::from_u128(0x0102), -Fr::one()];
+		let proof = vec![7u8; 10];
+		let bytes = encode_calldata(&pub_ins, &proof);
+		assert_eq!(bytes.len(), 2 * 32 + 10);
+
+		let mut first = [0u8; 32];
+		first[30] = 1;
+		first[31] = 2;
+		assert_eq!(&bytes[..32], &first);
+
+		// The modulus minus one, big-endian.
+		let mut second = (-Fr::one()).to_repr();
+		second.reverse();
+		assert_eq!(&bytes[32..64], &second);
+		assert_eq!(&bytes[64..], &proof[..]);
+	}
+
+	#[test]
+	fn test_invalid_encodings() {
+		let bytes = proof_to_bytes(&[Fr::one()], &[]);
+
+		let mut wrong_version = bytes.clone();
+		wrong_version[4] = ENCODING_VERSION + 1;
+		assert!(proof_from_bytes::<Fr>(&wrong_version).is_err());
+
+		let truncated = &bytes[..bytes.len() - 1];
+		assert!(proof_from_bytes::<Fr>(truncated).is_err());
+
+		let mut not_in_field = bytes;
+		not_in_field[9..41].copy_from_slice(&[0xff; 32]);
+		assert!(proof_from_bytes::<Fr>(&not_in_field).is_err());
+
+		let params = generate_params::<Bn256>(8);
+		assert!(vk_from_bytes::<Bn256, TestCircuit>(b"ETPF\x01", &params).is_err());
+	}
+}
@@ -0,0 +1,144 @@
+//! The listen addresses of the node, and the selection of their ports.
+
+use libp2p::multiaddr::Protocol;
+use std::{
+	net::{IpAddr, TcpListener},
+	str::FromStr,
+};
+
+use eigen_trust::{EigenError, Multiaddr};
+
+/// An example of a valid listen address, for the error messages.
+const EXAMPLE_ADDRESS: &str = "/ip4/0.0.0.0/tcp/58584";
+
+/// Parse a listen address, which has to be an IP address followed by a TCP
+/// port. The errors say what is wrong with it.
+pub fn parse_listen_addr(addr: &str) -> Result<Multiaddr, String> {
+	let multiaddr = Multiaddr::from_str(addr.trim()).map_err(|e| {
+		format!(
+			"{:?} is not a multiaddr ({}), expected e.g. {}",
+			addr, e, EXAMPLE_ADDRESS
+		)
+	})?;
+	let mut protocols = multiaddr.iter();
+	match (protocols.next(), protocols.next(), protocols.next()) {
+		(Some(Protocol::Ip4(_) | Protocol::Ip6(_)), Some(Protocol::Tcp(_)), None) => Ok(multiaddr),
+		(Some(Protocol::Ip4(_) | Protocol::Ip6(_)), Some(Protocol::Tcp(_)), Some(p)) => {
+			Err(format!(
+				"{:?} has a trailing {}, the listen address should end with the TCP port",
+				addr, p
+			))
+		},
+		(Some(Protocol::Ip4(_) | Protocol::Ip6(_)), ..) => Err(format!(
+			"{:?} has no TCP port, expected e.g. {}",
+			addr, EXAMPLE_ADDRESS
+		)),
+		_ => Err(format!(
+			"{:?} doesn't start with an /ip4 or /ip6 address, expected e.g. {}",
+			addr, EXAMPLE_ADDRESS
+		)),
+	}
+}
+
+/// The port to listen on, overriding the one of the main listen address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+	/// Pick a free port.
+	Auto,
+	/// Listen on the given port.
+	Fixed(u16),
+}
+
+impl FromStr for Port {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"auto" => Ok(Port::Auto),
+			_ => s
+				.parse()
+				.map(Port::Fixed)
+				.map_err(|_| format!("{:?} is not a port number or `auto`", s)),
+		}
+	}
+}
+
+impl Port {
+	/// Set the port of the listen address. A free port is picked by binding
+	/// to it and releasing it, so another process could still take it before
+	/// the node listens on it.
+	pub fn apply(self, addr: Multiaddr) -> Result<Multiaddr, EigenError> {
+		let port = match self {
+			Port::Fixed(port) => port,
+			Port::Auto => free_port(&addr)?,
+		};
+		Ok(addr
+			.into_iter()
+			.map(|p| match p {
+				Protocol::Tcp(_) => Protocol::Tcp(port),
+				p => p,
+			})
+			.collect())
+	}
+}
+
+/// Find a free TCP port on the IP address of the listen address.
+fn free_port(addr: &Multiaddr) -> Result<u16, EigenError> {
+	let ip = match addr.iter().next() {
+		Some(Protocol::Ip4(ip)) => IpAddr::V4(ip),
+		Some(Protocol::Ip6(ip)) => IpAddr::V6(ip),
+		_ => return Err(EigenError::InvalidAddress),
+	};
+	let port = TcpListener::bind((ip, 0))
+		.and_then(|listener| listener.local_addr())
+		.map_err(|e| {
+			eprintln!("Failed to find a free port on {}: {}", ip, e);
+			EigenError::ListenFailed
+		})?
+		.port();
+	Ok(port)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_parse_listen_addrs() {
+		assert!(parse_listen_addr("/ip4/0.0.0.0/tcp/58584").is_ok());
+		assert!(parse_listen_addr(" /ip6/::1/tcp/0 ").is_ok());
+
+		let err = parse_listen_addr("0.0.0.0:58584").unwrap_err();
+		assert!(err.contains("not a multiaddr"));
+		let err = parse_listen_addr("/ip4/0.0.0.0").unwrap_err();
+		assert!(err.contains("no TCP port"));
+		let err = parse_listen_addr("/ip4/0.0.0.0/udp/58584").unwrap_err();
+		assert!(err.contains("no TCP port"));
+		let err = parse_listen_addr("/ip4/0.0.0.0/tcp/58584/ws").unwrap_err();
+		assert!(err.contains("trailing /ws"));
+		let err = parse_listen_addr("/dns4/localhost/tcp/58584").unwrap_err();
+		assert!(err.contains("/ip4 or /ip6"));
+	}
+
+	#[test]
+	fn should_parse_ports() {
+		assert_eq!(Port::from_str("auto"), Ok(Port::Auto));
+		assert_eq!(Port::from_str("58584"), Ok(Port::Fixed(58584)));
+		assert!(Port::from_str("65536").is_err());
+		assert!(Port::from_str("any").is_err());
+	}
+
+	#[test]
+	fn should_apply_ports() {
+		let addr = parse_listen_addr("/ip4/127.0.0.1/tcp/0").unwrap();
+
+		let fixed = Port::Fixed(58584).apply(addr.clone()).unwrap();
+		assert_eq!(fixed.to_string(), "/ip4/127.0.0.1/tcp/58584");
+
+		let auto = Port::Auto.apply(addr).unwrap();
+		match auto.iter().nth(1) {
+			Some(Protocol::Tcp(port)) => assert_ne!(port, 0),
+			p => panic!("Unexpected protocol {:?}", p),
+		}
+	}
+}
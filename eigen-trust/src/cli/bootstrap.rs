@@ -0,0 +1,92 @@
+//! The bootstrap peers, as `multiaddr,peer_id` entries, from a file or the
+//! environment.
+
+use std::{fs, path::Path, str::FromStr};
+
+use eigen_trust::{EigenError, Multiaddr, PeerId};
+
+/// The environment variable with the bootstrap peers.
+pub const BOOTSTRAP_ENV: &str = "EIGEN_BOOTSTRAP";
+
+/// Parse the bootstrap peers. The entries are separated by new lines,
+/// whitespace or `;`, and everything after a `#` on a line is a comment.
+pub fn parse(text: &str) -> Result<Vec<(PeerId, Multiaddr)>, EigenError> {
+	let mut peers = Vec::new();
+	for line in text.lines() {
+		let line = line.split('#').next().unwrap_or_default();
+		for entry in line.split(|c: char| c.is_whitespace() || c == ';') {
+			if entry.is_empty() {
+				continue;
+			}
+			peers.push(parse_entry(entry)?);
+		}
+	}
+	Ok(peers)
+}
+
+/// Parse a single `multiaddr,peer_id` entry.
+pub fn parse_entry(entry: &str) -> Result<(PeerId, Multiaddr), EigenError> {
+	let (addr, peer_id) = entry.split_once(',').ok_or_else(|| {
+		eprintln!(
+			"Invalid bootstrap peer {:?}, expected multiaddr,peer_id",
+			entry
+		);
+		EigenError::InvalidConfig
+	})?;
+	let addr = Multiaddr::from_str(addr.trim()).map_err(|_| {
+		eprintln!("Invalid bootstrap address {:?}", addr);
+		EigenError::InvalidAddress
+	})?;
+	let peer_id = PeerId::from_str(peer_id.trim()).map_err(|_| {
+		eprintln!("Invalid bootstrap peer id {:?}", peer_id);
+		EigenError::InvalidPeerId
+	})?;
+	Ok((peer_id, addr))
+}
+
+/// Read the bootstrap peers from the file.
+pub fn read(path: &Path) -> Result<Vec<(PeerId, Multiaddr)>, EigenError> {
+	let text = fs::read_to_string(path).map_err(|e| {
+		eprintln!("Failed to read the bootstrap peers {:?}: {}", path, e);
+		EigenError::InvalidConfig
+	})?;
+	parse(&text)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const PEER_1: &str = "12D3KooWLyTCx9j2FMcsHe81RMoDfhXbdyyFgNGQMdcrnhShTvQh";
+	const PEER_2: &str = "12D3KooWKBKXsLwbmVBySEmbKayJzfWp3tPCKrnDCsmNy9prwjvy";
+
+	#[test]
+	fn should_parse_bootstrap_peers() {
+		let text = format!(
+			"# Local peers\n/ip4/127.0.0.1/tcp/58584,{}\n\n/ip4/127.0.0.1/tcp/58601,{} # second\n",
+			PEER_1, PEER_2
+		);
+		let peers = parse(&text).unwrap();
+		assert_eq!(peers.len(), 2);
+		assert_eq!(peers[0].0, PeerId::from_str(PEER_1).unwrap());
+		assert_eq!(
+			peers[1].1,
+			Multiaddr::from_str("/ip4/127.0.0.1/tcp/58601").unwrap()
+		);
+
+		// The environment variable form, on a single line.
+		let env = format!(
+			"/ip4/127.0.0.1/tcp/58584,{};/ip4/127.0.0.1/tcp/58601,{}",
+			PEER_1, PEER_2
+		);
+		assert_eq!(parse(&env).unwrap(), peers);
+		assert!(parse("").unwrap().is_empty());
+	}
+
+	#[test]
+	fn should_reject_invalid_bootstrap_peers() {
+		assert!(parse("/ip4/127.0.0.1/tcp/58584").is_err());
+		assert!(parse(&format!("not_an_address,{}", PEER_1)).is_err());
+		assert!(parse("/ip4/127.0.0.1/tcp/58584,not_a_peer_id").is_err());
+	}
+}
@@ -0,0 +1,430 @@
+//! The settings of the node, from the command line flags and the config file.
+
+use crate::{
+	address::{parse_listen_addr, Port},
+	bootstrap,
+	keys::decode_key,
+	keystore,
+	logger::LogFormat,
+};
+use clap::{ArgEnum, Args};
+use serde::Deserialize;
+use std::{
+	env, fs,
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use eigen_trust::{
+	storage::{Backend, DataDir, Retention},
+	EigenError, Keypair, LevelFilter, Multiaddr, PeerId,
+};
+
+const DEFAULT_ADDRESS: &str = "/ip4/0.0.0.0/tcp/0";
+/// The directory the params are cached in, by default.
+pub const DATA_DIR: &str = "../data";
+/// The degree of the params the circuit is set up with, by default.
+pub const DEFAULT_PARAMS_K: u32 = 18;
+const INTERVAL: u64 = 10;
+/// The shortest epoch interval for the params of the default degree, in
+/// seconds. The opinions have to be proven within an epoch, and the proving
+/// time roughly doubles with every degree.
+const MIN_INTERVAL_AT_DEFAULT_K: u64 = 5;
+
+/// The flags for the settings of the node.
+#[derive(Args, Debug)]
+pub struct NodeArgs {
+	/// Path to the TOML config file. The flags override the values from it.
+	#[clap(short, long)]
+	config: Option<PathBuf>,
+	/// The bs58 encoded keypair.
+	#[clap(short, long)]
+	key: Option<String>,
+	/// Path to the file holding the bs58 encoded keypair.
+	#[clap(long)]
+	key_path: Option<PathBuf>,
+	/// Path to the encrypted keystore. The passphrase is taken from
+	/// `EIGEN_KEYSTORE_PASSPHRASE`, or asked for.
+	#[clap(long)]
+	keystore: Option<PathBuf>,
+	/// Path to the file with the bootstrap peers, one `multiaddr,peer_id` per
+	/// line. Overrides `EIGEN_BOOTSTRAP` and the config.
+	#[clap(long)]
+	bootstrap_file: Option<PathBuf>,
+	/// The address to listen on, an IP address with a TCP port, e.g.
+	/// `/ip4/0.0.0.0/tcp/58584`.
+	#[clap(short, long, parse(try_from_str = parse_listen_addr))]
+	address: Option<Multiaddr>,
+	/// The port of the main address, or `auto` to pick a free one. The final
+	/// address is printed at startup.
+	#[clap(long)]
+	port: Option<Port>,
+	/// The epoch interval, in seconds.
+	#[clap(short, long)]
+	interval: Option<u64>,
+	/// Stop after this many epochs, instead of running forever.
+	#[clap(long)]
+	epochs: Option<u32>,
+	/// Path to the CSV file with the `peer_id,score` rows, set as the local
+	/// scores at startup.
+	#[clap(long)]
+	scores: Option<PathBuf>,
+	/// Path to the KZG params. Generated if missing.
+	#[clap(long)]
+	params: Option<PathBuf>,
+	/// The degree of the KZG params.
+	#[clap(long)]
+	params_k: Option<u32>,
+	/// Sign the opinions without proving them, and skip the params. For
+	/// trying out the network locally, the other nodes have to be mock too.
+	#[clap(long)]
+	mock_proofs: bool,
+	/// The log level: off, error, warn, info, debug or trace.
+	#[clap(long)]
+	log_level: Option<LevelFilter>,
+	/// The format of the logs.
+	#[clap(long, arg_enum)]
+	log_format: Option<LogFormat>,
+	/// Path to the IPC socket, for the admin console.
+	#[clap(long)]
+	ipc_socket: Option<PathBuf>,
+	/// The directory the state, the identity and the params of the node are
+	/// kept in. Without it, the node starts from scratch on every restart.
+	#[clap(long)]
+	data_dir: Option<PathBuf>,
+	/// The number of epochs the opinions are kept for.
+	#[clap(long)]
+	keep_opinions: Option<u64>,
+	/// The number of epochs the proofs of the opinions are kept for, at least
+	/// as long as the neighbors can catch up on them.
+	#[clap(long)]
+	keep_proofs: Option<u64>,
+	/// The backend of the store in the data dir.
+	#[clap(long, arg_enum)]
+	store: Option<StoreBackend>,
+	/// Path to a snapshot of the node state, written by the `snapshot`
+	/// console command. It replaces the persisted state at startup. A
+	/// relative path is in the snapshots dir of the data dir, if there is one.
+	#[clap(long)]
+	restore: Option<PathBuf>,
+}
+
+/// The settings of the node, read from the config file, e.g.:
+/// ```toml
+/// key_path = "keys/node.bs58"
+/// # Or, the encrypted keystore:
+/// # keystore_path = "keys/node.keystore"
+/// listen_addrs = ["/ip4/0.0.0.0/tcp/58584"]
+/// interval = 10
+/// scores_path = "scores.csv"
+/// params_path = "../data/params-18.bin"
+/// params_k = 18
+/// mock_proofs = false
+/// log_level = "info"
+/// log_format = "json"
+/// ipc_socket = "node.sock"
+/// data_dir = "node-data"
+/// keep_opinions = 90
+/// keep_proofs = 10
+/// # "sled" and "sqlite" need the `sled` and `sqlite` features.
+/// store = "memory"
+///
+/// [[bootstrap_peers]]
+/// address = "/ip4/127.0.0.1/tcp/58601"
+/// peer_id = "12D3KooWKBKXsLwbmVBySEmbKayJzfWp3tPCKrnDCsmNy9prwjvy"
+/// # Or, the file with the bootstrap peers:
+/// # bootstrap_file = "peers.txt"
+/// ```
+/// All of them are optional, and fall back to the defaults.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+	key_path: Option<PathBuf>,
+	keystore_path: Option<PathBuf>,
+	/// The addresses to listen on. The first one is the main address of the
+	/// node.
+	listen_addrs: Vec<String>,
+	bootstrap_peers: Vec<BootstrapPeer>,
+	bootstrap_file: Option<PathBuf>,
+	interval: Option<u64>,
+	scores_path: Option<PathBuf>,
+	params_path: Option<PathBuf>,
+	params_k: Option<u32>,
+	mock_proofs: bool,
+	log_level: Option<String>,
+	log_format: Option<LogFormat>,
+	ipc_socket: Option<PathBuf>,
+	data_dir: Option<PathBuf>,
+	keep_opinions: Option<u64>,
+	keep_proofs: Option<u64>,
+	store: Option<StoreBackend>,
+}
+
+/// The backends of the store, see [`Backend`].
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+	/// In memory, nothing is persisted.
+	Memory,
+	/// A sled database, with the `sled` feature.
+	#[cfg(feature = "sled")]
+	Sled,
+	/// A SQLite database, that can be queried with SQL while the node runs,
+	/// with the `sqlite` feature.
+	#[cfg(feature = "sqlite")]
+	Sqlite,
+}
+
+impl From<StoreBackend> for Backend {
+	fn from(backend: StoreBackend) -> Self {
+		match backend {
+			StoreBackend::Memory => Backend::Memory,
+			#[cfg(feature = "sled")]
+			StoreBackend::Sled => Backend::Sled,
+			#[cfg(feature = "sqlite")]
+			StoreBackend::Sqlite => Backend::Sqlite,
+		}
+	}
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct BootstrapPeer {
+	address: String,
+	peer_id: String,
+}
+
+impl Config {
+	/// Read the config file. The logger is not set up yet, so the errors are
+	/// printed directly.
+	fn load(path: &Path) -> Result<Self, EigenError> {
+		let contents = fs::read_to_string(path).map_err(|e| {
+			eprintln!("Failed to read the config {:?}: {}", path, e);
+			EigenError::InvalidConfig
+		})?;
+		toml::from_str(&contents).map_err(|e| {
+			eprintln!("Failed to parse the config {:?}: {}", path, e);
+			EigenError::InvalidConfig
+		})
+	}
+}
+
+/// The resolved settings of the node: the flags first, then the config file,
+/// then the defaults.
+pub struct Settings {
+	pub local_key: Keypair,
+	/// The addresses to listen on. The first one is the main address of the
+	/// node.
+	pub local_addresses: Vec<Multiaddr>,
+	pub bootstrap_nodes: Vec<(PeerId, Multiaddr)>,
+	pub interval: u64,
+	/// The number of epochs to run for. Without it, the node runs forever.
+	pub epochs: Option<u32>,
+	/// The local scores towards the neighbors, set at startup.
+	pub scores_path: Option<PathBuf>,
+	pub params_path: PathBuf,
+	pub params_k: u32,
+	/// Whether the opinions are only signed, and the params are not used.
+	pub mock_proofs: bool,
+	pub log_level: LevelFilter,
+	pub log_format: LogFormat,
+	/// The IPC socket of the node. Without it, the node can't be reached from
+	/// the console.
+	pub ipc_socket: Option<PathBuf>,
+	/// The data dir of the node, locked until the settings are dropped.
+	pub data_dir: Option<DataDir>,
+	/// The backend of the store in the data dir.
+	pub backend: Backend,
+	/// How many epochs of the opinions and the proofs are kept.
+	pub retention: Retention,
+	/// The snapshot the state of the node is restored from at startup.
+	pub restore: Option<PathBuf>,
+}
+
+impl Settings {
+	/// Resolve the settings. Without a key, a new one is generated.
+	pub fn resolve(args: NodeArgs) -> Result<Self, EigenError> {
+		let config = match &args.config {
+			Some(path) => Config::load(path)?,
+			None => Config::default(),
+		};
+
+		let log_level = match (args.log_level, &config.log_level) {
+			(Some(level), _) => level,
+			(None, Some(level)) => LevelFilter::from_str(level).map_err(|_| {
+				eprintln!("Invalid log level: {}", level);
+				EigenError::InvalidConfig
+			})?,
+			(None, None) => LevelFilter::Info,
+		};
+
+		// Opened first, so a data dir used by another node is rejected before
+		// asking for the passphrase of its keystore.
+		let data_dir = match args.data_dir.or(config.data_dir) {
+			Some(root) => Some(DataDir::open(&root).map_err(|e| {
+				eprintln!(
+					"Failed to open the data dir {:?}, is another node using it?",
+					root
+				);
+				e
+			})?),
+			None => None,
+		};
+
+		// Taking the keypair from the command line, the keystore, the key file, the
+		// identity keystore in the data dir, or generating a new one. A key file given
+		// with the flag goes before the keystore from the config.
+		let keystore_path = match (args.keystore, &args.key_path) {
+			(Some(path), _) => Some(path),
+			(None, Some(_)) => None,
+			(None, None) => config.keystore_path,
+		};
+		let local_key = if let Some(key) = args.key {
+			decode_key(&key)?
+		} else if let Some(path) = keystore_path {
+			keystore::load(&path)?
+		} else if let Some(path) = args.key_path.or(config.key_path) {
+			let key = fs::read_to_string(&path).map_err(|e| {
+				eprintln!("Failed to read the key {:?}: {}", path, e);
+				EigenError::InvalidKeypair
+			})?;
+			decode_key(&key)?
+		} else if let Some(data_dir) = &data_dir {
+			// A persisted node keeps its identity, or its state would belong to
+			// another PeerId after the restart.
+			keystore::identity(data_dir)?
+		} else {
+			Keypair::generate_ed25519()
+		};
+
+		// Taking the addresses from the command line, the config, or the default one.
+		let mut local_addresses = match args.address {
+			Some(addr) => vec![addr],
+			None if !config.listen_addrs.is_empty() => {
+				let mut addresses = Vec::new();
+				for addr in &config.listen_addrs {
+					addresses.push(parse_listen_addr(addr).map_err(|e| {
+						eprintln!("Invalid listen address in the config: {}", e);
+						EigenError::InvalidAddress
+					})?);
+				}
+				addresses
+			},
+			None => {
+				vec![Multiaddr::from_str(DEFAULT_ADDRESS).map_err(|_| EigenError::InvalidAddress)?]
+			},
+		};
+		// The port flag goes into the main address.
+		if let (Some(port), Some(addr)) = (args.port, local_addresses.first_mut()) {
+			*addr = port.apply(addr.clone())?;
+		}
+
+		// Taking the bootstrap peers from the file flag, the environment, or the
+		// config. Without any, the node waits to be dialed.
+		let bootstrap_nodes = if let Some(path) = args.bootstrap_file {
+			bootstrap::read(&path)?
+		} else if let Ok(peers) = env::var(bootstrap::BOOTSTRAP_ENV) {
+			bootstrap::parse(&peers)?
+		} else if !config.bootstrap_peers.is_empty() {
+			let mut bootstrap_nodes = Vec::new();
+			for peer in config.bootstrap_peers {
+				let entry = format!("{},{}", peer.address, peer.peer_id);
+				bootstrap_nodes.push(bootstrap::parse_entry(&entry)?);
+			}
+			bootstrap_nodes
+		} else if let Some(path) = config.bootstrap_file {
+			bootstrap::read(&path)?
+		} else {
+			Vec::new()
+		};
+
+		let interval = args.interval.or(config.interval).unwrap_or(INTERVAL);
+		let params_k = args
+			.params_k
+			.or(config.params_k)
+			.unwrap_or(DEFAULT_PARAMS_K);
+		let mock_proofs = args.mock_proofs || config.mock_proofs;
+		// Without the proofs, any interval will do.
+		let min_interval = if mock_proofs {
+			1
+		} else {
+			min_interval(params_k)
+		};
+		if interval < min_interval {
+			eprintln!(
+				"The interval of {} seconds is too short to prove the opinions with k = {}, \
+				 it should be at least {} seconds",
+				interval, params_k, min_interval
+			);
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		if args.epochs == Some(0) {
+			eprintln!("The number of epochs should be at least 1");
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		let default_retention = Retention::default();
+		let retention = Retention {
+			opinion_epochs: args
+				.keep_opinions
+				.or(config.keep_opinions)
+				.unwrap_or(default_retention.opinion_epochs),
+			proof_epochs: args
+				.keep_proofs
+				.or(config.keep_proofs)
+				.unwrap_or(default_retention.proof_epochs),
+		};
+		if retention.opinion_epochs == 0 || retention.proof_epochs == 0 {
+			eprintln!("The opinions and the proofs should be kept for at least 1 epoch");
+			return Err(EigenError::InvalidConfig);
+		}
+
+		let params_path = args
+			.params
+			.or(config.params_path)
+			.unwrap_or_else(|| match &data_dir {
+				Some(data_dir) => data_dir.params(params_k),
+				None => default_params_path(params_k),
+			});
+
+		Ok(Self {
+			local_key,
+			local_addresses,
+			bootstrap_nodes,
+			interval,
+			epochs: args.epochs,
+			scores_path: args.scores.or(config.scores_path),
+			params_path,
+			params_k,
+			mock_proofs,
+			log_level,
+			log_format: args
+				.log_format
+				.or(config.log_format)
+				.unwrap_or(LogFormat::Text),
+			ipc_socket: args.ipc_socket.or(config.ipc_socket),
+			data_dir,
+			backend: args
+				.store
+				.or(config.store)
+				.map(Backend::from)
+				.unwrap_or_default(),
+			retention,
+			restore: args.restore,
+		})
+	}
+}
+
+/// The shortest epoch interval for the params of degree `k`, in seconds.
+fn min_interval(k: u32) -> u64 {
+	if k >= DEFAULT_PARAMS_K {
+		let shift = (k - DEFAULT_PARAMS_K).min(32);
+		MIN_INTERVAL_AT_DEFAULT_K.saturating_mul(1 << shift)
+	} else {
+		(MIN_INTERVAL_AT_DEFAULT_K >> (DEFAULT_PARAMS_K - k).min(63)).max(1)
+	}
+}
+
+/// The path of the params of degree `k` in the data dir.
+pub fn default_params_path(k: u32) -> PathBuf {
+	Path::new(DATA_DIR).join(format!("params-{}.bin", k))
+}
@@ -0,0 +1,231 @@
+//! The admin console, sending the commands of the operator to a running node
+//! over its IPC socket.
+
+use std::{
+	io::{self, BufRead, Write},
+	path::Path,
+	str::FromStr,
+};
+
+use crate::report::{print_peers, print_status};
+use eigen_trust::{ipc::IpcClient, Command, EigenError, Epoch, PeerId, Reply};
+
+const HELP: &str = "Commands:
+  score <peer_id> <score>       Set the local score towards a neighbor
+  neighbors                     List the neighbors
+  epoch                         Print the current epoch
+  global [<epoch>]              Print the global trust score, at the current epoch by default
+  ban <peer_id>                 Block the peer
+  revoke <peer_id> <epoch>      Revoke the opinion sent to the neighbor
+  challenge <peer_id> <epoch>   Challenge the opinion of the neighbor
+  snapshot <path>               Write the snapshot of the node state, on the host of the node
+  archive <from> <to> <path>    Write the trust history of the epochs to an archive
+  import <path>                 Import the trust history from an archive
+  help                          Print this message
+  quit                          Leave the console";
+
+/// A line of the console.
+#[derive(Debug, PartialEq)]
+enum Line {
+	Command(Command),
+	/// The global score at the current epoch, which has to be asked for first.
+	CurrentGlobalScore,
+	Help,
+	Quit,
+	Empty,
+}
+
+fn parse_peer_id(arg: Option<&str>) -> Result<PeerId, String> {
+	let arg = arg.ok_or("Missing the peer id")?;
+	PeerId::from_str(arg).map_err(|_| format!("Invalid peer id: {}", arg))
+}
+
+fn parse_number<T: FromStr>(arg: Option<&str>, name: &str) -> Result<T, String> {
+	let arg = arg.ok_or(format!("Missing the {}", name))?;
+	arg.parse()
+		.map_err(|_| format!("Invalid {}: {}", name, arg))
+}
+
+/// Parse a line typed by the operator.
+fn parse_line(line: &str) -> Result<Line, String> {
+	let mut args = line.split_whitespace();
+	let name = match args.next() {
+		Some(name) => name,
+		None => return Ok(Line::Empty),
+	};
+	let line = match name {
+		"score" => Line::Command(Command::SetScore {
+			peer_id: parse_peer_id(args.next())?,
+			score: parse_number(args.next(), "score")?,
+		}),
+		"neighbors" => Line::Command(Command::Neighbors),
+		"epoch" => Line::Command(Command::CurrentEpoch),
+		"global" => match args.next() {
+			Some(epoch) => Line::Command(Command::GlobalScore {
+				epoch: Epoch(parse_number(Some(epoch), "epoch")?),
+			}),
+			None => Line::CurrentGlobalScore,
+		},
+		"ban" => Line::Command(Command::Ban {
+			peer_id: parse_peer_id(args.next())?,
+		}),
+		"revoke" => Line::Command(Command::Revoke {
+			peer_id: parse_peer_id(args.next())?,
+			epoch: Epoch(parse_number(args.next(), "epoch")?),
+		}),
+		"challenge" => Line::Command(Command::Challenge {
+			peer_id: parse_peer_id(args.next())?,
+			epoch: Epoch(parse_number(args.next(), "epoch")?),
+		}),
+		"snapshot" => Line::Command(Command::Snapshot {
+			path: args.next().ok_or("Missing the path")?.into(),
+		}),
+		"archive" => Line::Command(Command::ExportArchive {
+			from: Epoch(parse_number(args.next(), "epoch")?),
+			to: Epoch(parse_number(args.next(), "epoch")?),
+			path: args.next().ok_or("Missing the path")?.into(),
+		}),
+		"import" => Line::Command(Command::ImportArchive {
+			path: args.next().ok_or("Missing the path")?.into(),
+		}),
+		"help" => Line::Help,
+		"quit" | "exit" => Line::Quit,
+		_ => return Err(format!("Unknown command: {}, try help", name)),
+	};
+	if let Some(arg) = args.next() {
+		return Err(format!("Unexpected argument: {}", arg));
+	}
+	Ok(line)
+}
+
+fn print_reply(reply: &Reply) {
+	match reply {
+		Reply::Done => println!("Done"),
+		Reply::Epoch(epoch) => println!("{:?}", epoch),
+		Reply::Score(score) => println!("{}", score),
+		Reply::Neighbors(neighbors) => {
+			println!("{} neighbors", neighbors.len());
+			for neighbor in neighbors {
+				let identified = if neighbor.identified {
+					"identified"
+				} else {
+					"not identified"
+				};
+				println!(
+					"{} score: {} ({})",
+					neighbor.peer_id, neighbor.score, identified
+				);
+			}
+		},
+		Reply::Status(status) => print_status(status),
+		Reply::Peers(peers) => print_peers(peers),
+		Reply::Proof(export) => println!(
+			"Proof {} of {} bytes, use export-proof to save it",
+			export.cid,
+			export.calldata.len()
+		),
+		Reply::Error(e) => println!("Error: {}", e),
+	}
+}
+
+/// Run the console against the node at the socket, until the operator quits.
+pub async fn run(socket: &Path) -> Result<(), EigenError> {
+	let mut client = IpcClient::connect(socket).await.map_err(|e| {
+		eprintln!("Failed to connect to the node at {:?}", socket);
+		e
+	})?;
+	println!("Connected to {:?}, type help for the commands", socket);
+
+	let stdin = io::stdin();
+	loop {
+		print!("> ");
+		// The prompt is only cosmetic.
+		let _ = io::stdout().flush();
+		let mut line = String::new();
+		match stdin.lock().read_line(&mut line) {
+			Ok(0) => return Ok(()),
+			Ok(_) => {},
+			Err(e) => {
+				eprintln!("Failed to read the command: {}", e);
+				return Ok(());
+			},
+		}
+
+		let command = match parse_line(&line) {
+			Ok(Line::Command(command)) => command,
+			Ok(Line::CurrentGlobalScore) => match client.request(&Command::CurrentEpoch).await? {
+				Reply::Epoch(epoch) => Command::GlobalScore { epoch },
+				reply => {
+					print_reply(&reply);
+					continue;
+				},
+			},
+			Ok(Line::Help) => {
+				println!("{}", HELP);
+				continue;
+			},
+			Ok(Line::Quit) => return Ok(()),
+			Ok(Line::Empty) => continue,
+			Err(e) => {
+				println!("{}", e);
+				continue;
+			},
+		};
+		let reply = client.request(&command).await.map_err(|e| {
+			eprintln!("The node at {:?} stopped responding", socket);
+			e
+		})?;
+		print_reply(&reply);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const PEER: &str = "12D3KooWLyTCx9j2FMcsHe81RMoDfhXbdyyFgNGQMdcrnhShTvQh";
+
+	#[test]
+	fn should_parse_console_lines() {
+		let peer_id = PeerId::from_str(PEER).unwrap();
+		assert_eq!(
+			parse_line(&format!("score {} 5\n", PEER)),
+			Ok(Line::Command(Command::SetScore { peer_id, score: 5 }))
+		);
+		assert_eq!(
+			parse_line("global 7"),
+			Ok(Line::Command(Command::GlobalScore { epoch: Epoch(7) }))
+		);
+		assert_eq!(parse_line("global"), Ok(Line::CurrentGlobalScore));
+		assert_eq!(
+			parse_line("snapshot node.snapshot"),
+			Ok(Line::Command(Command::Snapshot {
+				path: "node.snapshot".into()
+			}))
+		);
+		assert_eq!(
+			parse_line("archive 3 9 history.archive"),
+			Ok(Line::Command(Command::ExportArchive {
+				from: Epoch(3),
+				to: Epoch(9),
+				path: "history.archive".into()
+			}))
+		);
+		assert_eq!(
+			parse_line("import history.archive"),
+			Ok(Line::Command(Command::ImportArchive {
+				path: "history.archive".into()
+			}))
+		);
+		assert_eq!(parse_line("  "), Ok(Line::Empty));
+		assert_eq!(parse_line("quit"), Ok(Line::Quit));
+
+		assert!(parse_line("score").is_err());
+		assert!(parse_line(&format!("score {} -1", PEER)).is_err());
+		assert!(parse_line("ban not_a_peer_id").is_err());
+		assert!(parse_line(&format!("ban {} extra", PEER)).is_err());
+		assert!(parse_line("snapshot").is_err());
+		assert!(parse_line("archive 3 history.archive").is_err());
+		assert!(parse_line("unknown").is_err());
+	}
+}
@@ -0,0 +1,182 @@
+//! Encoding of the identity keypairs, as bs58 strings of their protobuf
+//! encoding, and the conversions between the other formats of the secp256k1
+//! secret keys.
+
+use clap::ArgEnum;
+use eigen_trust::{
+	storage::{decode_keypair, encode_keypair},
+	EigenError, Keypair,
+};
+use libp2p::identity::secp256k1;
+
+/// Decode the bs58 encoded keypair.
+pub fn decode_key(key: &str) -> Result<Keypair, EigenError> {
+	let decoded_key = bs58::decode(key.trim())
+		.into_vec()
+		.map_err(|_| EigenError::InvalidKeypair)?;
+	decode_keypair(&decoded_key)
+}
+
+/// Encode the secp256k1 keypair as a bs58 string, that [`decode_key`] reads.
+pub fn encode_key(keypair: &Keypair) -> Result<String, EigenError> {
+	// Only the secp256k1 keys are written.
+	sk_bytes(keypair)?;
+	Ok(bs58::encode(encode_keypair(keypair)?).into_string())
+}
+
+/// The big-endian bytes of the secp256k1 secret key.
+pub fn sk_bytes(keypair: &Keypair) -> Result<[u8; 32], EigenError> {
+	match keypair {
+		Keypair::Secp256k1(kp) => Ok(kp.secret().to_bytes()),
+		_ => Err(EigenError::InvalidKeypair),
+	}
+}
+
+/// The secp256k1 keypair of the big-endian secret key bytes. Fails if the
+/// bytes are not a valid scalar.
+pub fn keypair_from_sk_bytes(bytes: &[u8]) -> Result<Keypair, EigenError> {
+	let sk =
+		secp256k1::SecretKey::from_bytes(bytes.to_vec()).map_err(|_| EigenError::InvalidKeypair)?;
+	Ok(Keypair::Secp256k1(sk.into()))
+}
+
+/// The secret key as the little-endian 64 bit limbs of the scalar, the
+/// layout the circuits use.
+pub fn sk_limbs(bytes: &[u8; 32]) -> [u64; 4] {
+	let mut limbs = [0u64; 4];
+	for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks(8)) {
+		let mut limb_bytes = [0u8; 8];
+		limb_bytes.copy_from_slice(chunk);
+		*limb = u64::from_be_bytes(limb_bytes);
+	}
+	limbs
+}
+
+/// The big-endian secret key bytes of the little-endian limbs.
+pub fn sk_bytes_from_limbs(limbs: &[u64; 4]) -> [u8; 32] {
+	let mut bytes = [0u8; 32];
+	for (chunk, limb) in bytes.rchunks_mut(8).zip(limbs) {
+		chunk.copy_from_slice(&limb.to_be_bytes());
+	}
+	bytes
+}
+
+/// The formats of the secp256k1 secret keys.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyFormat {
+	/// The bs58 encoded protobuf encoding of the libp2p keypair, used by the
+	/// node.
+	Protobuf,
+	/// The bs58 encoded 32 bytes of the secret key.
+	Raw,
+	/// The four little-endian 64 bit limbs of the secret scalar, separated by
+	/// commas. Decimal or `0x` prefixed hex.
+	Limbs,
+}
+
+impl KeyFormat {
+	/// Read the keypair in this format.
+	pub fn decode(self, key: &str) -> Result<Keypair, EigenError> {
+		let key = key.trim();
+		match self {
+			KeyFormat::Protobuf => decode_key(key),
+			KeyFormat::Raw => {
+				let bytes = bs58::decode(key)
+					.into_vec()
+					.map_err(|_| EigenError::InvalidKeypair)?;
+				if bytes.len() != 32 {
+					eprintln!("The raw secret key should be 32 bytes, not {}", bytes.len());
+					return Err(EigenError::InvalidKeypair);
+				}
+				keypair_from_sk_bytes(&bytes)
+			},
+			KeyFormat::Limbs => {
+				let mut limbs = [0u64; 4];
+				let mut parts = key.split(',');
+				for limb in limbs.iter_mut() {
+					let part = parts.next().ok_or_else(|| {
+						eprintln!("Expected 4 limbs, separated by commas");
+						EigenError::InvalidKeypair
+					})?;
+					*limb = parse_limb(part.trim())?;
+				}
+				if parts.next().is_some() {
+					eprintln!("Expected 4 limbs, separated by commas");
+					return Err(EigenError::InvalidKeypair);
+				}
+				keypair_from_sk_bytes(&sk_bytes_from_limbs(&limbs))
+			},
+		}
+	}
+
+	/// Write the secp256k1 keypair in this format.
+	pub fn encode(self, keypair: &Keypair) -> Result<String, EigenError> {
+		match self {
+			KeyFormat::Protobuf => encode_key(keypair),
+			KeyFormat::Raw => Ok(bs58::encode(sk_bytes(keypair)?).into_string()),
+			KeyFormat::Limbs => {
+				let limbs = sk_limbs(&sk_bytes(keypair)?);
+				let limbs: Vec<String> = limbs.iter().map(|l| format!("{:#018x}", l)).collect();
+				Ok(limbs.join(","))
+			},
+		}
+	}
+}
+
+fn parse_limb(limb: &str) -> Result<u64, EigenError> {
+	let res = match limb.strip_prefix("0x") {
+		Some(hex) => u64::from_str_radix(hex, 16),
+		None => limb.parse(),
+	};
+	res.map_err(|_| {
+		eprintln!("Invalid limb {:?}", limb);
+		EigenError::InvalidKeypair
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_convert_between_formats() {
+		let keypair = Keypair::generate_secp256k1();
+		let peer_id = keypair.public().to_peer_id();
+		let formats = [KeyFormat::Protobuf, KeyFormat::Raw, KeyFormat::Limbs];
+		for from in formats {
+			let key = from.encode(&keypair).unwrap();
+			for to in formats {
+				let converted = to.encode(&from.decode(&key).unwrap()).unwrap();
+				let decoded = to.decode(&converted).unwrap();
+				assert_eq!(decoded.public().to_peer_id(), peer_id);
+			}
+		}
+	}
+
+	#[test]
+	fn should_order_limbs() {
+		let mut bytes = [0u8; 32];
+		bytes[31] = 1;
+		bytes[0] = 2;
+		let limbs = sk_limbs(&bytes);
+		assert_eq!(limbs, [1, 0, 0, 2 << 56]);
+		assert_eq!(sk_bytes_from_limbs(&limbs), bytes);
+
+		let keypair = KeyFormat::Limbs.decode("1, 0, 0x0, 0").unwrap();
+		assert_eq!(sk_bytes(&keypair).unwrap(), {
+			let mut one = [0u8; 32];
+			one[31] = 1;
+			one
+		});
+	}
+
+	#[test]
+	fn should_reject_invalid_keys() {
+		assert!(KeyFormat::Raw.decode("abc").is_err());
+		assert!(KeyFormat::Limbs.decode("1,2,3").is_err());
+		assert!(KeyFormat::Limbs.decode("1,2,3,4,5").is_err());
+		assert!(KeyFormat::Limbs.decode("0,0,0,0").is_err());
+		assert!(KeyFormat::Limbs.decode("x,0,0,0").is_err());
+		assert!(encode_key(&Keypair::generate_ed25519()).is_err());
+	}
+}
@@ -0,0 +1,111 @@
+//! The passphrases of the keystores, see [`Keystore`] for the format of the
+//! files.
+
+use std::{env, path::Path};
+
+use eigen_trust::{
+	storage::{DataDir, Keystore},
+	EigenError, Keypair,
+};
+
+/// The environment variable with the passphrase. If not set, the passphrase
+/// is asked for on the terminal.
+pub const PASSPHRASE_ENV: &str = "EIGEN_KEYSTORE_PASSPHRASE";
+/// The environment variable with the new passphrase, when rotating it.
+pub const NEW_PASSPHRASE_ENV: &str = "EIGEN_KEYSTORE_NEW_PASSPHRASE";
+
+/// Read the passphrase from the environment, or ask for it.
+pub fn passphrase(prompt: &str) -> Result<String, EigenError> {
+	if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+		return Ok(passphrase);
+	}
+	prompt_passphrase(prompt)
+}
+
+fn prompt_passphrase(prompt: &str) -> Result<String, EigenError> {
+	rpassword::prompt_password(prompt).map_err(|e| {
+		eprintln!("Failed to read the passphrase: {}", e);
+		EigenError::KeystoreError
+	})
+}
+
+/// Read the passphrase for a new keystore from the environment variable, or
+/// ask for it twice.
+fn new_passphrase_from(var: &str) -> Result<String, EigenError> {
+	if let Ok(passphrase) = env::var(var) {
+		return Ok(passphrase);
+	}
+	let first = prompt_passphrase("New passphrase: ")?;
+	let second = prompt_passphrase("Repeat the passphrase: ")?;
+	if first != second {
+		eprintln!("The passphrases don't match");
+		return Err(EigenError::KeystoreError);
+	}
+	Ok(first)
+}
+
+/// Read the passphrase for a new keystore from the environment, or ask for it
+/// twice.
+pub fn new_passphrase() -> Result<String, EigenError> {
+	new_passphrase_from(PASSPHRASE_ENV)
+}
+
+/// Encrypt the keypair into a new keystore file.
+pub fn create(path: &Path, keypair: &Keypair) -> Result<(), EigenError> {
+	Keystore::create(path, keypair, &new_passphrase()?).map_err(|e| {
+		eprintln!(
+			"Failed to create the keystore {:?}, does it exist already?",
+			path
+		);
+		e
+	})?;
+	Ok(())
+}
+
+/// Decrypt the keypair from the keystore file.
+pub fn load(path: &Path) -> Result<Keypair, EigenError> {
+	let keystore = Keystore::open(path).map_err(|e| {
+		eprintln!("Failed to read the keystore {:?}", path);
+		e
+	})?;
+	let passphrase = passphrase("Keystore passphrase: ")?;
+	keystore.unlock(&passphrase).map_err(|e| {
+		eprintln!(
+			"Failed to unlock the keystore {:?}, wrong passphrase?",
+			path
+		);
+		e
+	})
+}
+
+/// Unlock the identity keystore in the data dir, or create it with a new
+/// secp256k1 keypair if there is none.
+pub fn identity(data_dir: &DataDir) -> Result<Keypair, EigenError> {
+	let path = data_dir.identity_keystore();
+	if path.exists() {
+		return load(&path);
+	}
+	let keypair = Keypair::generate_secp256k1();
+	create(&path, &keypair)?;
+	eprintln!("Created the identity keystore {:?}", path);
+	Ok(keypair)
+}
+
+/// Encrypt the keystore with a new passphrase, taken from
+/// [`NEW_PASSPHRASE_ENV`] or asked for twice. Returns the keypair.
+pub fn rotate(path: &Path) -> Result<Keypair, EigenError> {
+	let mut keystore = Keystore::open(path).map_err(|e| {
+		eprintln!("Failed to read the keystore {:?}", path);
+		e
+	})?;
+	let passphrase = passphrase("Current passphrase: ")?;
+	let keypair = keystore.unlock(&passphrase).map_err(|e| {
+		eprintln!(
+			"Failed to unlock the keystore {:?}, wrong passphrase?",
+			path
+		);
+		e
+	})?;
+	keystore.rotate(&passphrase, &new_passphrase_from(NEW_PASSPHRASE_ENV)?)?;
+	Ok(keypair)
+}
@@ -0,0 +1,72 @@
+//! The logger of the node: plain text for the terminal, or one JSON object
+//! per line for the log collectors.
+
+use clap::ArgEnum;
+use env_logger::Builder;
+use log::kv::{self, Key, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+
+use eigen_trust::LevelFilter;
+
+/// The format of the logs.
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+	/// Plain text lines.
+	Text,
+	/// JSON objects, with the structured fields of the records, like the
+	/// epoch, the peer id, the kind of the event and the durations.
+	Json,
+}
+
+/// Collects the key-value pairs of a record into the JSON object.
+struct JsonFields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> Visitor<'kvs> for JsonFields<'_> {
+	fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+		let value = if let Some(v) = value.to_u64() {
+			Value::from(v)
+		} else if let Some(v) = value.to_i64() {
+			Value::from(v)
+		} else if let Some(v) = value.to_f64() {
+			Value::from(v)
+		} else if let Some(v) = value.to_bool() {
+			Value::from(v)
+		} else {
+			Value::from(value.to_string())
+		};
+		self.0.insert(key.to_string(), value);
+		Ok(())
+	}
+}
+
+/// Set up the global logger.
+pub fn init(level: LevelFilter, format: LogFormat) {
+	let mut builder = Builder::from_default_env();
+	builder.filter(None, level);
+
+	match format {
+		LogFormat::Text => {
+			builder.format_timestamp(None);
+		},
+		LogFormat::Json => {
+			builder.format(|buf, record| {
+				let mut fields = Map::new();
+				fields.insert(
+					"timestamp".into(),
+					buf.timestamp_millis().to_string().into(),
+				);
+				fields.insert("level".into(), record.level().as_str().into());
+				fields.insert("target".into(), record.target().into());
+				fields.insert("message".into(), record.args().to_string().into());
+				// A record can't fail to be logged because of its fields.
+				let _ = record.key_values().visit(&mut JsonFields(&mut fields));
+				writeln!(buf, "{}", Value::Object(fields))
+			});
+		},
+	}
+
+	builder.init();
+}
@@ -0,0 +1,510 @@
+mod address;
+mod bootstrap;
+mod config;
+mod console;
+mod keys;
+mod keystore;
+mod logger;
+mod params;
+mod report;
+mod scores;
+mod top;
+
+use clap::{Parser, Subcommand};
+use config::{default_params_path, NodeArgs, Settings, DEFAULT_PARAMS_K};
+use keys::KeyFormat;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use eigen_trust::{
+	ipc::{self, IpcClient},
+	storage::write_atomic_private,
+	Command as NodeCommand, EigenError, Epoch, Keypair, Node, NodeConfig, PeerId, PeerScore, Reply,
+};
+
+#[derive(Parser, Debug)]
+struct Cli {
+	#[clap(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Generate a new secp256k1 keypair, and print it together with its
+	/// PeerId.
+	Keygen {
+		/// Write the key into the file, instead of printing it.
+		#[clap(long)]
+		out: Option<PathBuf>,
+	},
+	/// Start the node.
+	Run(NodeArgs),
+	/// Print the identity and the resolved settings of the node, without
+	/// starting it.
+	Inspect(NodeArgs),
+	/// Generate the KZG params, and cache them in the data dir.
+	Params {
+		/// The degree of the params.
+		#[clap(long, default_value_t = DEFAULT_PARAMS_K)]
+		k: u32,
+		/// Where to write the params, instead of the data dir.
+		#[clap(long)]
+		out: Option<PathBuf>,
+		/// Regenerate the params, even if they exist already.
+		#[clap(long)]
+		force: bool,
+	},
+	/// Connect to a running node, and issue commands to it.
+	Console {
+		/// Path to the IPC socket of the node.
+		#[clap(long)]
+		socket: PathBuf,
+	},
+	/// Print the status of a running node, for the health checks. Fails if
+	/// the node can't be reached.
+	Status {
+		/// Path to the IPC socket of the node.
+		#[clap(long)]
+		socket: PathBuf,
+		/// Print the status as JSON.
+		#[clap(long)]
+		json: bool,
+	},
+	/// List the neighbors of a running node, with the opinions exchanged over
+	/// the last epochs.
+	Peers {
+		/// Path to the IPC socket of the node.
+		#[clap(long)]
+		socket: PathBuf,
+		/// The number of past epochs to show the opinions of.
+		#[clap(long, default_value_t = 5)]
+		last: u64,
+		/// Print the neighbors as JSON.
+		#[clap(long)]
+		json: bool,
+	},
+	/// Show a live dashboard of a running node.
+	Top {
+		/// Path to the IPC socket of the node.
+		#[clap(long)]
+		socket: PathBuf,
+		/// The number of past epochs to show the opinions of.
+		#[clap(long, default_value_t = 3)]
+		last: u64,
+		/// The refresh interval, in seconds.
+		#[clap(long, default_value_t = 1)]
+		refresh: u64,
+	},
+	/// Export the proof of an opinion exchanged with a neighbor, with its
+	/// public inputs, as the calldata of the on-chain verifier. The opinion is
+	/// requested from the neighbor if the node doesn't have it.
+	ExportProof {
+		/// Path to the IPC socket of the node.
+		#[clap(long)]
+		socket: PathBuf,
+		/// The neighbor.
+		#[clap(long)]
+		peer: PeerId,
+		/// The epoch of the opinion.
+		#[clap(long)]
+		epoch: u64,
+		/// Where to write the proof.
+		#[clap(long)]
+		out: PathBuf,
+		/// Export the opinion sent to the neighbor, instead of the one it sent
+		/// to the node.
+		#[clap(long)]
+		sent: bool,
+	},
+	/// Set the local scores of a running node from a CSV file with the
+	/// `peer_id,score` rows.
+	ImportScores {
+		/// Path to the IPC socket of the node.
+		#[clap(long)]
+		socket: PathBuf,
+		/// Path to the CSV file.
+		path: PathBuf,
+	},
+	/// Manage the encrypted keystores.
+	Keystore {
+		#[clap(subcommand)]
+		command: KeystoreCommand,
+	},
+	/// Work with the secp256k1 secret keys.
+	Key {
+		#[clap(subcommand)]
+		command: KeyCommand,
+	},
+}
+
+#[derive(Subcommand, Debug)]
+enum KeyCommand {
+	/// Convert the secret key between the formats, and print it. The PeerId of
+	/// the key is printed to stderr, to check the right key was converted.
+	Convert {
+		/// The key to convert.
+		key: String,
+		/// The format of the key.
+		#[clap(long, arg_enum, default_value = "protobuf")]
+		from: KeyFormat,
+		/// The format to convert to.
+		#[clap(long, arg_enum)]
+		to: KeyFormat,
+	},
+}
+
+#[derive(Subcommand, Debug)]
+enum KeystoreCommand {
+	/// Generate a new secp256k1 keypair into a new keystore. An existing
+	/// keystore is never overwritten.
+	New {
+		/// Path to the keystore.
+		path: PathBuf,
+	},
+	/// Encrypt an existing bs58 encoded keypair into a new keystore.
+	Import {
+		/// Path to the keystore.
+		path: PathBuf,
+		/// The bs58 encoded keypair.
+		#[clap(long)]
+		key: String,
+	},
+	/// Unlock the keystore, and print the PeerId of its keypair.
+	Show {
+		/// Path to the keystore.
+		path: PathBuf,
+	},
+	/// Encrypt the keystore with a new passphrase, taken from
+	/// `EIGEN_KEYSTORE_NEW_PASSPHRASE`, or asked for. The keystores of the
+	/// older format are upgraded.
+	Rotate {
+		/// Path to the keystore.
+		path: PathBuf,
+	},
+}
+
+/// Generate a new keypair, and save or print it.
+fn keygen(out: Option<PathBuf>) -> Result<(), EigenError> {
+	let keypair = Keypair::generate_secp256k1();
+	let key = keys::encode_key(&keypair)?;
+	match out {
+		Some(path) => {
+			write_atomic_private(&path, key.as_bytes()).map_err(|e| {
+				eprintln!("Failed to write the key {:?}: {}", path, e);
+				EigenError::InvalidKeypair
+			})?;
+			println!("Key written to {:?}", path);
+		},
+		None => println!("Key: {}", key),
+	}
+	println!("PeerId: {}", keypair.public().to_peer_id());
+	Ok(())
+}
+
+/// Generate the params, unless there are valid ones already.
+fn generate_params(k: u32, out: Option<PathBuf>, force: bool) -> Result<(), EigenError> {
+	let path = out.unwrap_or_else(|| default_params_path(k));
+	if path.exists() && !force {
+		params::read(&path, k)?;
+		println!("Params for k = {} already at {:?}", k, path);
+		return Ok(());
+	}
+	params::generate(&path, k)?;
+	println!("Params for k = {} written to {:?}", k, path);
+	Ok(())
+}
+
+/// Run the keystore command.
+fn manage_keystore(command: KeystoreCommand) -> Result<(), EigenError> {
+	let (path, keypair) = match command {
+		KeystoreCommand::New { path } => {
+			let keypair = Keypair::generate_secp256k1();
+			keystore::create(&path, &keypair)?;
+			(path, keypair)
+		},
+		KeystoreCommand::Import { path, key } => {
+			let keypair = keys::decode_key(&key)?;
+			keystore::create(&path, &keypair)?;
+			(path, keypair)
+		},
+		KeystoreCommand::Show { path } => {
+			let keypair = keystore::load(&path)?;
+			(path, keypair)
+		},
+		KeystoreCommand::Rotate { path } => {
+			let keypair = keystore::rotate(&path)?;
+			(path, keypair)
+		},
+	};
+	println!("Keystore: {:?}", path);
+	println!("PeerId: {}", keypair.public().to_peer_id());
+	Ok(())
+}
+
+/// Run the key command.
+fn manage_key(command: KeyCommand) -> Result<(), EigenError> {
+	match command {
+		KeyCommand::Convert { key, from, to } => {
+			let keypair = from.decode(&key)?;
+			println!("{}", to.encode(&keypair)?);
+			eprintln!("PeerId: {}", keypair.public().to_peer_id());
+			Ok(())
+		},
+	}
+}
+
+/// Print the status of the running node.
+async fn status(socket: &Path, json: bool) -> Result<(), EigenError> {
+	let mut client = IpcClient::connect(socket).await.map_err(|e| {
+		eprintln!("Failed to connect to the node at {:?}", socket);
+		e
+	})?;
+	match client.request(&NodeCommand::Status).await? {
+		Reply::Status(status) if json => {
+			let json = serde_json::to_string(&status).map_err(|_| EigenError::IpcError)?;
+			println!("{}", json);
+			Ok(())
+		},
+		Reply::Status(status) => {
+			report::print_status(&status);
+			Ok(())
+		},
+		reply => {
+			eprintln!("Unexpected reply from the node: {:?}", reply);
+			Err(EigenError::IpcError)
+		},
+	}
+}
+
+/// Print the neighbors of the running node.
+async fn peers(socket: &Path, last: u64, json: bool) -> Result<(), EigenError> {
+	let mut client = IpcClient::connect(socket).await.map_err(|e| {
+		eprintln!("Failed to connect to the node at {:?}", socket);
+		e
+	})?;
+	match client.request(&NodeCommand::Peers { last }).await? {
+		Reply::Peers(peers) if json => {
+			let json = serde_json::to_string(&peers).map_err(|_| EigenError::IpcError)?;
+			println!("{}", json);
+			Ok(())
+		},
+		Reply::Peers(peers) => {
+			report::print_peers(&peers);
+			Ok(())
+		},
+		reply => {
+			eprintln!("Unexpected reply from the node: {:?}", reply);
+			Err(EigenError::IpcError)
+		},
+	}
+}
+
+/// Fetch the proof from the running node, and write it to the file.
+async fn export_proof(
+	socket: &Path,
+	peer_id: PeerId,
+	epoch: Epoch,
+	sent: bool,
+	out: &Path,
+) -> Result<(), EigenError> {
+	let mut client = IpcClient::connect(socket).await.map_err(|e| {
+		eprintln!("Failed to connect to the node at {:?}", socket);
+		e
+	})?;
+	let command = NodeCommand::ExportProof {
+		peer_id,
+		epoch,
+		sent,
+	};
+	match client.request(&command).await? {
+		Reply::Proof(export) => {
+			fs::write(out, &export.calldata).map_err(|e| {
+				eprintln!("Failed to write the proof {:?}: {}", out, e);
+				EigenError::InvalidOpinion
+			})?;
+			println!(
+				"Proof {} of {} bytes written to {:?}",
+				export.cid,
+				export.calldata.len(),
+				out
+			);
+			Ok(())
+		},
+		reply => {
+			eprintln!("The node failed to export the proof: {:?}", reply);
+			Err(EigenError::InvalidOpinion)
+		},
+	}
+}
+
+/// Send the scores from the file to the running node.
+async fn import_scores(socket: &Path, path: &Path) -> Result<(), EigenError> {
+	let scores: Vec<PeerScore> = scores::read(path)?
+		.into_iter()
+		.map(|(peer_id, score)| PeerScore { peer_id, score })
+		.collect();
+	let count = scores.len();
+
+	let mut client = IpcClient::connect(socket).await.map_err(|e| {
+		eprintln!("Failed to connect to the node at {:?}", socket);
+		e
+	})?;
+	match client.request(&NodeCommand::SetScores { scores }).await? {
+		Reply::Done => {
+			println!("Imported {} scores", count);
+			Ok(())
+		},
+		reply => {
+			eprintln!("The node failed to import the scores: {:?}", reply);
+			Err(EigenError::IpcError)
+		},
+	}
+}
+
+/// Print the settings the node would start with.
+fn inspect(settings: &Settings) {
+	println!("PeerId: {}", settings.local_key.public().to_peer_id());
+	for addr in &settings.local_addresses {
+		println!("Listen address: {}", addr);
+	}
+	for (peer_id, addr) in &settings.bootstrap_nodes {
+		println!("Bootstrap peer: {} {}", addr, peer_id);
+	}
+	println!("Interval: {} seconds", settings.interval);
+	if let Some(epochs) = settings.epochs {
+		println!("Epochs: {}", epochs);
+	}
+	if let Some(path) = &settings.scores_path {
+		println!("Scores: {:?}", path);
+	}
+	if settings.mock_proofs {
+		println!("Params: none, the opinions are not proven");
+	} else {
+		println!(
+			"Params: {:?} (k = {})",
+			settings.params_path, settings.params_k
+		);
+	}
+	println!("Log level: {}", settings.log_level);
+	println!("Log format: {:?}", settings.log_format);
+	if let Some(path) = &settings.ipc_socket {
+		println!("IPC socket: {:?}", path);
+	}
+	if let Some(data_dir) = &settings.data_dir {
+		println!("Data dir: {:?}", data_dir.root());
+		println!("Store: {:?}", settings.backend);
+	}
+	println!(
+		"Retention: {} epochs of opinions, {} of proofs",
+		settings.retention.opinion_epochs, settings.retention.proof_epochs
+	);
+}
+
+/// Start the node and run it until it fails.
+async fn run(settings: Settings) -> Result<(), EigenError> {
+	logger::init(settings.log_level, settings.log_format);
+
+	let mut local_addresses = settings.local_addresses.into_iter();
+	let local_address = local_addresses.next().ok_or(EigenError::InvalidAddress)?;
+
+	// The line for the scripts starting the node, to find out where it listens.
+	println!(
+		"listen_addr={} peer_id={}",
+		local_address,
+		settings.local_key.public().to_peer_id()
+	);
+
+	let mut node = if settings.mock_proofs {
+		log::warn!("Running with mock proofs, the opinions are only signed");
+		Node::new_mock(
+			settings.local_key,
+			local_address,
+			settings.bootstrap_nodes,
+			settings.interval,
+		)?
+	} else {
+		let params = params::load_or_generate(&settings.params_path, settings.params_k)?;
+		Node::new(
+			settings.local_key,
+			local_address,
+			settings.bootstrap_nodes,
+			settings.interval,
+			params,
+		)?
+	};
+
+	// The state is restored first, so the scores from the file override the
+	// persisted ones.
+	node.set_config(NodeConfig {
+		backend: settings.backend,
+		retention: settings.retention,
+		..NodeConfig::default()
+	});
+	let restore = match (&settings.restore, &settings.data_dir) {
+		(Some(path), Some(data_dir)) => Some(data_dir.snapshot(path)),
+		(path, _) => path.clone(),
+	};
+	if let Some(data_dir) = settings.data_dir {
+		node.set_data_dir(data_dir);
+	}
+	node.open_storage()?;
+	if let Some(path) = &restore {
+		node.restore(path)?;
+	}
+
+	if let Some(path) = &settings.scores_path {
+		let scores = scores::read(path)?;
+		log::info!("Loaded {} scores from {:?}", scores.len(), path);
+		node.get_peer_mut().set_scores(scores);
+	}
+
+	// The node listens on the rest of the addresses too.
+	for addr in local_addresses {
+		node.get_swarm_mut().listen_on(addr).map_err(|e| {
+			log::error!("swarm.listen_on {:?}", e);
+			EigenError::ListenFailed
+		})?;
+	}
+
+	if let Some(path) = &settings.ipc_socket {
+		ipc::spawn_server(node.handle(), path)?;
+	}
+
+	node.main_loop(settings.epochs).await?;
+
+	Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), EigenError> {
+	match Cli::parse().command {
+		Command::Keygen { out } => keygen(out),
+		Command::Run(args) => run(Settings::resolve(args)?).await,
+		Command::Inspect(args) => {
+			inspect(&Settings::resolve(args)?);
+			Ok(())
+		},
+		Command::Params { k, out, force } => generate_params(k, out, force),
+		Command::Console { socket } => console::run(&socket).await,
+		Command::Status { socket, json } => status(&socket, json).await,
+		Command::Peers { socket, last, json } => peers(&socket, last, json).await,
+		Command::Top {
+			socket,
+			last,
+			refresh,
+		} => top::run(&socket, last, Duration::from_secs(refresh.max(1))).await,
+		Command::ExportProof {
+			socket,
+			peer,
+			epoch,
+			out,
+			sent,
+		} => export_proof(&socket, peer, Epoch(epoch), sent, &out).await,
+		Command::ImportScores { socket, path } => import_scores(&socket, &path).await,
+		Command::Keystore { command } => manage_keystore(command),
+		Command::Key { command } => manage_key(command),
+	}
+}
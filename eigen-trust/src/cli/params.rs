@@ -0,0 +1,75 @@
+//! The KZG params of the node: read from the data dir, or generated and cached
+//! there if missing.
+
+use std::{
+	fs::{self, File},
+	io::Read,
+	path::Path,
+};
+
+use eigen_trust::{storage::write_atomic, EigenError};
+use eigen_trust_circuit::{
+	halo2wrong::{
+		curves::bn256::Bn256,
+		halo2::poly::{commitment::Params, kzg::commitment::ParamsKZG},
+	},
+	utils::generate_params,
+};
+
+/// Read the params, and check that their degree is `k`.
+pub fn read(path: &Path, k: u32) -> Result<ParamsKZG<Bn256>, EigenError> {
+	let mut buffer = Vec::new();
+	File::open(path)
+		.and_then(|mut file| file.read_to_end(&mut buffer))
+		.map_err(|e| {
+			eprintln!("Failed to read the params {:?}: {}", path, e);
+			EigenError::InvalidParams
+		})?;
+	let params = ParamsKZG::<Bn256>::read(&mut &buffer[..]).map_err(|e| {
+		eprintln!("Failed to decode the params {:?}: {}", path, e);
+		EigenError::InvalidParams
+	})?;
+	if params.k() != k {
+		eprintln!(
+			"The params {:?} are for k = {}, but the circuit needs k = {}",
+			path,
+			params.k(),
+			k
+		);
+		return Err(EigenError::InvalidParams);
+	}
+	Ok(params)
+}
+
+/// Generate the params for `k`, and write them to the path, creating its
+/// directory if needed.
+pub fn generate(path: &Path, k: u32) -> Result<ParamsKZG<Bn256>, EigenError> {
+	let params = generate_params::<Bn256>(k);
+	let mut buffer = Vec::new();
+	params
+		.write(&mut buffer)
+		.map_err(|_| EigenError::InvalidParams)?;
+
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir).map_err(|e| {
+			eprintln!("Failed to create the data dir {:?}: {}", dir, e);
+			EigenError::InvalidParams
+		})?;
+	}
+	write_atomic(path, &buffer).map_err(|e| {
+		eprintln!("Failed to write the params {:?}: {}", path, e);
+		EigenError::InvalidParams
+	})?;
+	Ok(params)
+}
+
+/// Read the params, or generate them if the file is missing. Params of the
+/// wrong degree are an error, rather than being overwritten.
+pub fn load_or_generate(path: &Path, k: u32) -> Result<ParamsKZG<Bn256>, EigenError> {
+	if path.exists() {
+		read(path, k)
+	} else {
+		eprintln!("No params at {:?}, generating them for k = {}", path, k);
+		generate(path, k)
+	}
+}
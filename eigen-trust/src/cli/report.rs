@@ -0,0 +1,70 @@
+//! Formatting of the node state, for the terminal.
+
+use eigen_trust::{NodeStatus, PeerReport};
+
+fn or_dash<T: ToString>(value: Option<T>) -> String {
+	value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+/// Print the state of the node.
+pub fn print_status(status: &NodeStatus) {
+	let epoch = status.epoch.map(|epoch| epoch.0);
+	println!("Uptime: {} seconds", status.uptime_secs);
+	println!("Epoch: {}", or_dash(epoch));
+	println!("Global score: {}", or_dash(status.global_score));
+	println!("Last epoch score: {}", or_dash(status.last_epoch_score));
+	println!("Pending requests: {}", status.pending_requests);
+	println!(
+		"Neighbors: {} ({} connected)",
+		status.neighbors, status.connected
+	);
+	println!(
+		"Last proof: {} ms, last verification: {} ms",
+		or_dash(status.last_proof_ms),
+		or_dash(status.last_verification_ms)
+	);
+	println!("Reclaimed by pruning: {} bytes", status.reclaimed_bytes);
+}
+
+/// Print the neighbors as a table, with a column for each epoch. The cells
+/// show the sent and received opinions, with a `*` on the verified ones.
+pub fn print_peers(peers: &[PeerReport]) {
+	let epochs: Vec<u64> = peers
+		.first()
+		.map(|peer| peer.opinions.iter().map(|op| op.epoch.0).collect())
+		.unwrap_or_default();
+
+	let mut header = format!(
+		"{:<54} {:>5} {:>3} {:>5} {:>7} {:>8}",
+		"PEER", "CONN", "ID", "SCORE", "STRIKES", "FAILURES"
+	);
+	for epoch in &epochs {
+		header.push_str(&format!(" {:>17}", format!("E{} SENT/RECV", epoch)));
+	}
+	println!("{}", header);
+
+	for peer in peers {
+		let conn = if peer.connected { "up" } else { "down" };
+		let identified = if peer.identified { "yes" } else { "no" };
+		let mut row = format!(
+			"{:<54} {:>5} {:>3} {:>5} {:>7} {:>8}",
+			peer.peer_id.to_base58(),
+			conn,
+			identified,
+			peer.score,
+			peer.strikes,
+			peer.failures
+		);
+		for op in &peer.opinions {
+			let sent = or_dash(op.sent.map(|v| format!("{:.3}", v)));
+			let received = or_dash(op.received.map(|v| format!("{:.3}", v)));
+			let verified = if op.verified { "*" } else { " " };
+			row.push_str(&format!(
+				" {:>16}{}",
+				format!("{}/{}", sent, received),
+				verified
+			));
+		}
+		println!("{}", row);
+	}
+}
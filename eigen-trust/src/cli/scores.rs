@@ -0,0 +1,66 @@
+//! The local scores towards the neighbors, as `peer_id,score` rows of a CSV
+//! file.
+
+use std::{fs, path::Path, str::FromStr};
+
+use eigen_trust::{EigenError, PeerId};
+
+/// Parse the scores. Blank lines, lines starting with `#`, and a
+/// `peer_id,score` header are skipped.
+pub fn parse(text: &str) -> Result<Vec<(PeerId, u32)>, EigenError> {
+	let mut scores = Vec::new();
+	for (i, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') || (i == 0 && line == "peer_id,score") {
+			continue;
+		}
+		let invalid = |what: &str| {
+			eprintln!("Invalid {} on line {}: {:?}", what, i + 1, line);
+			EigenError::InvalidConfig
+		};
+		let (peer_id, score) = line.split_once(',').ok_or_else(|| invalid("row"))?;
+		let peer_id = PeerId::from_str(peer_id.trim()).map_err(|_| invalid("peer id"))?;
+		let score = score.trim().parse().map_err(|_| invalid("score"))?;
+		scores.push((peer_id, score));
+	}
+	Ok(scores)
+}
+
+/// Read the scores from the CSV file.
+pub fn read(path: &Path) -> Result<Vec<(PeerId, u32)>, EigenError> {
+	let text = fs::read_to_string(path).map_err(|e| {
+		eprintln!("Failed to read the scores {:?}: {}", path, e);
+		EigenError::InvalidConfig
+	})?;
+	parse(&text)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const PEER_1: &str = "12D3KooWLyTCx9j2FMcsHe81RMoDfhXbdyyFgNGQMdcrnhShTvQh";
+	const PEER_2: &str = "12D3KooWKBKXsLwbmVBySEmbKayJzfWp3tPCKrnDCsmNy9prwjvy";
+
+	#[test]
+	fn should_parse_scores() {
+		let text = format!(
+			"peer_id,score\n{},5\n\n# Seeded by hand\n{}, 10\n",
+			PEER_1, PEER_2
+		);
+		let scores = parse(&text).unwrap();
+		assert_eq!(scores, vec![
+			(PeerId::from_str(PEER_1).unwrap(), 5),
+			(PeerId::from_str(PEER_2).unwrap(), 10),
+		]);
+		assert!(parse("").unwrap().is_empty());
+	}
+
+	#[test]
+	fn should_reject_invalid_scores() {
+		assert!(parse(PEER_1).is_err());
+		assert!(parse(&format!("{},-1", PEER_1)).is_err());
+		assert!(parse(&format!("{},0.5", PEER_1)).is_err());
+		assert!(parse("not_a_peer_id,5").is_err());
+	}
+}
@@ -0,0 +1,44 @@
+//! A `top`-style dashboard of a running node, refreshed from its IPC socket.
+
+use crate::report::{print_peers, print_status};
+use std::{
+	io::{self, Write},
+	path::Path,
+	time::Duration,
+};
+use tokio::time;
+
+use eigen_trust::{ipc::IpcClient, Command, EigenError, Reply};
+
+/// Clears the terminal, and moves the cursor to the top.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Show the dashboard of the node at the socket until interrupted, or until
+/// the node stops.
+pub async fn run(socket: &Path, last: u64, refresh: Duration) -> Result<(), EigenError> {
+	let mut client = IpcClient::connect(socket).await.map_err(|e| {
+		eprintln!("Failed to connect to the node at {:?}", socket);
+		e
+	})?;
+
+	let mut interval = time::interval(refresh);
+	loop {
+		interval.tick().await;
+		let status = client.request(&Command::Status).await?;
+		let peers = client.request(&Command::Peers { last }).await?;
+
+		print!("{}", CLEAR_SCREEN);
+		println!("{:?} (refreshed every {:?})", socket, refresh);
+		println!();
+		match (status, peers) {
+			(Reply::Status(status), Reply::Peers(peers)) => {
+				print_status(&status);
+				println!();
+				print_peers(&peers);
+			},
+			(status, peers) => println!("Unexpected replies: {:?}, {:?}", status, peers),
+		}
+		// The screen is only redrawn on the next tick.
+		let _ = io::stdout().flush();
+	}
+}
@@ -3,9 +3,18 @@
 //! - Seconds until next epoch
 //! - Current epoch
 //! - Current timestamp
+//! - Schedule of epoch intervals agreed on by the network
+//! - Epochs driven by an external beacon
 
+use libp2p::{core::PublicKey, identity::Keypair, PeerId};
+use serde::{Deserialize, Serialize};
 use std::{
 	fmt::{Display, Formatter, Result as FmtResult},
+	ops::Range,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
 	time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -13,7 +22,7 @@ use crate::EigenError;
 
 /// Epoch struct, which is a wrapper around epoch number and timestamp.
 // TODO: add epoch_number and timestamp as private fields
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Epoch(pub u64);
 
 impl Display for Epoch {
@@ -71,6 +80,268 @@ impl Epoch {
 	pub fn next(&self) -> Self {
 		Epoch(self.0 + 1)
 	}
+
+	/// Returns the epoch `n` epochs after this one, or `None` on overflow.
+	pub fn checked_add(&self, n: u64) -> Option<Self> {
+		self.0.checked_add(n).map(Epoch)
+	}
+
+	/// Returns the epoch `n` epochs before this one, or `None` on underflow.
+	pub fn checked_sub(&self, n: u64) -> Option<Self> {
+		self.0.checked_sub(n).map(Epoch)
+	}
+
+	/// Iterates over all epochs in the range, ending before `range.end`.
+	pub fn range(range: Range<Epoch>) -> impl Iterator<Item = Epoch> {
+		(range.start.0..range.end.0).map(Epoch)
+	}
+}
+
+/// Schedule of epoch intervals. Each entry is a pair of an activation epoch
+/// and the interval duration (in seconds) used from that epoch onwards, so the
+/// interval can be changed without restarting the network.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochSchedule {
+	entries: Vec<(Epoch, u64)>,
+}
+
+impl EpochSchedule {
+	/// Creates a schedule with a single interval, starting from the epoch 0.
+	pub fn new(interval: u64) -> Result<Self, EigenError> {
+		if interval == 0 {
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		Ok(Self {
+			entries: vec![(Epoch(0), interval)],
+		})
+	}
+
+	/// Adds a new interval, starting from the `activation` epoch.
+	/// Returns `false` if the same entry is already part of the schedule.
+	pub fn add(&mut self, activation: Epoch, interval: u64) -> Result<bool, EigenError> {
+		if self.entries.contains(&(activation, interval)) {
+			return Ok(false);
+		}
+		let (last_activation, _) = self.entries[self.entries.len() - 1];
+		if interval == 0 || activation <= last_activation {
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		self.entries.push((activation, interval));
+		Ok(true)
+	}
+
+	/// Returns the interval duration used in the specified epoch.
+	pub fn interval_at(&self, epoch: Epoch) -> u64 {
+		self.entries
+			.iter()
+			.rev()
+			.find(|(activation, _)| *activation <= epoch)
+			.map(|(_, interval)| *interval)
+			.unwrap_or(self.entries[0].1)
+	}
+
+	/// Returns the UNIX timestamp at which the specified epoch starts.
+	pub fn epoch_start(&self, epoch: Epoch) -> u64 {
+		let mut start = 0;
+		let mut entries = self.entries.iter().peekable();
+		while let Some((activation, interval)) = entries.next() {
+			match entries.peek() {
+				Some((next, _)) if *next <= epoch => {
+					start += (next.0 - activation.0) * interval;
+				},
+				_ => return start + (epoch.0 - activation.0) * interval,
+			}
+		}
+		start
+	}
+
+	/// Returns the epoch at the specified UNIX timestamp.
+	pub fn epoch_at(&self, timestamp: u64) -> Epoch {
+		let mut start = 0;
+		let mut entries = self.entries.iter().peekable();
+		while let Some((activation, interval)) = entries.next() {
+			if let Some((next, _)) = entries.peek() {
+				let end = start + (next.0 - activation.0) * interval;
+				if timestamp >= end {
+					start = end;
+					continue;
+				}
+			}
+			return Epoch(activation.0 + (timestamp - start) / interval);
+		}
+		Epoch(0)
+	}
+
+	/// Calculates the current epoch number based on the schedule.
+	pub fn current_epoch(&self) -> Result<Epoch, EigenError> {
+		let timestamp = Epoch::current_timestamp()?;
+		Ok(self.epoch_at(timestamp))
+	}
+
+	/// Calculates the seconds until the next epoch based on the schedule.
+	pub fn secs_until_next_epoch(&self) -> Result<u64, EigenError> {
+		let timestamp = Epoch::current_timestamp()?;
+		let next_epoch = self.epoch_at(timestamp).next();
+		Ok(self.epoch_start(next_epoch) - timestamp)
+	}
+}
+
+/// A source of epochs for the node. By default, epochs are derived from the
+/// wall clock, but they can also come from an external beacon.
+pub trait EpochSource: Send {
+	/// Returns the current epoch.
+	fn current_epoch(&self) -> Result<Epoch, EigenError>;
+	/// Returns the seconds until the node should check for the next epoch.
+	fn secs_until_next_epoch(&self) -> Result<u64, EigenError>;
+}
+
+impl EpochSource for EpochSchedule {
+	fn current_epoch(&self) -> Result<Epoch, EigenError> {
+		EpochSchedule::current_epoch(self)
+	}
+
+	fn secs_until_next_epoch(&self) -> Result<u64, EigenError> {
+		EpochSchedule::secs_until_next_epoch(self)
+	}
+}
+
+/// Epoch source driven by an external beacon, like an Ethereum block number
+/// or a drand round. The application feeds the latest round with
+/// `BeaconSource::update` (on any clone of the source), and every
+/// `rounds_per_epoch` rounds make up one epoch.
+#[derive(Clone, Debug)]
+pub struct BeaconSource {
+	round: Arc<AtomicU64>,
+	rounds_per_epoch: u64,
+	poll_interval: u64,
+}
+
+impl BeaconSource {
+	/// Creates a new beacon source. The node checks for the epoch change every
+	/// `poll_interval` seconds.
+	pub fn new(rounds_per_epoch: u64, poll_interval: u64) -> Result<Self, EigenError> {
+		if rounds_per_epoch == 0 || poll_interval == 0 {
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		Ok(Self {
+			round: Arc::new(AtomicU64::new(0)),
+			rounds_per_epoch,
+			poll_interval,
+		})
+	}
+
+	/// Updates the latest beacon round. Rounds older than the latest one are
+	/// ignored.
+	pub fn update(&self, round: u64) {
+		self.round.fetch_max(round, Ordering::SeqCst);
+	}
+
+	/// Returns the latest beacon round.
+	pub fn round(&self) -> u64 {
+		self.round.load(Ordering::SeqCst)
+	}
+}
+
+impl EpochSource for BeaconSource {
+	fn current_epoch(&self) -> Result<Epoch, EigenError> {
+		Ok(Epoch(self.round() / self.rounds_per_epoch))
+	}
+
+	fn secs_until_next_epoch(&self) -> Result<u64, EigenError> {
+		Ok(self.poll_interval)
+	}
+}
+
+/// Announcement of a new epoch interval, signed by one of the bootstrap
+/// nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpochConfig {
+	/// The first epoch that uses the new interval.
+	pub activation: Epoch,
+	/// The new interval duration in seconds.
+	pub interval: u64,
+	/// Public key of the signer.
+	pub pubkey: PublicKey,
+	/// Signature over the activation epoch and the interval.
+	pub signature: Vec<u8>,
+}
+
+impl EpochConfig {
+	/// Creates a new announcement, signed with the given keypair.
+	pub fn new(keypair: &Keypair, activation: Epoch, interval: u64) -> Result<Self, EigenError> {
+		let message = Self::message(activation, interval);
+		let signature = keypair
+			.sign(&message)
+			.map_err(|_| EigenError::SignatureError)?;
+		Ok(Self {
+			activation,
+			interval,
+			pubkey: keypair.public(),
+			signature,
+		})
+	}
+
+	/// The message that is signed by the bootstrap node.
+	fn message(activation: Epoch, interval: u64) -> Vec<u8> {
+		let mut message = b"eigen_trust/epoch_config".to_vec();
+		message.extend(activation.to_be_bytes());
+		message.extend(interval.to_be_bytes());
+		message
+	}
+
+	/// The encoding of the announcement: `activation (u64 BE) || interval
+	/// (u64 BE) || pubkey_len || pubkey || signature`, with the public key in
+	/// its protobuf encoding.
+	pub fn to_bytes(&self) -> Result<Vec<u8>, EigenError> {
+		let pubkey = self.pubkey.to_protobuf_encoding();
+		let pubkey_len = u8::try_from(pubkey.len()).map_err(|_| EigenError::InvalidEpochConfig)?;
+		let mut bytes = self.activation.to_be_bytes().to_vec();
+		bytes.extend(self.interval.to_be_bytes());
+		bytes.push(pubkey_len);
+		bytes.extend(pubkey);
+		bytes.extend(&self.signature);
+		Ok(bytes)
+	}
+
+	/// Decode the announcement from its encoding. The signature is not
+	/// checked.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, EigenError> {
+		if bytes.len() < 17 {
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		let (activation, rest) = bytes.split_at(8);
+		let (interval, rest) = rest.split_at(8);
+		let (pubkey_len, rest) = rest.split_at(1);
+		let pubkey_len = usize::from(pubkey_len[0]);
+		if rest.len() < pubkey_len {
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		let (pubkey, signature) = rest.split_at(pubkey_len);
+		let to_u64 = |bytes: &[u8]| -> Result<u64, EigenError> {
+			let bytes = bytes
+				.try_into()
+				.map_err(|_| EigenError::InvalidEpochConfig)?;
+			Ok(u64::from_be_bytes(bytes))
+		};
+		Ok(Self {
+			activation: Epoch(to_u64(activation)?),
+			interval: to_u64(interval)?,
+			pubkey: PublicKey::from_protobuf_encoding(pubkey)
+				.map_err(|_| EigenError::InvalidEpochConfig)?,
+			signature: signature.to_vec(),
+		})
+	}
+
+	/// Returns the peer id of the signer.
+	pub fn signer(&self) -> PeerId {
+		self.pubkey.to_peer_id()
+	}
+
+	/// Verifies the signature of the announcement.
+	pub fn verify(&self) -> bool {
+		let message = Self::message(self.activation, self.interval);
+		self.pubkey.verify(&message, &self.signature)
+	}
 }
 
 #[cfg(test)]
@@ -90,6 +361,34 @@ mod tests {
 		assert_eq!(epoch.previous(), Epoch(0));
 	}
 
+	#[test]
+	fn test_epoch_checked_arithmetic() {
+		let epoch = Epoch(5);
+		assert_eq!(epoch.checked_add(3), Some(Epoch(8)));
+		assert_eq!(epoch.checked_sub(5), Some(Epoch(0)));
+		assert_eq!(epoch.checked_sub(6), None);
+		assert_eq!(Epoch(u64::MAX).checked_add(1), None);
+	}
+
+	#[test]
+	fn test_epoch_range() {
+		let epochs: Vec<Epoch> = Epoch::range(Epoch(2)..Epoch(5)).collect();
+		assert_eq!(epochs, vec![Epoch(2), Epoch(3), Epoch(4)]);
+
+		let empty: Vec<Epoch> = Epoch::range(Epoch(5)..Epoch(2)).collect();
+		assert!(empty.is_empty());
+	}
+
+	#[test]
+	fn test_epoch_serde() {
+		let epoch = Epoch(123);
+		let json = serde_json::to_string(&epoch).unwrap();
+		assert_eq!(json, "123");
+
+		let decoded: Epoch = serde_json::from_str(&json).unwrap();
+		assert_eq!(decoded, epoch);
+	}
+
 	#[test]
 	fn test_epoch_to_be_bytes() {
 		let epoch = Epoch(0);
@@ -123,6 +422,88 @@ mod tests {
 		assert_eq!(expected, secs_until_next_epoch);
 	}
 
+	#[test]
+	fn test_schedule_single_interval() {
+		let schedule = EpochSchedule::new(10).unwrap();
+		assert_eq!(schedule.epoch_at(0), Epoch(0));
+		assert_eq!(schedule.epoch_at(125), Epoch(12));
+		assert_eq!(schedule.epoch_start(Epoch(12)), 120);
+		assert_eq!(schedule.interval_at(Epoch(12)), 10);
+
+		let current_epoch = schedule.current_epoch().unwrap();
+		assert_eq!(current_epoch, Epoch::current_epoch(10).unwrap());
+	}
+
+	#[test]
+	fn test_schedule_interval_change() {
+		let mut schedule = EpochSchedule::new(10).unwrap();
+		assert!(schedule.add(Epoch(10), 30).unwrap());
+		// Adding the same entry again is a no-op.
+		assert!(!schedule.add(Epoch(10), 30).unwrap());
+
+		// Epoch 10 starts at 100, the epochs after it last 30 seconds.
+		assert_eq!(schedule.epoch_start(Epoch(10)), 100);
+		assert_eq!(schedule.epoch_start(Epoch(12)), 160);
+		assert_eq!(schedule.epoch_at(99), Epoch(9));
+		assert_eq!(schedule.epoch_at(100), Epoch(10));
+		assert_eq!(schedule.epoch_at(165), Epoch(12));
+		assert_eq!(schedule.interval_at(Epoch(9)), 10);
+		assert_eq!(schedule.interval_at(Epoch(11)), 30);
+	}
+
+	#[test]
+	fn test_schedule_invalid_entries() {
+		assert!(EpochSchedule::new(0).is_err());
+
+		let mut schedule = EpochSchedule::new(10).unwrap();
+		schedule.add(Epoch(10), 30).unwrap();
+		assert!(schedule.add(Epoch(5), 20).is_err());
+		assert!(schedule.add(Epoch(20), 0).is_err());
+	}
+
+	#[test]
+	fn test_beacon_source() {
+		assert!(BeaconSource::new(0, 1).is_err());
+		assert!(BeaconSource::new(1, 0).is_err());
+
+		let source = BeaconSource::new(10, 1).unwrap();
+		let handle = source.clone();
+		assert_eq!(source.current_epoch().unwrap(), Epoch(0));
+
+		handle.update(125);
+		assert_eq!(source.current_epoch().unwrap(), Epoch(12));
+
+		// The beacon never goes backwards.
+		handle.update(90);
+		assert_eq!(source.round(), 125);
+		assert_eq!(source.secs_until_next_epoch().unwrap(), 1);
+	}
+
+	#[test]
+	fn test_epoch_config_signature() {
+		let keypair = Keypair::generate_secp256k1();
+		let config = EpochConfig::new(&keypair, Epoch(10), 30).unwrap();
+		assert!(config.verify());
+		assert_eq!(config.signer(), keypair.public().to_peer_id());
+
+		let mut tampered = config;
+		tampered.interval = 60;
+		assert!(!tampered.verify());
+	}
+
+	#[test]
+	fn test_epoch_config_encoding() {
+		let keypair = Keypair::generate_secp256k1();
+		let config = EpochConfig::new(&keypair, Epoch(10), 30).unwrap();
+		let bytes = config.to_bytes().unwrap();
+		let decoded = EpochConfig::from_bytes(&bytes).unwrap();
+		assert_eq!(decoded, config);
+		assert!(decoded.verify());
+
+		assert!(EpochConfig::from_bytes(&bytes[..16]).is_err());
+		assert!(EpochConfig::from_bytes(&bytes[..20]).is_err());
+	}
+
 	#[test]
 	fn test_epoch_current_timestamp() {
 		let timestamp = Epoch::current_timestamp().unwrap();
@@ -0,0 +1,294 @@
+//! The handle to a running node, for sending it commands from other tasks,
+//! like the admin console over the IPC socket.
+
+use crate::{epoch::Epoch, EigenError};
+use futures::channel::{
+	mpsc::{UnboundedReceiver, UnboundedSender},
+	oneshot,
+};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The commands the node runs on behalf of its handles.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+	/// Set the local score towards a neighbor.
+	SetScore {
+		/// The neighbor.
+		#[serde(with = "peer_id_str")]
+		peer_id: PeerId,
+		/// The new score.
+		score: u32,
+	},
+	/// Set the local scores towards many neighbors at once.
+	SetScores {
+		/// The neighbors and their new scores.
+		scores: Vec<PeerScore>,
+	},
+	/// List the neighbors.
+	Neighbors,
+	/// Get the current epoch.
+	CurrentEpoch,
+	/// Get the current state of the node.
+	Status,
+	/// List the neighbors, with the opinions exchanged over the last epochs.
+	Peers {
+		/// The number of past epochs.
+		last: u64,
+	},
+	/// Get the global trust score at the epoch.
+	GlobalScore {
+		/// The epoch of the score.
+		epoch: Epoch,
+	},
+	/// Export the proof of the opinion exchanged with the neighbor in the
+	/// epoch, with its public inputs.
+	ExportProof {
+		/// The neighbor.
+		#[serde(with = "peer_id_str")]
+		peer_id: PeerId,
+		/// The epoch of the opinion.
+		epoch: Epoch,
+		/// Export the opinion sent to the neighbor, instead of the one it sent
+		/// to us.
+		sent: bool,
+	},
+	/// Block the peer, and disconnect from it.
+	Ban {
+		/// The blocked peer.
+		#[serde(with = "peer_id_str")]
+		peer_id: PeerId,
+	},
+	/// Revoke the opinion sent to the neighbor in the epoch.
+	Revoke {
+		/// The receiver of the opinion.
+		#[serde(with = "peer_id_str")]
+		peer_id: PeerId,
+		/// The epoch of the opinion.
+		epoch: Epoch,
+	},
+	/// Challenge the opinion the neighbor sent to us in the epoch.
+	Challenge {
+		/// The author of the opinion.
+		#[serde(with = "peer_id_str")]
+		peer_id: PeerId,
+		/// The epoch of the opinion.
+		epoch: Epoch,
+	},
+	/// Write the snapshot of the node state to the path, on the host of the
+	/// node. A relative path is in the snapshots dir of the data dir, if
+	/// there is one.
+	Snapshot {
+		/// The path of the snapshot.
+		path: PathBuf,
+	},
+	/// Write the trust history of the epochs to an archive at the path, on
+	/// the host of the node. A relative path is in the archives dir of the
+	/// data dir, if there is one.
+	ExportArchive {
+		/// The first epoch of the history.
+		from: Epoch,
+		/// The last epoch of the history.
+		to: Epoch,
+		/// The path of the archive.
+		path: PathBuf,
+	},
+	/// Import the trust history from the archive at the path, on the host of
+	/// the node. A relative path is in the archives dir of the data dir.
+	ImportArchive {
+		/// The path of the archive.
+		path: PathBuf,
+	},
+}
+
+/// The local score towards a peer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerScore {
+	/// The peer.
+	#[serde(with = "peer_id_str")]
+	pub peer_id: PeerId,
+	/// The score.
+	pub score: u32,
+}
+
+/// A neighbor of the node, as seen by the handles.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NeighborInfo {
+	/// The neighbor.
+	#[serde(with = "peer_id_str")]
+	pub peer_id: PeerId,
+	/// Whether the public key of the neighbor is known.
+	pub identified: bool,
+	/// The local score towards the neighbor.
+	pub score: u32,
+}
+
+/// The current state of the node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+	/// The seconds since the node was created.
+	pub uptime_secs: u64,
+	/// The current epoch, if known.
+	pub epoch: Option<Epoch>,
+	/// The global trust score at the current epoch.
+	pub global_score: Option<f64>,
+	/// The score logged at the start of the last epoch the node took part in.
+	pub last_epoch_score: Option<f64>,
+	/// The number of requests waiting for a response.
+	pub pending_requests: usize,
+	/// The number of neighbors.
+	pub neighbors: usize,
+	/// The number of connected peers.
+	pub connected: usize,
+	/// How long the last opinion took to prove, in milliseconds.
+	pub last_proof_ms: Option<u64>,
+	/// How long the last opinion took to verify, in milliseconds.
+	pub last_verification_ms: Option<u64>,
+	/// The bytes reclaimed by pruning the store since the node started.
+	pub reclaimed_bytes: u64,
+}
+
+/// The opinions exchanged with a neighbor in an epoch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpochOpinions {
+	/// The epoch of the opinions.
+	pub epoch: Epoch,
+	/// The opinion sent to the neighbor, if any.
+	pub sent: Option<f64>,
+	/// The opinion received from the neighbor, if any.
+	pub received: Option<f64>,
+	/// Whether the received opinion was verified.
+	pub verified: bool,
+}
+
+/// A neighbor of the node, with the health of the connection and the recent
+/// opinions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerReport {
+	/// The neighbor.
+	#[serde(with = "peer_id_str")]
+	pub peer_id: PeerId,
+	/// Whether the public key of the neighbor is known.
+	pub identified: bool,
+	/// Whether the neighbor is connected.
+	pub connected: bool,
+	/// The local score towards the neighbor.
+	pub score: u32,
+	/// The number of challenges the neighbor failed.
+	pub strikes: u32,
+	/// The number of failed requests to the neighbor.
+	pub failures: u32,
+	/// The opinions exchanged in the recent epochs, oldest first.
+	pub opinions: Vec<EpochOpinions>,
+}
+
+/// The proof of an opinion, packaged for the on-chain verifier.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProofExport {
+	/// The CID of the proof bytes, the same the store refers to the proof by.
+	pub cid: String,
+	/// The public inputs and the proof, as the calldata of the verifier.
+	pub calldata: Vec<u8>,
+}
+
+/// The replies of the node to the commands.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reply", content = "value", rename_all = "snake_case")]
+pub enum Reply {
+	/// The command was run.
+	Done,
+	/// The current epoch.
+	Epoch(Epoch),
+	/// The global trust score.
+	Score(f64),
+	/// The neighbors of the node.
+	Neighbors(Vec<NeighborInfo>),
+	/// The current state of the node.
+	Status(NodeStatus),
+	/// The neighbors, with the recent opinions.
+	Peers(Vec<PeerReport>),
+	/// The proof of an opinion, for the on-chain verifier.
+	Proof(ProofExport),
+	/// The command failed.
+	Error(String),
+}
+
+impl Reply {
+	/// The reply to a command that only succeeds or fails.
+	pub(crate) fn from_result(result: Result<(), EigenError>) -> Self {
+		match result {
+			Ok(()) => Reply::Done,
+			Err(e) => Reply::Error(format!("{:?}", e)),
+		}
+	}
+}
+
+/// A command, together with the channel for its reply.
+pub(crate) type CommandRequest = (Command, oneshot::Sender<Reply>);
+/// The receiving end of the commands, polled in the main loop of the node.
+pub(crate) type CommandReceiver = UnboundedReceiver<CommandRequest>;
+
+/// A cloneable handle to the node, that stays valid while the main loop runs.
+#[derive(Clone, Debug)]
+pub struct NodeHandle {
+	commands: UnboundedSender<CommandRequest>,
+}
+
+impl NodeHandle {
+	pub(crate) fn new(commands: UnboundedSender<CommandRequest>) -> Self {
+		Self { commands }
+	}
+
+	/// Send the command to the node, and wait for its reply. Fails if the
+	/// node has stopped.
+	pub async fn request(&self, command: Command) -> Result<Reply, EigenError> {
+		let (tx, rx) = oneshot::channel();
+		self.commands
+			.unbounded_send((command, tx))
+			.map_err(|_| EigenError::NodeStopped)?;
+		rx.await.map_err(|_| EigenError::NodeStopped)
+	}
+}
+
+/// (De)serialize the peer ids as their base58 strings.
+pub(crate) mod peer_id_str {
+	use libp2p::PeerId;
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+	use std::str::FromStr;
+
+	pub fn serialize<S: Serializer>(peer_id: &PeerId, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&peer_id.to_base58())
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PeerId, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		PeerId::from_str(&s).map_err(D::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_serialize_commands() {
+		let peer_id = PeerId::random();
+		let command = Command::SetScore { peer_id, score: 5 };
+		let json = serde_json::to_string(&command).unwrap();
+		assert!(json.contains(&peer_id.to_base58()));
+		assert_eq!(serde_json::from_str::<Command>(&json).unwrap(), command);
+
+		let reply = Reply::Neighbors(vec![NeighborInfo {
+			peer_id,
+			identified: true,
+			score: 5,
+		}]);
+		let json = serde_json::to_string(&reply).unwrap();
+		assert_eq!(serde_json::from_str::<Reply>(&json).unwrap(), reply);
+
+		let invalid = r#"{"command":"ban","peer_id":"not_a_peer_id"}"#;
+		assert!(serde_json::from_str::<Command>(invalid).is_err());
+	}
+}
@@ -0,0 +1,161 @@
+//! The local IPC socket of the node. The commands and the replies are sent as
+//! JSON, one per line, over a unix domain socket.
+
+use crate::{
+	handle::{Command, NodeHandle, Reply},
+	EigenError,
+};
+use std::{fs, os::unix::net::UnixStream as StdUnixStream, path::Path};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+	net::{
+		unix::{OwnedReadHalf, OwnedWriteHalf},
+		UnixListener, UnixStream,
+	},
+	task::{self, JoinHandle},
+};
+
+/// Bind the socket at the path, and serve the commands of the connected
+/// clients with the handle, until the node stops. A stale socket file, left
+/// by a node that didn't shut down cleanly, is replaced, but a socket of a
+/// running node is not.
+pub fn spawn_server(handle: NodeHandle, path: &Path) -> Result<JoinHandle<()>, EigenError> {
+	if path.exists() {
+		if StdUnixStream::connect(path).is_ok() {
+			log::error!("The IPC socket {:?} is in use", path);
+			return Err(EigenError::IpcError);
+		}
+		fs::remove_file(path).map_err(|e| {
+			log::error!("Failed to remove the stale IPC socket {:?}: {}", path, e);
+			EigenError::IpcError
+		})?;
+	}
+	let listener = UnixListener::bind(path).map_err(|e| {
+		log::error!("Failed to bind the IPC socket {:?}: {}", path, e);
+		EigenError::IpcError
+	})?;
+	log::info!("IPC socket listening on {:?}", path);
+
+	Ok(task::spawn(async move {
+		loop {
+			match listener.accept().await {
+				Ok((stream, _)) => {
+					task::spawn(serve_client(handle.clone(), stream));
+				},
+				Err(e) => log::error!("IPC accept error {:?}", e),
+			}
+		}
+	}))
+}
+
+/// Answer the commands of a single client, until it disconnects.
+async fn serve_client(handle: NodeHandle, stream: UnixStream) {
+	let (reader, mut writer) = stream.into_split();
+	let mut lines = BufReader::new(reader).lines();
+	while let Ok(Some(line)) = lines.next_line().await {
+		let reply = match serde_json::from_str::<Command>(&line) {
+			Ok(command) => match handle.request(command).await {
+				Ok(reply) => reply,
+				// The node is gone, so there is nothing to serve anymore.
+				Err(_) => return,
+			},
+			Err(e) => Reply::Error(format!("Invalid command: {}", e)),
+		};
+		if write_line(&mut writer, &reply).await.is_err() {
+			return;
+		}
+	}
+}
+
+/// Write the value as a line of JSON.
+async fn write_line<T: serde::Serialize>(
+	writer: &mut OwnedWriteHalf,
+	value: &T,
+) -> Result<(), EigenError> {
+	let mut line = serde_json::to_vec(value).map_err(|_| EigenError::IpcError)?;
+	line.push(b'\n');
+	writer
+		.write_all(&line)
+		.await
+		.map_err(|_| EigenError::IpcError)
+}
+
+/// A client of the IPC socket of a running node.
+pub struct IpcClient {
+	lines: Lines<BufReader<OwnedReadHalf>>,
+	writer: OwnedWriteHalf,
+}
+
+impl IpcClient {
+	/// Connect to the socket of the node.
+	pub async fn connect(path: &Path) -> Result<Self, EigenError> {
+		let stream = UnixStream::connect(path).await.map_err(|e| {
+			log::debug!("Failed to connect to the IPC socket {:?}: {}", path, e);
+			EigenError::IpcError
+		})?;
+		let (reader, writer) = stream.into_split();
+		Ok(Self {
+			lines: BufReader::new(reader).lines(),
+			writer,
+		})
+	}
+
+	/// Send the command to the node, and wait for its reply.
+	pub async fn request(&mut self, command: &Command) -> Result<Reply, EigenError> {
+		write_line(&mut self.writer, command).await?;
+		let line = self
+			.lines
+			.next_line()
+			.await
+			.map_err(|_| EigenError::IpcError)?
+			.ok_or(EigenError::NodeStopped)?;
+		serde_json::from_str(&line).map_err(|_| EigenError::IpcError)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::epoch::Epoch;
+	use futures::{channel::mpsc::unbounded, StreamExt};
+	use std::env;
+	use tokio::io::AsyncReadExt;
+
+	#[tokio::test]
+	async fn should_serve_commands_over_socket() {
+		let path = env::temp_dir().join(format!("eigen-trust-{}.sock", std::process::id()));
+		let (tx, mut rx) = unbounded();
+		let server = spawn_server(NodeHandle::new(tx), &path).unwrap();
+		// A second node can't take over the socket.
+		let (tx2, _rx2) = unbounded();
+		assert!(spawn_server(NodeHandle::new(tx2), &path).is_err());
+
+		// Stand in for the node, answering with the epoch.
+		tokio::spawn(async move {
+			while let Some((command, reply_tx)) = rx.next().await {
+				let reply = match command {
+					Command::CurrentEpoch => Reply::Epoch(Epoch(7)),
+					_ => Reply::Done,
+				};
+				let _ = reply_tx.send(reply);
+			}
+		});
+
+		let mut client = IpcClient::connect(&path).await.unwrap();
+		let reply = client.request(&Command::CurrentEpoch).await.unwrap();
+		assert_eq!(reply, Reply::Epoch(Epoch(7)));
+		let reply = client.request(&Command::Neighbors).await.unwrap();
+		assert_eq!(reply, Reply::Done);
+
+		// Malformed commands are answered with an error.
+		let mut stream = UnixStream::connect(&path).await.unwrap();
+		stream.write_all(b"not json\n").await.unwrap();
+		let mut buf = [0u8; 1024];
+		let n = stream.read(&mut buf).await.unwrap();
+		let reply: Reply = serde_json::from_slice(&buf[..n]).unwrap();
+		assert!(matches!(reply, Reply::Error(_)));
+
+		server.abort();
+		fs::remove_file(&path).unwrap();
+	}
+}
@@ -87,6 +87,12 @@
 /// The module for epoch-related calculations, like seconds until the next
 /// epoch, current epoch, etc.
 mod epoch;
+/// The module for the handles to a running node, and the commands they send.
+mod handle;
+/// The module for the local IPC socket of the node, serving the commands of
+/// the handles.
+#[cfg(unix)]
+pub mod ipc;
 /// The module for the node setup, running the main loop, and handling network
 /// events.
 mod node;
@@ -98,13 +104,19 @@ mod node;
 mod peer;
 /// The module for defining the request-response protocol.
 mod protocol;
+/// The module for persisting the state of the node across restarts.
+pub mod storage;
 
 pub use eigen_trust_circuit;
-pub use epoch::Epoch;
+pub use epoch::{BeaconSource, Epoch, EpochConfig, EpochSchedule, EpochSource};
+pub use handle::{
+	Command, EpochOpinions, NeighborInfo, NodeHandle, NodeStatus, PeerReport, PeerScore,
+	ProofExport, Reply,
+};
 pub use libp2p::{identity::Keypair, Multiaddr, PeerId};
 pub use log::LevelFilter;
-pub use node::Node;
-pub use peer::Peer;
+pub use node::{Node, NodeConfig, NodeEvent};
+pub use peer::{NetworkConfig, Peer};
 
 use eigen_trust_circuit::halo2wrong::halo2::plonk::Error as H2Error;
 
@@ -141,6 +153,42 @@ pub enum EigenError {
 	ProvingError,
 	/// Verification error
 	VerificationError,
+	/// Invalid epoch interval or schedule entry.
+	InvalidEpochConfig,
+	/// The bytes are not a canonical encoding of an opinion.
+	InvalidOpinion,
+	/// The receiver of an opinion is not in the neighbor set of its author.
+	NeighborNotFound,
+	/// The local scores of the neighbors don't add up to one.
+	InvalidScores,
+	/// The opinion can't be revoked, because there is none, or its epoch is
+	/// outside of the reconciliation window.
+	InvalidRevocation,
+	/// There is no proven opinion of the neighbor to challenge.
+	InvalidChallenge,
+	/// The config file can't be read, or has invalid values.
+	InvalidConfig,
+	/// The KZG params can't be read or written, or are of the wrong degree.
+	InvalidParams,
+	/// The node stopped, so it can't run the commands anymore.
+	NodeStopped,
+	/// The IPC socket can't be bound or connected to, or the message is
+	/// malformed.
+	IpcError,
+	/// The store can't be opened, read or written, or holds malformed records.
+	StorageError,
+	/// The snapshot can't be read or written, is corrupted, or belongs to
+	/// another node.
+	InvalidSnapshot,
+	/// The archive of the trust history can't be read or written, or is
+	/// corrupted.
+	InvalidArchive,
+	/// The keystore can't be read or written, is malformed, or the passphrase
+	/// is wrong.
+	KeystoreError,
+	/// The data dir can't be created, is used by another process, or has an
+	/// unknown layout.
+	InvalidDataDir,
 	/// Halo2 error
 	Halo2Error(H2Error),
 }
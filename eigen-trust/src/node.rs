@@ -1,36 +1,221 @@
 //! The module for the node setup, running the main loop, and handling network
 //! events.
 
+#[cfg(feature = "sled")]
+use crate::storage::SledStore;
+#[cfg(feature = "sqlite")]
+use crate::storage::SqliteStore;
 use crate::{
-	epoch::Epoch,
-	peer::Peer,
+	epoch::{Epoch, EpochConfig, EpochSchedule, EpochSource},
+	handle::{
+		Command, CommandReceiver, CommandRequest, EpochOpinions, NeighborInfo, NodeHandle,
+		NodeStatus, PeerReport, ProofExport, Reply,
+	},
+	peer::{
+		opinion::{Opinion, Revocation},
+		NetworkConfig, Peer,
+	},
 	protocol::{
 		req_res::{Request, Response},
 		EigenEvent, EigenTrustBehaviour,
 	},
+	storage::{
+		proof_cid, proof_id, prune, read_archive, read_snapshot, recover, write_archive,
+		write_snapshot, Backend, DataDir, MemoryStore, PruneStats, Retention, Store, Tree, Wal,
+		WalEntry, WriteOp,
+	},
 	EigenError,
 };
 use eigen_trust_circuit::halo2wrong::{
 	curves::bn256::Bn256, halo2::poly::kzg::commitment::ParamsKZG,
 };
-use futures::StreamExt;
+use futures::{
+	channel::{
+		mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+		oneshot,
+	},
+	StreamExt,
+};
 use libp2p::{
-	core::{either::EitherError, upgrade::Version},
+	core::{either::EitherError, upgrade::Version, ConnectedPoint, PublicKey},
 	identify::IdentifyEvent,
 	identity::Keypair,
 	noise::{Keypair as NoiseKeypair, NoiseConfig, X25519Spec},
-	request_response::{RequestResponseEvent, RequestResponseMessage},
+	request_response::{RequestId, RequestResponseEvent, RequestResponseMessage},
 	swarm::{ConnectionHandlerUpgrErr, Swarm, SwarmBuilder, SwarmEvent},
 	tcp::TcpConfig,
 	yamux::YamuxConfig,
 	Multiaddr, PeerId, Transport,
 };
-use std::io::Error as IoError;
+use rand::Rng;
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	io::Error as IoError,
+	ops::{Range, RangeInclusive},
+	path::{Path, PathBuf},
+	sync::Arc,
+};
 use tokio::{
-	select,
+	select, task,
 	time::{self, Duration, Instant},
 };
 
+/// The maximum number of missed epochs the node will try to recover when it
+/// comes back online after downtime.
+pub const MAX_CATCH_UP_EPOCHS: u64 = 10;
+
+/// The epochs missed between the last epoch the node took part in and the
+/// current epoch, both exclusive, capped to the last `MAX_CATCH_UP_EPOCHS`.
+fn catch_up_epochs(last_epoch: Epoch, current_epoch: Epoch) -> Range<Epoch> {
+	let first_missed = last_epoch.0.saturating_add(1);
+	let start = first_missed.max(current_epoch.0.saturating_sub(MAX_CATCH_UP_EPOCHS));
+	Epoch(start.min(current_epoch.0))..current_epoch
+}
+
+/// Optional settings of the node.
+#[derive(Clone, Debug)]
+pub struct NodeConfig {
+	/// The maximum random delay before sending the requests for opinions at
+	/// the start of an epoch. Used to spread the requests of all nodes across
+	/// the first half of the epoch, instead of sending them at the same time.
+	pub max_jitter: Duration,
+	/// The number of past epochs in which late opinions still update the
+	/// global trust score.
+	pub reconciliation_window: u64,
+	/// The number of failed challenges after which a neighbor is blocked.
+	pub max_strikes: u32,
+	/// The data dir, for keeping the scores, the opinions, the address book
+	/// and the epoch checkpoints across restarts, see [`DataDir`] for the
+	/// layout. Without it, everything is kept in memory.
+	pub data_dir: Option<PathBuf>,
+	/// The backend of the store in the data dir. Switching it doesn't move
+	/// the state, a snapshot can be restored into the new one.
+	pub backend: Backend,
+	/// How many epochs of the opinions and their proofs are kept. The proofs
+	/// are kept for at least as long as the neighbors can catch up on, or
+	/// challenge, the opinions.
+	pub retention: Retention,
+}
+
+impl Default for NodeConfig {
+	fn default() -> Self {
+		Self {
+			max_jitter: Duration::ZERO,
+			reconciliation_window: 2,
+			max_strikes: 3,
+			data_dir: None,
+			backend: Backend::default(),
+			retention: Retention::default(),
+		}
+	}
+}
+
+/// The events emitted by the node to the application.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeEvent {
+	/// The global trust score of a past epoch changed, because of the opinions
+	/// that arrived late.
+	ScoreUpdated {
+		/// The epoch of the score.
+		epoch: Epoch,
+		/// The updated score.
+		score: f64,
+	},
+	/// A neighbor failed to back up its opinion when challenged.
+	Strike {
+		/// The challenged neighbor.
+		peer_id: PeerId,
+		/// The number of challenges it failed so far.
+		strikes: u32,
+	},
+	/// A neighbor failed too many challenges, and was blocked.
+	Blocked {
+		/// The blocked neighbor.
+		peer_id: PeerId,
+	},
+	/// A neighbor revoked the opinion it sent to us, so it no longer counts.
+	OpinionRevoked {
+		/// The author of the opinion.
+		peer_id: PeerId,
+		/// The epoch of the opinion.
+		epoch: Epoch,
+	},
+	/// A new epoch started.
+	EpochStarted {
+		/// The epoch.
+		epoch: Epoch,
+		/// The global trust score at the start of the epoch.
+		score: f64,
+	},
+	/// The opinion towards a neighbor was proven.
+	OpinionProven {
+		/// The receiver of the opinion.
+		peer_id: PeerId,
+		/// The epoch of the opinion.
+		epoch: Epoch,
+		/// How long the proving took.
+		duration_ms: u64,
+	},
+	/// The opinion of a neighbor was verified.
+	OpinionVerified {
+		/// The author of the opinion.
+		peer_id: PeerId,
+		/// The epoch of the opinion.
+		epoch: Epoch,
+		/// Whether the opinion is valid.
+		valid: bool,
+		/// How long the verification took.
+		duration_ms: u64,
+	},
+	/// A neighbor answered the request for its opinion.
+	OpinionReceived {
+		/// The author of the opinion.
+		peer_id: PeerId,
+		/// The epoch of the opinion.
+		epoch: Epoch,
+		/// The time between the request and the response.
+		latency_ms: u64,
+	},
+	/// A request to a neighbor failed.
+	RequestFailed {
+		/// The neighbor.
+		peer_id: PeerId,
+	},
+	/// The store was pruned at the start of the epoch.
+	Pruned {
+		/// The epoch the pruning started in.
+		epoch: Epoch,
+		/// What was removed.
+		stats: PruneStats,
+	},
+}
+
+/// The duration in milliseconds, for the structured logs.
+pub(crate) fn duration_ms(duration: Duration) -> u64 {
+	u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// The result of verifying the opinion of a neighbor in the background.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct VerifiedOpinion {
+	peer_id: PeerId,
+	k: Epoch,
+	hash: [u8; 32],
+	valid: bool,
+	/// How long the verification took.
+	duration_ms: u64,
+}
+
+/// The result of pruning the store in the background.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PrunedStore {
+	epoch: Epoch,
+	/// What was removed, if the pruning succeeded.
+	stats: Option<PruneStats>,
+	/// How long the pruning took.
+	duration_ms: u64,
+}
+
 /// The Node struct.
 pub struct Node {
 	/// Swarm object.
@@ -39,8 +224,67 @@ pub struct Node {
 	local_address: Multiaddr,
 	/// Bootstrap nodes.
 	bootstrap_nodes: Vec<(PeerId, Multiaddr)>,
-	interval: Duration,
+	/// Schedule of epoch intervals.
+	schedule: EpochSchedule,
+	/// The accepted epoch configs, by their activation epoch. They are
+	/// persisted, and sent to every newly identified neighbor.
+	epoch_configs: BTreeMap<Epoch, EpochConfig>,
+	/// External source of epochs. If not set, epochs come from the schedule.
+	epoch_source: Option<Box<dyn EpochSource>>,
+	config: NodeConfig,
 	peer: Peer,
+	/// The last epoch the node took part in.
+	last_epoch: Option<Epoch>,
+	/// Pending requests for the missed epochs, sent while catching up.
+	catch_up_requests: HashSet<RequestId>,
+	/// The requests for opinions waiting for the jitter delay to pass.
+	pending_requests: Option<(Instant, Epoch)>,
+	/// The epochs of the pending requests for opinions. An opinion is only
+	/// accepted in response to a request for its epoch, so old opinions can't
+	/// be replayed as responses.
+	requested_epochs: HashMap<RequestId, Epoch>,
+	/// The epochs of the opinions under the pending challenges.
+	challenges: HashMap<RequestId, Epoch>,
+	/// The number of failed challenges of each neighbor.
+	strikes: HashMap<PeerId, u32>,
+	/// The neighbors that failed too many challenges.
+	blocklist: HashSet<PeerId>,
+	/// The number of failed outbound requests to each neighbor.
+	failures: HashMap<PeerId, u32>,
+	/// How long the last background verification took.
+	last_verification_ms: Option<u64>,
+	/// Global trust scores logged in the recent epochs.
+	logged_scores: HashMap<Epoch, f64>,
+	/// Subscribers to the node events.
+	subscribers: Vec<UnboundedSender<NodeEvent>>,
+	/// The results of the background verification of the opinions.
+	verified_tx: UnboundedSender<VerifiedOpinion>,
+	verified_rx: UnboundedReceiver<VerifiedOpinion>,
+	/// The results of the background pruning of the store.
+	pruned_tx: UnboundedSender<PrunedStore>,
+	pruned_rx: UnboundedReceiver<PrunedStore>,
+	/// Whether the store is being pruned.
+	pruning: bool,
+	/// The bytes reclaimed by the pruning since the node was created.
+	reclaimed_bytes: u64,
+	/// The commands sent from the handles of the node.
+	commands_tx: UnboundedSender<CommandRequest>,
+	commands_rx: CommandReceiver,
+	/// The proof exports waiting for the opinions requested from the
+	/// neighbors, with the epochs of the opinions.
+	pending_exports: HashMap<RequestId, (Epoch, oneshot::Sender<Reply>)>,
+	/// When the node was created.
+	started_at: Instant,
+	/// When the pending requests for opinions were sent.
+	request_sent_at: HashMap<RequestId, Instant>,
+	/// The addresses the peers were last dialed on, dialed again at startup.
+	address_book: HashMap<PeerId, Multiaddr>,
+	/// The store of the node state, if persisted.
+	store: Option<Arc<dyn Store>>,
+	/// The log of the accepted opinions, if persisted.
+	wal: Option<Wal>,
+	/// The data dir, locked while the node is running.
+	data_dir: Option<DataDir>,
 }
 
 impl Node {
@@ -52,6 +296,42 @@ impl Node {
 		bootstrap_nodes: Vec<(PeerId, Multiaddr)>,
 		interval_secs: u64,
 		params: ParamsKZG<Bn256>,
+	) -> Result<Self, EigenError> {
+		let peer = Peer::new(local_key.clone(), params)?;
+		Self::with_peer(
+			local_key,
+			local_address,
+			bootstrap_nodes,
+			interval_secs,
+			peer,
+		)
+	}
+
+	/// Create a new node that signs its opinions without proving them, see
+	/// [`Peer::new_mock`]. It doesn't need the params, so it starts right away,
+	/// but it only works in a network of mock nodes.
+	pub fn new_mock(
+		local_key: Keypair,
+		local_address: Multiaddr,
+		bootstrap_nodes: Vec<(PeerId, Multiaddr)>,
+		interval_secs: u64,
+	) -> Result<Self, EigenError> {
+		let peer = Peer::new_mock(local_key.clone());
+		Self::with_peer(
+			local_key,
+			local_address,
+			bootstrap_nodes,
+			interval_secs,
+			peer,
+		)
+	}
+
+	fn with_peer(
+		local_key: Keypair,
+		local_address: Multiaddr,
+		bootstrap_nodes: Vec<(PeerId, Multiaddr)>,
+		interval_secs: u64,
+		peer: Peer,
 	) -> Result<Self, EigenError> {
 		let noise_keys = NoiseKeypair::<X25519Spec>::new()
 			.into_authentic(&local_key)
@@ -60,6 +340,8 @@ impl Node {
 				EigenError::InvalidKeypair
 			})?;
 
+		let schedule = EpochSchedule::new(interval_secs)?;
+
 		// 30 years in seconds
 		// Basically, we want connections to be open for a long time.
 		let connection_duration = Duration::from_secs(86400 * 365 * 30);
@@ -72,7 +354,6 @@ impl Node {
 			.timeout(connection_duration)
 			.boxed();
 
-		let peer = Peer::new(local_key.clone(), params)?;
 		let beh =
 			EigenTrustBehaviour::new(connection_duration, interval_duration, local_key.public());
 
@@ -85,12 +366,45 @@ impl Node {
 			EigenError::ListenFailed
 		})?;
 
+		let (verified_tx, verified_rx) = unbounded();
+		let (pruned_tx, pruned_rx) = unbounded();
+		let (commands_tx, commands_rx) = unbounded();
+
 		Ok(Self {
 			swarm,
 			local_address,
 			bootstrap_nodes,
-			interval: interval_duration,
+			schedule,
+			epoch_configs: BTreeMap::new(),
+			epoch_source: None,
+			config: NodeConfig::default(),
 			peer,
+			last_epoch: None,
+			catch_up_requests: HashSet::new(),
+			pending_requests: None,
+			requested_epochs: HashMap::new(),
+			challenges: HashMap::new(),
+			strikes: HashMap::new(),
+			blocklist: HashSet::new(),
+			failures: HashMap::new(),
+			last_verification_ms: None,
+			logged_scores: HashMap::new(),
+			subscribers: Vec::new(),
+			verified_tx,
+			verified_rx,
+			pruned_tx,
+			pruned_rx,
+			pruning: false,
+			reclaimed_bytes: 0,
+			commands_tx,
+			commands_rx,
+			pending_exports: HashMap::new(),
+			started_at: Instant::now(),
+			request_sent_at: HashMap::new(),
+			address_book: HashMap::new(),
+			store: None,
+			wal: None,
+			data_dir: None,
 		})
 	}
 
@@ -114,16 +428,1074 @@ impl Node {
 		&mut self.peer
 	}
 
+	/// Set the optional settings of the node.
+	pub fn set_config(&mut self, config: NodeConfig) {
+		self.config = config;
+	}
+
+	/// Use the data dir opened already, instead of the one in the config.
+	pub fn set_data_dir(&mut self, data_dir: DataDir) {
+		self.data_dir = Some(data_dir);
+	}
+
+	/// Open the store in the data dir, unless a store is set already. Called
+	/// at the start of the main loop, or earlier, to have the state restored
+	/// before changing it.
+	pub fn open_storage(&mut self) -> Result<(), EigenError> {
+		if self.store.is_some() {
+			return Ok(());
+		}
+		if self.data_dir.is_none() {
+			if let Some(root) = &self.config.data_dir {
+				self.data_dir = Some(DataDir::open(root)?);
+			}
+		}
+		let data_dir = match &self.data_dir {
+			Some(data_dir) => data_dir,
+			None => return Ok(()),
+		};
+		let wal_path = data_dir.wal();
+		let store: Arc<dyn Store> = match self.config.backend {
+			Backend::Memory => {
+				log::warn!("The store is kept in memory, and is lost when the node stops");
+				Arc::new(MemoryStore::default())
+			},
+			#[cfg(feature = "sled")]
+			Backend::Sled => {
+				let path = data_dir.store();
+				let store = SledStore::open(&path)?;
+				log::info!("Opened the store at {:?}", path);
+				Arc::new(store)
+			},
+			#[cfg(feature = "sqlite")]
+			Backend::Sqlite => {
+				let path = data_dir.sqlite();
+				let store = SqliteStore::open(&path)?;
+				log::info!("Opened the store at {:?}", path);
+				Arc::new(store)
+			},
+		};
+		let stats = recover(store.as_ref())?;
+		if !stats.is_clean() {
+			log::warn!(
+				"Recovered the store: discarded {} torn records and {} opinions without \
+				 their proofs, repaired {} proofs",
+				stats.torn_records,
+				stats.dangling_opinions,
+				stats.repaired_proofs
+			);
+		}
+		self.set_store(store)?;
+		let (wal, entries) = Wal::open(&wal_path)?;
+		self.set_wal(wal, entries);
+		Ok(())
+	}
+
+	/// Replay the entries of the write-ahead log, and append the accepted
+	/// opinions to it from now on. The cached opinions that were accepted,
+	/// but whose verification didn't make it to the store, are verified
+	/// again.
+	pub fn set_wal(&mut self, wal: Wal, entries: Vec<WalEntry>) {
+		let mut recovered = 0;
+		for entry in entries {
+			let key = (entry.peer_id, entry.epoch);
+			let opinion = match self.peer.find_neighbor_opinion(&key) {
+				Some(opinion) if entry.matches(opinion) => opinion.clone(),
+				_ => {
+					log::debug!(
+						"The accepted opinion of {:?} in {:?} is not cached anymore",
+						entry.peer_id,
+						entry.epoch
+					);
+					continue;
+				},
+			};
+			if !self.peer.is_verified(entry.peer_id, &opinion) {
+				self.peer
+					.record_verification(entry.peer_id, opinion.hash(), true);
+				self.reconcile_score(entry.epoch);
+				recovered += 1;
+			}
+		}
+		log::info!("Recovered {} accepted opinions from the WAL", recovered);
+		self.wal = Some(wal);
+	}
+
+	/// The epochs before which the opinions, and their proofs, are pruned at
+	/// the epoch.
+	fn retention_cutoffs(&self, epoch: Epoch) -> (Epoch, Epoch) {
+		let retention = self.config.retention;
+		let min_epochs = MAX_CATCH_UP_EPOCHS.max(self.config.reconciliation_window) + 1;
+		let proof_epochs = retention.proof_epochs.max(min_epochs);
+		let opinion_epochs = retention.opinion_epochs.max(proof_epochs);
+		let before = |epochs: u64| Epoch((epoch.0 + 1).saturating_sub(epochs));
+		(before(opinion_epochs), before(proof_epochs))
+	}
+
+	/// Prune the data outside of the retention: the caches right away, and the
+	/// store in a background task, unless the previous one is still running.
+	fn prune_data(&mut self, epoch: Epoch) {
+		let (opinions_before, proofs_before) = self.retention_cutoffs(epoch);
+		self.peer.prune(opinions_before, proofs_before);
+
+		let store = match &self.store {
+			Some(store) if !self.pruning => store.clone(),
+			_ => return,
+		};
+		self.pruning = true;
+		let tx = self.pruned_tx.clone();
+		task::spawn_blocking(move || {
+			let start = Instant::now();
+			let stats = prune(store.as_ref(), opinions_before, proofs_before)
+				.map_err(|e| log::error!("Failed to prune the store: {:?}", e))
+				.ok();
+			let result = PrunedStore {
+				epoch,
+				stats,
+				duration_ms: duration_ms(start.elapsed()),
+			};
+			// The node is gone if the receiver is dropped.
+			let _ = tx.unbounded_send(result);
+		});
+	}
+
+	/// Record the result of the background pruning.
+	fn handle_pruned_store(&mut self, result: PrunedStore) {
+		self.pruning = false;
+		let stats = match result.stats {
+			Some(stats) => stats,
+			None => return,
+		};
+		self.reclaimed_bytes += stats.reclaimed_bytes;
+		log::info!(
+			kind = "store_pruned",
+			epoch = result.epoch.0,
+			opinions = stats.opinions,
+			proofs = stats.proofs,
+			checkpoints = stats.checkpoints,
+			reclaimed_bytes = stats.reclaimed_bytes,
+			size_on_disk = stats.size_on_disk,
+			duration_ms = result.duration_ms;
+			"Pruned the store in {:?}: {} opinions, {} proofs, {} checkpoints, {} bytes reclaimed",
+			result.epoch, stats.opinions, stats.proofs, stats.checkpoints, stats.reclaimed_bytes
+		);
+		self.emit(NodeEvent::Pruned {
+			epoch: result.epoch,
+			stats,
+		});
+	}
+
+	/// Drop the entries of the write-ahead log that are in the store already,
+	/// once it is flushed, keeping the ones of the epochs that are still
+	/// reconcilable.
+	fn compact_wal(&mut self) {
+		let store = match &self.store {
+			Some(store) => store.clone(),
+			None => return,
+		};
+		if store.flush().is_err() {
+			return;
+		}
+		let window = self.config.reconciliation_window;
+		let last_epoch = self.last_epoch;
+		if let Some(wal) = &mut self.wal {
+			let res = wal.retain(|entry| {
+				last_epoch.map_or(true, |last_epoch| entry.epoch.0 + window >= last_epoch.0)
+			});
+			if let Err(e) = res {
+				log::error!("Failed to compact the WAL: {:?}", e);
+			}
+		}
+	}
+
+	/// Restore the state of the node from the store, and persist the changes
+	/// to it from now on. The last checkpoint becomes the last epoch, so the
+	/// node catches up on the epochs it missed.
+	pub fn set_store(&mut self, store: Arc<dyn Store>) -> Result<(), EigenError> {
+		self.peer.set_store(store.clone())?;
+		self.load_state(store.as_ref())?;
+		self.store = Some(store);
+		Ok(())
+	}
+
+	/// Load the address book, the checkpoints and the epoch configs from the
+	/// store.
+	fn load_state(&mut self, store: &dyn Store) -> Result<(), EigenError> {
+		self.address_book.extend(store.load_addresses()?);
+		for config in store.load_epoch_configs()? {
+			if !config.verify() {
+				log::warn!("Skipping the epoch config with an invalid signature");
+				continue;
+			}
+			if let Err(e) = self.add_epoch_config(&config) {
+				log::warn!("Skipping the epoch config {:?}: {:?}", config.activation, e);
+			}
+		}
+		let checkpoints = store.load_checkpoints()?;
+		if let Some((epoch, _)) = checkpoints.last() {
+			self.last_epoch = Some(*epoch);
+		}
+		for (epoch, score) in checkpoints {
+			self.record_score(epoch, score);
+		}
+		log::info!(
+			"Restored {} known addresses, last epoch: {:?}",
+			self.address_book.len(),
+			self.last_epoch
+		);
+		Ok(())
+	}
+
+	/// Write the state of the node to a single archive: the local scores,
+	/// the address book, the checkpoints, and the opinions of the epochs the
+	/// neighbors can still catch up on or reconcile. It can be restored with
+	/// [`Node::restore`], on this or another host.
+	pub fn snapshot(&self, path: &Path) -> Result<(), EigenError> {
+		let window = MAX_CATCH_UP_EPOCHS.max(self.config.reconciliation_window);
+		let since = Epoch(
+			self.last_epoch
+				.map_or(0, |epoch| epoch.0.saturating_sub(window)),
+		);
+
+		let archive = MemoryStore::default();
+		self.peer.export(&archive, since)?;
+		for (peer_id, addr) in &self.address_book {
+			archive.save_address(peer_id, addr)?;
+		}
+		for (epoch, score) in &self.logged_scores {
+			archive.save_checkpoint(*epoch, *score)?;
+		}
+		for config in self.epoch_configs.values() {
+			archive.save_epoch_config(config)?;
+		}
+		write_snapshot(path, self.swarm.local_peer_id(), &archive)?;
+		log::info!("Wrote the snapshot to {:?}", path);
+		Ok(())
+	}
+
+	/// Write the trust history of the epochs to an archive, for the new nodes
+	/// to import: the opinions exchanged with the neighbors, the global trust
+	/// scores logged at the start of the epochs, and the history imported
+	/// from the archives of other nodes.
+	pub fn export_archive(
+		&self,
+		path: &Path,
+		epochs: RangeInclusive<Epoch>,
+	) -> Result<(), EigenError> {
+		let local_id = *self.swarm.local_peer_id();
+		let state = MemoryStore::default();
+		self.peer.export(&state, *epochs.start())?;
+
+		let history = MemoryStore::default();
+		for ((peer_id, epoch), opinion) in state.load_opinions(Tree::LocalOpinions)? {
+			if epochs.contains(&epoch) {
+				history.save_history_opinion(epoch, &local_id, &peer_id, &opinion)?;
+			}
+		}
+		for ((peer_id, epoch), opinion) in state.load_opinions(Tree::NeighborOpinions)? {
+			if epochs.contains(&epoch) {
+				history.save_history_opinion(epoch, &peer_id, &local_id, &opinion)?;
+			}
+		}
+		for (epoch, score) in &self.logged_scores {
+			if epochs.contains(epoch) {
+				history.save_history_score(*epoch, &local_id, *score)?;
+			}
+		}
+		if let Some(store) = &self.store {
+			for ((epoch, author, subject), opinion) in store.load_history(epochs.clone())? {
+				history.save_history_opinion(epoch, &author, &subject, &opinion)?;
+			}
+			for (epoch, peer_id, score) in store.load_history_scores(epochs.clone())? {
+				history.save_history_score(epoch, &peer_id, score)?;
+			}
+		}
+
+		write_archive(path, &local_id, &history, epochs.clone())?;
+		log::info!(
+			"Wrote the history of {} to {} to the archive {:?}",
+			epochs.start(),
+			epochs.end(),
+			path
+		);
+		Ok(())
+	}
+
+	/// Import the trust history from an archive written by
+	/// [`Node::export_archive`] on any node. The history is kept in the store,
+	/// apart from the state of the node, so it needs a data dir. The opinions
+	/// are imported as they are, without verifying their proofs.
+	pub fn import_archive(&self, path: &Path) -> Result<(), EigenError> {
+		let store = self.store.as_ref().ok_or_else(|| {
+			log::error!("The history can only be imported into a store");
+			EigenError::StorageError
+		})?;
+		let (info, archive) = read_archive(path)?;
+		let epochs = info.epochs.clone();
+		let opinions = archive.load_history(epochs.clone())?;
+		for ((epoch, author, subject), opinion) in &opinions {
+			store.save_history_opinion(*epoch, author, subject, opinion)?;
+		}
+		for (epoch, peer_id, score) in archive.load_history_scores(epochs)? {
+			store.save_history_score(epoch, &peer_id, score)?;
+		}
+		store.flush()?;
+		log::info!(
+			"Imported {} opinions of {} to {} from the archive of {}",
+			opinions.len(),
+			info.epochs.start(),
+			info.epochs.end(),
+			info.source
+		);
+		Ok(())
+	}
+
+	/// Replace the state of the node with the one in the archive written by
+	/// [`Node::snapshot`]. The archive has to be of the same identity. The
+	/// store, if there is one, is replaced too, and the write-ahead log is
+	/// emptied, as its entries belong to the replaced state. The history
+	/// imported with [`Node::import_archive`] is kept.
+	pub fn restore(&mut self, path: &Path) -> Result<(), EigenError> {
+		let archive = read_snapshot(path, self.swarm.local_peer_id())?;
+		if let Some(store) = &self.store {
+			// The history shares the proofs with the state, so it is copied
+			// into the archive to count their references together.
+			let all_epochs = Epoch(0)..=Epoch(u64::MAX);
+			for ((epoch, author, subject), opinion) in store.load_history(all_epochs.clone())? {
+				archive.save_history_opinion(epoch, &author, &subject, &opinion)?;
+			}
+			for (epoch, peer_id, score) in store.load_history_scores(all_epochs)? {
+				archive.save_history_score(epoch, &peer_id, score)?;
+			}
+			// Replaced at once, so a crash leaves either the old or the restored
+			// state.
+			let mut batch = Vec::new();
+			for tree in Tree::ALL {
+				for (key, _) in store.records(tree)? {
+					batch.push(WriteOp::Remove(tree, key));
+				}
+				for (key, value) in archive.records(tree)? {
+					batch.push(WriteOp::Insert(tree, key, value));
+				}
+			}
+			store.write_batch(&batch)?;
+			store.flush()?;
+		}
+		if let Some(wal) = &mut self.wal {
+			wal.retain(|_| false)?;
+		}
+
+		self.peer.clear_state();
+		self.peer.load(&archive)?;
+		self.address_book.clear();
+		self.logged_scores.clear();
+		self.last_epoch = None;
+		self.epoch_configs.clear();
+		self.schedule = EpochSchedule::new(self.schedule.interval_at(Epoch(0)))?;
+		self.load_state(&archive)?;
+		log::info!("Restored the snapshot from {:?}", path);
+		Ok(())
+	}
+
+	/// Write to the store, if there is one. The failures are only logged, so
+	/// the node keeps working from the memory.
+	fn persist(&self, what: &str, write: impl FnOnce(&dyn Store) -> Result<(), EigenError>) {
+		if let Some(store) = &self.store {
+			if let Err(e) = write(store.as_ref()) {
+				log::error!("Failed to persist the {}: {:?}", what, e);
+			}
+		}
+	}
+
+	/// Subscribe to the events emitted by the node.
+	pub fn subscribe(&mut self) -> UnboundedReceiver<NodeEvent> {
+		let (tx, rx) = unbounded();
+		self.subscribers.push(tx);
+		rx
+	}
+
+	/// Get a handle for sending commands to the node, once its main loop runs.
+	pub fn handle(&self) -> NodeHandle {
+		NodeHandle::new(self.commands_tx.clone())
+	}
+
+	/// Run the command sent from a handle, and send back the reply. Exporting
+	/// the proof of a neighbor's opinion that is not cached first requests the
+	/// opinion from the neighbor, and replies once it arrives.
+	fn run_command(&mut self, command: Command, reply_tx: oneshot::Sender<Reply>) {
+		if let Command::ExportProof {
+			peer_id,
+			epoch,
+			sent: false,
+		} = command
+		{
+			if self.peer.find_neighbor_opinion(&(peer_id, epoch)).is_none() {
+				let request_id = self
+					.get_swarm_mut()
+					.behaviour_mut()
+					.send_request(&peer_id, Request::new(epoch));
+				self.requested_epochs.insert(request_id, epoch);
+				self.pending_exports.insert(request_id, (epoch, reply_tx));
+				return;
+			}
+		}
+		// The reply is dropped if the handle is gone.
+		let _ = reply_tx.send(self.handle_command(command));
+	}
+
+	/// Reply to the proof export waiting for the response to the request, if
+	/// there is one.
+	fn finish_export(&mut self, peer_id: PeerId, request_id: RequestId) {
+		if let Some((epoch, reply_tx)) = self.pending_exports.remove(&request_id) {
+			let reply = match self.export_proof(peer_id, epoch, false) {
+				Ok(export) => Reply::Proof(export),
+				Err(e) => Reply::Error(format!("{:?}", e)),
+			};
+			let _ = reply_tx.send(reply);
+		}
+	}
+
+	/// Package the proof of a cached opinion, together with its public inputs,
+	/// as the calldata of the on-chain verifier, along with the CID of the
+	/// proof. The opinion is either the one the neighbor sent to us, or the
+	/// one we sent to it.
+	pub fn export_proof(
+		&self,
+		peer_id: PeerId,
+		epoch: Epoch,
+		sent: bool,
+	) -> Result<ProofExport, EigenError> {
+		let local = self.peer.keypair.public();
+		let remote = self
+			.peer
+			.get_pub_key(peer_id)
+			.ok_or(EigenError::PeerNotIdentified)?;
+		let key = (peer_id, epoch);
+		let (opinion, calldata) = if sent {
+			let opinion = self
+				.peer
+				.find_local_opinion(&key)
+				.ok_or(EigenError::InvalidOpinion)?;
+			(opinion, opinion.to_onchain_bytes(&local, &remote)?)
+		} else {
+			let opinion = self
+				.peer
+				.find_neighbor_opinion(&key)
+				.ok_or(EigenError::InvalidOpinion)?;
+			(opinion, opinion.to_onchain_bytes(&remote, &local)?)
+		};
+		Ok(ProofExport {
+			cid: proof_cid(&proof_id(&opinion.proof_bytes)),
+			calldata,
+		})
+	}
+
+	/// Run the command sent from a handle.
+	fn handle_command(&mut self, command: Command) -> Reply {
+		match command {
+			Command::SetScore { peer_id, score } => {
+				self.peer.set_score(peer_id, score);
+				Reply::Done
+			},
+			Command::SetScores { scores } => {
+				let scores = scores.into_iter().map(|s| (s.peer_id, s.score));
+				self.peer.set_scores(scores);
+				Reply::Done
+			},
+			Command::Neighbors => {
+				let neighbors = self
+					.peer
+					.neighbors()
+					.into_iter()
+					.map(|peer_id| NeighborInfo {
+						peer_id,
+						identified: self.peer.get_pub_key(peer_id).is_some(),
+						score: self.peer.get_score(peer_id),
+					})
+					.collect();
+				Reply::Neighbors(neighbors)
+			},
+			Command::CurrentEpoch => match self.current_epoch() {
+				Ok(epoch) => Reply::Epoch(epoch),
+				Err(e) => Reply::Error(format!("{:?}", e)),
+			},
+			Command::GlobalScore { epoch } => Reply::Score(self.peer.global_trust_score_at(epoch)),
+			Command::Status => Reply::Status(self.status()),
+			Command::Peers { last } => Reply::Peers(self.peer_reports(last)),
+			Command::ExportProof {
+				peer_id,
+				epoch,
+				sent,
+			} => match self.export_proof(peer_id, epoch, sent) {
+				Ok(export) => Reply::Proof(export),
+				Err(e) => Reply::Error(format!("{:?}", e)),
+			},
+			Command::Ban { peer_id } => {
+				self.ban(peer_id);
+				Reply::Done
+			},
+			Command::Revoke { peer_id, epoch } => {
+				Reply::from_result(self.revoke_opinion(peer_id, epoch))
+			},
+			Command::Challenge { peer_id, epoch } => {
+				Reply::from_result(self.challenge(peer_id, epoch))
+			},
+			Command::Snapshot { path } => {
+				let path = match &self.data_dir {
+					Some(data_dir) => data_dir.snapshot(&path),
+					None => path,
+				};
+				Reply::from_result(self.snapshot(&path))
+			},
+			Command::ExportArchive { from, to, path } => {
+				let path = match &self.data_dir {
+					Some(data_dir) => data_dir.archive(&path),
+					None => path,
+				};
+				Reply::from_result(self.export_archive(&path, from..=to))
+			},
+			Command::ImportArchive { path } => {
+				let path = match &self.data_dir {
+					Some(data_dir) => data_dir.archive(&path),
+					None => path,
+				};
+				Reply::from_result(self.import_archive(&path))
+			},
+		}
+	}
+
+	/// The current state of the node, as shown by the dashboard.
+	pub fn status(&self) -> NodeStatus {
+		let epoch = self.current_epoch().ok().or(self.last_epoch);
+		let last_epoch_score = self
+			.last_epoch
+			.and_then(|epoch| self.logged_scores.get(&epoch).cloned());
+		NodeStatus {
+			uptime_secs: self.started_at.elapsed().as_secs(),
+			epoch,
+			global_score: epoch.map(|epoch| self.peer.global_trust_score_at(epoch)),
+			last_epoch_score,
+			pending_requests: self.requested_epochs.len() + self.challenges.len(),
+			neighbors: self.peer.neighbors().len(),
+			connected: self.swarm.connected_peers().count(),
+			last_proof_ms: self.peer.last_proof_ms(),
+			last_verification_ms: self.last_verification_ms,
+			reclaimed_bytes: self.reclaimed_bytes,
+		}
+	}
+
+	/// The neighbors, with the opinions exchanged with them over the `last`
+	/// epochs before the current one.
+	pub fn peer_reports(&self, last: u64) -> Vec<PeerReport> {
+		let current = self.current_epoch().ok().or(self.last_epoch);
+		let epochs: Vec<Epoch> = match current {
+			Some(current) => {
+				let start = Epoch(current.0.saturating_sub(last));
+				Epoch::range(start..current).collect()
+			},
+			None => Vec::new(),
+		};
+		self.peer
+			.neighbors()
+			.into_iter()
+			.map(|peer_id| {
+				let opinions = epochs
+					.iter()
+					.map(|&epoch| {
+						let key = (peer_id, epoch);
+						let received = self.peer.find_neighbor_opinion(&key);
+						EpochOpinions {
+							epoch,
+							sent: self.peer.find_local_opinion(&key).map(|op| op.op),
+							received: received.map(|op| op.op),
+							verified: received
+								.map_or(false, |op| self.peer.is_verified(peer_id, op)),
+						}
+					})
+					.collect();
+				PeerReport {
+					peer_id,
+					identified: self.peer.get_pub_key(peer_id).is_some(),
+					connected: self.swarm.is_connected(&peer_id),
+					score: self.peer.get_score(peer_id),
+					strikes: self.strikes.get(&peer_id).cloned().unwrap_or(0),
+					failures: self.failures.get(&peer_id).cloned().unwrap_or(0),
+					opinions,
+				}
+			})
+			.collect()
+	}
+
+	/// Send the event to all subscribers, dropping the ones that are gone.
+	fn emit(&mut self, event: NodeEvent) {
+		self.subscribers
+			.retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+	}
+
+	/// Get the schedule of epoch intervals.
+	pub fn get_schedule(&self) -> &EpochSchedule {
+		&self.schedule
+	}
+
+	/// Use an external source of epochs, like a blockchain or a randomness
+	/// beacon, instead of the wall clock.
+	pub fn set_epoch_source(&mut self, source: Box<dyn EpochSource>) {
+		self.epoch_source = Some(source);
+	}
+
+	/// Returns the current epoch, from the external source if set, or from the
+	/// schedule otherwise.
+	pub fn current_epoch(&self) -> Result<Epoch, EigenError> {
+		match &self.epoch_source {
+			Some(source) => source.current_epoch(),
+			None => self.schedule.current_epoch(),
+		}
+	}
+
+	/// Returns the seconds until the node should check for the next epoch.
+	fn secs_until_next_epoch(&self) -> Result<u64, EigenError> {
+		match &self.epoch_source {
+			Some(source) => source.secs_until_next_epoch(),
+			None => self.schedule.secs_until_next_epoch(),
+		}
+	}
+
 	/// Send the request for an opinion to all neighbors, in the passed epoch.
 	pub fn send_epoch_requests(&mut self, epoch: Epoch) {
 		for peer_id in self.peer.neighbors() {
 			let request = Request::new(epoch);
+			let request_id = self
+				.get_swarm_mut()
+				.behaviour_mut()
+				.send_request(&peer_id, request);
+			self.requested_epochs.insert(request_id, epoch);
+			self.request_sent_at.insert(request_id, Instant::now());
+		}
+	}
+
+	/// Send the requests for opinions in the passed epoch after a random delay,
+	/// so the nodes don't all send them at once. The requests are tied to the
+	/// epoch, even if the delay passes the epoch boundary.
+	fn schedule_epoch_requests(&mut self, epoch: Epoch) {
+		let delay = self.epoch_requests_delay(epoch);
+		if delay.is_zero() {
+			self.send_epoch_requests(epoch);
+		} else {
+			self.pending_requests = Some((Instant::now() + delay, epoch));
+		}
+	}
+
+	/// Check the opinion received in response to the request. It is accepted
+	/// only if it is for the requested epoch, and the epoch is still within
+	/// the window of the epochs the node catches up on.
+	fn accept_opinion(&mut self, request_id: RequestId, opinion: &Opinion<NetworkConfig>) -> bool {
+		let requested = match self.requested_epochs.remove(&request_id) {
+			Some(epoch) => epoch,
+			None => return false,
+		};
+		let in_window = self.last_epoch.map_or(true, |last_epoch| {
+			opinion.k <= last_epoch && opinion.k.0 + MAX_CATCH_UP_EPOCHS >= last_epoch.0
+		});
+		opinion.k == requested && in_window
+	}
+
+	/// Returns a random delay for sending the requests for opinions in the
+	/// specified epoch, bounded by the first half of the epoch.
+	pub fn epoch_requests_delay(&self, epoch: Epoch) -> Duration {
+		let half_epoch = Duration::from_secs(self.schedule.interval_at(epoch) / 2);
+		let max_jitter = self.config.max_jitter.min(half_epoch);
+		max_jitter.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+	}
+
+	/// Remember the score logged at the start of an epoch, and forget the
+	/// ones outside of the reconciliation window.
+	fn record_score(&mut self, epoch: Epoch, score: f64) {
+		let window = self.config.reconciliation_window;
+		self.logged_scores.insert(epoch, score);
+		self.logged_scores.retain(|k, _| k.0 + window >= epoch.0);
+		self.persist("checkpoint", |store| store.save_checkpoint(epoch, score));
+	}
+
+	/// Recalculate the score that depends on the opinions in the specified
+	/// epoch, if it was already logged. Used when opinions arrive late.
+	fn reconcile_score(&mut self, opinion_epoch: Epoch) {
+		let epoch = opinion_epoch.next();
+		let logged_score = match self.logged_scores.get(&epoch) {
+			Some(score) => *score,
+			None => return,
+		};
+
+		let score = self.peer.global_trust_score_at(epoch);
+		if score != logged_score {
+			log::info!(
+				kind = "score_updated", epoch = epoch.0, score = score;
+				"{:?} score updated: {} -> {}", epoch, logged_score, score
+			);
+			self.logged_scores.insert(epoch, score);
+			self.persist("checkpoint", |store| store.save_checkpoint(epoch, score));
+			self.emit(NodeEvent::ScoreUpdated { epoch, score });
+		}
+	}
+
+	/// Verify the opinion of the neighbor in a background task, so the proof
+	/// is checked once, when it arrives. The result is sent back to the main
+	/// loop.
+	fn spawn_verification(&self, peer_id: PeerId, opinion: Opinion<NetworkConfig>) {
+		let pubkey_p = match self.peer.get_pub_key(peer_id) {
+			Some(pubkey) => pubkey,
+			None => {
+				log::debug!("Pubkey not found for {:?}, skipping verification.", peer_id);
+				return;
+			},
+		};
+		let verifier = self.peer.opinion_verifier();
+		let tx = self.verified_tx.clone();
+		task::spawn_blocking(move || {
+			let start = Instant::now();
+			let valid = verifier.verify(&pubkey_p, &opinion);
+			let duration_ms = duration_ms(start.elapsed());
+			log::debug!(
+				kind = "opinion_verified",
+				epoch = opinion.k.0,
+				peer_id = peer_id.to_base58(),
+				valid = valid,
+				duration_ms = duration_ms;
+				"Verified the opinion of {:?} in {:?}: {}", peer_id, opinion.k, valid
+			);
+			let result = VerifiedOpinion {
+				peer_id,
+				k: opinion.k,
+				hash: opinion.hash(),
+				valid,
+				duration_ms,
+			};
+			// The node is gone if the receiver is dropped.
+			let _ = tx.unbounded_send(result);
+		});
+	}
+
+	/// Record the result of a background verification. A valid opinion can
+	/// change the already logged score.
+	fn handle_verified_opinion(&mut self, result: VerifiedOpinion) {
+		self.last_verification_ms = Some(result.duration_ms);
+		self.emit(NodeEvent::OpinionVerified {
+			peer_id: result.peer_id,
+			epoch: result.k,
+			valid: result.valid,
+			duration_ms: result.duration_ms,
+		});
+		// The acceptance is logged before it is recorded, so it survives a
+		// crash.
+		let key = (result.peer_id, result.k);
+		match (&mut self.wal, self.peer.find_neighbor_opinion(&key)) {
+			(Some(wal), Some(opinion)) if result.valid && opinion.hash() == result.hash => {
+				if let Err(e) = wal.append(&WalEntry::new(result.peer_id, opinion)) {
+					log::error!("Failed to log the accepted opinion: {:?}", e);
+				}
+			},
+			_ => {},
+		}
+		self.peer
+			.record_verification(result.peer_id, result.hash, result.valid);
+		if result.valid {
+			self.reconcile_score(result.k);
+		} else {
+			log::debug!(
+				"Invalid opinion from {:?} in {:?}",
+				result.peer_id,
+				result.k
+			);
+		}
+	}
+
+	/// Returns true if the epoch is still within the reconciliation window, so
+	/// its opinions can change the logged scores.
+	fn is_reconcilable(&self, epoch: Epoch) -> bool {
+		self.last_epoch.map_or(true, |last_epoch| {
+			epoch.0 + self.config.reconciliation_window >= last_epoch.0
+		})
+	}
+
+	/// Revoke the opinion sent to the neighbor in a recent epoch. The opinion
+	/// is not sent anymore, and the neighbor is asked to stop counting it.
+	pub fn revoke_opinion(&mut self, peer_id: PeerId, epoch: Epoch) -> Result<(), EigenError> {
+		if !self.is_reconcilable(epoch) {
+			return Err(EigenError::InvalidRevocation);
+		}
+		let opinion = self
+			.peer
+			.remove_local_opinion(&(peer_id, epoch))
+			.ok_or(EigenError::InvalidRevocation)?;
+		let revocation = Revocation::new(&self.peer.keypair, &opinion)?;
+		// Answer the later requests with an empty opinion.
+		self.peer
+			.cache_local_opinion((peer_id, epoch), Opinion::empty());
+
+		self.get_swarm_mut()
+			.behaviour_mut()
+			.send_request(&peer_id, Request::Revocation(revocation));
+		Ok(())
+	}
+
+	/// Handle the revocation of an opinion received from a neighbor. It is
+	/// honored if it is signed by the neighbor, matches the cached opinion, and
+	/// is still within the reconciliation window.
+	fn handle_revocation(&mut self, source: PeerId, revocation: &Revocation) -> bool {
+		if !revocation.verify() || revocation.pubkey.to_peer_id() != source {
+			log::debug!("Invalid revocation from {:?}", source);
+			return false;
+		}
+		if !self.is_reconcilable(revocation.epoch) {
+			log::debug!("Revocation from {:?} is too late", source);
+			return false;
+		}
+
+		let key = (source, revocation.epoch);
+		if !revocation.revokes(&self.peer.get_neighbor_opinion(&key)) {
+			return false;
+		}
+		self.peer.remove_neighbor_opinion(&key);
+		log::info!(
+			kind = "opinion_revoked", epoch = revocation.epoch.0, peer_id = source.to_base58();
+			"{:?} revoked its opinion in {:?}", source, revocation.epoch
+		);
+		self.emit(NodeEvent::OpinionRevoked {
+			peer_id: source,
+			epoch: revocation.epoch,
+		});
+		self.reconcile_score(revocation.epoch);
+		true
+	}
+
+	/// Challenge the neighbor to reveal the neighbor set behind the opinion it
+	/// sent to us in the epoch. Only proven opinions can be challenged, or the
+	/// signed ones in the mock mode.
+	pub fn challenge(&mut self, peer_id: PeerId, epoch: Epoch) -> Result<(), EigenError> {
+		let opinion = self.peer.get_neighbor_opinion(&(peer_id, epoch));
+		let unproven = opinion.proof_bytes.is_empty() && !self.peer.is_mock();
+		if opinion.k != epoch || unproven {
+			return Err(EigenError::InvalidChallenge);
+		}
+		let request_id = self
+			.get_swarm_mut()
+			.behaviour_mut()
+			.send_request(&peer_id, Request::Challenge(epoch));
+		self.challenges.insert(request_id, epoch);
+		Ok(())
+	}
+
+	/// Check the neighbor set revealed in response to a challenge. Returns
+	/// false if the set doesn't match the commitment in the challenged opinion.
+	fn handle_reveal(
+		&mut self,
+		peer_id: PeerId,
+		epoch: Epoch,
+		neighbors: &[(PublicKey, f64)],
+	) -> bool {
+		let opinion = self.peer.get_neighbor_opinion(&(peer_id, epoch));
+		opinion.matches_neighbors(neighbors, &self.peer.keypair.public())
+	}
+
+	/// Give a strike to the neighbor for failing a challenge. A neighbor that
+	/// reaches the maximum number of strikes is blocked, and its opinions are
+	/// not accepted anymore.
+	fn strike(&mut self, peer_id: PeerId) {
+		let strikes = self.strikes.entry(peer_id).or_insert(0);
+		*strikes += 1;
+		let strikes = *strikes;
+		log::info!(
+			kind = "strike", peer_id = peer_id.to_base58(), strikes = strikes;
+			"{:?} failed a challenge, strikes: {}", peer_id, strikes
+		);
+		self.emit(NodeEvent::Strike { peer_id, strikes });
+
+		if strikes >= self.config.max_strikes {
+			self.ban(peer_id);
+		}
+	}
+
+	/// Block the peer: its connections are closed and refused, and its
+	/// opinions are not accepted anymore.
+	pub fn ban(&mut self, peer_id: PeerId) {
+		if !self.blocklist.insert(peer_id) {
+			return;
+		}
+		log::info!(kind = "blocked", peer_id = peer_id.to_base58(); "Blocking {:?}", peer_id);
+		self.swarm.ban_peer_id(peer_id);
+		self.peer.remove_neighbor(peer_id);
+		self.emit(NodeEvent::Blocked { peer_id });
+	}
+
+	/// Returns true if the neighbor was blocked for failing the challenges.
+	pub fn is_blocked(&self, peer_id: &PeerId) -> bool {
+		self.blocklist.contains(peer_id)
+	}
+
+	/// Set the last epoch the node took part in. Used when the node is
+	/// restarted, so it can recover the epochs it missed in the meantime.
+	pub fn set_last_epoch(&mut self, epoch: Epoch) {
+		self.last_epoch = Some(epoch);
+	}
+
+	/// Returns true while the node is still recovering the missed epochs.
+	pub fn is_catching_up(&self) -> bool {
+		!self.catch_up_requests.is_empty()
+	}
+
+	/// Send the requests for opinions in all epochs that were missed between
+	/// the last epoch the node took part in and the current epoch.
+	/// Only the last `MAX_CATCH_UP_EPOCHS` epochs are requested.
+	pub fn send_catch_up_requests(&mut self, current_epoch: Epoch) {
+		let last_epoch = match self.last_epoch {
+			Some(epoch) => epoch,
+			None => return,
+		};
+		let missed = catch_up_epochs(last_epoch, current_epoch);
+		if missed.is_empty() {
+			return;
+		}
+
+		log::info!(
+			"Catching up on epochs {} to {}",
+			missed.start.0,
+			missed.end.0 - 1
+		);
+		for k in Epoch::range(missed) {
+			for peer_id in self.peer.neighbors() {
+				let request = Request::new(k);
+				let request_id = self
+					.get_swarm_mut()
+					.behaviour_mut()
+					.send_request(&peer_id, request);
+				self.catch_up_requests.insert(request_id);
+				self.requested_epochs.insert(request_id, k);
+				self.request_sent_at.insert(request_id, Instant::now());
+			}
+		}
+	}
+
+	/// Mark a catch-up request as finished. Once all of them are finished, the
+	/// node rejoins the live epoch.
+	fn finish_catch_up_request(&mut self, request_id: RequestId) {
+		if !self.catch_up_requests.remove(&request_id) || self.is_catching_up() {
+			return;
+		}
+
+		log::info!("Catch-up finished");
+		// Send the requests for the live epoch, that were held back, after the
+		// same delay as in a regular epoch.
+		if let Some(epoch) = self.last_epoch {
+			self.schedule_epoch_requests(epoch);
+		}
+	}
+
+	/// Announce a new epoch interval to the network, starting from the
+	/// `activation` epoch. Other nodes only accept it if this node is one of
+	/// their bootstrap nodes.
+	pub fn announce_epoch_config(
+		&mut self,
+		activation: Epoch,
+		interval_secs: u64,
+	) -> Result<(), EigenError> {
+		let config = EpochConfig::new(&self.peer.keypair, activation, interval_secs)?;
+		if self.add_epoch_config(&config)? {
+			self.persist("epoch config", |store| store.save_epoch_config(&config));
+		}
+		self.broadcast_epoch_config(&config, None);
+		Ok(())
+	}
+
+	/// Add the epoch config to the schedule. The configs can arrive in any
+	/// order, so the schedule is built again from all of them. Returns `false`
+	/// if the config is known already.
+	fn add_epoch_config(&mut self, config: &EpochConfig) -> Result<bool, EigenError> {
+		if let Some(known) = self.epoch_configs.get(&config.activation) {
+			if known.interval == config.interval {
+				return Ok(false);
+			}
+			return Err(EigenError::InvalidEpochConfig);
+		}
+		let mut configs = self.epoch_configs.clone();
+		configs.insert(config.activation, config.clone());
+		let mut schedule = EpochSchedule::new(self.schedule.interval_at(Epoch(0)))?;
+		for config in configs.values() {
+			schedule.add(config.activation, config.interval)?;
+		}
+		self.schedule = schedule;
+		self.epoch_configs = configs;
+		Ok(true)
+	}
+
+	/// Send all the accepted epoch configs to the neighbor, so a node that
+	/// joins late, or restarts with an empty store, agrees on the epochs.
+	fn send_epoch_configs(&mut self, peer_id: PeerId) {
+		let configs: Vec<EpochConfig> = self.epoch_configs.values().cloned().collect();
+		for config in configs {
+			self.get_swarm_mut()
+				.behaviour_mut()
+				.send_request(&peer_id, Request::EpochConfig(config));
+		}
+	}
+
+	/// Send the epoch config to all neighbors, except the one we got it from.
+	fn broadcast_epoch_config(&mut self, config: &EpochConfig, source: Option<PeerId>) {
+		for peer_id in self.peer.neighbors() {
+			if Some(peer_id) == source {
+				continue;
+			}
+			let request = Request::EpochConfig(config.clone());
 			self.get_swarm_mut()
 				.behaviour_mut()
 				.send_request(&peer_id, request);
 		}
 	}
 
+	/// Handle the epoch config received from a neighbor. The config is only
+	/// accepted if it is signed by one of the bootstrap nodes and activates
+	/// after the last epoch the node took part in, so the configs active
+	/// already still reach the nodes that join late. Newly accepted configs
+	/// are persisted, and forwarded to the neighbors.
+	fn handle_epoch_config(&mut self, source: PeerId, config: &EpochConfig) -> bool {
+		let signer = config.signer();
+		let is_bootstrap = self
+			.bootstrap_nodes
+			.iter()
+			.any(|(peer_id, _)| *peer_id == signer);
+		let is_open = self
+			.last_epoch
+			.map_or(true, |last_epoch| config.activation > last_epoch);
+		if !is_bootstrap || !config.verify() {
+			log::debug!("Rejected epoch config from {:?}", source);
+			return false;
+		}
+		if !is_open {
+			// Known configs are still acknowledged, as every neighbor sends them
+			// once identified.
+			return self
+				.epoch_configs
+				.get(&config.activation)
+				.map_or(false, |known| known.interval == config.interval);
+		}
+
+		match self.add_epoch_config(config) {
+			Ok(true) => {
+				self.persist("epoch config", |store| store.save_epoch_config(config));
+				log::info!(
+					"Interval changes to {} seconds at {:?}",
+					config.interval,
+					config.activation
+				);
+				self.broadcast_epoch_config(config, Some(source));
+				true
+			},
+			Ok(false) => true,
+			Err(e) => {
+				log::debug!("Failed to add epoch config from {:?}: {:?}", source, e);
+				false
+			},
+		}
+	}
+
 	/// Handle the request response event.
 	fn handle_req_res_events(&mut self, event: RequestResponseEvent<Request, Response>) {
 		use RequestResponseEvent::*;
@@ -135,11 +1507,42 @@ impl Node {
 					request, channel, ..
 				},
 			} => {
-				// First we calculate the local opinions for the requested epoch.
-				self.peer.calculate_local_opinion(peer, request.get_epoch());
-				// Then we send the local opinion to the peer.
-				let opinion = self.peer.get_local_opinion(&(peer, request.get_epoch()));
-				let response = Response::Success(opinion);
+				let response = match request {
+					Request::Opinion(epoch) => {
+						// First we calculate the local opinions for the requested epoch.
+						let cached = self.peer.find_local_opinion(&(peer, epoch)).is_some();
+						self.peer.calculate_local_opinion(peer, epoch);
+						// Then we send the local opinion to the peer.
+						let opinion = self.peer.get_local_opinion(&(peer, epoch));
+						let proven = !cached && opinion.k == epoch;
+						if let (true, Some(duration_ms)) = (proven, self.peer.last_proof_ms()) {
+							self.emit(NodeEvent::OpinionProven {
+								peer_id: peer,
+								epoch,
+								duration_ms,
+							});
+						}
+						Response::Success(opinion)
+					},
+					Request::EpochConfig(config) => {
+						if self.handle_epoch_config(peer, &config) {
+							Response::Ack
+						} else {
+							Response::InvalidRequest
+						}
+					},
+					Request::Revocation(revocation) => {
+						if self.handle_revocation(peer, &revocation) {
+							Response::Ack
+						} else {
+							Response::InvalidRequest
+						}
+					},
+					Request::Challenge(epoch) => match self.peer.get_neighbor_set(&(peer, epoch)) {
+						Some(neighbors) => Response::Reveal(neighbors),
+						None => Response::InvalidRequest,
+					},
+				};
 				let res = self
 					.get_swarm_mut()
 					.behaviour_mut()
@@ -150,14 +1553,52 @@ impl Node {
 			},
 			Message {
 				peer,
-				message: Res { response, .. },
+				message: Res {
+					response,
+					request_id,
+				},
 			} => {
-				// If we receive a response, we update the neighbors's opinion about us.
-				if let Response::Success(opinion) = response {
-					self.peer.cache_neighbor_opinion((peer, opinion.k), opinion);
-				} else {
-					log::error!("Received error response {:?}", response);
+				// Anything but the matching neighbor set fails the challenge.
+				if let Some(epoch) = self.challenges.remove(&request_id) {
+					let revealed = match &response {
+						Response::Reveal(neighbors) => self.handle_reveal(peer, epoch, neighbors),
+						_ => false,
+					};
+					if !revealed {
+						self.strike(peer);
+					}
+					return;
 				}
+				let sent_at = self.request_sent_at.remove(&request_id);
+				match response {
+					// If we receive a response, we update the neighbors's opinion about us.
+					Response::Success(opinion) => {
+						if self.is_blocked(&peer) {
+							log::debug!("Rejected opinion from blocked {:?}", peer);
+						} else if self.accept_opinion(request_id, &opinion) {
+							let k = opinion.k;
+							if let Some(sent_at) = sent_at {
+								self.emit(NodeEvent::OpinionReceived {
+									peer_id: peer,
+									epoch: k,
+									latency_ms: duration_ms(sent_at.elapsed()),
+								});
+							}
+							self.peer.cache_neighbor_opinion((peer, k), opinion.clone());
+							self.spawn_verification(peer, opinion);
+						} else {
+							log::debug!(
+								"Rejected opinion for {:?} from {:?}, not requested",
+								opinion.k,
+								peer
+							);
+						}
+					},
+					Response::Ack => log::debug!("Request {:?} acknowledged", request_id),
+					_ => log::error!("Received error response {:?}", response),
+				}
+				self.finish_catch_up_request(request_id);
+				self.finish_export(peer, request_id);
 			},
 			OutboundFailure {
 				peer,
@@ -170,6 +1611,16 @@ impl Node {
 					peer,
 					error
 				);
+				*self.failures.entry(peer).or_insert(0) += 1;
+				self.emit(NodeEvent::RequestFailed { peer_id: peer });
+				self.request_sent_at.remove(&request_id);
+				self.requested_epochs.remove(&request_id);
+				self.finish_catch_up_request(request_id);
+				self.finish_export(peer, request_id);
+				// Not answering a challenge fails it.
+				if self.challenges.remove(&request_id).is_some() {
+					self.strike(peer);
+				}
 			},
 			InboundFailure {
 				peer,
@@ -193,8 +1644,15 @@ impl Node {
 	fn handle_identify_events(&mut self, event: IdentifyEvent) {
 		match event {
 			IdentifyEvent::Received { peer_id, info } => {
+				let is_new = self.peer.get_pub_key(peer_id).is_none();
 				self.peer.identify_neighbor(peer_id, info.public_key);
-				log::info!("Neighbor identified {:?}", peer_id);
+				if is_new {
+					self.send_epoch_configs(peer_id);
+				}
+				log::info!(
+					kind = "neighbor_identified", peer_id = peer_id.to_base58();
+					"Neighbor identified {:?}", peer_id
+				);
 			},
 			IdentifyEvent::Sent { peer_id } => {
 				log::debug!("Identify request sent to {:?}", peer_id);
@@ -225,17 +1683,31 @@ impl Node {
 			},
 			SwarmEvent::NewListenAddr { address, .. } => log::info!("Listening on {:?}", address),
 			// When we connect to a peer, we automatically add him as a neighbor.
-			SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+			SwarmEvent::ConnectionEstablished {
+				peer_id, endpoint, ..
+			} => {
 				let res = self.get_peer_mut().add_neighbor(peer_id);
 				if let Err(e) = res {
 					log::error!("Failed to add neighbor {:?}", e);
 				}
-				log::info!("Connection established with {:?}", peer_id);
+				// Only the dialed addresses can be dialed again, the remote ports of
+				// the incoming connections are ephemeral.
+				if let ConnectedPoint::Dialer { address, .. } = endpoint {
+					self.persist("address", |store| store.save_address(&peer_id, &address));
+					self.address_book.insert(peer_id, address);
+				}
+				log::info!(
+					kind = "connection_established", peer_id = peer_id.to_base58();
+					"Connection established with {:?}", peer_id
+				);
 			},
 			// When we disconnect from a peer, we automatically remove him from the neighbors list.
 			SwarmEvent::ConnectionClosed { peer_id, cause, .. } => {
 				self.get_peer_mut().remove_neighbor(peer_id);
-				log::info!("Connection closed with {:?} ({:?})", peer_id, cause);
+				log::info!(
+					kind = "connection_closed", peer_id = peer_id.to_base58();
+					"Connection closed with {:?} ({:?})", peer_id, cause
+				);
 			},
 			SwarmEvent::Dialing(peer_id) => log::info!("Dialing {:?}", peer_id),
 			e => log::debug!("{:?}", e),
@@ -248,6 +1720,20 @@ impl Node {
 		log::debug!("swarm.dial {:?}", res);
 	}
 
+	/// Dial the peers of the address book, that are not bootstrap nodes.
+	pub fn dial_known_peers(&mut self) {
+		for (peer_id, addr) in &self.address_book {
+			if self.bootstrap_nodes.iter().any(|(id, _)| id == peer_id) {
+				continue;
+			}
+			let res = self
+				.swarm
+				.dial(addr.clone())
+				.map_err(|_| EigenError::DialError);
+			log::debug!("swarm.dial {:?}", res);
+		}
+	}
+
 	/// Dial pre-configured bootstrap nodes.
 	pub fn dial_bootstrap_nodes(&mut self) {
 		// We want to connect to all bootstrap nodes.
@@ -264,21 +1750,19 @@ impl Node {
 	}
 
 	/// Start the main loop of the program. This function has two main tasks:
-	/// - To start an interval timer for sending the request for opinions.
+	/// - To start an epoch timer for sending the request for opinions.
 	/// - To handle the swarm + request/response events.
 	/// The amount of intervals/epochs is determined by the `interval_limit`
 	/// parameter.
 	pub async fn main_loop(mut self, interval_limit: Option<u32>) -> Result<(), EigenError> {
+		self.open_storage()?;
 		self.dial_bootstrap_nodes();
+		self.dial_known_peers();
 
-		let now = Instant::now();
-		let secs_until_next_epoch = Epoch::secs_until_next_epoch(self.interval.as_secs())?;
+		let secs_until_next_epoch = self.secs_until_next_epoch()?;
 		log::info!("Epoch starts in: {} seconds", secs_until_next_epoch);
 		// Figure out when the next epoch will start.
-		let start = now + Duration::from_secs(secs_until_next_epoch);
-
-		// Setup the interval timer.
-		let mut interval = time::interval_at(start, self.interval);
+		let mut next_epoch_start = Instant::now() + Duration::from_secs(secs_until_next_epoch);
 
 		// Count the number of epochs passed
 		let mut count = 0;
@@ -286,18 +1770,43 @@ impl Node {
 		loop {
 			select! {
 				biased;
-				// The interval timer tick. This is where we request opinions from the neighbors.
-				_ = interval.tick() => {
-					let current_epoch = Epoch::current_epoch(self.interval.as_secs())?;
+				// The epoch timer. This is where we request opinions from the neighbors.
+				_ = time::sleep_until(next_epoch_start) => {
+					let current_epoch = self.current_epoch()?;
+
+					// The interval can change from epoch to epoch, so the timer is set
+					// again from the schedule.
+					let secs_until_next_epoch = self.secs_until_next_epoch()?;
+					next_epoch_start = Instant::now() + Duration::from_secs(secs_until_next_epoch);
+
+					// With an external epoch source, the timer only checks whether the
+					// epoch has changed.
+					if self.last_epoch.map_or(false, |last_epoch| current_epoch <= last_epoch) {
+						continue;
+					}
 
 					// Log out the global trust score for the previous epoch.
 					let ops = self.peer.get_neighbor_opinions_at(current_epoch.previous());
 					let ops_non_zero: Vec<&f64> = ops.iter().filter(|&&item| item > 0.0).collect();
 					let score = self.peer.global_trust_score_at(current_epoch);
-					log::info!("{:?} started, score: {}, ops: {:?}", current_epoch, score, ops_non_zero);
-
-					// Send the request for opinions to all neighbors.
-					self.send_epoch_requests(current_epoch);
+					log::info!(
+						kind = "epoch_started", epoch = current_epoch.0, score = score;
+						"{:?} started, score: {}, ops: {:?}", current_epoch, score, ops_non_zero
+					);
+					self.record_score(current_epoch, score);
+					self.emit(NodeEvent::EpochStarted { epoch: current_epoch, score });
+
+					// If we were offline for some epochs, recover them first. The live
+					// epoch requests are sent once the catch-up is finished.
+					self.send_catch_up_requests(current_epoch);
+					self.last_epoch = Some(current_epoch);
+					self.compact_wal();
+					self.prune_data(current_epoch);
+
+					// Send the request for opinions to all neighbors, after a random delay.
+					if !self.is_catching_up() {
+						self.schedule_epoch_requests(current_epoch);
+					}
 
 					// Increment the epoch counter, break out of the loop if we reached the limit
 					if let Some(num) = interval_limit {
@@ -307,11 +1816,30 @@ impl Node {
 						}
 					}
 				},
+				// The delayed requests for opinions.
+				_ = time::sleep_until(self.pending_requests.map_or(next_epoch_start, |(at, _)| at)),
+					if self.pending_requests.is_some() => {
+					if let Some((_, epoch)) = self.pending_requests.take() {
+						self.send_epoch_requests(epoch);
+					}
+				},
 				// The swarm event.
 				event = self.swarm.select_next_some() => self.handle_swarm_events(event),
+				// The opinions verified in the background.
+				result = self.verified_rx.select_next_some() => self.handle_verified_opinion(result),
+				// The store pruned in the background.
+				result = self.pruned_rx.select_next_some() => self.handle_pruned_store(result),
+				// The commands from the handles.
+				(command, reply_tx) = self.commands_rx.select_next_some() => {
+					self.run_command(command, reply_tx);
+				},
 			}
 		}
 
+		if let Some(store) = &self.store {
+			store.flush()?;
+		}
+
 		Ok(())
 	}
 }
@@ -319,7 +1847,8 @@ impl Node {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use eigen_trust_circuit::halo2wrong::halo2::poly::commitment::ParamsProver;
+	use crate::{epoch::BeaconSource, peer::MIN_SCORE};
+	use eigen_trust_circuit::{ecdsa::SigData, test_utils::params};
 	use std::str::FromStr;
 
 	const INTERVAL: u64 = 120;
@@ -343,7 +1872,7 @@ mod tests {
 			(peer_id2, local_address2.clone()),
 		];
 
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 
 		let mut node1 = Node::new(
 			local_key1,
@@ -408,7 +1937,7 @@ mod tests {
 			(peer_id2, local_address2.clone()),
 		];
 
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 
 		let mut node1 = Node::new(
 			local_key1,
@@ -491,7 +2020,7 @@ mod tests {
 			(peer_id2, local_address2.clone()),
 		];
 
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 
 		let mut node1 = Node::new(
 			local_key1,
@@ -555,7 +2084,7 @@ mod tests {
 		let local_address1 = Multiaddr::from_str(ADDR_1).unwrap();
 		let local_address2 = Multiaddr::from_str(ADDR_2).unwrap();
 
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 
 		let mut node1 = Node::new(
 			local_key1,
@@ -635,7 +2164,7 @@ mod tests {
 			(peer_id2, local_address2.clone()),
 		];
 
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 
 		let mut node1 = Node::new(
 			local_key1,
@@ -713,6 +2242,856 @@ mod tests {
 		assert_eq!(peer2_neighbor_opinion.op, 0.1);
 	}
 
+	#[test]
+	fn should_catch_up_on_epochs_after_last_one() {
+		assert_eq!(catch_up_epochs(Epoch(1), Epoch(4)), Epoch(2)..Epoch(4));
+		assert!(catch_up_epochs(Epoch(3), Epoch(4)).is_empty());
+		assert!(catch_up_epochs(Epoch(4), Epoch(4)).is_empty());
+		assert!(catch_up_epochs(Epoch(6), Epoch(4)).is_empty());
+		assert_eq!(
+			catch_up_epochs(Epoch(1), Epoch(30)),
+			Epoch(30 - MAX_CATCH_UP_EPOCHS)..Epoch(30)
+		);
+	}
+
+	#[tokio::test]
+	async fn should_catch_up_on_missed_epochs() {
+		const ADDR_1: &str = "/ip4/127.0.0.1/tcp/56729";
+		const ADDR_2: &str = "/ip4/127.0.0.1/tcp/58624";
+
+		let local_key1 = Keypair::generate_secp256k1();
+		let peer_id1 = local_key1.public().to_peer_id();
+
+		let local_key2 = Keypair::generate_secp256k1();
+		let peer_id2 = local_key2.public().to_peer_id();
+
+		let local_address1 = Multiaddr::from_str(ADDR_1).unwrap();
+		let local_address2 = Multiaddr::from_str(ADDR_2).unwrap();
+
+		let bootstrap_nodes = vec![
+			(peer_id1, local_address1.clone()),
+			(peer_id2, local_address2.clone()),
+		];
+
+		let params = params(18);
+
+		let mut node1 = Node::new(
+			local_key1,
+			local_address1,
+			bootstrap_nodes.clone(),
+			INTERVAL,
+			params.clone(),
+		)
+		.unwrap();
+
+		let mut node2 = Node::new(
+			local_key2,
+			local_address2,
+			bootstrap_nodes,
+			INTERVAL,
+			params,
+		)
+		.unwrap();
+
+		node1.dial_bootstrap_nodes();
+
+		for _ in 0..9 {
+			select! {
+				event2 = node2.get_swarm_mut().select_next_some() => node2.handle_swarm_events(event2),
+				event1 = node1.get_swarm_mut().select_next_some() => node1.handle_swarm_events(event1),
+			}
+		}
+
+		// Node 1 was last active in epoch 1, and came back in epoch 4.
+		node1.set_config(NodeConfig {
+			max_jitter: Duration::from_secs(10),
+			..NodeConfig::default()
+		});
+		node1.set_last_epoch(Epoch(1));
+		node1.send_catch_up_requests(Epoch(4));
+		assert!(node1.is_catching_up());
+		// Only the epochs after the last one are requested, once per neighbor.
+		let mut requested: Vec<Epoch> = node1.requested_epochs.values().copied().collect();
+		requested.sort();
+		assert_eq!(requested, vec![Epoch(2), Epoch(3)]);
+
+		// Expecting 2 request messages
+		// Expecting 2 response sent messages
+		// Expecting 2 response received messages
+		// Total of 6 messages
+		for _ in 0..6 {
+			select! {
+				event1 = node1.get_swarm_mut().select_next_some() => {
+					node1.handle_swarm_events(event1);
+				},
+				event2 = node2.get_swarm_mut().select_next_some() => {
+					node2.handle_swarm_events(event2);
+				},
+			}
+		}
+
+		assert!(!node1.is_catching_up());
+		// The requests for the live epoch wait for the jitter, like in a
+		// regular epoch.
+		assert!(!node1.requested_epochs.values().any(|k| *k == Epoch(1)));
+		assert_eq!(
+			node1.pending_requests.map(|(_, epoch)| epoch),
+			Some(Epoch(1))
+		);
+	}
+
+	#[tokio::test]
+	async fn should_accept_epoch_config_from_bootstrap_node() {
+		const ADDR_1: &str = "/ip4/127.0.0.1/tcp/56730";
+		const ADDR_2: &str = "/ip4/127.0.0.1/tcp/58625";
+
+		let local_key1 = Keypair::generate_secp256k1();
+		let peer_id1 = local_key1.public().to_peer_id();
+
+		let local_key2 = Keypair::generate_secp256k1();
+		let peer_id2 = local_key2.public().to_peer_id();
+
+		let local_address1 = Multiaddr::from_str(ADDR_1).unwrap();
+		let local_address2 = Multiaddr::from_str(ADDR_2).unwrap();
+
+		let bootstrap_nodes = vec![
+			(peer_id1, local_address1.clone()),
+			(peer_id2, local_address2.clone()),
+		];
+
+		let params = params(18);
+
+		let mut node1 = Node::new(
+			local_key1,
+			local_address1,
+			bootstrap_nodes.clone(),
+			INTERVAL,
+			params.clone(),
+		)
+		.unwrap();
+
+		let mut node2 = Node::new(
+			local_key2,
+			local_address2,
+			bootstrap_nodes,
+			INTERVAL,
+			params,
+		)
+		.unwrap();
+
+		node1.dial_bootstrap_nodes();
+
+		for _ in 0..9 {
+			select! {
+				event2 = node2.get_swarm_mut().select_next_some() => node2.handle_swarm_events(event2),
+				event1 = node1.get_swarm_mut().select_next_some() => node1.handle_swarm_events(event1),
+			}
+		}
+
+		let current_epoch = node1.get_schedule().current_epoch().unwrap();
+		let activation = current_epoch.checked_add(10).unwrap();
+		node1.announce_epoch_config(activation, 60).unwrap();
+
+		// Expecting 1 request message
+		// Expecting 1 response sent message
+		// Expecting 1 response received message
+		for _ in 0..3 {
+			select! {
+				event1 = node1.get_swarm_mut().select_next_some() => {
+					node1.handle_swarm_events(event1);
+				},
+				event2 = node2.get_swarm_mut().select_next_some() => {
+					node2.handle_swarm_events(event2);
+				},
+			}
+		}
+
+		assert_eq!(node2.get_schedule().interval_at(activation), 60);
+		assert_eq!(node2.get_schedule(), node1.get_schedule());
+	}
+
+	#[tokio::test]
+	async fn should_send_epoch_configs_to_late_joiner() {
+		const ADDR_1: &str = "/ip4/127.0.0.1/tcp/56750";
+		const ADDR_2: &str = "/ip4/127.0.0.1/tcp/58626";
+
+		let local_key1 = Keypair::generate_secp256k1();
+		let peer_id1 = local_key1.public().to_peer_id();
+		let local_key2 = Keypair::generate_secp256k1();
+
+		let local_address1 = Multiaddr::from_str(ADDR_1).unwrap();
+		let local_address2 = Multiaddr::from_str(ADDR_2).unwrap();
+
+		let mut node1 =
+			Node::new_mock(local_key1, local_address1.clone(), Vec::new(), INTERVAL).unwrap();
+		let mut node2 = Node::new_mock(
+			local_key2,
+			local_address2,
+			vec![(peer_id1, local_address1)],
+			INTERVAL,
+		)
+		.unwrap();
+
+		// The config is active already when node 2 joins.
+		let current_epoch = node1.get_schedule().current_epoch().unwrap();
+		node1.announce_epoch_config(current_epoch, 60).unwrap();
+		assert_ne!(node2.get_schedule(), node1.get_schedule());
+
+		node2.dial_bootstrap_nodes();
+
+		// The connection and the identify exchange, then the config sent to the
+		// newly identified neighbor: 1 request, 1 response sent and 1 response
+		// received.
+		for _ in 0..12 {
+			select! {
+				event2 = node2.get_swarm_mut().select_next_some() => node2.handle_swarm_events(event2),
+				event1 = node1.get_swarm_mut().select_next_some() => node1.handle_swarm_events(event1),
+			}
+		}
+
+		assert_eq!(node2.get_schedule(), node1.get_schedule());
+	}
+
+	#[tokio::test]
+	async fn should_bound_epoch_requests_delay() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56731";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		let epoch = Epoch(1);
+		assert!(node.epoch_requests_delay(epoch).is_zero());
+
+		let max_jitter = Duration::from_secs(10);
+		node.set_config(NodeConfig {
+			max_jitter,
+			..NodeConfig::default()
+		});
+		assert!(node.epoch_requests_delay(epoch) <= max_jitter);
+
+		// The delay never goes past the first half of the epoch.
+		let max_jitter = Duration::from_secs(INTERVAL * 10);
+		node.set_config(NodeConfig {
+			max_jitter,
+			..NodeConfig::default()
+		});
+		assert!(node.epoch_requests_delay(epoch) <= Duration::from_secs(INTERVAL / 2));
+	}
+
+	#[tokio::test]
+	async fn should_use_external_epoch_source() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56732";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		let clock_epoch = node.current_epoch().unwrap();
+		assert_eq!(clock_epoch, node.get_schedule().current_epoch().unwrap());
+
+		let beacon = BeaconSource::new(10, 1).unwrap();
+		node.set_epoch_source(Box::new(beacon.clone()));
+		beacon.update(42);
+		assert_eq!(node.current_epoch().unwrap(), Epoch(4));
+	}
+
+	#[tokio::test]
+	async fn should_reconcile_score_on_late_opinion() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56733";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		let mut events = node.subscribe();
+
+		// Score logged at the start of epoch 3, before the opinions from epoch 2
+		// arrived.
+		node.record_score(Epoch(3), 0.5);
+		node.reconcile_score(Epoch(2));
+
+		let expected = NodeEvent::ScoreUpdated {
+			epoch: Epoch(3),
+			score: MIN_SCORE,
+		};
+		assert_eq!(events.try_next().unwrap(), Some(expected));
+
+		// Scores outside of the window are not reconciled.
+		node.record_score(Epoch(10), 0.5);
+		node.reconcile_score(Epoch(2));
+		assert!(events.try_next().is_err());
+	}
+
+	#[tokio::test]
+	async fn should_accept_only_requested_opinions() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56735";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		node.set_last_epoch(Epoch(20));
+
+		let neighbor_id = PeerId::random();
+		node.get_peer_mut().add_neighbor(neighbor_id).unwrap();
+		let sig = SigData::empty();
+
+		// The opinion for the requested epoch is accepted, but only once.
+		node.send_epoch_requests(Epoch(20));
+		let request_id = *node.requested_epochs.keys().next().unwrap();
+		let opinion = Opinion::new(Epoch(20), sig, 0.5, Vec::new());
+		assert!(node.accept_opinion(request_id, &opinion));
+		assert!(!node.accept_opinion(request_id, &opinion));
+
+		// An opinion from an older epoch is not accepted as the response.
+		node.send_epoch_requests(Epoch(20));
+		let request_id = *node.requested_epochs.keys().next().unwrap();
+		let old_opinion = Opinion::new(Epoch(19), sig, 0.5, Vec::new());
+		assert!(!node.accept_opinion(request_id, &old_opinion));
+
+		// Neither are the epochs outside of the window, even if requested.
+		node.send_epoch_requests(Epoch(5));
+		let request_id = *node.requested_epochs.keys().next().unwrap();
+		let stale_opinion = Opinion::new(Epoch(5), sig, 0.5, Vec::new());
+		assert!(!node.accept_opinion(request_id, &stale_opinion));
+	}
+
+	#[tokio::test]
+	async fn should_honor_revocation() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56736";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		let mut events = node.subscribe();
+		node.set_last_epoch(Epoch(5));
+
+		let neighbor_key = Keypair::generate_secp256k1();
+		let neighbor_id = neighbor_key.public().to_peer_id();
+		let sig = SigData::empty();
+		let opinion = Opinion::new(Epoch(4), sig, 0.5, Vec::new());
+		node.get_peer_mut()
+			.cache_neighbor_opinion((neighbor_id, Epoch(4)), opinion.clone());
+
+		// Only the author can revoke the opinion.
+		let other_key = Keypair::generate_secp256k1();
+		let forged = Revocation::new(&other_key, &opinion).unwrap();
+		assert!(!node.handle_revocation(neighbor_id, &forged));
+
+		let revocation = Revocation::new(&neighbor_key, &opinion).unwrap();
+		assert!(node.handle_revocation(neighbor_id, &revocation));
+		let removed = node
+			.get_peer()
+			.get_neighbor_opinion(&(neighbor_id, Epoch(4)));
+		assert_eq!(removed, Opinion::empty());
+		let expected = NodeEvent::OpinionRevoked {
+			peer_id: neighbor_id,
+			epoch: Epoch(4),
+		};
+		assert_eq!(events.try_next().unwrap(), Some(expected));
+
+		// Revocations outside of the window are not honored.
+		let old_opinion = Opinion::new(Epoch(1), sig, 0.5, Vec::new());
+		node.get_peer_mut()
+			.cache_neighbor_opinion((neighbor_id, Epoch(1)), old_opinion.clone());
+		let late = Revocation::new(&neighbor_key, &old_opinion).unwrap();
+		assert!(!node.handle_revocation(neighbor_id, &late));
+	}
+
+	#[tokio::test]
+	async fn should_revoke_local_opinion() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56737";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		node.set_last_epoch(Epoch(5));
+
+		let neighbor_id = PeerId::random();
+		let sig = SigData::empty();
+		let opinion = Opinion::new(Epoch(4), sig, 0.5, Vec::new());
+		node.get_peer_mut()
+			.cache_local_opinion((neighbor_id, Epoch(4)), opinion);
+
+		node.revoke_opinion(neighbor_id, Epoch(4)).unwrap();
+		let local = node.get_peer().get_local_opinion(&(neighbor_id, Epoch(4)));
+		assert_eq!(local, Opinion::empty());
+
+		// There is nothing left to revoke.
+		node.get_peer_mut()
+			.remove_local_opinion(&(neighbor_id, Epoch(4)));
+		assert!(node.revoke_opinion(neighbor_id, Epoch(4)).is_err());
+		// Nor can an opinion outside of the window be revoked.
+		assert!(node.revoke_opinion(neighbor_id, Epoch(1)).is_err());
+	}
+
+	#[tokio::test]
+	async fn should_block_after_failed_challenges() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56738";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		let mut events = node.subscribe();
+
+		let neighbor_id = PeerId::random();
+		node.get_peer_mut().add_neighbor(neighbor_id).unwrap();
+
+		// Empty opinions can't be challenged.
+		assert!(node.challenge(neighbor_id, Epoch(3)).is_err());
+		let opinion = Opinion::new(Epoch(3), SigData::empty(), 0.5, vec![1, 2, 3]);
+		node.get_peer_mut()
+			.cache_neighbor_opinion((neighbor_id, Epoch(3)), opinion);
+		node.challenge(neighbor_id, Epoch(3)).unwrap();
+
+		// The revealed set doesn't match the empty commitment.
+		let neighbors = vec![(Keypair::generate_secp256k1().public(), 1.0)];
+		assert!(!node.handle_reveal(neighbor_id, Epoch(3), &neighbors));
+
+		for strikes in 1..=3 {
+			node.strike(neighbor_id);
+			let expected = NodeEvent::Strike {
+				peer_id: neighbor_id,
+				strikes,
+			};
+			assert_eq!(events.try_next().unwrap(), Some(expected));
+		}
+		let expected = NodeEvent::Blocked {
+			peer_id: neighbor_id,
+		};
+		assert_eq!(events.try_next().unwrap(), Some(expected));
+		assert!(node.is_blocked(&neighbor_id));
+		assert!(!node.get_peer().neighbors().contains(&neighbor_id));
+	}
+
+	#[tokio::test]
+	async fn should_run_handle_commands() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56739";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		let mut events = node.subscribe();
+
+		let neighbor_key = Keypair::generate_secp256k1();
+		let neighbor_id = neighbor_key.public().to_peer_id();
+		node.get_peer_mut().add_neighbor(neighbor_id).unwrap();
+
+		let reply = node.handle_command(Command::SetScore {
+			peer_id: neighbor_id,
+			score: 5,
+		});
+		assert_eq!(reply, Reply::Done);
+		let reply = node.handle_command(Command::Neighbors);
+		assert_eq!(
+			reply,
+			Reply::Neighbors(vec![NeighborInfo {
+				peer_id: neighbor_id,
+				identified: false,
+				score: 5,
+			}])
+		);
+		let reply = node.handle_command(Command::GlobalScore { epoch: Epoch(3) });
+		assert_eq!(reply, Reply::Score(MIN_SCORE));
+
+		// Errors are sent back instead of stopping the node.
+		let reply = node.handle_command(Command::Challenge {
+			peer_id: neighbor_id,
+			epoch: Epoch(3),
+		});
+		assert!(matches!(reply, Reply::Error(_)));
+
+		let reply = node.handle_command(Command::Ban {
+			peer_id: neighbor_id,
+		});
+		assert_eq!(reply, Reply::Done);
+		let expected = NodeEvent::Blocked {
+			peer_id: neighbor_id,
+		};
+		assert_eq!(events.try_next().unwrap(), Some(expected));
+		assert_eq!(
+			node.handle_command(Command::Neighbors),
+			Reply::Neighbors(Vec::new())
+		);
+
+		// The handle fails once the node is gone.
+		let handle = node.handle();
+		drop(node);
+		let res = handle.request(Command::Neighbors).await;
+		assert!(matches!(res, Err(EigenError::NodeStopped)));
+	}
+
+	#[tokio::test]
+	async fn should_restore_from_store() {
+		const ADDR_1: &str = "/ip4/127.0.0.1/tcp/56742";
+		const ADDR_2: &str = "/ip4/127.0.0.1/tcp/56743";
+
+		let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+		let local_key = Keypair::generate_secp256k1();
+		let neighbor_id = PeerId::random();
+		let opinion = Opinion::new(Epoch(4), SigData::empty(), 0.5, Vec::new());
+
+		let local_address = Multiaddr::from_str(ADDR_1).unwrap();
+		let mut node =
+			Node::new_mock(local_key.clone(), local_address, Vec::new(), INTERVAL).unwrap();
+		node.set_store(store.clone()).unwrap();
+		let peer = node.get_peer_mut();
+		peer.set_score(neighbor_id, 7);
+		peer.cache_neighbor_opinion((neighbor_id, Epoch(4)), opinion.clone());
+		peer.record_verification(neighbor_id, opinion.hash(), true);
+		node.record_score(Epoch(5), 0.6);
+		node.announce_epoch_config(Epoch(30), 60).unwrap();
+		drop(node);
+
+		let local_address = Multiaddr::from_str(ADDR_2).unwrap();
+		let mut node = Node::new_mock(local_key, local_address, Vec::new(), INTERVAL).unwrap();
+		node.set_store(store).unwrap();
+		let peer = node.get_peer();
+		assert_eq!(peer.get_score(neighbor_id), 7);
+		assert_eq!(peer.get_neighbor_opinion(&(neighbor_id, Epoch(4))), opinion);
+		assert!(peer.is_verified(neighbor_id, &opinion));
+		assert_eq!(node.last_epoch, Some(Epoch(5)));
+		assert_eq!(node.logged_scores.get(&Epoch(5)), Some(&0.6));
+		assert_eq!(node.get_schedule().interval_at(Epoch(30)), 60);
+	}
+
+	#[tokio::test]
+	async fn should_snapshot_and_restore() {
+		const ADDR_1: &str = "/ip4/127.0.0.1/tcp/56746";
+		const ADDR_2: &str = "/ip4/127.0.0.1/tcp/56747";
+		const ADDR_3: &str = "/ip4/127.0.0.1/tcp/56748";
+
+		let suffix: u64 = rand::random();
+		let path = std::env::temp_dir().join(format!("eigen-trust-node-{}.snapshot", suffix));
+		let local_key = Keypair::generate_secp256k1();
+		let neighbor_id = PeerId::random();
+		let neighbor_addr = Multiaddr::from_str("/ip4/127.0.0.1/tcp/58584").unwrap();
+		let recent = Opinion::new(Epoch(19), SigData::empty(), 0.5, Vec::new());
+		let old = Opinion::new(Epoch(2), SigData::empty(), 0.25, Vec::new());
+
+		let local_address = Multiaddr::from_str(ADDR_1).unwrap();
+		let mut node =
+			Node::new_mock(local_key.clone(), local_address, Vec::new(), INTERVAL).unwrap();
+		let peer = node.get_peer_mut();
+		peer.set_score(neighbor_id, 7);
+		peer.cache_neighbor_opinion((neighbor_id, Epoch(19)), recent.clone());
+		peer.record_verification(neighbor_id, recent.hash(), true);
+		peer.cache_neighbor_opinion((neighbor_id, Epoch(2)), old.clone());
+		node.address_book.insert(neighbor_id, neighbor_addr.clone());
+		node.record_score(Epoch(20), 0.6);
+		node.set_last_epoch(Epoch(20));
+		node.announce_epoch_config(Epoch(30), 60).unwrap();
+		node.snapshot(&path).unwrap();
+		drop(node);
+
+		// The restored state replaces the persisted one.
+		let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+		let stale_id = PeerId::random();
+		store.save_score(&stale_id, 3).unwrap();
+		let local_address = Multiaddr::from_str(ADDR_2).unwrap();
+		let mut node =
+			Node::new_mock(local_key.clone(), local_address, Vec::new(), INTERVAL).unwrap();
+		node.set_store(store.clone()).unwrap();
+		node.restore(&path).unwrap();
+		let peer = node.get_peer();
+		assert_eq!(peer.get_score(neighbor_id), 7);
+		assert_eq!(peer.get_score(stale_id), 0);
+		assert_eq!(
+			peer.find_neighbor_opinion(&(neighbor_id, Epoch(19))),
+			Some(&recent)
+		);
+		assert!(peer.is_verified(neighbor_id, &recent));
+		assert_eq!(peer.find_neighbor_opinion(&(neighbor_id, Epoch(2))), None);
+		assert_eq!(node.address_book.get(&neighbor_id), Some(&neighbor_addr));
+		assert_eq!(node.last_epoch, Some(Epoch(20)));
+		assert_eq!(node.get_schedule().interval_at(Epoch(30)), 60);
+		assert_eq!(store.load_scores().unwrap(), vec![(neighbor_id, 7)]);
+
+		// The snapshot can't be restored with another identity.
+		let local_address = Multiaddr::from_str(ADDR_3).unwrap();
+		let other_key = Keypair::generate_secp256k1();
+		let mut node = Node::new_mock(other_key, local_address, Vec::new(), INTERVAL).unwrap();
+		assert!(matches!(
+			node.restore(&path),
+			Err(EigenError::InvalidSnapshot)
+		));
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[cfg(feature = "sled")]
+	#[tokio::test]
+	async fn should_prune_outside_of_retention() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56749";
+
+		let store: Arc<dyn Store> = Arc::new(SledStore::temporary().unwrap());
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let mut node = Node::new_mock(local_key, local_address, Vec::new(), INTERVAL).unwrap();
+		// The proofs are kept for longer, so the neighbors can catch up.
+		node.set_config(NodeConfig {
+			retention: Retention {
+				opinion_epochs: 15,
+				proof_epochs: 1,
+			},
+			..NodeConfig::default()
+		});
+		node.set_store(store.clone()).unwrap();
+		let mut events = node.subscribe();
+
+		let neighbor_id = PeerId::random();
+		let opinion = |k: u64| Opinion::new(Epoch(k), SigData::empty(), 0.5, vec![7; 64]);
+		let peer = node.get_peer_mut();
+		for k in [10, 17, 25] {
+			peer.cache_neighbor_opinion((neighbor_id, Epoch(k)), opinion(k));
+			peer.record_verification(neighbor_id, opinion(k).hash(), true);
+		}
+
+		node.prune_data(Epoch(30));
+		let peer = node.get_peer();
+		assert_eq!(peer.find_neighbor_opinion(&(neighbor_id, Epoch(10))), None);
+		let stripped = opinion(17).without_proof();
+		assert_eq!(
+			peer.find_neighbor_opinion(&(neighbor_id, Epoch(17))),
+			Some(&stripped)
+		);
+		assert!(peer.is_verified(neighbor_id, &stripped));
+		assert_eq!(
+			peer.find_neighbor_opinion(&(neighbor_id, Epoch(25))),
+			Some(&opinion(25))
+		);
+
+		let result = node.pruned_rx.next().await.unwrap();
+		node.handle_pruned_store(result);
+		let stats = match events.next().await.unwrap() {
+			NodeEvent::Pruned { epoch, stats } => {
+				assert_eq!(epoch, Epoch(30));
+				stats
+			},
+			event => panic!("Unexpected event {:?}", event),
+		};
+		assert_eq!((stats.opinions, stats.proofs), (1, 1));
+		assert!(stats.size_on_disk.is_some());
+		assert_eq!(node.status().reclaimed_bytes, stats.reclaimed_bytes);
+
+		let opinions = store.load_opinions(Tree::NeighborOpinions).unwrap();
+		assert_eq!(opinions, vec![
+			((neighbor_id, Epoch(17)), stripped.clone()),
+			((neighbor_id, Epoch(25)), opinion(25)),
+		]);
+		assert_eq!(
+			store
+				.load_verification(&neighbor_id, &stripped.hash())
+				.unwrap(),
+			Some(true)
+		);
+	}
+
+	#[tokio::test]
+	async fn should_replay_wal() {
+		const ADDR_1: &str = "/ip4/127.0.0.1/tcp/56744";
+		const ADDR_2: &str = "/ip4/127.0.0.1/tcp/56745";
+
+		let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+		let suffix: u64 = rand::random();
+		let wal_path = std::env::temp_dir().join(format!("eigen-trust-node-{}.wal", suffix));
+		let local_key = Keypair::generate_secp256k1();
+		let neighbor_id = PeerId::random();
+		let opinion = Opinion::new(Epoch(4), SigData::empty(), 0.5, vec![1, 2, 3]);
+		let unverified = Opinion::new(Epoch(5), SigData::empty(), 0.25, Vec::new());
+
+		let local_address = Multiaddr::from_str(ADDR_1).unwrap();
+		let mut node =
+			Node::new_mock(local_key.clone(), local_address, Vec::new(), INTERVAL).unwrap();
+		node.set_store(store.clone()).unwrap();
+		let (wal, entries) = Wal::open(&wal_path).unwrap();
+		node.set_wal(wal, entries);
+		let peer = node.get_peer_mut();
+		peer.cache_neighbor_opinion((neighbor_id, Epoch(4)), opinion.clone());
+		peer.cache_neighbor_opinion((neighbor_id, Epoch(5)), unverified.clone());
+		node.handle_verified_opinion(VerifiedOpinion {
+			peer_id: neighbor_id,
+			k: Epoch(4),
+			hash: opinion.hash(),
+			valid: true,
+			duration_ms: 1,
+		});
+		// The crash loses the last write to the store.
+		store
+			.remove_verification(&neighbor_id, &opinion.hash())
+			.unwrap();
+		drop(node);
+
+		let local_address = Multiaddr::from_str(ADDR_2).unwrap();
+		let mut node = Node::new_mock(local_key, local_address, Vec::new(), INTERVAL).unwrap();
+		node.set_store(store).unwrap();
+		assert!(!node.get_peer().is_verified(neighbor_id, &opinion));
+		let (wal, entries) = Wal::open(&wal_path).unwrap();
+		assert_eq!(entries, vec![WalEntry::new(neighbor_id, &opinion)]);
+		node.set_wal(wal, entries);
+		assert!(node.get_peer().is_verified(neighbor_id, &opinion));
+		assert!(!node.get_peer().is_verified(neighbor_id, &unverified));
+		std::fs::remove_file(wal_path).unwrap();
+	}
+
+	#[tokio::test]
+	async fn should_report_recent_opinions() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56740";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+		let beacon = BeaconSource::new(10, 1).unwrap();
+		node.set_epoch_source(Box::new(beacon.clone()));
+		beacon.update(50);
+
+		let neighbor_id = PeerId::random();
+		let peer = node.get_peer_mut();
+		peer.add_neighbor(neighbor_id).unwrap();
+		peer.set_score(neighbor_id, 5);
+		let sent = Opinion::new(Epoch(3), SigData::empty(), 0.25, Vec::new());
+		let received = Opinion::new(Epoch(4), SigData::empty(), 0.5, Vec::new());
+		peer.cache_local_opinion((neighbor_id, Epoch(3)), sent);
+		peer.cache_neighbor_opinion((neighbor_id, Epoch(4)), received.clone());
+		peer.record_verification(neighbor_id, received.hash(), true);
+
+		let status = node.status();
+		assert_eq!(status.epoch, Some(Epoch(5)));
+		assert_eq!(status.last_epoch_score, None);
+		assert_eq!(status.pending_requests, 0);
+		assert_eq!(status.neighbors, 1);
+		assert_eq!(status.connected, 0);
+		assert_eq!(status.global_score, Some(MIN_SCORE + 0.5));
+
+		let reports = node.peer_reports(2);
+		assert_eq!(reports.len(), 1);
+		let report = &reports[0];
+		assert_eq!(report.peer_id, neighbor_id);
+		assert!(!report.connected);
+		assert_eq!(report.score, 5);
+		node.set_last_epoch(Epoch(5));
+		node.record_score(Epoch(5), 0.6);
+		node.send_epoch_requests(Epoch(5));
+		let status = node.status();
+		assert_eq!(status.last_epoch_score, Some(0.6));
+		assert_eq!(status.pending_requests, 1);
+
+		assert_eq!(report.opinions, vec![
+			EpochOpinions {
+				epoch: Epoch(3),
+				sent: Some(0.25),
+				received: None,
+				verified: false,
+			},
+			EpochOpinions {
+				epoch: Epoch(4),
+				sent: None,
+				received: Some(0.5),
+				verified: true,
+			},
+		]);
+	}
+
+	#[tokio::test]
+	async fn should_request_opinion_for_export() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56741";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+
+		let neighbor_key = Keypair::generate_secp256k1();
+		let neighbor_id = neighbor_key.public().to_peer_id();
+		node.get_peer_mut().add_neighbor(neighbor_id).unwrap();
+
+		// The neighbor is not identified yet.
+		let export = |sent| Command::ExportProof {
+			peer_id: neighbor_id,
+			epoch: Epoch(3),
+			sent,
+		};
+		let reply = node.handle_command(export(true));
+		assert_eq!(reply, Reply::Error("PeerNotIdentified".to_string()));
+
+		// Nothing was sent to the neighbor, so there is nothing to export.
+		node.get_peer_mut()
+			.identify_neighbor(neighbor_id, neighbor_key.public());
+		let reply = node.handle_command(export(true));
+		assert_eq!(reply, Reply::Error("InvalidOpinion".to_string()));
+
+		// The opinion of the neighbor is requested first.
+		let (reply_tx, mut reply_rx) = oneshot::channel();
+		node.run_command(export(false), reply_tx);
+		assert_eq!(node.pending_exports.len(), 1);
+		assert_eq!(node.requested_epochs.len(), 1);
+		assert_eq!(reply_rx.try_recv(), Ok(None));
+
+		// And the export fails if the request does.
+		let request_id = *node.pending_exports.keys().next().unwrap();
+		node.finish_export(neighbor_id, request_id);
+		let reply = reply_rx.try_recv().unwrap();
+		assert_eq!(reply, Some(Reply::Error("InvalidOpinion".to_string())));
+		assert!(node.pending_exports.is_empty());
+	}
+
+	#[tokio::test]
+	async fn should_verify_opinion_in_background() {
+		const ADDR: &str = "/ip4/127.0.0.1/tcp/56734";
+
+		let local_key = Keypair::generate_secp256k1();
+		let local_address = Multiaddr::from_str(ADDR).unwrap();
+		let params = params(18);
+
+		let mut node = Node::new(local_key, local_address, Vec::new(), INTERVAL, params).unwrap();
+
+		let neighbor_key = Keypair::generate_secp256k1();
+		let neighbor_id = neighbor_key.public().to_peer_id();
+		let peer = node.get_peer_mut();
+		peer.add_neighbor(neighbor_id).unwrap();
+		peer.identify_neighbor(neighbor_id, neighbor_key.public());
+
+		let opinion = Opinion::empty();
+		peer.cache_neighbor_opinion((neighbor_id, Epoch(0)), opinion.clone());
+		assert!(!node.get_peer().is_verified(neighbor_id, &opinion));
+
+		node.spawn_verification(neighbor_id, opinion.clone());
+		let result = node.verified_rx.next().await.unwrap();
+		assert_eq!(result, VerifiedOpinion {
+			peer_id: neighbor_id,
+			k: Epoch(0),
+			hash: opinion.hash(),
+			valid: true,
+			duration_ms: result.duration_ms,
+		});
+
+		let mut events = node.subscribe();
+		node.handle_verified_opinion(result.clone());
+		assert!(node.get_peer().is_verified(neighbor_id, &opinion));
+		let expected = NodeEvent::OpinionVerified {
+			peer_id: neighbor_id,
+			epoch: Epoch(0),
+			valid: true,
+			duration_ms: result.duration_ms,
+		};
+		assert_eq!(events.try_next().unwrap(), Some(expected));
+	}
+
 	#[tokio::test]
 	async fn should_run_main_loop() {
 		const ADDR_1: &str = "/ip4/127.0.0.1/tcp/56728";
@@ -733,7 +3112,7 @@ mod tests {
 			(peer_id2, local_address2.clone()),
 		];
 
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 
 		let mut node1 = Node::new(
 			local_key1,
@@ -6,27 +6,49 @@
 
 pub mod opinion;
 
-use crate::{epoch::Epoch, EigenError};
+use crate::{
+	epoch::Epoch,
+	node::duration_ms,
+	storage::{Store, Tree},
+	EigenError,
+};
 use eigen_trust_circuit::{
+	config::CircuitConfig,
 	halo2wrong::{
 		curves::{
 			bn256::{Bn256, Fr as Bn256Scalar, G1Affine},
 			secp256k1::Secp256k1Affine,
 			FieldExt,
 		},
-		halo2::{plonk::ProvingKey, poly::kzg::commitment::ParamsKZG},
+		halo2::{
+			plonk::{ProvingKey, VerifyingKey},
+			poly::kzg::commitment::ParamsKZG,
+		},
 	},
 	utils::{keygen, random_circuit},
 };
 use libp2p::{core::PublicKey, identity::Keypair, PeerId};
-use opinion::{Opinion, SCALE};
+use opinion::{MessageHasher, Opinion, SCALE};
 use rand::thread_rng;
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range, sync::Arc, time::Instant};
+
+/// The circuit config of the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkConfig;
+
+impl CircuitConfig for NetworkConfig {
+	type MessageHasher = MessageHasher;
+	type Scalar = Bn256Scalar;
+	type SignatureCurve = Secp256k1Affine;
+
+	const MAX_NEIGHBORS: usize = 256;
+	const SCALE: u64 = SCALE as u64;
+}
 
 /// The number of neighbors the peer can have.
 /// This is also the maximum number of peers that can be connected to the
 /// node.
-pub const MAX_NEIGHBORS: usize = 256;
+pub const MAX_NEIGHBORS: usize = NetworkConfig::MAX_NEIGHBORS;
 /// Minimum score a peer can have.
 pub const MIN_SCORE: f64 = 0.1;
 
@@ -35,11 +57,56 @@ pub struct Peer {
 	pub(crate) neighbors: [Option<PeerId>; MAX_NEIGHBORS],
 	pubkeys: HashMap<PeerId, PublicKey>,
 	neighbor_scores: HashMap<PeerId, u32>,
-	cached_neighbor_opinion: HashMap<(PeerId, Epoch), Opinion<MAX_NEIGHBORS>>,
-	cached_local_opinion: HashMap<(PeerId, Epoch), Opinion<MAX_NEIGHBORS>>,
-	keypair: Keypair,
-	params: ParamsKZG<Bn256>,
+	cached_neighbor_opinion: HashMap<(PeerId, Epoch), Opinion<NetworkConfig>>,
+	cached_local_opinion: HashMap<(PeerId, Epoch), Opinion<NetworkConfig>>,
+	/// The neighbor sets committed in the local opinions, revealed when the
+	/// receiver challenges the opinion.
+	cached_neighbor_sets: HashMap<(PeerId, Epoch), Vec<(PublicKey, f64)>>,
+	/// Results of the verification of the neighbor opinions, keyed by the
+	/// author and the hash of the opinion.
+	verified_opinions: HashMap<(PeerId, [u8; 32]), bool>,
+	pub(crate) keypair: Keypair,
+	/// How long the last opinion took to prove.
+	last_proof_ms: Option<u64>,
+	/// The keys for proving the opinions. Without them, the opinions are only
+	/// signed.
+	keys: Option<ProvingKeys>,
+	/// The store the scores and the opinions are written to, if persisted.
+	store: Option<Arc<dyn Store>>,
+}
+
+/// The params and the keys of the circuit.
+struct ProvingKeys {
+	params: Arc<ParamsKZG<Bn256>>,
 	proving_key: ProvingKey<G1Affine>,
+	verifying_key: Arc<VerifyingKey<G1Affine>>,
+}
+
+/// Everything needed to verify the opinions sent to a peer, so they can be
+/// verified away from the peer, e.g. in a background task.
+#[derive(Clone)]
+pub struct OpinionVerifier {
+	pubkey_v: PublicKey,
+	/// The params and the verifying key. Without them, only the signatures of
+	/// the opinions are checked.
+	keys: Option<(Arc<ParamsKZG<Bn256>>, Arc<VerifyingKey<G1Affine>>)>,
+}
+
+impl OpinionVerifier {
+	/// Verify the opinion of the author with the public key `pubkey_p`.
+	pub fn verify(&self, pubkey_p: &PublicKey, opinion: &Opinion<NetworkConfig>) -> bool {
+		let res = match &self.keys {
+			Some((params, vk)) => opinion.verify(pubkey_p, &self.pubkey_v, params, vk),
+			None => opinion.verify_mock(pubkey_p, &self.pubkey_v),
+		};
+		match res {
+			Ok(valid) => valid,
+			Err(e) => {
+				log::debug!("Error while verifying opinion: {:?}", e);
+				false
+			},
+		}
+	}
 }
 
 impl Peer {
@@ -47,20 +114,146 @@ impl Peer {
 	pub fn new(keypair: Keypair, params: ParamsKZG<Bn256>) -> Result<Self, EigenError> {
 		// TODO: Do proving key generation outside the construct
 		let mut rng = thread_rng();
-		let min_score = Bn256Scalar::from_u128((MIN_SCORE * SCALE).round() as u128);
-		let random_circuit =
-			random_circuit::<Bn256, Secp256k1Affine, _, MAX_NEIGHBORS>(min_score, &mut rng);
+		let min_score =
+			Bn256Scalar::from_u128((MIN_SCORE * NetworkConfig::SCALE as f64).round() as u128);
+		let random_circuit = random_circuit::<NetworkConfig, _>(min_score, &mut rng);
 		let pk = keygen(&params, &random_circuit).map_err(EigenError::Halo2Error)?;
-		Ok(Peer {
+		let keys = ProvingKeys {
+			params: Arc::new(params),
+			verifying_key: Arc::new(pk.get_vk().clone()),
+			proving_key: pk,
+		};
+		Ok(Self::with_keys(keypair, Some(keys)))
+	}
+
+	/// Creates a new peer that only signs its opinions, without proving them,
+	/// and accepts the opinions of the neighbors by their signatures. For
+	/// testing the protocol without the params and the key generation.
+	pub fn new_mock(keypair: Keypair) -> Self {
+		Self::with_keys(keypair, None)
+	}
+
+	fn with_keys(keypair: Keypair, keys: Option<ProvingKeys>) -> Self {
+		Peer {
 			neighbors: [None; MAX_NEIGHBORS],
 			pubkeys: HashMap::new(),
 			neighbor_scores: HashMap::new(),
 			cached_neighbor_opinion: HashMap::new(),
 			cached_local_opinion: HashMap::new(),
+			cached_neighbor_sets: HashMap::new(),
+			verified_opinions: HashMap::new(),
 			keypair,
-			params,
-			proving_key: pk,
-		})
+			last_proof_ms: None,
+			keys,
+			store: None,
+		}
+	}
+
+	/// Load the scores, the opinions and the results of their verification
+	/// from the store, and write the changes to it from now on.
+	pub fn set_store(&mut self, store: Arc<dyn Store>) -> Result<(), EigenError> {
+		self.load(store.as_ref())?;
+		self.store = Some(store);
+		Ok(())
+	}
+
+	/// Load the scores, the opinions and the results of their verification
+	/// from the store, on top of the ones in the memory.
+	pub(crate) fn load(&mut self, store: &dyn Store) -> Result<(), EigenError> {
+		self.neighbor_scores.extend(store.load_scores()?);
+		self.cached_local_opinion
+			.extend(store.load_opinions(Tree::LocalOpinions)?);
+		self.cached_neighbor_opinion
+			.extend(store.load_opinions(Tree::NeighborOpinions)?);
+		for (peer_id, hash, valid) in store.load_verifications()? {
+			self.verified_opinions.insert((peer_id, hash), valid);
+		}
+		Ok(())
+	}
+
+	/// Write the scores, the opinions since the epoch, and the results of the
+	/// verification of those opinions to the store.
+	pub(crate) fn export(&self, store: &dyn Store, since: Epoch) -> Result<(), EigenError> {
+		for (peer_id, score) in &self.neighbor_scores {
+			store.save_score(peer_id, *score)?;
+		}
+		for (key, opinion) in &self.cached_local_opinion {
+			if key.1 >= since {
+				store.save_opinion(Tree::LocalOpinions, key, opinion)?;
+			}
+		}
+		for (key, opinion) in &self.cached_neighbor_opinion {
+			if key.1 < since {
+				continue;
+			}
+			store.save_opinion(Tree::NeighborOpinions, key, opinion)?;
+			let (peer_id, _) = key;
+			let hash = opinion.hash();
+			if let Some(valid) = self.verified_opinions.get(&(*peer_id, hash)) {
+				store.save_verification(peer_id, &hash, *valid)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Forget the opinions of the epochs before `opinions_before`, and the
+	/// proofs of the ones before `proofs_before`. The results of the
+	/// verification follow the opinions. The store is pruned separately, see
+	/// [`crate::storage::prune`].
+	pub(crate) fn prune(&mut self, opinions_before: Epoch, proofs_before: Epoch) {
+		self.cached_local_opinion
+			.retain(|(_, k), _| *k >= opinions_before);
+		for ((_, k), opinion) in self.cached_local_opinion.iter_mut() {
+			if *k < proofs_before && !opinion.proof_bytes.is_empty() {
+				*opinion = opinion.without_proof();
+			}
+		}
+		self.cached_neighbor_sets
+			.retain(|(_, k), _| *k >= proofs_before);
+
+		let verified = &mut self.verified_opinions;
+		self.cached_neighbor_opinion
+			.retain(|(peer_id, k), opinion| {
+				if *k < opinions_before {
+					verified.remove(&(*peer_id, opinion.hash()));
+				}
+				*k >= opinions_before
+			});
+		for ((peer_id, k), opinion) in self.cached_neighbor_opinion.iter_mut() {
+			if *k < proofs_before && !opinion.proof_bytes.is_empty() {
+				let stripped = opinion.without_proof();
+				// The hash covers the proof, so the result is moved to the new one.
+				if let Some(valid) = verified.remove(&(*peer_id, opinion.hash())) {
+					verified.insert((*peer_id, stripped.hash()), valid);
+				}
+				*opinion = stripped;
+			}
+		}
+	}
+
+	/// Forget the scores, the opinions and the results of their verification,
+	/// so they can be loaded again. The store is left as it is.
+	pub(crate) fn clear_state(&mut self) {
+		self.neighbor_scores.clear();
+		self.cached_local_opinion.clear();
+		self.cached_neighbor_opinion.clear();
+		self.cached_neighbor_sets.clear();
+		self.verified_opinions.clear();
+	}
+
+	/// Write to the store, if there is one. The failures are only logged, so
+	/// the peer keeps working from the memory.
+	fn persist(&self, what: &str, write: impl FnOnce(&dyn Store) -> Result<(), EigenError>) {
+		if let Some(store) = &self.store {
+			if let Err(e) = write(store.as_ref()) {
+				log::error!("Failed to persist the {}: {:?}", what, e);
+			}
+		}
+	}
+
+	/// Returns true if the opinions are only signed, and not proven.
+	pub fn is_mock(&self) -> bool {
+		self.keys.is_none()
 	}
 
 	/// Adds a neighbor in the first available spot.
@@ -98,6 +291,20 @@ impl Peer {
 	/// Set the local score towards a neighbor.
 	pub fn set_score(&mut self, peer_id: PeerId, score: u32) {
 		self.neighbor_scores.insert(peer_id, score);
+		self.persist("score", |store| store.save_score(&peer_id, score));
+	}
+
+	/// Set the local scores towards many neighbors at once, e.g. when seeding
+	/// the trust relationships at startup.
+	pub fn set_scores(&mut self, scores: impl IntoIterator<Item = (PeerId, u32)>) {
+		for (peer_id, score) in scores {
+			self.set_score(peer_id, score);
+		}
+	}
+
+	/// Returns the local score towards a neighbor, or zero if it was not set.
+	pub fn get_score(&self, peer_id: PeerId) -> u32 {
+		self.neighbor_scores.get(&peer_id).cloned().unwrap_or(0)
 	}
 
 	/// Calculate the local trust score toward all neighbors in the specified
@@ -112,19 +319,36 @@ impl Peer {
 			return;
 		}
 
-		let op_ji = self.get_neighbor_opinions_at(k.previous());
-		let normalized_score = self.get_normalized_score(*score);
+		let op_ji = self.get_neighbor_slots_at(k.previous());
+		let neighbors = self.get_neighbor_scores();
 		let pubkey_op = self.get_pub_key(peer_id);
+		let start = Instant::now();
 		let opinion = match pubkey_op {
-			Some(pubkey) => Opinion::generate(
-				&self.keypair,
-				&pubkey,
-				k,
-				op_ji,
-				normalized_score,
-				&self.params,
-				&self.proving_key,
-			)
+			Some(pubkey) => match &self.keys {
+				Some(keys) => Opinion::generate(
+					&self.keypair,
+					&pubkey,
+					k,
+					&op_ji,
+					&neighbors,
+					&keys.params,
+					&keys.proving_key,
+				),
+				None => Opinion::generate_mock(&self.keypair, &pubkey, k, &op_ji, &neighbors),
+			}
+			.map(|opinion| {
+				let duration_ms = duration_ms(start.elapsed());
+				log::debug!(
+					kind = "opinion_generated",
+					epoch = k.0,
+					peer_id = peer_id.to_base58(),
+					duration_ms = duration_ms;
+					"Generated the opinion for {:?} in {:?}", peer_id, k
+				);
+				self.last_proof_ms = Some(duration_ms);
+				self.cached_neighbor_sets.insert((peer_id, k), neighbors);
+				opinion
+			})
 			.unwrap_or_else(|e| {
 				log::debug!("Error while generating opinion for {:?}: {:?}", peer_id, e);
 				Opinion::empty()
@@ -141,34 +365,109 @@ impl Peer {
 		self.cache_local_opinion((peer_id, opinion.k), opinion);
 	}
 
+	/// Returns how long the last opinion took to prove, in milliseconds.
+	pub fn last_proof_ms(&self) -> Option<u64> {
+		self.last_proof_ms
+	}
+
 	/// Returns all of the opinions of the neighbors in the specified epoch.
+	/// Only the opinions that were verified count, the rest are zero.
 	pub fn get_neighbor_opinions_at(&self, k: Epoch) -> [f64; MAX_NEIGHBORS] {
 		self.neighbors.map(|peer| {
-			let peer_pk_pair =
-				peer.and_then(|peer_id| self.get_pub_key(peer_id).map(|pk| (peer_id, pk)));
-			peer_pk_pair
-				.map(|(peer_id, pubkey_p)| {
-					let opinion = self.get_neighbor_opinion(&(peer_id, k));
-					let pubkey_v = self.keypair.public();
-					let vk = self.proving_key.get_vk();
-
-					match opinion.verify(&pubkey_p, &pubkey_v, &self.params, vk) {
-						Ok(true) => opinion.op,
-						Err(e) => {
-							log::debug!(
-								"Error while verifying opinion from {:?}: {:?}",
-								peer_id,
-								e
-							);
-							0.0
-						},
-						_ => 0.0,
-					}
-				})
-				.unwrap_or(0.0)
+			peer.map(|peer_id| {
+				let opinion = self.get_neighbor_opinion(&(peer_id, k));
+				if self.is_verified(peer_id, &opinion) {
+					opinion.op
+				} else {
+					0.0
+				}
+			})
+			.unwrap_or(0.0)
 		})
 	}
 
+	/// Returns the opinions of the neighbors in the specified epoch, like
+	/// [`Peer::get_neighbor_opinions_at`], together with the public keys of
+	/// the neighbors. The slots without an identified neighbor have no key.
+	pub fn get_neighbor_slots_at(&self, k: Epoch) -> Vec<(Option<PublicKey>, f64)> {
+		let op_ji = self.get_neighbor_opinions_at(k);
+		self.neighbors
+			.iter()
+			.zip(op_ji)
+			.map(|(peer, op)| (peer.and_then(|peer_id| self.get_pub_key(peer_id)), op))
+			.collect()
+	}
+
+	/// Returns the verifier of the opinions sent to this peer.
+	pub fn opinion_verifier(&self) -> OpinionVerifier {
+		OpinionVerifier {
+			pubkey_v: self.keypair.public(),
+			keys: self
+				.keys
+				.as_ref()
+				.map(|keys| (keys.params.clone(), keys.verifying_key.clone())),
+		}
+	}
+
+	/// Record the result of the verification of an opinion, with the hash
+	/// `hash`, from the neighbor.
+	pub fn record_verification(&mut self, peer_id: PeerId, hash: [u8; 32], valid: bool) {
+		self.verified_opinions.insert((peer_id, hash), valid);
+		self.persist("verification", |store| {
+			store.save_verification(&peer_id, &hash, valid)
+		});
+	}
+
+	/// Returns true if the opinion of the neighbor was verified and is valid.
+	pub fn is_verified(&self, peer_id: PeerId, opinion: &Opinion<NetworkConfig>) -> bool {
+		let key = (peer_id, opinion.hash());
+		self.verified_opinions.get(&key).cloned().unwrap_or(false)
+	}
+
+	/// Verify the cached opinion of the neighbor in the specified epoch right
+	/// away, and record the result.
+	pub fn verify_neighbor_opinion(&mut self, peer_id: PeerId, k: Epoch) -> bool {
+		let opinion = self.get_neighbor_opinion(&(peer_id, k));
+		let valid = match self.get_pub_key(peer_id) {
+			Some(pubkey_p) => self.opinion_verifier().verify(&pubkey_p, &opinion),
+			None => false,
+		};
+		self.record_verification(peer_id, opinion.hash(), valid);
+		valid
+	}
+
+	/// Verifies all of the cached opinions of a neighbor in the range of
+	/// epochs, as a single batch. The proofs of every epoch in the range are
+	/// checked again, so the cost grows with the length of the range.
+	pub fn verify_neighbor_history(
+		&self,
+		peer_id: PeerId,
+		epochs: Range<Epoch>,
+	) -> Result<bool, EigenError> {
+		let pubkey_p = self
+			.get_pub_key(peer_id)
+			.ok_or(EigenError::PeerNotIdentified)?;
+		let opinions: Vec<(Opinion<NetworkConfig>, PublicKey)> = Epoch::range(epochs)
+			.filter_map(|k| self.cached_neighbor_opinion.get(&(peer_id, k)))
+			.map(|opinion| (opinion.clone(), pubkey_p.clone()))
+			.collect();
+
+		let pubkey_v = self.keypair.public();
+		match &self.keys {
+			Some(keys) => {
+				Opinion::verify_batch(&opinions, &pubkey_v, &keys.params, &keys.verifying_key)
+			},
+			None => {
+				for (opinion, pubkey_p) in &opinions {
+					if !opinion.verify_mock(pubkey_p, &pubkey_v)? {
+						return Ok(false);
+					}
+				}
+				Ok(true)
+			},
+		}
+	}
+
 	/// Calculate the global trust score at the specified epoch.
 	pub fn global_trust_score_at(&self, at: Epoch) -> f64 {
 		let op_ji = self.get_neighbor_opinions_at(at.previous());
@@ -185,6 +484,19 @@ impl Peer {
 		sum
 	}
 
+	/// Returns the identified neighbors with a score, together with their
+	/// normalized scores. These are the neighbors committed in the opinions.
+	pub fn get_neighbor_scores(&self) -> Vec<(PublicKey, f64)> {
+		self.neighbors()
+			.into_iter()
+			.filter_map(|peer_id| {
+				let score = *self.neighbor_scores.get(&peer_id)?;
+				let pubkey = self.get_pub_key(peer_id)?;
+				(score > 0).then(|| (pubkey, self.get_normalized_score(score)))
+			})
+			.collect()
+	}
+
 	/// Returns the normalized score.
 	pub fn get_normalized_score(&self, score: u32) -> f64 {
 		let sum = self.get_sum_of_scores();
@@ -194,33 +506,96 @@ impl Peer {
 	}
 
 	/// Returns the local score towards a neighbor in a specified epoch.
-	pub fn get_local_opinion(&self, key: &(PeerId, Epoch)) -> Opinion<MAX_NEIGHBORS> {
+	pub fn get_local_opinion(&self, key: &(PeerId, Epoch)) -> Opinion<NetworkConfig> {
 		self.cached_local_opinion
 			.get(key)
 			.unwrap_or(&Opinion::empty())
 			.clone()
 	}
 
+	/// Returns the local opinion towards a peer in a specified epoch, if there
+	/// is one.
+	pub fn find_local_opinion(&self, key: &(PeerId, Epoch)) -> Option<&Opinion<NetworkConfig>> {
+		self.cached_local_opinion.get(key)
+	}
+
 	/// Caches the local opinion towards a peer in a specified epoch.
-	pub fn cache_local_opinion(&mut self, key: (PeerId, Epoch), opinion: Opinion<MAX_NEIGHBORS>) {
+	pub fn cache_local_opinion(&mut self, key: (PeerId, Epoch), opinion: Opinion<NetworkConfig>) {
+		self.persist("local opinion", |store| {
+			store.save_opinion(Tree::LocalOpinions, &key, &opinion)
+		});
 		self.cached_local_opinion.insert(key, opinion);
 	}
 
+	/// Returns the neighbor set committed in the local opinion towards a peer
+	/// in a specified epoch, if the opinion was generated.
+	pub fn get_neighbor_set(&self, key: &(PeerId, Epoch)) -> Option<Vec<(PublicKey, f64)>> {
+		self.cached_neighbor_sets.get(key).cloned()
+	}
+
 	/// Returns the neighbor's opinion towards us in a specified epoch.
-	pub fn get_neighbor_opinion(&self, key: &(PeerId, Epoch)) -> Opinion<MAX_NEIGHBORS> {
+	pub fn get_neighbor_opinion(&self, key: &(PeerId, Epoch)) -> Opinion<NetworkConfig> {
 		self.cached_neighbor_opinion
 			.get(key)
 			.unwrap_or(&Opinion::empty())
 			.clone()
 	}
 
-	/// Caches the neighbor opinion towards us in specified epoch.
+	/// Returns the neighbor's opinion towards us in a specified epoch, if
+	/// there is one.
+	pub fn find_neighbor_opinion(&self, key: &(PeerId, Epoch)) -> Option<&Opinion<NetworkConfig>> {
+		self.cached_neighbor_opinion.get(key)
+	}
+
+	/// Caches the neighbor opinion towards us in specified epoch. The result
+	/// of the verification of the replaced opinion is forgotten.
 	pub fn cache_neighbor_opinion(
 		&mut self,
 		key: (PeerId, Epoch),
-		opinion: Opinion<MAX_NEIGHBORS>,
+		opinion: Opinion<NetworkConfig>,
 	) {
-		self.cached_neighbor_opinion.insert(key, opinion);
+		let (peer_id, _) = key;
+		self.persist("neighbor opinion", |store| {
+			store.save_opinion(Tree::NeighborOpinions, &key, &opinion)
+		});
+		if let Some(old) = self.cached_neighbor_opinion.insert(key, opinion) {
+			self.forget_verification(peer_id, old.hash());
+		}
+	}
+
+	/// Forget the result of the verification of the opinion with the hash.
+	fn forget_verification(&mut self, peer_id: PeerId, hash: [u8; 32]) {
+		self.verified_opinions.remove(&(peer_id, hash));
+		self.persist("verification", |store| {
+			store.remove_verification(&peer_id, &hash)
+		});
+	}
+
+	/// Removes the local opinion towards a peer, so it is not sent anymore,
+	/// and returns it.
+	pub fn remove_local_opinion(
+		&mut self,
+		key: &(PeerId, Epoch),
+	) -> Option<Opinion<NetworkConfig>> {
+		self.persist("local opinion", |store| {
+			store.remove_opinion(Tree::LocalOpinions, key)
+		});
+		self.cached_local_opinion.remove(key)
+	}
+
+	/// Removes the neighbor opinion towards us, together with the result of
+	/// its verification, and returns it.
+	pub fn remove_neighbor_opinion(
+		&mut self,
+		key: &(PeerId, Epoch),
+	) -> Option<Opinion<NetworkConfig>> {
+		let (peer_id, _) = key;
+		let opinion = self.cached_neighbor_opinion.remove(key)?;
+		self.persist("neighbor opinion", |store| {
+			store.remove_opinion(Tree::NeighborOpinions, key)
+		});
+		self.forget_verification(*peer_id, opinion.hash());
+		Some(opinion)
 	}
 
 	/// Get the public key of a neighbor.
@@ -233,17 +608,14 @@ impl Peer {
 mod tests {
 	use super::*;
 	use eigen_trust_circuit::{
-		ecdsa::SigData,
-		halo2wrong::{
-			curves::secp256k1::Fq as Secp256k1Scalar, halo2::poly::commitment::ParamsProver,
-		},
+		ecdsa::SigData, halo2wrong::curves::secp256k1::Fq as Secp256k1Scalar, test_utils::params,
 	};
 	use libp2p::core::identity::Keypair;
 
 	#[test]
 	fn should_create_peer() {
 		let kp = Keypair::generate_secp256k1();
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 		let peer = Peer::new(kp, params).unwrap();
 		assert_eq!(peer.get_sum_of_scores(), 0);
 	}
@@ -251,7 +623,7 @@ mod tests {
 	#[test]
 	fn should_cache_local_and_global_opinion() {
 		let kp = Keypair::generate_secp256k1();
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 		let mut peer = Peer::new(kp, params).unwrap();
 
 		let epoch = Epoch(0);
@@ -269,10 +641,93 @@ mod tests {
 		assert_eq!(peer.get_neighbor_opinion(&(neighbor_id, epoch)), opinion);
 	}
 
+	#[test]
+	fn should_set_scores_in_batch() {
+		let kp = Keypair::generate_secp256k1();
+		let params = params(18);
+		let mut peer = Peer::new(kp, params).unwrap();
+
+		let neighbor1 = PeerId::random();
+		let neighbor2 = PeerId::random();
+		peer.add_neighbor(neighbor1).unwrap();
+		peer.add_neighbor(neighbor2).unwrap();
+		peer.set_score(neighbor1, 1);
+
+		peer.set_scores(vec![(neighbor1, 3), (neighbor2, 5)]);
+		assert_eq!(peer.get_score(neighbor1), 3);
+		assert_eq!(peer.get_score(neighbor2), 5);
+		assert_eq!(peer.get_sum_of_scores(), 8);
+		assert_eq!(peer.get_score(PeerId::random()), 0);
+	}
+
+	#[test]
+	fn should_count_only_verified_opinions() {
+		let kp = Keypair::generate_secp256k1();
+		let params = params(18);
+		let mut peer = Peer::new(kp, params).unwrap();
+
+		let epoch = Epoch(3);
+		let neighbor_id = PeerId::random();
+		let pubkey = Keypair::generate_secp256k1().public();
+		peer.add_neighbor(neighbor_id).unwrap();
+		peer.identify_neighbor(neighbor_id, pubkey);
+
+		let sig = SigData::<Secp256k1Scalar>::empty();
+		let opinion = Opinion::new(epoch, sig, 0.5, Vec::new());
+		peer.cache_neighbor_opinion((neighbor_id, epoch), opinion.clone());
+
+		// Not verified yet.
+		assert!(!peer.is_verified(neighbor_id, &opinion));
+		assert_eq!(peer.get_neighbor_opinions_at(epoch)[0], 0.0);
+
+		peer.record_verification(neighbor_id, opinion.hash(), true);
+		assert_eq!(peer.get_neighbor_opinions_at(epoch)[0], 0.5);
+
+		// The opinion has no valid proof.
+		assert!(!peer.verify_neighbor_opinion(neighbor_id, epoch));
+		assert_eq!(peer.get_neighbor_opinions_at(epoch)[0], 0.0);
+
+		// Replacing the opinion forgets the old result.
+		peer.record_verification(neighbor_id, opinion.hash(), true);
+		let other = Opinion::new(epoch, sig, 0.25, Vec::new());
+		peer.cache_neighbor_opinion((neighbor_id, epoch), other);
+		assert!(!peer.is_verified(neighbor_id, &opinion));
+	}
+
+	#[test]
+	fn should_exchange_mock_opinions() {
+		let epoch = Epoch(2);
+		let kp_1 = Keypair::generate_secp256k1();
+		let kp_2 = Keypair::generate_secp256k1();
+		let peer_id_1 = kp_1.public().to_peer_id();
+		let peer_id_2 = kp_2.public().to_peer_id();
+
+		let mut peer_1 = Peer::new_mock(kp_1.clone());
+		let mut peer_2 = Peer::new_mock(kp_2.clone());
+		assert!(peer_1.is_mock());
+		peer_1.add_neighbor(peer_id_2).unwrap();
+		peer_1.identify_neighbor(peer_id_2, kp_2.public());
+		peer_1.set_score(peer_id_2, 5);
+		peer_2.add_neighbor(peer_id_1).unwrap();
+		peer_2.identify_neighbor(peer_id_1, kp_1.public());
+
+		peer_1.calculate_local_opinion(peer_id_2, epoch);
+		let opinion = peer_1.get_local_opinion(&(peer_id_2, epoch));
+		assert_eq!(opinion.k, epoch);
+		assert!(opinion.proof_bytes.is_empty());
+
+		peer_2.cache_neighbor_opinion((peer_id_1, epoch), opinion);
+		assert!(peer_2.verify_neighbor_opinion(peer_id_1, epoch));
+		assert!(peer_2
+			.verify_neighbor_history(peer_id_1, epoch..epoch.next())
+			.unwrap());
+		assert_eq!(peer_2.get_neighbor_opinions_at(epoch)[0], MIN_SCORE);
+	}
+
 	#[test]
 	fn should_add_and_remove_neghbours() {
 		let kp = Keypair::generate_secp256k1();
-		let params = ParamsKZG::new(18);
+		let params = params(18);
 		let mut peer = Peer::new(kp, params).unwrap();
 		let neighbor_id = PeerId::random();
 
@@ -291,10 +746,10 @@ mod tests {
 		let local_keypair = Keypair::generate_secp256k1();
 		let local_pubkey = local_keypair.public();
 
-		let params = ParamsKZG::<Bn256>::new(18);
-		let min_score = Bn256Scalar::from_u128((MIN_SCORE * SCALE).round() as u128);
-		let random_circuit =
-			random_circuit::<Bn256, Secp256k1Affine, _, MAX_NEIGHBORS>(min_score, &mut rng.clone());
+		let params = params(18);
+		let min_score =
+			Bn256Scalar::from_u128((MIN_SCORE * NetworkConfig::SCALE as f64).round() as u128);
+		let random_circuit = random_circuit::<NetworkConfig, _>(min_score, &mut rng.clone());
 		let pk = keygen(&params, &random_circuit).unwrap();
 
 		let mut peer = Peer::new(local_keypair, params.clone()).unwrap();
@@ -311,19 +766,22 @@ mod tests {
 			peer.set_score(peer_id, 5);
 
 			// Create neighbor opinion.
-			let mut op_ji = [0.; MAX_NEIGHBORS];
-			op_ji[0] = 0.1;
+			let mut op_ji = vec![(None, 0.); MAX_NEIGHBORS];
+			op_ji[0] = (Some(local_pubkey.clone()), 0.1);
 			let c_v = 1.;
+			let neighbors = [(local_pubkey.clone(), c_v)];
 			let opinion =
-				Opinion::generate(&kp, &local_pubkey, epoch, op_ji, c_v, &params, &pk).unwrap();
+				Opinion::generate(&kp, &local_pubkey, epoch, &op_ji, &neighbors, &params, &pk)
+					.unwrap();
 
 			// Sanity check
 			assert!(opinion
 				.verify(&pubkey, &local_pubkey, &params, &pk.get_vk())
 				.unwrap());
 
-			// Cache neighbor opinion.
+			// Cache and verify neighbor opinion.
 			peer.cache_neighbor_opinion((peer_id, epoch), opinion);
+			assert!(peer.verify_neighbor_opinion(peer_id, epoch));
 		}
 
 		for peer_id in peer.neighbors() {
@@ -1,11 +1,18 @@
 use super::MIN_SCORE;
 use crate::{EigenError, Epoch};
 use eigen_trust_circuit::{
-	ecdsa::{generate_signature, Keypair, SigData},
+	compress_public_inputs,
+	config::CircuitConfig,
+	ecdsa::{generate_signature, verify_signature, Keypair, SigData},
+	eddsa::native::SecretKey as EddsaSecretKey,
+	gadgets::{
+		commitment::commit,
+		merkle::{compute_root, MerkleTree},
+	},
 	halo2wrong::{
 		curves::{
 			bn256::{Bn256, Fr as Bn256Scalar, G1Affine},
-			group::{Curve, Group},
+			group::{ff::PrimeField, Curve, Group},
 			secp256k1::{Fp as Secp256k1Base, Fq as Secp256k1Scalar, Secp256k1Affine},
 			CurveAffine, FieldExt,
 		},
@@ -14,82 +21,448 @@ use eigen_trust_circuit::{
 			poly::kzg::commitment::ParamsKZG,
 		},
 	},
-	poseidon::{params::Params5x5Bn254, Poseidon},
-	utils::{prove, verify},
-	EigenTrustCircuit,
+	hasher::Hasher,
+	utils::{encode_calldata, prove, verify, verify_batch},
+	EigenTrustCircuit, NUM_PUBLIC_INPUTS,
 };
 use libp2p::core::{identity::Keypair as IdentityKeypair, PublicKey as IdentityPublicKey};
-use rand::thread_rng;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::{cmp::Ordering, marker::PhantomData};
+
+/// The hash function used for the opinion messages. It has to match between
+/// all the peers in the network, so it is chosen at compile time.
+#[cfg(not(feature = "rescue-prime"))]
+pub type MessageHasher = eigen_trust_circuit::hasher::PoseidonHasher<
+	eigen_trust_circuit::poseidon::params::Params5x5Bn254,
+>;
+/// The hash function used for the opinion messages, with the `rescue-prime`
+/// feature enabled.
+#[cfg(feature = "rescue-prime")]
+pub type MessageHasher = eigen_trust_circuit::hasher::RescuePrimeHasher<
+	eigen_trust_circuit::rescue_prime::params::RescuePrime5Bn254,
+>;
 
-pub type Posedion5x5 = Poseidon<Bn256Scalar, 5, Params5x5Bn254>;
 pub const SCALE: f64 = 100000000.;
 
+/// Domain separator of the opinion hashes, so they can't collide with the
+/// hashes of other structures encoded the same way.
+pub const OPINION_HASH_DOMAIN: &[u8] = b"eigen_trust/opinion";
+/// Domain separator of the salt of the commitment to the neighbor slots.
+const PEERS_SALT_DOMAIN: &[u8] = b"eigen_trust/peers";
+/// Domain separator of the salt of the commitment to the local scores.
+const SCORES_SALT_DOMAIN: &[u8] = b"eigen_trust/scores";
+/// Version of the opinion encoding, bumped on every change of the layout. The
+/// older layouts are still decoded, and upgraded to the current one, so the
+/// cached opinions survive the changes of the protocol.
+pub const OPINION_ENCODING_VERSION: u8 = 2;
+/// How far the normalized scores of a revealed neighbor set can be from
+/// adding up to one, because of the floating point errors.
+const SCORE_SUM_TOLERANCE: f64 = 1e-6;
+
+/// Commitment to the neighbor set of the author of an opinion, and to the
+/// scores it gives to each of them, with the proof that the receiver of the
+/// opinion is one of the neighbors. The root is signed together with the
+/// opinion, so the author can't swap neighbor sets between its opinions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NeighborCommitment {
+	/// The Merkle root over the leaves `H(pk_x, pk_y, c_j)` of all the
+	/// neighbors.
+	pub root: Bn256Scalar,
+	/// The score given to the receiver.
+	pub c_v: f64,
+	/// The index of the receiver's leaf.
+	pub index: u64,
+	/// The siblings on the path from the receiver's leaf to the root.
+	pub path: Vec<Bn256Scalar>,
+}
+
+impl NeighborCommitment {
+	/// Check that the receiver, with the score `c_v`, is included in the
+	/// neighbor tree of the config `C`.
+	pub fn includes<C: CircuitConfig<Scalar = Bn256Scalar>>(
+		&self,
+		pubkey_v: &Secp256k1Affine,
+	) -> bool {
+		let depth = neighbors_depth::<C>();
+		if self.path.len() != depth || self.index >> depth != 0 {
+			return false;
+		}
+		let index = match usize::try_from(self.index) {
+			Ok(index) => index,
+			Err(_) => return false,
+		};
+		let leaf = neighbor_leaf::<C>(pubkey_v, self.c_v);
+		compute_root::<_, 5, C::MessageHasher>(leaf, index, &self.path) == self.root
+	}
+}
+
+/// The depth of the neighbor tree, enough to hold `C::MAX_NEIGHBORS` leaves.
+fn neighbors_depth<C: CircuitConfig>() -> usize {
+	C::MAX_NEIGHBORS.next_power_of_two().trailing_zeros() as usize
+}
+
+/// The tree over the leaves of the neighbors, in the order they are given.
+fn neighbor_tree<C: CircuitConfig<Scalar = Bn256Scalar>>(
+	neighbors: &[(IdentityPublicKey, f64)],
+) -> Result<MerkleTree<Bn256Scalar, 5, C::MessageHasher>, EigenError> {
+	let leaves = neighbors
+		.iter()
+		.map(|(pubkey_j, c_j)| Ok(neighbor_leaf::<C>(&convert_pubkey(pubkey_j)?, *c_j)))
+		.collect::<Result<Vec<_>, EigenError>>()?;
+	MerkleTree::new(leaves, neighbors_depth::<C>()).ok_or(EigenError::InvalidNumNeighbours)
+}
+
+/// The leaf of a neighbor with the score `c_j`: `H(pk_x, pk_y, c_j, 0, 0)`.
+fn neighbor_leaf<C: CircuitConfig<Scalar = Bn256Scalar>>(
+	pubkey: &Secp256k1Affine,
+	c_j: f64,
+) -> Bn256Scalar {
+	let pk_x = Bn256Scalar::from_bytes_wide(&to_wide(pubkey.x.to_bytes()));
+	let pk_y = Bn256Scalar::from_bytes_wide(&to_wide(pubkey.y.to_bytes()));
+	let c_j = Bn256Scalar::from_u128((c_j * C::SCALE as f64).round() as u128);
+	C::MessageHasher::hash([pk_x, pk_y, c_j, Bn256Scalar::zero(), Bn256Scalar::zero()])
+}
+
+/// The key of a neighbor slot in the circuit: `H(pk_x, pk_y, 0, 0, 0)`, or zero
+/// for an empty slot.
+fn slot_key<C: CircuitConfig<Scalar = Bn256Scalar>>(
+	pubkey: Option<&IdentityPublicKey>,
+) -> Result<Bn256Scalar, EigenError> {
+	let pubkey = match pubkey {
+		Some(pubkey) => convert_pubkey(pubkey)?,
+		None => return Ok(Bn256Scalar::zero()),
+	};
+	let pk_x = Bn256Scalar::from_bytes_wide(&to_wide(pubkey.x.to_bytes()));
+	let pk_y = Bn256Scalar::from_bytes_wide(&to_wide(pubkey.y.to_bytes()));
+	let zero = Bn256Scalar::zero();
+	Ok(C::MessageHasher::hash([pk_x, pk_y, zero, zero, zero]))
+}
+
+/// The salt of a commitment in the epoch, derived from the secret key, so the
+/// commitment doesn't reveal its values, and is the same for all the opinions
+/// of the epoch. The domain separates the salts of the different commitments.
+fn commitment_salt(
+	kp: &IdentityKeypair,
+	k: Epoch,
+	domain: &[u8],
+) -> Result<Bn256Scalar, EigenError> {
+	match kp {
+		IdentityKeypair::Secp256k1(secp_kp) => {
+			let mut hasher = Sha256::new();
+			hasher.update(domain);
+			hasher.update(secp_kp.secret().to_bytes());
+			hasher.update(k.to_be_bytes());
+			Ok(Bn256Scalar::from_bytes_wide(&to_wide(
+				hasher.finalize().into(),
+			)))
+		},
+		_ => Err(EigenError::InvalidKeypair),
+	}
+}
+
+/// Scale the local scores of the neighbors to integers that add up to exactly
+/// `scale`, by rounding down, and handing out the rest to the scores with the
+/// largest remainders. Fails if the scores don't add up to one.
+fn scale_scores(
+	neighbors: &[(IdentityPublicKey, f64)],
+	scale: u64,
+) -> Result<Vec<u64>, EigenError> {
+	let sum: f64 = neighbors.iter().map(|(_, c_j)| c_j).sum();
+	let valid = neighbors
+		.iter()
+		.all(|(_, c_j)| c_j.is_finite() && *c_j >= 0.);
+	if !valid || (sum - 1.).abs() > SCORE_SUM_TOLERANCE {
+		return Err(EigenError::InvalidScores);
+	}
+
+	let exact: Vec<f64> = neighbors
+		.iter()
+		.map(|(_, c_j)| c_j / sum * scale as f64)
+		.collect();
+	let mut scaled: Vec<u64> = exact.iter().map(|c_j| c_j.floor() as u64).collect();
+	let total: u64 = scaled.iter().sum();
+	let rest = scale.checked_sub(total).ok_or(EigenError::InvalidScores)?;
+
+	let mut by_remainder: Vec<usize> = (0..scaled.len()).collect();
+	by_remainder.sort_by(|&a, &b| {
+		let remainder_a = exact[a] - exact[a].floor();
+		let remainder_b = exact[b] - exact[b].floor();
+		remainder_b
+			.partial_cmp(&remainder_a)
+			.unwrap_or(Ordering::Equal)
+	});
+	for &i in by_remainder.iter().cycle().take(rest as usize) {
+		scaled[i] += 1;
+	}
+	Ok(scaled)
+}
+
+/// The opinion of a peer towards a neighbor, proven with the circuit of the
+/// config `C`. The libp2p identity keys are secp256k1 keys, so the config has
+/// to verify the signatures over secp256k1.
 #[derive(Clone, Debug, PartialEq)]
-pub struct Opinion<const N: usize> {
+pub struct Opinion<C: CircuitConfig> {
 	pub(crate) k: Epoch,
 	pub(crate) sig_i: SigData<Secp256k1Scalar>,
 	pub(crate) op: f64,
+	pub(crate) neighbors: NeighborCommitment,
+	/// The salted commitment to the keys of the neighbor slots, whose
+	/// opinions are summed in the proof.
+	pub(crate) peers: Bn256Scalar,
+	/// The salted commitment to the local scores of the author, which add up
+	/// to the scale. The same for all the opinions of the author in an epoch.
+	pub(crate) scores: Bn256Scalar,
 	pub(crate) proof_bytes: Vec<u8>,
+	_config: PhantomData<C>,
 }
 
-impl<const N: usize> Opinion<N> {
+impl<C> Opinion<C>
+where
+	C: CircuitConfig<Scalar = Bn256Scalar, SignatureCurve = Secp256k1Affine>,
+{
 	pub fn new(k: Epoch, sig_i: SigData<Secp256k1Scalar>, op: f64, proof_bytes: Vec<u8>) -> Self {
 		Self {
 			k,
 			sig_i,
 			op,
+			neighbors: NeighborCommitment::default(),
+			peers: Bn256Scalar::zero(),
+			scores: Bn256Scalar::zero(),
 			proof_bytes,
+			_config: PhantomData,
 		}
 	}
 
-	/// Creates a new opinion.
+	/// Attach the commitment to the neighbor set of the author.
+	pub fn with_neighbors(mut self, neighbors: NeighborCommitment) -> Self {
+		self.neighbors = neighbors;
+		self
+	}
+
+	/// Creates a new opinion. There must be an opinion of every neighbor
+	/// slot, `C::MAX_NEIGHBORS` in total, together with the key of the
+	/// neighbor in the slot, or `None` for an empty slot. The keys of the
+	/// slots are committed in the opinion, and the opinions of the empty slots
+	/// are left out. The scores `c_j` given to all the neighbors are committed
+	/// in the opinion, and the receiver must be one of them.
 	pub fn generate(
 		kp: &IdentityKeypair,
 		pubkey_v: &IdentityPublicKey,
 		k: Epoch,
-		op_ji: [f64; N],
-		c_v: f64,
+		op_ji: &[(Option<IdentityPublicKey>, f64)],
+		neighbors: &[(IdentityPublicKey, f64)],
 		params: &ParamsKZG<Bn256>,
 		pk: &ProvingKey<G1Affine>,
 	) -> Result<Self, EigenError> {
-		let mut rng = thread_rng();
+		Self::generate_with(
+			kp,
+			pubkey_v,
+			k,
+			op_ji,
+			neighbors,
+			Some((params, pk)),
+			false,
+			None,
+		)
+	}
+
+	/// Creates a new opinion that is signed, but not proven, for running the
+	/// protocol without the params. Only [`Opinion::verify_mock`] accepts it.
+	pub fn generate_mock(
+		kp: &IdentityKeypair,
+		pubkey_v: &IdentityPublicKey,
+		k: Epoch,
+		op_ji: &[(Option<IdentityPublicKey>, f64)],
+		neighbors: &[(IdentityPublicKey, f64)],
+	) -> Result<Self, EigenError> {
+		Self::generate_with(kp, pubkey_v, k, op_ji, neighbors, None, false, None)
+	}
+
+	/// Creates a new opinion, drawing all the randomness from the seed. The
+	/// same seed, keys and inputs give a byte-identical opinion, so the proofs
+	/// can be reproduced, and compared against golden fixtures.
+	#[allow(clippy::too_many_arguments)]
+	pub fn generate_seeded(
+		kp: &IdentityKeypair,
+		pubkey_v: &IdentityPublicKey,
+		k: Epoch,
+		op_ji: &[(Option<IdentityPublicKey>, f64)],
+		neighbors: &[(IdentityPublicKey, f64)],
+		params: &ParamsKZG<Bn256>,
+		pk: &ProvingKey<G1Affine>,
+		seed: [u8; 32],
+	) -> Result<Self, EigenError> {
+		Self::generate_with(
+			kp,
+			pubkey_v,
+			k,
+			op_ji,
+			neighbors,
+			Some((params, pk)),
+			false,
+			Some(seed),
+		)
+	}
+
+	/// Creates a new opinion, with a proof that exposes the hash of the public
+	/// inputs as the only instance. The proving key must be generated from a
+	/// circuit with the compressed public inputs.
+	pub fn generate_compressed(
+		kp: &IdentityKeypair,
+		pubkey_v: &IdentityPublicKey,
+		k: Epoch,
+		op_ji: &[(Option<IdentityPublicKey>, f64)],
+		neighbors: &[(IdentityPublicKey, f64)],
+		params: &ParamsKZG<Bn256>,
+		pk: &ProvingKey<G1Affine>,
+	) -> Result<Self, EigenError> {
+		Self::generate_with(
+			kp,
+			pubkey_v,
+			k,
+			op_ji,
+			neighbors,
+			Some((params, pk)),
+			true,
+			None,
+		)
+	}
+
+	/// Without the params and the proving key, the opinion is only signed.
+	#[allow(clippy::too_many_arguments)]
+	fn generate_with(
+		kp: &IdentityKeypair,
+		pubkey_v: &IdentityPublicKey,
+		k: Epoch,
+		op_ji: &[(Option<IdentityPublicKey>, f64)],
+		neighbors: &[(IdentityPublicKey, f64)],
+		keys: Option<(&ParamsKZG<Bn256>, &ProvingKey<G1Affine>)>,
+		compressed: bool,
+		seed: Option<[u8; 32]>,
+	) -> Result<Self, EigenError> {
+		if op_ji.len() != C::MAX_NEIGHBORS || neighbors.len() > C::MAX_NEIGHBORS {
+			return Err(EigenError::InvalidNumNeighbours);
+		}
+
+		let mut rng = ChaCha20Rng::from_seed(seed.unwrap_or_else(|| thread_rng().gen()));
 
 		let keypair = convert_keypair(kp)?;
 		let pubkey_i = keypair.public().to_owned();
+
+		// Commit to the neighbor set, and prove the receiver is in it.
+		let index = neighbors
+			.iter()
+			.position(|(pubkey_j, _)| pubkey_j == pubkey_v)
+			.ok_or(EigenError::NeighborNotFound)?;
+		let c_v = neighbors[index].1;
+		let tree = neighbor_tree::<C>(neighbors)?;
+
+		// Commit to the local scores, that add up to the scale. The opinion is
+		// weighted with the score of the receiver.
+		let scaled_scores = scale_scores(neighbors, C::SCALE)?;
+		let c_v_scaled = scaled_scores[index] as f64;
+		let mut scores_f: Vec<Bn256Scalar> = scaled_scores
+			.iter()
+			.map(|c_j| Bn256Scalar::from(*c_j))
+			.collect();
+		scores_f.resize(C::MAX_NEIGHBORS, Bn256Scalar::zero());
+		let scores_salt = commitment_salt(kp, k, SCORES_SALT_DOMAIN)?;
+		let scores = commit::<_, C::MessageHasher>(scores_salt, &scores_f);
+
+		let neighbors = NeighborCommitment {
+			root: tree.root(),
+			c_v,
+			index: index as u64,
+			path: tree.path(index).ok_or(EigenError::NeighborNotFound)?,
+		};
+
 		let pubkey_v = convert_pubkey(pubkey_v)?;
 
 		let pk_v_x = Bn256Scalar::from_bytes_wide(&to_wide(pubkey_v.x.to_bytes()));
 		let pk_v_y = Bn256Scalar::from_bytes_wide(&to_wide(pubkey_v.y.to_bytes()));
 		let epoch_f = Bn256Scalar::from_u128(u128::from(k.0));
 
-		// Turn into scaled values and round the to avoid rounding errors.
-		let op_ji_scaled = op_ji.map(|op| (op * SCALE).round());
-		let c_v_scaled = (c_v * SCALE).round();
-		let min_score_scaled = (MIN_SCORE * SCALE).round();
+		// Commit to the keys of the neighbor slots.
+		let pubkey_ji = op_ji
+			.iter()
+			.map(|(pubkey_j, _)| slot_key::<C>(pubkey_j.as_ref()))
+			.collect::<Result<Vec<_>, EigenError>>()?;
+		let peers_salt = commitment_salt(kp, k, PEERS_SALT_DOMAIN)?;
+		let peers = commit::<_, C::MessageHasher>(peers_salt, &pubkey_ji);
+
+		// Turn into scaled values and round the to avoid rounding errors. The
+		// empty slots count as zero, like in the circuit.
+		let scale = C::SCALE as f64;
+		let op_ji_scaled: Vec<f64> = op_ji
+			.iter()
+			.map(|(pubkey_j, op)| match pubkey_j {
+				Some(_) => (op * scale).round(),
+				None => 0.,
+			})
+			.collect();
+		let min_score_scaled = (MIN_SCORE * scale).round();
 
 		let t_i_scaled = op_ji_scaled
 			.iter()
 			.fold(min_score_scaled, |acc, op| acc + op);
 		let op_v_scaled = t_i_scaled * c_v_scaled;
 		// Unscale the value.
-		let op_v_unscaled = op_v_scaled / (SCALE * SCALE);
+		let op_v_unscaled = op_v_scaled / (scale * scale);
 
 		let min_score = Bn256Scalar::from_u128(min_score_scaled as u128);
-		let op_ji_f = op_ji_scaled.map(|op| Bn256Scalar::from_u128(op as u128));
-		let c_v_f = Bn256Scalar::from_u128(c_v_scaled as u128);
+		let op_ji_f = op_ji_scaled
+			.iter()
+			.map(|op| Bn256Scalar::from_u128(*op as u128))
+			.collect();
 		let op_v_f = Bn256Scalar::from_u128(op_v_scaled as u128);
 
-		let m_hash_input = [Bn256Scalar::zero(), epoch_f, pk_v_x, pk_v_y, op_v_f];
-		let pos = Posedion5x5::new(m_hash_input);
-		let out = pos.permute()[0];
+		let m_hash_input = [neighbors.root, epoch_f, pk_v_x, pk_v_y, op_v_f];
+		let out = C::MessageHasher::hash(m_hash_input);
 		let m_hash = Secp256k1Scalar::from_bytes(&out.to_bytes()).unwrap();
-		let sig_i = generate_signature(keypair, m_hash, &mut rng)
+		// The nonce depends on the message too, so a seed reused for another
+		// message doesn't reuse the nonce, which would reveal the key.
+		let mut sig_seed = Sha256::new();
+		sig_seed.update(rng.gen::<[u8; 32]>());
+		sig_seed.update(m_hash.to_bytes());
+		let mut sig_rng = ChaCha20Rng::from_seed(sig_seed.finalize().into());
+		let sig_i = generate_signature(keypair, m_hash, &mut sig_rng)
 			.map_err(|_| EigenError::SignatureError)?;
 
+		let (params, pk) = match keys {
+			Some(keys) => keys,
+			None => {
+				return Ok(Self {
+					k,
+					sig_i,
+					op: op_v_unscaled,
+					neighbors,
+					peers,
+					scores,
+					proof_bytes: Vec::new(),
+					_config: PhantomData,
+				})
+			},
+		};
+
 		let aux_generator =
 			<Secp256k1Affine as CurveAffine>::CurveExt::random(&mut rng).to_affine();
-		let circuit =
-			EigenTrustCircuit::new(pubkey_i, sig_i, op_ji_f, c_v_f, min_score, aux_generator);
+		let mut circuit = EigenTrustCircuit::<C>::new(
+			pubkey_i,
+			sig_i,
+			op_ji_f,
+			scores_f,
+			index,
+			min_score,
+			aux_generator,
+		)
+		.with_neighbors(pubkey_ji, peers_salt)
+		.with_scores_salt(scores_salt);
+		if compressed {
+			circuit = circuit.with_compressed_public_inputs();
+		}
 
 		let r = Bn256Scalar::from_bytes_wide(&to_wide(sig_i.r.to_bytes()));
 		let s = Bn256Scalar::from_bytes_wide(&to_wide(sig_i.s.to_bytes()));
@@ -97,7 +470,13 @@ impl<const N: usize> Opinion<N> {
 		let pk_ix = Bn256Scalar::from_bytes_wide(&to_wide(pubkey_i.x.to_bytes()));
 		let pk_iy = Bn256Scalar::from_bytes_wide(&to_wide(pubkey_i.y.to_bytes()));
 
-		let pub_ins = vec![op_v_f, r, s, m_hash, pk_ix, pk_iy];
+		let index_v = Bn256Scalar::from(neighbors.index);
+		let pub_ins = [op_v_f, r, s, m_hash, pk_ix, pk_iy, peers, scores, index_v];
+		let pub_ins = if compressed {
+			vec![compress_public_inputs(pub_ins)]
+		} else {
+			pub_ins.to_vec()
+		};
 
 		let proof_bytes = prove(params, circuit.clone(), &[&pub_ins], pk, &mut rng)
 			.map_err(|_| EigenError::ProvingError)?;
@@ -111,7 +490,11 @@ impl<const N: usize> Opinion<N> {
 			k,
 			sig_i,
 			op: op_v_unscaled,
+			neighbors,
+			peers,
+			scores,
 			proof_bytes,
+			_config: PhantomData,
 		})
 	}
 
@@ -126,17 +509,69 @@ impl<const N: usize> Opinion<N> {
 			k,
 			sig_i,
 			op: op_v,
+			neighbors: NeighborCommitment::default(),
+			peers: Bn256Scalar::zero(),
+			scores: Bn256Scalar::zero(),
 			proof_bytes,
+			_config: PhantomData,
 		}
 	}
 
-	/// Verifies the proof.
+	/// Verifies the proof. The signed message binds the opinion to the
+	/// receiver and to the epoch, so `pubkey_v` must be the verifier's own key,
+	/// and the opinion doesn't verify for any other receiver or epoch.
 	pub fn verify(
 		&self,
 		pubkey_p: &IdentityPublicKey,
 		pubkey_v: &IdentityPublicKey,
 		params: &ParamsKZG<Bn256>,
 		vk: &VerifyingKey<G1Affine>,
+	) -> Result<bool, EigenError> {
+		self.verify_with(pubkey_p, pubkey_v, params, vk, false)
+	}
+
+	/// Verifies an opinion made with [`Opinion::generate_mock`]: the signature
+	/// of the author and the commitment to the receiver are checked natively,
+	/// and the proof is ignored.
+	pub fn verify_mock(
+		&self,
+		pubkey_p: &IdentityPublicKey,
+		pubkey_v: &IdentityPublicKey,
+	) -> Result<bool, EigenError> {
+		if self.k == Epoch(0) {
+			return Ok(true);
+		}
+		if self.public_inputs(pubkey_p, pubkey_v)?.is_none() {
+			return Ok(false);
+		}
+		// A zero `s` has no inverse, and can't be a valid signature.
+		if self.sig_i.s == Secp256k1Scalar::zero() {
+			return Ok(false);
+		}
+		let pk_p = convert_pubkey(pubkey_p)?;
+		Ok(verify_signature(&self.sig_i, &pk_p))
+	}
+
+	/// Verifies a proof with the compressed public inputs. The public inputs
+	/// are reconstructed from the content of the opinion, and hashed into the
+	/// single instance.
+	pub fn verify_compressed(
+		&self,
+		pubkey_p: &IdentityPublicKey,
+		pubkey_v: &IdentityPublicKey,
+		params: &ParamsKZG<Bn256>,
+		vk: &VerifyingKey<G1Affine>,
+	) -> Result<bool, EigenError> {
+		self.verify_with(pubkey_p, pubkey_v, params, vk, true)
+	}
+
+	fn verify_with(
+		&self,
+		pubkey_p: &IdentityPublicKey,
+		pubkey_v: &IdentityPublicKey,
+		params: &ParamsKZG<Bn256>,
+		vk: &VerifyingKey<G1Affine>,
+		compressed: bool,
 	) -> Result<bool, EigenError> {
 		if self.k == Epoch(0) {
 			return Ok(true);
@@ -144,21 +579,126 @@ impl<const N: usize> Opinion<N> {
 
 		let mut rng = thread_rng();
 
+		let pub_ins = match self.public_inputs(pubkey_p, pubkey_v)? {
+			Some(pub_ins) if compressed => vec![compress_public_inputs(pub_ins)],
+			Some(pub_ins) => pub_ins.to_vec(),
+			None => return Ok(false),
+		};
+
+		let proof_res = verify(params, &[&pub_ins], &self.proof_bytes, vk, &mut rng)
+			.map_err(|_| EigenError::VerificationError)?;
+
+		Ok(proof_res)
+	}
+
+	/// Batch verification of the proofs of many opinions, sharing the final
+	/// pairing check between them. Each opinion comes with the public key of
+	/// its author.
+	/// Used for verifying the history of a neighbor's opinions, across many
+	/// epochs.
+	pub fn verify_batch(
+		opinions: &[(Self, IdentityPublicKey)],
+		pubkey_v: &IdentityPublicKey,
+		params: &ParamsKZG<Bn256>,
+		vk: &VerifyingKey<G1Affine>,
+	) -> Result<bool, EigenError> {
+		let mut rng = thread_rng();
+
+		let mut pub_ins = Vec::new();
+		let mut proofs = Vec::new();
+		for (opinion, pubkey_p) in opinions {
+			// Empty opinions have nothing to verify.
+			if opinion.k == Epoch(0) {
+				continue;
+			}
+
+			match opinion.public_inputs(pubkey_p, pubkey_v)? {
+				Some(pub_in) => pub_ins.push(pub_in),
+				None => return Ok(false),
+			}
+			proofs.push(opinion.proof_bytes.as_slice());
+		}
+
+		let pub_ins_refs: Vec<[&[Bn256Scalar]; 1]> =
+			pub_ins.iter().map(|pub_in| [pub_in.as_slice()]).collect();
+		let pub_ins_slices: Vec<&[&[Bn256Scalar]]> = pub_ins_refs
+			.iter()
+			.map(|pub_in| pub_in.as_slice())
+			.collect();
+
+		verify_batch(params, &pub_ins_slices, &proofs, vk, &mut rng)
+			.map_err(|_| EigenError::VerificationError)
+	}
+
+	/// Package the proof for the generated Solidity verifier: the public
+	/// inputs `[op_v, r, s, m_hash, pk_x, pk_y, peers, scores, index_v]`,
+	/// ABI-encoded as 32 byte
+	/// words, followed by the proof. Fails if the public inputs can't be
+	/// reconstructed from the content of the opinion.
+	pub fn to_onchain_bytes(
+		&self,
+		pubkey_p: &IdentityPublicKey,
+		pubkey_v: &IdentityPublicKey,
+	) -> Result<Vec<u8>, EigenError> {
+		let pub_ins = self
+			.public_inputs(pubkey_p, pubkey_v)?
+			.ok_or(EigenError::InvalidOpinion)?;
+		Ok(encode_calldata(&pub_ins, &self.proof_bytes))
+	}
+
+	/// Check the neighbor set revealed by the author in response to a
+	/// challenge. It must hash to the committed root, hold the receiver with
+	/// the committed score at the committed index, and its scores must add up
+	/// to one.
+	pub fn matches_neighbors(
+		&self,
+		neighbors: &[(IdentityPublicKey, f64)],
+		pubkey_v: &IdentityPublicKey,
+	) -> bool {
+		let receiver = usize::try_from(self.neighbors.index)
+			.ok()
+			.and_then(|index| neighbors.get(index));
+		match receiver {
+			Some((pubkey, c_v)) if pubkey == pubkey_v && *c_v == self.neighbors.c_v => {},
+			_ => return false,
+		}
+
+		let sum: f64 = neighbors.iter().map(|(_, c_j)| c_j).sum();
+		if (sum - 1.).abs() > SCORE_SUM_TOLERANCE {
+			return false;
+		}
+
+		neighbor_tree::<C>(neighbors).map_or(false, |tree| tree.root() == self.neighbors.root)
+	}
+
+	/// Reconstructs the public inputs of the proof. Returns `None` if the
+	/// signed message does not match the content of the opinion, or the
+	/// receiver is not in the committed neighbor set.
+	fn public_inputs(
+		&self,
+		pubkey_p: &IdentityPublicKey,
+		pubkey_v: &IdentityPublicKey,
+	) -> Result<Option<[Bn256Scalar; NUM_PUBLIC_INPUTS]>, EigenError> {
 		let pk_p = convert_pubkey(pubkey_p)?;
 		let pk_v = convert_pubkey(pubkey_v)?;
+		if !self.neighbors.includes::<C>(&pk_v) {
+			return Ok(None);
+		}
 
 		let epoch_f = Bn256Scalar::from_u128(u128::from(self.k.0));
 		let pk_v_x = Bn256Scalar::from_bytes_wide(&to_wide(pk_v.x.to_bytes()));
 		let pk_v_y = Bn256Scalar::from_bytes_wide(&to_wide(pk_v.y.to_bytes()));
-		let op_v_f = Bn256Scalar::from_u128((self.op * SCALE * SCALE).round() as u128);
+		let scale = C::SCALE as f64;
+		let op_v_f = Bn256Scalar::from_u128((self.op * scale * scale).round() as u128);
 
-		let m_hash_input = [Bn256Scalar::zero(), epoch_f, pk_v_x, pk_v_y, op_v_f];
-		let pos = Posedion5x5::new(m_hash_input);
-		let out = pos.permute()[0];
+		let m_hash_input = [self.neighbors.root, epoch_f, pk_v_x, pk_v_y, op_v_f];
+		let out = C::MessageHasher::hash(m_hash_input);
 		let m_hash = Secp256k1Scalar::from_bytes(&out.to_bytes()).unwrap();
 
 		// TODO: Do inside the circuit
-		let sig_res = self.sig_i.m_hash == m_hash;
+		if self.sig_i.m_hash != m_hash {
+			return Ok(None);
+		}
 
 		let r = Bn256Scalar::from_bytes_wide(&to_wide(self.sig_i.r.to_bytes()));
 		let s = Bn256Scalar::from_bytes_wide(&to_wide(self.sig_i.s.to_bytes()));
@@ -166,12 +706,268 @@ impl<const N: usize> Opinion<N> {
 		let pk_ix = Bn256Scalar::from_bytes_wide(&to_wide(pk_p.x.to_bytes()));
 		let pk_iy = Bn256Scalar::from_bytes_wide(&to_wide(pk_p.y.to_bytes()));
 
-		let pub_ins = vec![op_v_f, r, s, m_hash, pk_ix, pk_iy];
+		let index_v = Bn256Scalar::from(self.neighbors.index);
 
-		let proof_res = verify(params, &[&pub_ins], &self.proof_bytes, vk, &mut rng)
-			.map_err(|_| EigenError::VerificationError)?;
+		Ok(Some([
+			op_v_f,
+			r,
+			s,
+			m_hash,
+			pk_ix,
+			pk_iy,
+			self.peers,
+			self.scores,
+			index_v,
+		]))
+	}
+}
+
+impl<C: CircuitConfig> Opinion<C> {
+	/// The canonical encoding of the opinion, used for storing, signing,
+	/// gossiping and archiving it:
+	/// `version || k (u64 BE) || op (f64 BE) || r || s || m_hash || root ||
+	/// c_v (f64 BE) || index (u64 BE) || path_len (u64 BE) || path || peers ||
+	/// scores || proof_len (u64 BE) || proof_bytes`, with the scalars in their
+	/// little-endian representation.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = vec![OPINION_ENCODING_VERSION];
+		bytes.extend(self.k.to_be_bytes());
+		bytes.extend(self.op.to_be_bytes());
+		bytes.extend(self.sig_i.r.to_bytes());
+		bytes.extend(self.sig_i.s.to_bytes());
+		bytes.extend(self.sig_i.m_hash.to_bytes());
+		bytes.extend(self.neighbors.root.to_bytes());
+		bytes.extend(self.neighbors.c_v.to_be_bytes());
+		bytes.extend(self.neighbors.index.to_be_bytes());
+		bytes.extend((self.neighbors.path.len() as u64).to_be_bytes());
+		for sibling in &self.neighbors.path {
+			bytes.extend(sibling.to_bytes());
+		}
+		bytes.extend(self.peers.to_bytes());
+		bytes.extend(self.scores.to_bytes());
+		bytes.extend((self.proof_bytes.len() as u64).to_be_bytes());
+		bytes.extend(&self.proof_bytes);
+		bytes
+	}
+
+	/// Decode the opinion from its canonical encoding, or from one of the
+	/// older layouts. Fails on unknown versions, truncated or trailing bytes,
+	/// scalars out of the field, or scores that are not finite numbers.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, EigenError> {
+		match bytes.first() {
+			// Before the version byte, the encoding started with the epoch, and
+			// the top byte of any epoch reachable in practice is zero.
+			Some(0) => Self::decode_body(bytes, 0),
+			Some(&version) if version <= OPINION_ENCODING_VERSION => {
+				Self::decode_body(&bytes[1..], version)
+			},
+			_ => Err(EigenError::InvalidOpinion),
+		}
+	}
+
+	/// The layout of the opinions in the protocol V1:
+	/// `k (u64 BE) || op (f64 BE) || r || s || m_hash || proof_bytes`, without
+	/// a version, the commitments, or the length of the proof.
+	pub fn to_v1_bytes(&self) -> Vec<u8> {
+		let mut bytes = self.k.to_be_bytes().to_vec();
+		bytes.extend(self.op.to_be_bytes());
+		bytes.extend(self.sig_i.r.to_bytes());
+		bytes.extend(self.sig_i.s.to_bytes());
+		bytes.extend(self.sig_i.m_hash.to_bytes());
+		bytes.extend(&self.proof_bytes);
+		bytes
+	}
+
+	/// Decode an opinion sent with the protocol V1. The proof is the rest of
+	/// the bytes, and the commitments are left empty, so the opinion only
+	/// verifies if it is empty.
+	pub fn from_v1_bytes(bytes: &[u8]) -> Result<Self, EigenError> {
+		let mut reader = bytes;
+		let k = u64::from_be_bytes(read_array(&mut reader)?);
+		let op = read_f64(&mut reader)?;
+		let r = read_scalar(&mut reader)?;
+		let s = read_scalar(&mut reader)?;
+		let m_hash = read_scalar(&mut reader)?;
+		Ok(Self {
+			k: Epoch(k),
+			sig_i: SigData { r, s, m_hash },
+			op,
+			neighbors: NeighborCommitment::default(),
+			peers: Bn256Scalar::zero(),
+			scores: Bn256Scalar::zero(),
+			proof_bytes: reader.to_vec(),
+			_config: PhantomData,
+		})
+	}
+
+	/// Decode the fields of the opinion, that follow the version byte, in the
+	/// layout of the version. The unversioned layout, the version 0, has no
+	/// commitment to the neighbor set, and the version 1 has no commitments to
+	/// the neighbor slots and to the local scores.
+	fn decode_body(bytes: &[u8], version: u8) -> Result<Self, EigenError> {
+		let mut reader = bytes;
+		let k = u64::from_be_bytes(read_array(&mut reader)?);
+		let op = read_f64(&mut reader)?;
+		let r = read_scalar(&mut reader)?;
+		let s = read_scalar(&mut reader)?;
+		let m_hash = read_scalar(&mut reader)?;
+
+		let neighbors = if version >= 1 {
+			let root = read_scalar(&mut reader)?;
+			let c_v = read_f64(&mut reader)?;
+			let index = u64::from_be_bytes(read_array(&mut reader)?);
+			let path_len = u64::from_be_bytes(read_array(&mut reader)?);
+			let mut path = Vec::new();
+			for _ in 0..path_len {
+				path.push(read_scalar(&mut reader)?);
+			}
+			NeighborCommitment {
+				root,
+				c_v,
+				index,
+				path,
+			}
+		} else {
+			NeighborCommitment::default()
+		};
+		let (peers, scores) = if version >= 2 {
+			(read_scalar(&mut reader)?, read_scalar(&mut reader)?)
+		} else {
+			(Bn256Scalar::zero(), Bn256Scalar::zero())
+		};
+
+		let proof_len = u64::from_be_bytes(read_array(&mut reader)?);
+		let proof_len = usize::try_from(proof_len).map_err(|_| EigenError::InvalidOpinion)?;
+		if reader.len() != proof_len {
+			return Err(EigenError::InvalidOpinion);
+		}
+
+		Ok(Self {
+			k: Epoch(k),
+			sig_i: SigData { r, s, m_hash },
+			op,
+			neighbors,
+			peers,
+			scores,
+			proof_bytes: reader.to_vec(),
+			_config: PhantomData,
+		})
+	}
+
+	/// The opinion without its proof, kept once the proof is past the
+	/// retention. It can't be verified anymore.
+	pub fn without_proof(&self) -> Self {
+		Self {
+			k: self.k,
+			sig_i: self.sig_i,
+			op: self.op,
+			neighbors: self.neighbors.clone(),
+			peers: self.peers,
+			scores: self.scores,
+			proof_bytes: Vec::new(),
+			_config: PhantomData,
+		}
+	}
+
+	/// The domain separated hash of the canonical encoding.
+	pub fn hash(&self) -> [u8; 32] {
+		let mut hasher = Sha256::new();
+		hasher.update(OPINION_HASH_DOMAIN);
+		hasher.update(self.to_bytes());
+		hasher.finalize().into()
+	}
+}
+
+impl<C: CircuitConfig> Serialize for Opinion<C> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_bytes(&self.to_bytes())
+	}
+}
+
+impl<'de, C: CircuitConfig> Deserialize<'de> for Opinion<C> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let bytes = Vec::<u8>::deserialize(deserializer)?;
+		Self::from_bytes(&bytes).map_err(|e| D::Error::custom(format!("{:?}", e)))
+	}
+}
 
-		Ok(sig_res && proof_res)
+/// Read the next `N` bytes.
+fn read_array<const N: usize>(reader: &mut &[u8]) -> Result<[u8; N], EigenError> {
+	if reader.len() < N {
+		return Err(EigenError::InvalidOpinion);
+	}
+	let (head, tail) = reader.split_at(N);
+	*reader = tail;
+	head.try_into().map_err(|_| EigenError::InvalidOpinion)
+}
+
+/// Read the next scalar, rejecting the non-canonical representations.
+fn read_scalar<F: PrimeField<Repr = [u8; 32]>>(reader: &mut &[u8]) -> Result<F, EigenError> {
+	let bytes = read_array(reader)?;
+	let scalar: Option<F> = F::from_repr(bytes).into();
+	scalar.ok_or(EigenError::InvalidOpinion)
+}
+
+/// Read the next float, rejecting the ones that are not finite.
+fn read_f64(reader: &mut &[u8]) -> Result<f64, EigenError> {
+	let value = f64::from_be_bytes(read_array(reader)?);
+	if !value.is_finite() {
+		return Err(EigenError::InvalidOpinion);
+	}
+	Ok(value)
+}
+
+/// Revocation of an opinion issued in a recent epoch, signed by its author.
+/// The receiver of the opinion stops counting it, as long as the epoch is
+/// still within its reconciliation window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revocation {
+	/// The epoch of the revoked opinion.
+	pub epoch: Epoch,
+	/// The hash of the revoked opinion.
+	pub opinion_hash: [u8; 32],
+	/// Public key of the author of the opinion.
+	pub pubkey: IdentityPublicKey,
+	/// Signature over the epoch and the hash of the opinion.
+	pub signature: Vec<u8>,
+}
+
+impl Revocation {
+	/// Revoke the opinion, signing with the keypair of its author.
+	pub fn new<C: CircuitConfig>(
+		keypair: &IdentityKeypair,
+		opinion: &Opinion<C>,
+	) -> Result<Self, EigenError> {
+		let opinion_hash = opinion.hash();
+		let message = Self::message(opinion.k, &opinion_hash);
+		let signature = keypair
+			.sign(&message)
+			.map_err(|_| EigenError::SignatureError)?;
+		Ok(Self {
+			epoch: opinion.k,
+			opinion_hash,
+			pubkey: keypair.public(),
+			signature,
+		})
+	}
+
+	/// The message that is signed by the author.
+	fn message(epoch: Epoch, opinion_hash: &[u8; 32]) -> Vec<u8> {
+		let mut message = b"eigen_trust/revocation".to_vec();
+		message.extend(epoch.to_be_bytes());
+		message.extend(opinion_hash);
+		message
+	}
+
+	/// Verifies the signature of the revocation.
+	pub fn verify(&self) -> bool {
+		let message = Self::message(self.epoch, &self.opinion_hash);
+		self.pubkey.verify(&message, &self.signature)
+	}
+
+	/// Returns true if the revocation is for the opinion.
+	pub fn revokes<C: CircuitConfig>(&self, opinion: &Opinion<C>) -> bool {
+		opinion.k == self.epoch && opinion.hash() == self.opinion_hash
 	}
 }
 
@@ -193,6 +989,21 @@ pub fn convert_keypair(kp: &IdentityKeypair) -> Result<Keypair<Secp256k1Affine>,
 	}
 }
 
+/// Derive the EdDSA secret key from the libp2p keypair. The opinions are
+/// only signed with ECDSA, the key is for signing other messages that are
+/// verified with the EdDSA or the Schnorr gadget of the circuit.
+pub fn convert_eddsa_keypair(kp: &IdentityKeypair) -> Result<EddsaSecretKey, EigenError> {
+	match kp {
+		IdentityKeypair::Secp256k1(secp_kp) => {
+			let sk_bytes = secp_kp.secret().to_bytes();
+			// Domain separate the derived key from the ECDSA one.
+			let input = [b"eigen_trust/eddsa".as_slice(), &sk_bytes].concat();
+			Ok(EddsaSecretKey::from_bytes(&input))
+		},
+		_ => Err(EigenError::InvalidKeypair),
+	}
+}
+
 /// Convert the libp2p public key into halo2 public key.
 pub fn convert_pubkey(pk: &IdentityPublicKey) -> Result<Secp256k1Affine, EigenError> {
 	match pk {
@@ -231,25 +1042,33 @@ pub fn to_wide(p: [u8; 32]) -> [u8; 64] {
 mod test {
 	use super::*;
 	use eigen_trust_circuit::{
-		halo2wrong::halo2::poly::commitment::ParamsProver,
+		test_utils::{params, TestConfig},
 		utils::{keygen, random_circuit},
 	};
 
 	const N: usize = 3;
+	type Config = TestConfig<N>;
+
+	/// The opinions of the neighbor slots, all taken by random neighbors.
+	fn slots(op_ji: [f64; N]) -> Vec<(Option<IdentityPublicKey>, f64)> {
+		op_ji
+			.iter()
+			.map(|op| (Some(IdentityKeypair::generate_secp256k1().public()), *op))
+			.collect()
+	}
 
 	#[test]
 	fn should_verify_empty_opinion() {
 		let rng = &mut thread_rng();
-		let op = Opinion::<N>::empty();
+		let op = Opinion::<Config>::empty();
 		let local_keypair = IdentityKeypair::generate_secp256k1();
 		let local_pubkey = local_keypair.public();
 
 		let keypair_v = IdentityKeypair::generate_secp256k1();
 		let pubkey_v = keypair_v.public();
-		let params = ParamsKZG::<Bn256>::new(18);
-		let min_score = Bn256Scalar::from_u128((MIN_SCORE * SCALE).round() as u128);
-		let random_circuit =
-			random_circuit::<Bn256, Secp256k1Affine, _, N>(min_score, &mut rng.clone());
+		let params = params(18);
+		let min_score = Bn256Scalar::from_u128((MIN_SCORE * Config::SCALE as f64).round() as u128);
+		let random_circuit = random_circuit::<Config, _>(min_score, &mut rng.clone());
 		let pk = keygen(&params, &random_circuit).unwrap();
 		let res = op
 			.verify(&local_pubkey, &pubkey_v, &params, &pk.get_vk())
@@ -267,20 +1086,475 @@ mod test {
 		let pubkey_v = keypair_v.public();
 
 		let epoch = Epoch(1);
-		let op_ji = [0.1; N];
+		let mut op_ji = slots([0.1; N]);
+		// The opinion of an empty slot is left out.
+		op_ji[N - 1] = (None, 0.5);
 		let c_v = 0.1;
-		let min_score = Bn256Scalar::from_u128((MIN_SCORE * SCALE).round() as u128);
+		// The receiver is the second of the neighbors.
+		let other_neighbor = IdentityKeypair::generate_secp256k1().public();
+		let min_score = Bn256Scalar::from_u128((MIN_SCORE * Config::SCALE as f64).round() as u128);
 
-		let params = ParamsKZG::<Bn256>::new(18);
-		let random_circuit =
-			random_circuit::<Bn256, Secp256k1Affine, _, N>(min_score, &mut rng.clone());
+		let params = params(18);
+		let random_circuit = random_circuit::<Config, _>(min_score, &mut rng.clone());
 		let pk = keygen(&params, &random_circuit).unwrap();
-		let proof =
-			Opinion::<N>::generate(&local_keypair, &pubkey_v, epoch, op_ji, c_v, &params, &pk)
-				.unwrap();
+		let proof = Opinion::<Config>::generate(
+			&local_keypair,
+			&pubkey_v,
+			epoch,
+			&op_ji,
+			&[(other_neighbor, 1. - c_v), (pubkey_v.clone(), c_v)],
+			&params,
+			&pk,
+		)
+		.unwrap();
 
 		assert!(proof
 			.verify(&local_pubkey, &pubkey_v, &params, pk.get_vk())
 			.unwrap());
+		assert!((proof.op - (MIN_SCORE + 0.2) * c_v).abs() < 1e-9);
+
+		// The proof is bound to the committed neighbor slots, and to the
+		// committed local scores, at the slot of the receiver.
+		let mut tampered = proof.clone();
+		tampered.peers += Bn256Scalar::one();
+		assert!(!tampered
+			.verify(&local_pubkey, &pubkey_v, &params, pk.get_vk())
+			.unwrap());
+		let mut tampered = proof.clone();
+		tampered.scores += Bn256Scalar::one();
+		assert!(!tampered
+			.verify(&local_pubkey, &pubkey_v, &params, pk.get_vk())
+			.unwrap());
+
+		let onchain = proof.to_onchain_bytes(&local_pubkey, &pubkey_v).unwrap();
+		assert_eq!(
+			onchain.len(),
+			NUM_PUBLIC_INPUTS * 32 + proof.proof_bytes.len()
+		);
+		assert_eq!(&onchain[NUM_PUBLIC_INPUTS * 32..], &proof.proof_bytes[..]);
+		// Only the receiver can reconstruct the public inputs.
+		let other_pubkey = IdentityKeypair::generate_secp256k1().public();
+		assert!(proof
+			.to_onchain_bytes(&local_pubkey, &other_pubkey)
+			.is_err());
+	}
+
+	#[test]
+	fn should_scale_scores_to_exactly_the_scale() {
+		let neighbors: Vec<(IdentityPublicKey, f64)> = (0..3)
+			.map(|_| (IdentityKeypair::generate_secp256k1().public(), 1. / 3.))
+			.collect();
+		let scaled = scale_scores(&neighbors, 100_000_000).unwrap();
+		assert_eq!(scaled.iter().sum::<u64>(), 100_000_000);
+		assert!(scaled
+			.iter()
+			.all(|c_j| (33_333_333..=33_333_334).contains(c_j)));
+
+		// The scores must add up to one.
+		let half = [(neighbors[0].0.clone(), 0.5)];
+		assert!(matches!(
+			scale_scores(&half, 100_000_000),
+			Err(EigenError::InvalidScores)
+		));
+	}
+
+	#[test]
+	fn should_verify_mock_opinion() {
+		let local_keypair = IdentityKeypair::generate_secp256k1();
+		let local_pubkey = local_keypair.public();
+		let pubkey_v = IdentityKeypair::generate_secp256k1().public();
+
+		let opinion = Opinion::<Config>::generate_mock(
+			&local_keypair,
+			&pubkey_v,
+			Epoch(1),
+			&slots([0.1; N]),
+			&[(pubkey_v.clone(), 1.)],
+		)
+		.unwrap();
+		assert!(opinion.proof_bytes.is_empty());
+		assert!(opinion.verify_mock(&local_pubkey, &pubkey_v).unwrap());
+
+		// Signed by someone else, or for someone else.
+		let other_pubkey = IdentityKeypair::generate_secp256k1().public();
+		assert!(!opinion.verify_mock(&other_pubkey, &pubkey_v).unwrap());
+		assert!(!opinion.verify_mock(&local_pubkey, &other_pubkey).unwrap());
+
+		// A tampered value breaks the signature.
+		let mut tampered = opinion;
+		tampered.op *= 2.;
+		assert!(!tampered.verify_mock(&local_pubkey, &pubkey_v).unwrap());
+	}
+
+	/// A config with another scale than the network.
+	#[derive(Clone, Debug, PartialEq, Eq)]
+	struct SmallScaleConfig;
+
+	impl CircuitConfig for SmallScaleConfig {
+		type MessageHasher = <Config as CircuitConfig>::MessageHasher;
+		type Scalar = Bn256Scalar;
+		type SignatureCurve = Secp256k1Affine;
+
+		const MAX_NEIGHBORS: usize = N;
+		const SCALE: u64 = 1_000_000;
+	}
+
+	#[test]
+	fn should_unscale_opinion_with_config_scale() {
+		let local_keypair = IdentityKeypair::generate_secp256k1();
+		let local_pubkey = local_keypair.public();
+		let pubkey_v = IdentityKeypair::generate_secp256k1().public();
+
+		let opinion = Opinion::<SmallScaleConfig>::generate_mock(
+			&local_keypair,
+			&pubkey_v,
+			Epoch(1),
+			&slots([0.1; N]),
+			&[(pubkey_v.clone(), 1.)],
+		)
+		.unwrap();
+		assert!((opinion.op - (MIN_SCORE + 0.1 * N as f64)).abs() < 1e-9);
+		assert!(opinion.verify_mock(&local_pubkey, &pubkey_v).unwrap());
+	}
+
+	#[test]
+	fn test_seeded_proof_generate() {
+		let rng = &mut thread_rng();
+		let local_keypair = IdentityKeypair::generate_secp256k1();
+		let local_pubkey = local_keypair.public();
+		let pubkey_v = IdentityKeypair::generate_secp256k1().public();
+
+		let op_ji = slots([0.1; N]);
+		let neighbors = [(pubkey_v.clone(), 1.)];
+		let min_score = Bn256Scalar::from_u128((MIN_SCORE * Config::SCALE as f64).round() as u128);
+
+		let params = params(18);
+		let random_circuit = random_circuit::<Config, _>(min_score, &mut rng.clone());
+		let pk = keygen(&params, &random_circuit).unwrap();
+		let generate = |seed| {
+			Opinion::<Config>::generate_seeded(
+				&local_keypair,
+				&pubkey_v,
+				Epoch(1),
+				&op_ji,
+				&neighbors,
+				&params,
+				&pk,
+				seed,
+			)
+			.unwrap()
+		};
+
+		let opinion = generate([1; 32]);
+		assert!(opinion
+			.verify(&local_pubkey, &pubkey_v, &params, pk.get_vk())
+			.unwrap());
+		assert_eq!(generate([1; 32]).to_bytes(), opinion.to_bytes());
+
+		let other = generate([2; 32]);
+		assert_ne!(other.sig_i, opinion.sig_i);
+		assert_ne!(other.proof_bytes, opinion.proof_bytes);
+	}
+
+	#[test]
+	fn test_compressed_proof_generate() {
+		let rng = &mut thread_rng();
+		let local_keypair = IdentityKeypair::generate_secp256k1();
+		let local_pubkey = local_keypair.public();
+
+		let keypair_v = IdentityKeypair::generate_secp256k1();
+		let pubkey_v = keypair_v.public();
+
+		let epoch = Epoch(1);
+		let op_ji = slots([0.1; N]);
+		let c_v = 1.;
+		let min_score = Bn256Scalar::from_u128((MIN_SCORE * Config::SCALE as f64).round() as u128);
+
+		let params = params(18);
+		let random_circuit = random_circuit::<Config, _>(min_score, &mut rng.clone())
+			.with_compressed_public_inputs();
+		let pk = keygen(&params, &random_circuit).unwrap();
+		let proof = Opinion::<Config>::generate_compressed(
+			&local_keypair,
+			&pubkey_v,
+			epoch,
+			&op_ji,
+			&[(pubkey_v.clone(), c_v)],
+			&params,
+			&pk,
+		)
+		.unwrap();
+
+		assert!(proof
+			.verify_compressed(&local_pubkey, &pubkey_v, &params, pk.get_vk())
+			.unwrap());
+
+		// The opinion is bound to the verifier through the hash.
+		let other_pubkey = IdentityKeypair::generate_secp256k1().public();
+		assert!(!proof
+			.verify_compressed(&local_pubkey, &other_pubkey, &params, pk.get_vk())
+			.unwrap());
+	}
+
+	#[test]
+	fn should_encode_decode_opinion() {
+		let sig_i = SigData {
+			r: Secp256k1Scalar::from_u128(1),
+			s: Secp256k1Scalar::from_u128(2),
+			m_hash: Secp256k1Scalar::from_u128(3),
+		};
+		let neighbors = NeighborCommitment {
+			root: Bn256Scalar::from_u128(4),
+			c_v: 0.5,
+			index: 1,
+			path: vec![Bn256Scalar::from_u128(5), Bn256Scalar::from_u128(6)],
+		};
+		let mut opinion =
+			Opinion::<Config>::new(Epoch(7), sig_i, 0.25, vec![4, 5, 6]).with_neighbors(neighbors);
+		opinion.peers = Bn256Scalar::from_u128(7);
+		opinion.scores = Bn256Scalar::from_u128(8);
+
+		let bytes = opinion.to_bytes();
+		assert_eq!(
+			bytes.len(),
+			1 + 8 + 8 + 3 * 32 + 32 + 8 + 8 + 8 + 2 * 32 + 2 * 32 + 8 + 3
+		);
+		assert_eq!(bytes[0], OPINION_ENCODING_VERSION);
+		assert_eq!(Opinion::<Config>::from_bytes(&bytes).unwrap(), opinion);
+
+		let json = serde_json::to_string(&opinion).unwrap();
+		let decoded: Opinion<Config> = serde_json::from_str(&json).unwrap();
+		assert_eq!(decoded, opinion);
+		assert_eq!(decoded.hash(), opinion.hash());
+	}
+
+	#[test]
+	fn should_reject_non_canonical_opinion_bytes() {
+		let opinion = Opinion::<Config>::new(Epoch(7), SigData::empty(), 0.25, vec![4, 5, 6]);
+		let bytes = opinion.to_bytes();
+
+		// Truncated, or with trailing bytes.
+		assert!(Opinion::<Config>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		assert!(Opinion::<Config>::from_bytes(&trailing).is_err());
+
+		// Scalar out of the field.
+		let mut big_scalar = bytes.clone();
+		big_scalar[17..49].copy_from_slice(&[0xff; 32]);
+		assert!(Opinion::<Config>::from_bytes(&big_scalar).is_err());
+
+		// Not a number.
+		let mut nan = bytes.clone();
+		nan[9..17].copy_from_slice(&f64::NAN.to_be_bytes());
+		assert!(Opinion::<Config>::from_bytes(&nan).is_err());
+
+		// Unknown version.
+		let mut unknown = bytes;
+		unknown[0] = OPINION_ENCODING_VERSION + 1;
+		assert!(Opinion::<Config>::from_bytes(&unknown).is_err());
+	}
+
+	#[test]
+	fn should_upgrade_unversioned_opinion_bytes() {
+		let opinion = Opinion::<Config>::new(Epoch(7), SigData::empty(), 0.25, vec![4, 5, 6]);
+		let bytes = opinion.to_bytes();
+
+		// The encoding before the version byte was introduced, without the
+		// commitment to the neighbor set.
+		let mut unversioned = Vec::new();
+		unversioned.extend(7u64.to_be_bytes());
+		unversioned.extend(0.25f64.to_be_bytes());
+		unversioned.extend([0; 3 * 32]);
+		unversioned.extend(3u64.to_be_bytes());
+		unversioned.extend([4, 5, 6]);
+		let upgraded = Opinion::<Config>::from_bytes(&unversioned).unwrap();
+		assert_eq!(upgraded, opinion);
+		assert_eq!(upgraded.to_bytes(), bytes);
+
+		// The version 1, without the commitments to the neighbor slots and to
+		// the local scores, that follow the empty path.
+		let commitments_at = 1 + 8 + 8 + 3 * 32 + 32 + 8 + 8 + 8;
+		let v1 = [
+			&[1][..],
+			&bytes[1..commitments_at],
+			&bytes[commitments_at + 2 * 32..],
+		]
+		.concat();
+		let upgraded = Opinion::<Config>::from_bytes(&v1).unwrap();
+		assert_eq!(upgraded, opinion);
+		assert_eq!(upgraded.to_bytes(), bytes);
+	}
+
+	#[test]
+	fn should_hash_opinion() {
+		let opinion = Opinion::<Config>::new(Epoch(7), SigData::empty(), 0.25, vec![4, 5, 6]);
+		let other = Opinion::<Config>::new(Epoch(8), SigData::empty(), 0.25, vec![4, 5, 6]);
+		assert_ne!(opinion.hash(), other.hash());
+
+		// The hash is domain separated from the plain hash of the encoding.
+		let plain: [u8; 32] = Sha256::digest(opinion.to_bytes()).into();
+		assert_ne!(opinion.hash(), plain);
+	}
+
+	#[test]
+	fn should_include_receiver_in_neighbor_commitment() {
+		let neighbors: Vec<(Secp256k1Affine, f64)> = [0.5, 0.3, 0.2]
+			.iter()
+			.map(|c_j| {
+				let pubkey = IdentityKeypair::generate_secp256k1().public();
+				(convert_pubkey(&pubkey).unwrap(), *c_j)
+			})
+			.collect();
+		let leaves = neighbors
+			.iter()
+			.map(|(pk_j, c_j)| neighbor_leaf::<Config>(pk_j, *c_j))
+			.collect();
+		let tree =
+			MerkleTree::<_, 5, <Config as CircuitConfig>::MessageHasher>::new(leaves, 2).unwrap();
+		let commitment = NeighborCommitment {
+			root: tree.root(),
+			c_v: 0.3,
+			index: 1,
+			path: tree.path(1).unwrap(),
+		};
+		assert!(commitment.includes::<Config>(&neighbors[1].0));
+
+		// Other peers, or other scores, are not included.
+		assert!(!commitment.includes::<Config>(&neighbors[0].0));
+		let other_score = NeighborCommitment {
+			c_v: 0.5,
+			..commitment.clone()
+		};
+		assert!(!other_score.includes::<Config>(&neighbors[1].0));
+		let other_index = NeighborCommitment {
+			index: 5,
+			..commitment
+		};
+		assert!(!other_index.includes::<Config>(&neighbors[1].0));
+	}
+
+	#[test]
+	fn should_match_revealed_neighbors() {
+		let neighbors: Vec<(IdentityPublicKey, f64)> = [0.5, 0.3, 0.2]
+			.iter()
+			.map(|c_j| (IdentityKeypair::generate_secp256k1().public(), *c_j))
+			.collect();
+		let tree = neighbor_tree::<Config>(&neighbors).unwrap();
+		let commitment = NeighborCommitment {
+			root: tree.root(),
+			c_v: 0.3,
+			index: 1,
+			path: tree.path(1).unwrap(),
+		};
+		let opinion = Opinion::<Config>::new(Epoch(3), SigData::empty(), 0.25, Vec::new())
+			.with_neighbors(commitment);
+		let pubkey_v = &neighbors[1].0;
+		assert!(opinion.matches_neighbors(&neighbors, pubkey_v));
+
+		// Revealed to someone else.
+		assert!(!opinion.matches_neighbors(&neighbors, &neighbors[0].0));
+
+		// A different set than the committed one.
+		let mut other = neighbors.clone();
+		other.swap(0, 2);
+		assert!(!opinion.matches_neighbors(&other, pubkey_v));
+		let mut other = neighbors.clone();
+		other.pop();
+		assert!(!opinion.matches_neighbors(&other, pubkey_v));
+
+		// A set that gives out more than the whole score, even if committed.
+		let mut inflated = neighbors.clone();
+		inflated[0].1 = 0.9;
+		let tree = neighbor_tree::<Config>(&inflated).unwrap();
+		let commitment = NeighborCommitment {
+			root: tree.root(),
+			c_v: 0.3,
+			index: 1,
+			path: tree.path(1).unwrap(),
+		};
+		let opinion = opinion.with_neighbors(commitment);
+		assert!(!opinion.matches_neighbors(&inflated, pubkey_v));
+	}
+
+	#[test]
+	fn should_sign_and_verify_revocation() {
+		let keypair = IdentityKeypair::generate_secp256k1();
+		let opinion = Opinion::<Config>::new(Epoch(7), SigData::empty(), 0.25, vec![4, 5, 6]);
+		let other = Opinion::<Config>::new(Epoch(8), SigData::empty(), 0.25, vec![4, 5, 6]);
+
+		let revocation = Revocation::new(&keypair, &opinion).unwrap();
+		assert!(revocation.verify());
+		assert!(revocation.revokes(&opinion));
+		assert!(!revocation.revokes(&other));
+
+		// Moving the revocation to another epoch breaks the signature.
+		let moved = Revocation {
+			epoch: Epoch(8),
+			..revocation.clone()
+		};
+		assert!(!moved.verify());
+
+		// So does claiming another author.
+		let forged = Revocation {
+			pubkey: IdentityKeypair::generate_secp256k1().public(),
+			..revocation
+		};
+		assert!(!forged.verify());
+	}
+
+	#[test]
+	fn should_derive_eddsa_key() {
+		let keypair = IdentityKeypair::generate_secp256k1();
+		let sk = convert_eddsa_keypair(&keypair).unwrap();
+		assert_eq!(sk, convert_eddsa_keypair(&keypair).unwrap());
+
+		let other_keypair = IdentityKeypair::generate_secp256k1();
+		assert_ne!(sk, convert_eddsa_keypair(&other_keypair).unwrap());
+
+		let ed_keypair = IdentityKeypair::generate_ed25519();
+		assert!(convert_eddsa_keypair(&ed_keypair).is_err());
+	}
+
+	#[test]
+	fn should_verify_batch_of_opinions() {
+		let rng = &mut thread_rng();
+		let local_keypair = IdentityKeypair::generate_secp256k1();
+		let local_pubkey = local_keypair.public();
+
+		let keypair_v = IdentityKeypair::generate_secp256k1();
+		let pubkey_v = keypair_v.public();
+
+		let op_ji = slots([0.1; N]);
+		let c_v = 1.;
+		let min_score = Bn256Scalar::from_u128((MIN_SCORE * Config::SCALE as f64).round() as u128);
+
+		let params = params(18);
+		let random_circuit = random_circuit::<Config, _>(min_score, &mut rng.clone());
+		let pk = keygen(&params, &random_circuit).unwrap();
+
+		let mut opinions = Vec::new();
+		for epoch in Epoch::range(Epoch(1)..Epoch(3)) {
+			let opinion = Opinion::<Config>::generate(
+				&local_keypair,
+				&pubkey_v,
+				epoch,
+				&op_ji,
+				&[(pubkey_v.clone(), c_v)],
+				&params,
+				&pk,
+			)
+			.unwrap();
+			opinions.push((opinion, local_pubkey.clone()));
+		}
+		opinions.push((Opinion::empty(), local_pubkey.clone()));
+
+		let res = Opinion::verify_batch(&opinions, &pubkey_v, &params, pk.get_vk()).unwrap();
+		assert!(res);
+
+		// Tampering with a single opinion should fail the whole batch.
+		opinions[1].0.op = 0.2;
+		let res = Opinion::verify_batch(&opinions, &pubkey_v, &params, pk.get_vk()).unwrap();
+		assert!(!res);
 	}
 }
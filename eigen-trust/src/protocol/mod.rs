@@ -10,7 +10,7 @@ use libp2p::{
 	NetworkBehaviour, PeerId,
 };
 use req_res::{EigenTrustCodec, EigenTrustProtocol, Request, Response};
-use std::{iter::once, time::Duration};
+use std::time::Duration;
 
 const PROTOCOL_VERSION: &str = "eigen_trust/1.0.0";
 
@@ -49,7 +49,11 @@ impl EigenTrustBehaviour {
 		local_public_key: PublicKey,
 	) -> Self {
 		// Setting up the request/response protocol.
-		let protocols = once((EigenTrustProtocol::new(), ProtocolSupport::Full));
+		// The latest version is preferred, V1 is kept for the older nodes.
+		let protocols = [
+			(EigenTrustProtocol::new(), ProtocolSupport::Full),
+			(EigenTrustProtocol::v1(), ProtocolSupport::Full),
+		];
 		let mut cfg = RequestResponseConfig::default();
 		// Keep the connection alive in request/response protocol
 		cfg.set_connection_keep_alive(connection_duration);
@@ -1,14 +1,19 @@
 //! The module for defining the request-response protocol.
 
 use crate::{
-	epoch::Epoch,
-	peer::{opinion::Opinion, MAX_NEIGHBORS},
+	epoch::{Epoch, EpochConfig},
+	peer::{
+		opinion::{Opinion, Revocation},
+		NetworkConfig,
+	},
 };
 use async_trait::async_trait;
-use eigen_trust_circuit::{ecdsa::SigData, halo2wrong::curves::secp256k1::Fq as Secp256k1Scalar};
 use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use libp2p::request_response::{ProtocolName, RequestResponseCodec};
-use std::io::Result;
+use libp2p::{
+	core::PublicKey,
+	request_response::{ProtocolName, RequestResponseCodec},
+};
+use std::io::{Error, ErrorKind, Result};
 
 /// EigenTrust protocol struct.
 #[derive(Debug, Clone, Default)]
@@ -17,8 +22,18 @@ pub struct EigenTrustProtocol {
 }
 
 impl EigenTrustProtocol {
-	/// Create a new EigenTrust protocol.
+	/// Create a new EigenTrust protocol, of the latest version.
 	pub fn new() -> Self {
+		Self {
+			version: EigenTrustProtocolVersion::V2,
+		}
+	}
+
+	/// The first version of the protocol, that only has the requests for
+	/// opinions, and sends them in the layout of
+	/// [`Opinion::to_v1_bytes`]. Still spoken with the nodes that don't
+	/// support the latest one.
+	pub fn v1() -> Self {
 		Self {
 			version: EigenTrustProtocolVersion::V1,
 		}
@@ -28,34 +43,52 @@ impl EigenTrustProtocol {
 /// The version of the EigenTrust protocol.
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum EigenTrustProtocolVersion {
+	/// The request is the bare epoch of the opinion.
 	V1,
+	/// The requests and responses start with the tag of their kind.
+	V2,
 }
 
 impl Default for EigenTrustProtocolVersion {
 	fn default() -> Self {
-		Self::V1
+		Self::V2
 	}
 }
 
+/// The tags of the responses. In V1, every tag other than the success and
+/// the invalid request is the code of an internal error.
+const SUCCESS_RESPONSE: u8 = 0;
+const INVALID_REQUEST_RESPONSE: u8 = 1;
+const INTERNAL_ERROR_RESPONSE: u8 = 2;
+const REVEAL_RESPONSE: u8 = 3;
+const ACK_RESPONSE: u8 = 4;
+
+fn unsupported_in_v1() -> Error {
+	Error::new(ErrorKind::InvalidInput, "Not supported by the protocol V1")
+}
+
 /// The EigenTrust protocol codec.
 #[derive(Clone, Debug, Default)]
 pub struct EigenTrustCodec;
 
-/// The EigenTrust protocol request struct.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Request {
-	epoch: Epoch,
+/// The EigenTrust protocol request enum.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+	/// Request for the opinion in the given epoch.
+	Opinion(Epoch),
+	/// Announcement of a new epoch interval.
+	EpochConfig(EpochConfig),
+	/// Revocation of an opinion sent earlier.
+	Revocation(Revocation),
+	/// Challenge to reveal the neighbor set behind the opinion in the given
+	/// epoch.
+	Challenge(Epoch),
 }
 
 impl Request {
-	/// Create a new request.
+	/// Create a new request for the opinion in the given epoch.
 	pub fn new(epoch: Epoch) -> Self {
-		Self { epoch }
-	}
-
-	/// Get the epoch of the request.
-	pub fn get_epoch(&self) -> Epoch {
-		self.epoch
+		Self::Opinion(epoch)
 	}
 }
 
@@ -63,9 +96,13 @@ impl Request {
 #[derive(Clone, Debug, PartialEq)]
 pub enum Response {
 	/// Successful response with an opinion.
-	Success(Opinion<MAX_NEIGHBORS>),
+	Success(Opinion<NetworkConfig>),
 	/// Failed response, because of invalid request.
 	InvalidRequest,
+	/// Request was accepted, with nothing to send back.
+	Ack,
+	/// The neighbor set behind an opinion, in response to a challenge.
+	Reveal(Vec<(PublicKey, f64)>),
 	/// Failed response, because of the internal error.
 	InternalError(u8),
 }
@@ -75,6 +112,7 @@ impl ProtocolName for EigenTrustProtocol {
 	fn protocol_name(&self) -> &[u8] {
 		match self.version {
 			EigenTrustProtocolVersion::V1 => b"/eigen_trust/1.0.0",
+			EigenTrustProtocolVersion::V2 => b"/eigen_trust/2.0.0",
 		}
 	}
 }
@@ -96,10 +134,61 @@ impl RequestResponseCodec for EigenTrustCodec {
 	{
 		match protocol.version {
 			EigenTrustProtocolVersion::V1 => {
-				let mut buf = [0; 8];
+				let mut k_bytes = [0; 8];
+				io.read_exact(&mut k_bytes).await?;
+				Ok(Request::Opinion(Epoch(u64::from_be_bytes(k_bytes))))
+			},
+			EigenTrustProtocolVersion::V2 => {
+				let mut buf = [0; 1];
 				io.read_exact(&mut buf).await?;
-				let k = u64::from_be_bytes(buf);
-				Ok(Request::new(Epoch(k)))
+				let request = match buf[0] {
+					0 => {
+						let mut k_bytes = [0; 8];
+						io.read_exact(&mut k_bytes).await?;
+						let k = u64::from_be_bytes(k_bytes);
+						Request::Opinion(Epoch(k))
+					},
+					1 => {
+						// Epoch config
+						let mut bytes = Vec::new();
+						io.read_to_end(&mut bytes).await?;
+						let config = EpochConfig::from_bytes(&bytes)
+							.map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+						Request::EpochConfig(config)
+					},
+					2 => {
+						// Revocation
+						let mut epoch_bytes = [0; 8];
+						let mut opinion_hash = [0; 32];
+						let mut pubkey_len = [0; 1];
+						let mut signature = Vec::new();
+
+						io.read_exact(&mut epoch_bytes).await?;
+						io.read_exact(&mut opinion_hash).await?;
+						io.read_exact(&mut pubkey_len).await?;
+						let mut pubkey_bytes = vec![0; usize::from(pubkey_len[0])];
+						io.read_exact(&mut pubkey_bytes).await?;
+						io.read_to_end(&mut signature).await?;
+
+						let pubkey = PublicKey::from_protobuf_encoding(&pubkey_bytes)
+							.map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+						Request::Revocation(Revocation {
+							epoch: Epoch(u64::from_be_bytes(epoch_bytes)),
+							opinion_hash,
+							pubkey,
+							signature,
+						})
+					},
+					3 => {
+						// Challenge
+						let mut k = [0; 8];
+						io.read_exact(&mut k).await?;
+						Request::Challenge(Epoch(u64::from_be_bytes(k)))
+					},
+					_ => return Err(Error::new(ErrorKind::InvalidData, "Unknown request")),
+				};
+				Ok(request)
 			},
 		}
 	}
@@ -113,50 +202,56 @@ impl RequestResponseCodec for EigenTrustCodec {
 	where
 		T: AsyncRead + Unpin + Send,
 	{
-		match protocol.version {
-			EigenTrustProtocolVersion::V1 => {
-				let mut buf = [0; 1];
-				io.read_exact(&mut buf).await?;
-				let response = match buf[0] {
-					0 => {
-						// Opinion
-						let mut k_bytes = [0; 8];
-						let mut op_bytes = [0; 8];
-						let mut r = [0; 32];
-						let mut s = [0; 32];
-						let mut m_hash = [0; 32];
-						let mut proof_bytes = Vec::new();
-
-						io.read_exact(&mut k_bytes).await?;
-						io.read_exact(&mut op_bytes).await?;
-						io.read_exact(&mut r).await?;
-						io.read_exact(&mut s).await?;
-						io.read_exact(&mut m_hash).await?;
-						io.read_to_end(&mut proof_bytes).await?;
-
-						let k = u64::from_be_bytes(k_bytes);
-						let op = f64::from_be_bytes(op_bytes);
-
-						let r_f = Secp256k1Scalar::from_bytes(&r).unwrap();
-						let s_f = Secp256k1Scalar::from_bytes(&s).unwrap();
-						let m_hash_f = Secp256k1Scalar::from_bytes(&m_hash).unwrap();
-
-						let sig_data = SigData {
-							r: r_f,
-							s: s_f,
-							m_hash: m_hash_f,
-						};
-
-						let opinion = Opinion::new(Epoch(k), sig_data, op, proof_bytes);
-
-						Response::Success(opinion)
-					},
-					1 => Response::InvalidRequest,
-					other => Response::InternalError(other),
-				};
-				Ok(response)
+		let mut buf = [0; 1];
+		io.read_exact(&mut buf).await?;
+		let response = match (&protocol.version, buf[0]) {
+			(version, SUCCESS_RESPONSE) => {
+				let mut bytes = Vec::new();
+				io.read_to_end(&mut bytes).await?;
+				let opinion = match version {
+					EigenTrustProtocolVersion::V1 => Opinion::from_v1_bytes(&bytes),
+					EigenTrustProtocolVersion::V2 => Opinion::from_bytes(&bytes),
+				}
+				.map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+
+				Response::Success(opinion)
 			},
-		}
+			(_, INVALID_REQUEST_RESPONSE) => Response::InvalidRequest,
+			(EigenTrustProtocolVersion::V1, code) => Response::InternalError(code),
+			(EigenTrustProtocolVersion::V2, INTERNAL_ERROR_RESPONSE) => {
+				let mut code = [0; 1];
+				io.read_exact(&mut code).await?;
+				Response::InternalError(code[0])
+			},
+			(EigenTrustProtocolVersion::V2, ACK_RESPONSE) => Response::Ack,
+			(EigenTrustProtocolVersion::V2, REVEAL_RESPONSE) => {
+				let mut len = [0; 2];
+				io.read_exact(&mut len).await?;
+
+				let mut neighbors = Vec::new();
+				for _ in 0..u16::from_be_bytes(len) {
+					let mut pubkey_len = [0; 1];
+					io.read_exact(&mut pubkey_len).await?;
+					let mut pubkey_bytes = vec![0; usize::from(pubkey_len[0])];
+					io.read_exact(&mut pubkey_bytes).await?;
+					let mut score = [0; 8];
+					io.read_exact(&mut score).await?;
+
+					let pubkey = PublicKey::from_protobuf_encoding(&pubkey_bytes)
+						.map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+					let score = f64::from_be_bytes(score);
+					if !score.is_finite() {
+						return Err(Error::new(ErrorKind::InvalidData, "Invalid score"));
+					}
+					neighbors.push((pubkey, score));
+				}
+				Response::Reveal(neighbors)
+			},
+			(EigenTrustProtocolVersion::V2, _) => {
+				return Err(Error::new(ErrorKind::InvalidData, "Unknown response"));
+			},
+		};
+		Ok(response)
 	}
 
 	/// Write the request to the given stream.
@@ -170,9 +265,44 @@ impl RequestResponseCodec for EigenTrustCodec {
 		T: AsyncWrite + Unpin + Send,
 	{
 		match protocol.version {
-			EigenTrustProtocolVersion::V1 => {
-				let k = req.get_epoch();
-				io.write_all(&k.to_be_bytes()).await?;
+			EigenTrustProtocolVersion::V1 => match req {
+				Request::Opinion(k) => io.write_all(&k.to_be_bytes()).await,
+				_ => Err(unsupported_in_v1()),
+			},
+			EigenTrustProtocolVersion::V2 => {
+				let mut bytes = Vec::new();
+				match req {
+					Request::Opinion(k) => {
+						bytes.push(0);
+						bytes.extend(k.to_be_bytes());
+					},
+					Request::EpochConfig(config) => {
+						bytes.push(1);
+
+						let config = config
+							.to_bytes()
+							.map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{:?}", e)))?;
+						bytes.extend(config);
+					},
+					Request::Revocation(revocation) => {
+						bytes.push(2);
+
+						let pubkey_bytes = revocation.pubkey.to_protobuf_encoding();
+						let pubkey_len = u8::try_from(pubkey_bytes.len())
+							.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+						bytes.extend(revocation.epoch.to_be_bytes());
+						bytes.extend(revocation.opinion_hash);
+						bytes.push(pubkey_len);
+						bytes.extend(pubkey_bytes);
+						bytes.extend(revocation.signature);
+					},
+					Request::Challenge(k) => {
+						bytes.push(3);
+						bytes.extend(k.to_be_bytes());
+					},
+				}
+				io.write_all(&bytes).await?;
 				Ok(())
 			},
 		}
@@ -188,37 +318,57 @@ impl RequestResponseCodec for EigenTrustCodec {
 	where
 		T: AsyncWrite + Unpin + Send,
 	{
-		match protocol.version {
-			EigenTrustProtocolVersion::V1 => {
-				let mut bytes = Vec::new();
-				match res {
-					Response::Success(opinion) => {
-						bytes.push(0);
+		let mut bytes = Vec::new();
+		match (&protocol.version, res) {
+			(EigenTrustProtocolVersion::V1, Response::Success(opinion)) => {
+				bytes.push(SUCCESS_RESPONSE);
 
-						// Opinion
-						bytes.extend(opinion.k.to_be_bytes());
-						bytes.extend(opinion.op.to_be_bytes());
-						bytes.extend(opinion.sig_i.r.to_bytes());
-						bytes.extend(opinion.sig_i.s.to_bytes());
-						bytes.extend(opinion.sig_i.m_hash.to_bytes());
-						bytes.extend(opinion.proof_bytes);
-					},
-					Response::InvalidRequest => bytes.push(1),
-					Response::InternalError(code) => bytes.push(code),
-				};
-				io.write_all(&bytes).await?;
-				Ok(())
+				bytes.extend(opinion.to_v1_bytes());
 			},
-		}
+			(EigenTrustProtocolVersion::V2, Response::Success(opinion)) => {
+				bytes.push(SUCCESS_RESPONSE);
+
+				bytes.extend(opinion.to_bytes());
+			},
+			(_, Response::InvalidRequest) => bytes.push(INVALID_REQUEST_RESPONSE),
+			(EigenTrustProtocolVersion::V1, Response::InternalError(code))
+				if code > INVALID_REQUEST_RESPONSE =>
+			{
+				bytes.push(code)
+			},
+			(EigenTrustProtocolVersion::V1, _) => return Err(unsupported_in_v1()),
+			(EigenTrustProtocolVersion::V2, Response::InternalError(code)) => {
+				bytes.push(INTERNAL_ERROR_RESPONSE);
+				bytes.push(code);
+			},
+			(EigenTrustProtocolVersion::V2, Response::Ack) => bytes.push(ACK_RESPONSE),
+			(EigenTrustProtocolVersion::V2, Response::Reveal(neighbors)) => {
+				bytes.push(REVEAL_RESPONSE);
+
+				let len = u16::try_from(neighbors.len())
+					.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+				bytes.extend(len.to_be_bytes());
+				for (pubkey, score) in neighbors {
+					let pubkey_bytes = pubkey.to_protobuf_encoding();
+					let pubkey_len = u8::try_from(pubkey_bytes.len())
+						.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+					bytes.push(pubkey_len);
+					bytes.extend(pubkey_bytes);
+					bytes.extend(score.to_be_bytes());
+				}
+			},
+		};
+		io.write_all(&bytes).await
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use libp2p::identity::Keypair;
 
 	impl Response {
-		pub fn success(self) -> Opinion<MAX_NEIGHBORS> {
+		pub fn success(self) -> Opinion<NetworkConfig> {
 			match self {
 				Response::Success(opinion) => opinion,
 				_ => panic!("Response::success called on invalid response"),
@@ -237,14 +387,115 @@ mod tests {
 			.await
 			.unwrap();
 
-		let bytes = epoch.to_be_bytes();
+		let mut bytes = vec![0];
+		bytes.extend(epoch.to_be_bytes());
 		assert_eq!(buf, bytes);
 
 		let req = codec
 			.read_request(&EigenTrustProtocol::default(), &mut &bytes[..])
 			.await
 			.unwrap();
-		assert_eq!(req.get_epoch(), epoch);
+		assert_eq!(req, Request::new(epoch));
+	}
+
+	#[tokio::test]
+	async fn should_correctly_write_read_epoch_config_request() {
+		let keypair = Keypair::generate_secp256k1();
+		let config = EpochConfig::new(&keypair, Epoch(10), 30).unwrap();
+		let req = Request::EpochConfig(config.clone());
+
+		let mut codec = EigenTrustCodec::default();
+		let mut buf = vec![];
+		codec
+			.write_request(&EigenTrustProtocol::default(), &mut buf, req.clone())
+			.await
+			.unwrap();
+
+		let read_req = codec
+			.read_request(&EigenTrustProtocol::default(), &mut &buf[..])
+			.await
+			.unwrap();
+		assert_eq!(read_req, req);
+
+		if let Request::EpochConfig(read_config) = read_req {
+			assert!(read_config.verify());
+		}
+	}
+
+	#[tokio::test]
+	async fn should_correctly_write_read_revocation_request() {
+		let keypair = Keypair::generate_secp256k1();
+		let opinion = Opinion::<NetworkConfig>::empty();
+		let revocation = Revocation::new(&keypair, &opinion).unwrap();
+		let req = Request::Revocation(revocation);
+
+		let mut codec = EigenTrustCodec::default();
+		let mut buf = vec![];
+		codec
+			.write_request(&EigenTrustProtocol::default(), &mut buf, req.clone())
+			.await
+			.unwrap();
+
+		let read_req = codec
+			.read_request(&EigenTrustProtocol::default(), &mut &buf[..])
+			.await
+			.unwrap();
+		assert_eq!(read_req, req);
+
+		if let Request::Revocation(read_revocation) = read_req {
+			assert!(read_revocation.verify());
+			assert!(read_revocation.revokes(&opinion));
+		}
+	}
+
+	#[tokio::test]
+	async fn should_correctly_write_read_challenge() {
+		let req = Request::Challenge(Epoch(7));
+		let neighbors = vec![
+			(Keypair::generate_secp256k1().public(), 0.75),
+			(Keypair::generate_secp256k1().public(), 0.25),
+		];
+		let res = Response::Reveal(neighbors);
+
+		let mut codec = EigenTrustCodec::default();
+		let mut buf = vec![];
+		codec
+			.write_request(&EigenTrustProtocol::default(), &mut buf, req.clone())
+			.await
+			.unwrap();
+		let read_req = codec
+			.read_request(&EigenTrustProtocol::default(), &mut &buf[..])
+			.await
+			.unwrap();
+		assert_eq!(read_req, req);
+
+		let mut buf = vec![];
+		codec
+			.write_response(&EigenTrustProtocol::default(), &mut buf, res.clone())
+			.await
+			.unwrap();
+		let read_res = codec
+			.read_response(&EigenTrustProtocol::default(), &mut &buf[..])
+			.await
+			.unwrap();
+		assert_eq!(read_res, res);
+
+		// A truncated reveal is rejected.
+		buf.pop();
+		let res = codec
+			.read_response(&EigenTrustProtocol::default(), &mut &buf[..])
+			.await;
+		assert!(res.is_err());
+	}
+
+	#[tokio::test]
+	async fn should_fail_to_read_unknown_request() {
+		let mut codec = EigenTrustCodec::default();
+		let bytes = [9, 0, 0];
+		let res = codec
+			.read_request(&EigenTrustProtocol::default(), &mut &bytes[..])
+			.await;
+		assert!(res.is_err());
 	}
 
 	#[tokio::test]
@@ -261,11 +512,7 @@ mod tests {
 
 		let mut bytes = vec![];
 		bytes.push(0);
-		bytes.extend(opinion.k.to_be_bytes());
-		bytes.extend(opinion.op.to_be_bytes());
-		bytes.extend(opinion.sig_i.r.to_bytes());
-		bytes.extend(opinion.sig_i.s.to_bytes());
-		bytes.extend(opinion.sig_i.m_hash.to_bytes());
+		bytes.extend(opinion.to_bytes());
 
 		// compare the written bytes with the expected bytes
 		assert_eq!(buf, bytes);
@@ -301,10 +548,29 @@ mod tests {
 		assert_eq!(read_res, bad_res);
 	}
 
+	#[tokio::test]
+	async fn should_correctly_write_read_ack_response() {
+		let res = Response::Ack;
+
+		let mut buf = vec![];
+		let mut codec = EigenTrustCodec::default();
+		codec
+			.write_response(&EigenTrustProtocol::default(), &mut buf, res.clone())
+			.await
+			.unwrap();
+		assert_eq!(buf, vec![ACK_RESPONSE]);
+
+		let read_res = codec
+			.read_response(&EigenTrustProtocol::default(), &mut &buf[..])
+			.await
+			.unwrap();
+		assert_eq!(read_res, res);
+	}
+
 	#[tokio::test]
 	async fn should_correctly_write_read_internal_error_response() {
 		// Testing internal error
-		let bad_res = Response::InternalError(2);
+		let bad_res = Response::InternalError(255);
 
 		let mut buf = vec![];
 		let mut codec = EigenTrustCodec::default();
@@ -313,15 +579,103 @@ mod tests {
 			.await
 			.unwrap();
 
-		let mut bytes = vec![];
-		bytes.push(2);
-		assert_eq!(buf, bytes);
+		assert_eq!(buf, vec![INTERNAL_ERROR_RESPONSE, 255]);
 
 		let read_res = codec
-			.read_response(&EigenTrustProtocol::default(), &mut &bytes[..])
+			.read_response(&EigenTrustProtocol::default(), &mut &buf[..])
 			.await
 			.unwrap();
 
 		assert_eq!(read_res, bad_res);
 	}
+
+	#[tokio::test]
+	async fn should_not_confuse_ack_with_internal_error() {
+		let mut codec = EigenTrustCodec::default();
+		let mut buf = vec![];
+		codec
+			.write_response(
+				&EigenTrustProtocol::default(),
+				&mut buf,
+				Response::InternalError(2),
+			)
+			.await
+			.unwrap();
+		let read_res = codec
+			.read_response(&EigenTrustProtocol::default(), &mut &buf[..])
+			.await
+			.unwrap();
+		assert_eq!(read_res, Response::InternalError(2));
+	}
+
+	#[tokio::test]
+	async fn should_speak_protocol_v1() {
+		let v1 = EigenTrustProtocol::v1();
+		assert_eq!(v1.protocol_name(), b"/eigen_trust/1.0.0");
+		assert_eq!(
+			EigenTrustProtocol::new().protocol_name(),
+			b"/eigen_trust/2.0.0"
+		);
+
+		// The request is the bare epoch.
+		let mut codec = EigenTrustCodec::default();
+		let mut buf = vec![];
+		codec
+			.write_request(&v1, &mut buf, Request::new(Epoch(3)))
+			.await
+			.unwrap();
+		assert_eq!(buf, Epoch(3).to_be_bytes());
+		let req = codec.read_request(&v1, &mut &buf[..]).await.unwrap();
+		assert_eq!(req, Request::new(Epoch(3)));
+
+		// The requests added after V1 can't be sent with it.
+		let res = codec
+			.write_request(&v1, &mut vec![], Request::Challenge(Epoch(3)))
+			.await;
+		assert!(res.is_err());
+
+		// Any other response code is an internal error.
+		let res = codec.read_response(&v1, &mut &[2u8][..]).await.unwrap();
+		assert_eq!(res, Response::InternalError(2));
+		let mut buf = vec![];
+		codec
+			.write_response(&v1, &mut buf, Response::InternalError(2))
+			.await
+			.unwrap();
+		assert_eq!(buf, vec![2]);
+		let res = codec.write_response(&v1, &mut vec![], Response::Ack).await;
+		assert!(res.is_err());
+	}
+
+	#[tokio::test]
+	async fn should_write_read_opinion_in_v1_layout() {
+		let v1 = EigenTrustProtocol::v1();
+		let opinion = Opinion::<NetworkConfig>::empty();
+		let proof = vec![7; 64];
+
+		// The response of the nodes speaking V1: the bare fields of the opinion,
+		// and the proof up to the end.
+		let mut bytes = vec![SUCCESS_RESPONSE];
+		bytes.extend(Epoch(5).to_be_bytes());
+		bytes.extend(0.25f64.to_be_bytes());
+		bytes.extend(opinion.sig_i.r.to_bytes());
+		bytes.extend(opinion.sig_i.s.to_bytes());
+		bytes.extend(opinion.sig_i.m_hash.to_bytes());
+		bytes.extend(&proof);
+
+		let mut codec = EigenTrustCodec::default();
+		let res = codec.read_response(&v1, &mut &bytes[..]).await.unwrap();
+		let read_opinion = res.success();
+		assert_eq!(read_opinion.k, Epoch(5));
+		assert_eq!(read_opinion.op, 0.25);
+		assert_eq!(read_opinion.sig_i, opinion.sig_i);
+		assert_eq!(read_opinion.proof_bytes, proof);
+
+		let mut buf = vec![];
+		codec
+			.write_response(&v1, &mut buf, Response::Success(read_opinion))
+			.await
+			.unwrap();
+		assert_eq!(buf, bytes);
+	}
 }
@@ -0,0 +1,287 @@
+//! The archives of the trust history: the global trust scores and the
+//! opinions, with their proofs, over a range of epochs, so a new node can
+//! bootstrap the history from an archival node out of band.
+//!
+//! The layout is `magic || version || source_len (u16 BE) || source ||
+//! first (u64 BE) || last (u64 BE) || records || checksum`, where the source
+//! is the PeerId of the node that wrote the archive, `first..=last` are the
+//! epochs, and the checksum is the SHA-256 of everything before it. Each
+//! record is `kind || len (u32 BE) || payload`, with the payload of:
+//! - a score, `epoch (u64 BE) || score (f64 BE) || peer_id`,
+//! - a proof, its bytes,
+//! - an opinion, `author_len || author || subject_len || subject || has_proof
+//!   || proof_id (if has_proof) || opinion`, with the opinion encoded without
+//!   its proof.
+//!
+//! The proofs are written once, before the first opinion that has them. The
+//! records of an unknown kind are skipped, so the newer archives of the same
+//! version can add new kinds.
+
+use super::{proof_cid, proof_id, read_array, take, write_atomic, MemoryStore, ProofId, Store};
+use crate::{
+	epoch::Epoch,
+	peer::{opinion::Opinion, NetworkConfig},
+	EigenError,
+};
+use libp2p::PeerId;
+use sha2::{Digest, Sha256};
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	ops::RangeInclusive,
+	path::Path,
+};
+
+/// The first bytes of an archive.
+const ARCHIVE_MAGIC: &[u8; 8] = b"EIGENARC";
+/// The version of the archive layout.
+pub const ARCHIVE_VERSION: u8 = 1;
+
+const SCORE_RECORD: u8 = 0;
+const PROOF_RECORD: u8 = 1;
+const OPINION_RECORD: u8 = 2;
+
+fn invalid() -> EigenError {
+	EigenError::InvalidArchive
+}
+
+fn archive_error(what: &str, path: &Path, e: std::io::Error) -> EigenError {
+	log::error!("Failed to {} the archive at {:?}: {}", what, path, e);
+	EigenError::InvalidArchive
+}
+
+/// The header of an archive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveInfo {
+	/// The node that wrote the archive.
+	pub source: PeerId,
+	/// The epochs of the history in the archive.
+	pub epochs: RangeInclusive<Epoch>,
+}
+
+fn push_record(bytes: &mut Vec<u8>, kind: u8, payload: &[u8]) {
+	bytes.push(kind);
+	bytes.extend((payload.len() as u32).to_be_bytes());
+	bytes.extend(payload);
+}
+
+fn push_peer_id(bytes: &mut Vec<u8>, peer_id: &PeerId) {
+	let peer_id = peer_id.to_bytes();
+	bytes.push(peer_id.len() as u8);
+	bytes.extend(peer_id);
+}
+
+/// Write the history of the store in the epochs to the file, as the archive
+/// of the source node. The file is replaced at once.
+pub fn write_archive(
+	path: &Path,
+	source: &PeerId,
+	store: &dyn Store,
+	epochs: RangeInclusive<Epoch>,
+) -> Result<(), EigenError> {
+	let mut bytes = ARCHIVE_MAGIC.to_vec();
+	bytes.push(ARCHIVE_VERSION);
+	let source = source.to_bytes();
+	bytes.extend((source.len() as u16).to_be_bytes());
+	bytes.extend(source);
+	bytes.extend(epochs.start().0.to_be_bytes());
+	bytes.extend(epochs.end().0.to_be_bytes());
+
+	for (epoch, peer_id, score) in store.load_history_scores(epochs.clone())? {
+		let mut payload = epoch.0.to_be_bytes().to_vec();
+		payload.extend(score.to_be_bytes());
+		payload.extend(peer_id.to_bytes());
+		push_record(&mut bytes, SCORE_RECORD, &payload);
+	}
+
+	let mut written_proofs = HashSet::new();
+	for ((_, author, subject), opinion) in store.load_history(epochs)? {
+		let mut payload = Vec::new();
+		push_peer_id(&mut payload, &author);
+		push_peer_id(&mut payload, &subject);
+		if opinion.proof_bytes.is_empty() {
+			payload.push(0);
+		} else {
+			let id = proof_id(&opinion.proof_bytes);
+			if written_proofs.insert(id) {
+				push_record(&mut bytes, PROOF_RECORD, &opinion.proof_bytes);
+			}
+			payload.push(1);
+			payload.extend(id);
+		}
+		payload.extend(opinion.without_proof().to_bytes());
+		push_record(&mut bytes, OPINION_RECORD, &payload);
+	}
+
+	let checksum = Sha256::digest(&bytes);
+	bytes.extend(checksum);
+	write_atomic(path, &bytes).map_err(|e| archive_error("write", path, e))
+}
+
+/// Read the archive into a store holding its history. Fails if the file is
+/// corrupted, of an unknown version, or has an opinion outside of its epochs
+/// or without its proof.
+pub fn read_archive(path: &Path) -> Result<(ArchiveInfo, MemoryStore), EigenError> {
+	let bytes = fs::read(path).map_err(|e| archive_error("read", path, e))?;
+	if bytes.len() < 32 {
+		return Err(EigenError::InvalidArchive);
+	}
+	let (body, checksum) = bytes.split_at(bytes.len() - 32);
+	if Sha256::digest(body).as_slice() != checksum {
+		log::error!("The checksum of the archive at {:?} doesn't match", path);
+		return Err(EigenError::InvalidArchive);
+	}
+
+	let mut reader = body;
+	if take(&mut reader, ARCHIVE_MAGIC.len(), invalid)? != ARCHIVE_MAGIC {
+		log::error!("{:?} is not an archive", path);
+		return Err(EigenError::InvalidArchive);
+	}
+	let version = take(&mut reader, 1, invalid)?[0];
+	if version != ARCHIVE_VERSION {
+		log::error!("Unknown archive version {}", version);
+		return Err(EigenError::InvalidArchive);
+	}
+	let source_len = u16::from_be_bytes(read_array(&mut reader, invalid)?);
+	let source = read_peer_id(&mut reader, source_len.into())?;
+	let first = Epoch(u64::from_be_bytes(read_array(&mut reader, invalid)?));
+	let last = Epoch(u64::from_be_bytes(read_array(&mut reader, invalid)?));
+	let epochs = first..=last;
+
+	let store = MemoryStore::default();
+	let mut proofs: HashMap<ProofId, Vec<u8>> = HashMap::new();
+	while !reader.is_empty() {
+		let kind = take(&mut reader, 1, invalid)?[0];
+		let len = u32::from_be_bytes(read_array(&mut reader, invalid)?);
+		let mut payload = take(&mut reader, len as usize, invalid)?;
+		match kind {
+			SCORE_RECORD => {
+				let epoch = Epoch(u64::from_be_bytes(read_array(&mut payload, invalid)?));
+				let score = f64::from_be_bytes(read_array(&mut payload, invalid)?);
+				let peer_id_len = payload.len();
+				let peer_id = read_peer_id(&mut payload, peer_id_len)?;
+				check_epoch(&epochs, epoch)?;
+				store.save_history_score(epoch, &peer_id, score)?;
+			},
+			PROOF_RECORD => {
+				proofs.insert(proof_id(payload), payload.to_vec());
+			},
+			OPINION_RECORD => {
+				let author_len = take(&mut payload, 1, invalid)?[0];
+				let author = read_peer_id(&mut payload, author_len.into())?;
+				let subject_len = take(&mut payload, 1, invalid)?[0];
+				let subject = read_peer_id(&mut payload, subject_len.into())?;
+				let has_proof = take(&mut payload, 1, invalid)?[0] == 1;
+				let id = if has_proof {
+					Some(read_array::<32>(&mut payload, invalid)?)
+				} else {
+					None
+				};
+				let mut opinion = Opinion::<NetworkConfig>::from_bytes(payload)
+					.map_err(|_| EigenError::InvalidArchive)?;
+				if let Some(id) = id {
+					opinion.proof_bytes = proofs.get(&id).cloned().ok_or_else(|| {
+						log::error!("The proof {} is missing from the archive", proof_cid(&id));
+						EigenError::InvalidArchive
+					})?;
+				}
+				check_epoch(&epochs, opinion.k)?;
+				store.save_history_opinion(opinion.k, &author, &subject, &opinion)?;
+			},
+			kind => log::warn!("Skipping an archive record of the unknown kind {}", kind),
+		}
+	}
+	Ok((ArchiveInfo { source, epochs }, store))
+}
+
+fn check_epoch(epochs: &RangeInclusive<Epoch>, epoch: Epoch) -> Result<(), EigenError> {
+	if !epochs.contains(&epoch) {
+		log::error!(
+			"The archive has a record of {}, outside of its epochs",
+			epoch
+		);
+		return Err(EigenError::InvalidArchive);
+	}
+	Ok(())
+}
+
+fn read_peer_id(reader: &mut &[u8], len: usize) -> Result<PeerId, EigenError> {
+	PeerId::from_bytes(take(reader, len, invalid)?).map_err(|_| EigenError::InvalidArchive)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::storage::Tree;
+	use eigen_trust_circuit::{ecdsa::SigData, halo2wrong::curves::secp256k1::Fq};
+
+	fn temp_path(name: &str) -> std::path::PathBuf {
+		let suffix: u64 = rand::random();
+		std::env::temp_dir().join(format!("eigen-trust-{}-{}.archive", name, suffix))
+	}
+
+	fn opinion(k: u64, proof: &[u8]) -> Opinion<NetworkConfig> {
+		Opinion::new(Epoch(k), SigData::<Fq>::empty(), 0.5, proof.to_vec())
+	}
+
+	#[test]
+	fn should_write_and_read_archive() {
+		let source = PeerId::random();
+		let peer_1 = PeerId::random();
+		let peer_2 = PeerId::random();
+		let store = MemoryStore::default();
+		for k in 1..=4 {
+			store
+				.save_history_opinion(Epoch(k), &peer_1, &peer_2, &opinion(k, &[1; 32]))
+				.unwrap();
+			store
+				.save_history_opinion(Epoch(k), &peer_2, &peer_1, &opinion(k, &[]))
+				.unwrap();
+			store.save_history_score(Epoch(k), &peer_1, 0.25).unwrap();
+		}
+
+		let path = temp_path("roundtrip");
+		write_archive(&path, &source, &store, Epoch(2)..=Epoch(3)).unwrap();
+		let (info, imported) = read_archive(&path).unwrap();
+		assert_eq!(info, ArchiveInfo {
+			source,
+			epochs: Epoch(2)..=Epoch(3),
+		});
+		let all_epochs = Epoch(0)..=Epoch(u64::MAX);
+		assert_eq!(
+			imported.load_history(all_epochs.clone()).unwrap(),
+			store.load_history(Epoch(2)..=Epoch(3)).unwrap()
+		);
+		assert_eq!(imported.load_history_scores(all_epochs).unwrap(), vec![
+			(Epoch(2), peer_1, 0.25),
+			(Epoch(3), peer_1, 0.25)
+		]);
+		// The shared proof is written and stored once.
+		assert_eq!(imported.records(Tree::Proofs).unwrap().len(), 1);
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn should_reject_corrupted_archive() {
+		let store = MemoryStore::default();
+		let peer_id = PeerId::random();
+		store
+			.save_history_opinion(Epoch(1), &peer_id, &peer_id, &opinion(1, &[1; 32]))
+			.unwrap();
+
+		let path = temp_path("corrupted");
+		write_archive(&path, &peer_id, &store, Epoch(1)..=Epoch(1)).unwrap();
+		let mut bytes = fs::read(&path).unwrap();
+		let middle = bytes.len() / 2;
+		bytes[middle] ^= 1;
+		fs::write(&path, &bytes).unwrap();
+		assert!(matches!(
+			read_archive(&path),
+			Err(EigenError::InvalidArchive)
+		));
+
+		fs::write(&path, b"EIGENARC").unwrap();
+		assert!(read_archive(&path).is_err());
+		fs::remove_file(path).unwrap();
+	}
+}
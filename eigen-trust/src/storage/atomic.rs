@@ -0,0 +1,51 @@
+//! The writes of whole files that survive a crash: after it, the file has
+//! either the old or the new content, never a part of the new one.
+
+use std::{
+	ffi::OsString,
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+/// The temporary file next to the path, written before replacing it.
+fn tmp_path(path: &Path) -> PathBuf {
+	let mut name = path
+		.file_name()
+		.map(|name| name.to_os_string())
+		.unwrap_or_else(OsString::new);
+	name.push(".tmp");
+	path.with_file_name(name)
+}
+
+fn write_with(path: &Path, bytes: &[u8], options: &OpenOptions) -> io::Result<()> {
+	let tmp_path = tmp_path(path);
+	let mut file = options.open(&tmp_path)?;
+	file.write_all(bytes)?;
+	file.sync_all()?;
+	fs::rename(&tmp_path, path)?;
+	// The rename is only durable once the directory is synced.
+	#[cfg(unix)]
+	if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		File::open(dir)?.sync_all()?;
+	}
+	Ok(())
+}
+
+/// Replace the file with the bytes at once. They are written to a temporary
+/// file next to it, which is synced to the disk and renamed over the file.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+	let mut options = OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	write_with(path, bytes, &options)
+}
+
+/// Replace the file with the bytes at once, like [`write_atomic`], and make it
+/// readable only by its owner.
+pub fn write_atomic_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+	let mut options = OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+	write_with(path, bytes, &options)
+}
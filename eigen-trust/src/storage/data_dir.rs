@@ -0,0 +1,228 @@
+//! The data directory of the node, holding all of its files:
+//!
+//! ```text
+//! <data_dir>/
+//!   VERSION             The version of the layout.
+//!   LOCK                Locked by the process using the directory.
+//!   identity.keystore   The encrypted identity keypair.
+//!   opinions.wal        The write-ahead log of the accepted opinions.
+//!   store/              The sled database.
+//!   store.sqlite        The SQLite database, if used instead.
+//!   params/             The KZG params, as `params-<k>.bin`.
+//!   snapshots/          The snapshots given by a relative path.
+//!   archives/           The archives of the trust history given by a relative
+//!                       path.
+//! ```
+//!
+//! The proving and verifying keys are derived from the params at startup, so
+//! they are not kept on the disk.
+
+use super::write_atomic;
+use crate::EigenError;
+use fs2::FileExt;
+use std::{
+	fs::{self, File, OpenOptions},
+	path::{Path, PathBuf},
+};
+
+/// The version of the layout.
+pub const DATA_DIR_VERSION: u32 = 1;
+
+const VERSION_FILE: &str = "VERSION";
+const LOCK_FILE: &str = "LOCK";
+const IDENTITY_KEYSTORE: &str = "identity.keystore";
+const WAL_FILE: &str = "opinions.wal";
+const STORE_DIR: &str = "store";
+const SQLITE_FILE: &str = "store.sqlite";
+const PARAMS_DIR: &str = "params";
+const SNAPSHOTS_DIR: &str = "snapshots";
+const ARCHIVES_DIR: &str = "archives";
+
+fn data_dir_error(what: &str, path: &Path, e: std::io::Error) -> EigenError {
+	log::error!("Failed to {} {:?}: {}", what, path, e);
+	EigenError::InvalidDataDir
+}
+
+/// An open data directory. It is locked until dropped, so only one process
+/// uses it at a time.
+#[derive(Debug)]
+pub struct DataDir {
+	root: PathBuf,
+	/// The locked file, unlocked once closed.
+	_lock: File,
+}
+
+impl DataDir {
+	/// Open the data directory, creating it if it doesn't exist. Fails if
+	/// another process has it open, if it has another version of the layout,
+	/// or if it is not empty and has no version, so the files of something
+	/// else are never mixed with the ones of the node.
+	pub fn open(root: &Path) -> Result<Self, EigenError> {
+		fs::create_dir_all(root).map_err(|e| data_dir_error("create", root, e))?;
+
+		// Locking first, so two processes don't set up the directory at once.
+		let lock_path = root.join(LOCK_FILE);
+		let lock = OpenOptions::new()
+			.write(true)
+			.create(true)
+			.open(&lock_path)
+			.map_err(|e| data_dir_error("open", &lock_path, e))?;
+		lock.try_lock_exclusive().map_err(|e| {
+			log::error!("The data dir {:?} is used by another process: {}", root, e);
+			EigenError::InvalidDataDir
+		})?;
+
+		let version_path = root.join(VERSION_FILE);
+		if version_path.exists() {
+			let version = fs::read_to_string(&version_path)
+				.map_err(|e| data_dir_error("read", &version_path, e))?;
+			if version.trim().parse::<u32>().ok() != Some(DATA_DIR_VERSION) {
+				log::error!(
+					"The data dir {:?} has the layout version {:?}, expected {}",
+					root,
+					version.trim(),
+					DATA_DIR_VERSION
+				);
+				return Err(EigenError::InvalidDataDir);
+			}
+		} else {
+			let entries = fs::read_dir(root).map_err(|e| data_dir_error("read", root, e))?;
+			for entry in entries {
+				let entry = entry.map_err(|e| data_dir_error("read", root, e))?;
+				if entry.file_name() != LOCK_FILE {
+					log::error!(
+						"{:?} is not empty, and not a data dir: found {:?}",
+						root,
+						entry.file_name()
+					);
+					return Err(EigenError::InvalidDataDir);
+				}
+			}
+			let version = format!("{}\n", DATA_DIR_VERSION);
+			write_atomic(&version_path, version.as_bytes())
+				.map_err(|e| data_dir_error("write", &version_path, e))?;
+		}
+
+		for dir in [STORE_DIR, PARAMS_DIR, SNAPSHOTS_DIR, ARCHIVES_DIR] {
+			let path = root.join(dir);
+			fs::create_dir_all(&path).map_err(|e| data_dir_error("create", &path, e))?;
+		}
+
+		Ok(Self {
+			root: root.to_path_buf(),
+			_lock: lock,
+		})
+	}
+
+	/// The root of the data directory.
+	pub fn root(&self) -> &Path {
+		&self.root
+	}
+
+	/// The path of the identity keystore.
+	pub fn identity_keystore(&self) -> PathBuf {
+		self.root.join(IDENTITY_KEYSTORE)
+	}
+
+	/// The path of the write-ahead log of the accepted opinions.
+	pub fn wal(&self) -> PathBuf {
+		self.root.join(WAL_FILE)
+	}
+
+	/// The directory of the sled database.
+	pub fn store(&self) -> PathBuf {
+		self.root.join(STORE_DIR)
+	}
+
+	/// The path of the SQLite database.
+	pub fn sqlite(&self) -> PathBuf {
+		self.root.join(SQLITE_FILE)
+	}
+
+	/// The path of the params of degree `k`.
+	pub fn params(&self, k: u32) -> PathBuf {
+		self.root.join(PARAMS_DIR).join(format!("params-{}.bin", k))
+	}
+
+	/// The path of a snapshot. The relative paths are in the snapshots
+	/// directory.
+	pub fn snapshot(&self, path: &Path) -> PathBuf {
+		self.resolve(SNAPSHOTS_DIR, path)
+	}
+
+	/// The path of an archive of the trust history. The relative paths are in
+	/// the archives directory.
+	pub fn archive(&self, path: &Path) -> PathBuf {
+		self.resolve(ARCHIVES_DIR, path)
+	}
+
+	fn resolve(&self, dir: &str, path: &Path) -> PathBuf {
+		if path.is_relative() {
+			self.root.join(dir).join(path)
+		} else {
+			path.to_path_buf()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn temp_dir(name: &str) -> PathBuf {
+		let suffix: u64 = rand::random();
+		std::env::temp_dir().join(format!("eigen-trust-{}-{}", name, suffix))
+	}
+
+	#[test]
+	fn should_create_and_reopen() {
+		let root = temp_dir("data-dir");
+		let data_dir = DataDir::open(&root).unwrap();
+		assert!(data_dir.store().is_dir());
+		assert!(data_dir.params(18).parent().unwrap().is_dir());
+		assert_eq!(fs::read_to_string(root.join(VERSION_FILE)).unwrap(), "1\n");
+		assert_eq!(
+			data_dir.snapshot(Path::new("a.snapshot")),
+			root.join("snapshots/a.snapshot")
+		);
+		assert_eq!(
+			data_dir.snapshot(Path::new("/tmp/a.snapshot")),
+			Path::new("/tmp/a.snapshot")
+		);
+		assert_eq!(
+			data_dir.archive(Path::new("a.archive")),
+			root.join("archives/a.archive")
+		);
+		fs::write(data_dir.wal(), b"").unwrap();
+		drop(data_dir);
+
+		DataDir::open(&root).unwrap();
+		fs::remove_dir_all(root).unwrap();
+	}
+
+	#[test]
+	fn should_lock() {
+		let root = temp_dir("data-dir-lock");
+		let data_dir = DataDir::open(&root).unwrap();
+		assert!(matches!(
+			DataDir::open(&root),
+			Err(EigenError::InvalidDataDir)
+		));
+		drop(data_dir);
+		DataDir::open(&root).unwrap();
+		fs::remove_dir_all(root).unwrap();
+	}
+
+	#[test]
+	fn should_reject_other_dirs() {
+		let root = temp_dir("data-dir-other");
+		fs::create_dir_all(&root).unwrap();
+		fs::write(root.join("notes.txt"), b"").unwrap();
+		assert!(DataDir::open(&root).is_err());
+
+		fs::remove_file(root.join("notes.txt")).unwrap();
+		fs::write(root.join(VERSION_FILE), b"2\n").unwrap();
+		assert!(DataDir::open(&root).is_err());
+		fs::remove_dir_all(root).unwrap();
+	}
+}
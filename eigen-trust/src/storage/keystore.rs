@@ -0,0 +1,344 @@
+//! Keystore files, holding the identity keypair encrypted with a passphrase.
+//!
+//! The encryption key is derived from the passphrase with scrypt, and the
+//! keypair is encrypted with AES-256-GCM:
+//! `magic || version || log_n || r (u32 BE) || p (u32 BE) || salt (16 bytes)
+//! || nonce (12 bytes) || ciphertext || tag (16 bytes)`. Everything before the
+//! ciphertext is authenticated with it, so the scrypt parameters can't be
+//! tampered with. The plaintext is the protobuf encoding of the keypair.
+//!
+//! The keystores written before the format was versioned are
+//! `salt || nonce || ciphertext || tag`, with the fixed scrypt parameters and
+//! the bs58 encoded keypair as the plaintext. They can still be unlocked, and
+//! [`Keystore::rotate`] upgrades them.
+
+use super::write_atomic_private;
+use crate::EigenError;
+use aes_gcm::{
+	aead::{Aead, NewAead, Payload},
+	Aes256Gcm, Key, Nonce,
+};
+use libp2p::identity::{ed25519, secp256k1, Keypair};
+use rand::Rng;
+use scrypt::{scrypt, Params};
+use std::{
+	fs,
+	io::{self, ErrorKind},
+	path::{Path, PathBuf},
+};
+
+/// The first bytes of a versioned keystore.
+const KEYSTORE_MAGIC: &[u8; 8] = b"EIGENKEY";
+/// The version of the keystore format.
+pub const KEYSTORE_VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// The length of the header of a versioned keystore, up to the ciphertext.
+const HEADER_LEN: usize = KEYSTORE_MAGIC.len() + 1 + 1 + 4 + 4 + SALT_LEN + NONCE_LEN;
+
+/// The protobuf headers of the private keys: the key type field and the
+/// length of the data field.
+const ED25519_HEADER: [u8; 4] = [0x08, 0x01, 0x12, 0x40];
+const SECP256K1_HEADER: [u8; 4] = [0x08, 0x02, 0x12, 0x20];
+
+/// The scrypt cost parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct KdfParams {
+	log_n: u8,
+	r: u32,
+	p: u32,
+}
+
+/// The parameters of the new keystores, and of the unversioned ones.
+const DEFAULT_KDF_PARAMS: KdfParams = KdfParams {
+	log_n: 15,
+	r: 8,
+	p: 1,
+};
+
+impl KdfParams {
+	/// Derive the encryption key from the passphrase.
+	fn derive_key(&self, passphrase: &str, salt: &[u8]) -> Result<[u8; 32], EigenError> {
+		let params =
+			Params::new(self.log_n, self.r, self.p).map_err(|_| EigenError::KeystoreError)?;
+		let mut key = [0u8; 32];
+		scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+			.map_err(|_| EigenError::KeystoreError)?;
+		Ok(key)
+	}
+}
+
+/// The protobuf encoding of the keypair, as read by [`decode_keypair`].
+pub fn encode_keypair(keypair: &Keypair) -> Result<Vec<u8>, EigenError> {
+	let (header, secret) = match keypair {
+		Keypair::Ed25519(kp) => (ED25519_HEADER, kp.encode().to_vec()),
+		Keypair::Secp256k1(kp) => (SECP256K1_HEADER, kp.secret().to_bytes().to_vec()),
+		#[allow(unreachable_patterns)]
+		_ => return Err(EigenError::InvalidKeypair),
+	};
+	let mut bytes = header.to_vec();
+	bytes.extend(secret);
+	Ok(bytes)
+}
+
+/// Decode the protobuf encoding of an ed25519 or a secp256k1 keypair. libp2p
+/// can only decode the ed25519 ones, so both are decoded here.
+pub fn decode_keypair(bytes: &[u8]) -> Result<Keypair, EigenError> {
+	if let Some(sk_bytes) = bytes.strip_prefix(&SECP256K1_HEADER) {
+		let sk = secp256k1::SecretKey::from_bytes(sk_bytes.to_vec())
+			.map_err(|_| EigenError::InvalidKeypair)?;
+		return Ok(Keypair::Secp256k1(sk.into()));
+	}
+	if let Some(kp_bytes) = bytes.strip_prefix(&ED25519_HEADER) {
+		let kp = ed25519::Keypair::decode(&mut kp_bytes.to_vec())
+			.map_err(|_| EigenError::InvalidKeypair)?;
+		return Ok(Keypair::Ed25519(kp));
+	}
+	Err(EigenError::InvalidKeypair)
+}
+
+fn keystore_error(what: &str, path: &Path, e: std::io::Error) -> EigenError {
+	log::error!("Failed to {} the keystore {:?}: {}", what, path, e);
+	EigenError::KeystoreError
+}
+
+/// Encrypt the keypair with the passphrase, with a fresh salt and nonce.
+fn encrypt(keypair: &Keypair, passphrase: &str) -> Result<Vec<u8>, EigenError> {
+	let rng = &mut rand::thread_rng();
+	let salt: [u8; SALT_LEN] = rng.gen();
+	let nonce: [u8; NONCE_LEN] = rng.gen();
+	let params = DEFAULT_KDF_PARAMS;
+
+	let mut bytes = KEYSTORE_MAGIC.to_vec();
+	bytes.push(KEYSTORE_VERSION);
+	bytes.push(params.log_n);
+	bytes.extend(params.r.to_be_bytes());
+	bytes.extend(params.p.to_be_bytes());
+	bytes.extend(salt);
+	bytes.extend(nonce);
+
+	let enc_key = params.derive_key(passphrase, &salt)?;
+	let cipher = Aes256Gcm::new(Key::from_slice(&enc_key));
+	let payload = Payload {
+		msg: &encode_keypair(keypair)?,
+		aad: &bytes,
+	};
+	let ciphertext = cipher
+		.encrypt(Nonce::from_slice(&nonce), payload)
+		.map_err(|_| EigenError::KeystoreError)?;
+	bytes.extend(ciphertext);
+	Ok(bytes)
+}
+
+/// Decrypt the keypair with the passphrase. Fails if the passphrase is wrong,
+/// or the keystore was tampered with.
+fn decrypt(bytes: &[u8], passphrase: &str) -> Result<Keypair, EigenError> {
+	match bytes.strip_prefix(KEYSTORE_MAGIC) {
+		Some(rest) => decrypt_versioned(bytes, rest, passphrase),
+		None => decrypt_unversioned(bytes, passphrase),
+	}
+}
+
+fn decrypt_versioned(bytes: &[u8], rest: &[u8], passphrase: &str) -> Result<Keypair, EigenError> {
+	if bytes.len() < HEADER_LEN {
+		return Err(EigenError::KeystoreError);
+	}
+	if rest[0] != KEYSTORE_VERSION {
+		log::error!("Unknown keystore version {}", rest[0]);
+		return Err(EigenError::KeystoreError);
+	}
+	let (header, ciphertext) = bytes.split_at(HEADER_LEN);
+	let fields = &header[KEYSTORE_MAGIC.len() + 1..];
+	let (log_n, fields) = fields.split_at(1);
+	let (r, fields) = fields.split_at(4);
+	let (p, fields) = fields.split_at(4);
+	let (salt, nonce) = fields.split_at(SALT_LEN);
+	let to_u32 = |b: &[u8]| -> Result<u32, EigenError> {
+		let b: [u8; 4] = b.try_into().map_err(|_| EigenError::KeystoreError)?;
+		Ok(u32::from_be_bytes(b))
+	};
+	let params = KdfParams {
+		log_n: log_n[0],
+		r: to_u32(r)?,
+		p: to_u32(p)?,
+	};
+
+	let enc_key = params.derive_key(passphrase, salt)?;
+	let cipher = Aes256Gcm::new(Key::from_slice(&enc_key));
+	let payload = Payload {
+		msg: ciphertext,
+		aad: header,
+	};
+	let plaintext = cipher
+		.decrypt(Nonce::from_slice(nonce), payload)
+		.map_err(|_| EigenError::KeystoreError)?;
+	decode_keypair(&plaintext)
+}
+
+fn decrypt_unversioned(bytes: &[u8], passphrase: &str) -> Result<Keypair, EigenError> {
+	if bytes.len() < SALT_LEN + NONCE_LEN {
+		return Err(EigenError::KeystoreError);
+	}
+	let (salt, rest) = bytes.split_at(SALT_LEN);
+	let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+	let enc_key = DEFAULT_KDF_PARAMS.derive_key(passphrase, salt)?;
+	let cipher = Aes256Gcm::new(Key::from_slice(&enc_key));
+	let plaintext = cipher
+		.decrypt(Nonce::from_slice(nonce), ciphertext)
+		.map_err(|_| EigenError::KeystoreError)?;
+	let plaintext = String::from_utf8(plaintext).map_err(|_| EigenError::KeystoreError)?;
+	let key = bs58::decode(plaintext.trim())
+		.into_vec()
+		.map_err(|_| EigenError::KeystoreError)?;
+	decode_keypair(&key)
+}
+
+/// The keystore file of a keypair.
+#[derive(Clone, Debug)]
+pub struct Keystore {
+	path: PathBuf,
+	bytes: Vec<u8>,
+}
+
+impl Keystore {
+	/// Encrypt the keypair with the passphrase into a new keystore file. Fails
+	/// if the file exists, so a keystore is never overwritten by accident.
+	pub fn create(path: &Path, keypair: &Keypair, passphrase: &str) -> Result<Self, EigenError> {
+		if path.exists() {
+			let e = io::Error::new(ErrorKind::AlreadyExists, "the file exists");
+			return Err(keystore_error("create", path, e));
+		}
+		let bytes = encrypt(keypair, passphrase)?;
+		write_atomic_private(path, &bytes).map_err(|e| keystore_error("write", path, e))?;
+		Ok(Self {
+			path: path.to_path_buf(),
+			bytes,
+		})
+	}
+
+	/// Read the keystore file, without decrypting it.
+	pub fn open(path: &Path) -> Result<Self, EigenError> {
+		let bytes = fs::read(path).map_err(|e| keystore_error("read", path, e))?;
+		Ok(Self {
+			path: path.to_path_buf(),
+			bytes,
+		})
+	}
+
+	/// The path of the keystore file.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// The version of the format of the keystore, 0 for the unversioned ones.
+	pub fn version(&self) -> u8 {
+		match self.bytes.strip_prefix(KEYSTORE_MAGIC) {
+			Some(rest) => rest.first().cloned().unwrap_or(0),
+			None => 0,
+		}
+	}
+
+	/// Decrypt the keypair with the passphrase. Fails if the passphrase is
+	/// wrong, or the file was tampered with.
+	pub fn unlock(&self, passphrase: &str) -> Result<Keypair, EigenError> {
+		decrypt(&self.bytes, passphrase)
+	}
+
+	/// Encrypt the keypair again with a new passphrase, and with a fresh salt,
+	/// in the current format. The file is replaced at once, so it keeps the
+	/// old passphrase if the rotation fails.
+	pub fn rotate(&mut self, passphrase: &str, new_passphrase: &str) -> Result<(), EigenError> {
+		let keypair = self.unlock(passphrase)?;
+		let bytes = encrypt(&keypair, new_passphrase)?;
+		write_atomic_private(&self.path, &bytes)
+			.map_err(|e| keystore_error("rotate", &self.path, e))?;
+		self.bytes = bytes;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn temp_path(name: &str) -> PathBuf {
+		let suffix: u64 = rand::random();
+		std::env::temp_dir().join(format!("eigen-trust-{}-{}.keystore", name, suffix))
+	}
+
+	#[test]
+	fn should_encode_keypairs() {
+		for keypair in [Keypair::generate_secp256k1(), Keypair::generate_ed25519()] {
+			let decoded = decode_keypair(&encode_keypair(&keypair).unwrap()).unwrap();
+			assert_eq!(decoded.public(), keypair.public());
+		}
+		assert!(decode_keypair(&[0x08, 0x02, 0x12, 0x20, 1]).is_err());
+	}
+
+	#[test]
+	fn should_create_unlock_and_rotate() {
+		let path = temp_path("rotate");
+		let keypair = Keypair::generate_secp256k1();
+		let keystore = Keystore::create(&path, &keypair, "first").unwrap();
+		assert_eq!(keystore.version(), KEYSTORE_VERSION);
+		assert!(Keystore::create(&path, &keypair, "first").is_err());
+
+		let mut keystore = Keystore::open(&path).unwrap();
+		assert_eq!(keystore.unlock("first").unwrap().public(), keypair.public());
+		assert!(keystore.unlock("second").is_err());
+
+		assert!(keystore.rotate("wrong", "second").is_err());
+		keystore.rotate("first", "second").unwrap();
+		let keystore = Keystore::open(&path).unwrap();
+		assert!(keystore.unlock("first").is_err());
+		assert_eq!(
+			keystore.unlock("second").unwrap().public(),
+			keypair.public()
+		);
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn should_reject_tampered_header() {
+		let path = temp_path("tampered");
+		let keypair = Keypair::generate_secp256k1();
+		Keystore::create(&path, &keypair, "pass").unwrap();
+
+		// Lowering the cost of scrypt fails the authentication.
+		let mut bytes = fs::read(&path).unwrap();
+		bytes[KEYSTORE_MAGIC.len() + 1] -= 1;
+		fs::write(&path, &bytes).unwrap();
+		assert!(Keystore::open(&path).unwrap().unlock("pass").is_err());
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn should_unlock_and_upgrade_unversioned() {
+		let path = temp_path("unversioned");
+		let keypair = Keypair::generate_secp256k1();
+		let key = bs58::encode(encode_keypair(&keypair).unwrap()).into_string();
+
+		let salt = [1u8; SALT_LEN];
+		let nonce = [2u8; NONCE_LEN];
+		let enc_key = DEFAULT_KDF_PARAMS.derive_key("pass", &salt).unwrap();
+		let cipher = Aes256Gcm::new(Key::from_slice(&enc_key));
+		let ciphertext = cipher
+			.encrypt(Nonce::from_slice(&nonce), key.as_bytes())
+			.unwrap();
+		let mut bytes = salt.to_vec();
+		bytes.extend(nonce);
+		bytes.extend(ciphertext);
+		fs::write(&path, bytes).unwrap();
+
+		let mut keystore = Keystore::open(&path).unwrap();
+		assert_eq!(keystore.version(), 0);
+		assert_eq!(keystore.unlock("pass").unwrap().public(), keypair.public());
+		keystore.rotate("pass", "pass").unwrap();
+		let keystore = Keystore::open(&path).unwrap();
+		assert_eq!(keystore.version(), KEYSTORE_VERSION);
+		assert_eq!(keystore.unlock("pass").unwrap().public(), keypair.public());
+		fs::remove_file(path).unwrap();
+	}
+}
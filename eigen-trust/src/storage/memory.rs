@@ -0,0 +1,71 @@
+//! The [`Store`] kept in the memory.
+
+use super::{Store, Tree, WriteOp};
+use crate::EigenError;
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::{Mutex, MutexGuard},
+};
+
+type Trees = HashMap<Tree, BTreeMap<Vec<u8>, Vec<u8>>>;
+
+/// The store in the memory, lost once dropped. Used to build the snapshots.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+	trees: Mutex<Trees>,
+}
+
+impl MemoryStore {
+	fn trees(&self) -> Result<MutexGuard<Trees>, EigenError> {
+		self.trees.lock().map_err(|_| EigenError::StorageError)
+	}
+}
+
+impl Store for MemoryStore {
+	fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, EigenError> {
+		let trees = self.trees()?;
+		Ok(trees.get(&tree).and_then(|t| t.get(key)).cloned())
+	}
+
+	fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), EigenError> {
+		let mut trees = self.trees()?;
+		trees
+			.entry(tree)
+			.or_default()
+			.insert(key.to_vec(), value.to_vec());
+		Ok(())
+	}
+
+	fn remove(&self, tree: Tree, key: &[u8]) -> Result<(), EigenError> {
+		let mut trees = self.trees()?;
+		if let Some(t) = trees.get_mut(&tree) {
+			t.remove(key);
+		}
+		Ok(())
+	}
+
+	fn records(&self, tree: Tree) -> Result<Vec<(Vec<u8>, Vec<u8>)>, EigenError> {
+		let trees = self.trees()?;
+		let records = trees
+			.get(&tree)
+			.map(|t| t.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+			.unwrap_or_default();
+		Ok(records)
+	}
+
+	fn flush(&self) -> Result<(), EigenError> {
+		Ok(())
+	}
+
+	fn write_batch(&self, batch: &[WriteOp]) -> Result<(), EigenError> {
+		let mut trees = self.trees()?;
+		for op in batch {
+			let tree = trees.entry(op.tree()).or_default();
+			match op {
+				WriteOp::Insert(_, key, value) => tree.insert(key.clone(), value.clone()),
+				WriteOp::Remove(_, key) => tree.remove(key),
+			};
+		}
+		Ok(())
+	}
+}
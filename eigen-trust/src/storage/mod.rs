@@ -0,0 +1,649 @@
+//! The persistence of the node state, so it survives the restarts: the local
+//! scores, the opinions, the address book, the epoch checkpoints and the
+//! accepted epoch configs.
+//!
+//! A [`Store`] is a set of ordered key-value trees. The records are encoded
+//! by the provided methods of the trait, so the backends only deal with the
+//! bytes. The proofs of the opinions are stored once for all the opinions
+//! that have them, by their [`ProofId`]. The store is a [`MemoryStore`] by
+//! default, a `SledStore` with the `sled` feature, or a `SqliteStore` with
+//! the `sqlite` feature, to query the history of the node with SQL. The writes
+//! that change several records are applied at once with
+//! [`Store::write_batch`], and the store is checked with [`recover`] at
+//! startup. The accepted
+//! opinions are also appended to a [`Wal`], that is synced on every write, and
+//! the identity keypair is kept in a [`Keystore`]. All of the files are in a
+//! [`DataDir`], and are replaced with [`write_atomic`]. The trust history of
+//! other nodes, imported from their archives with [`read_archive`], is kept
+//! in [`Tree::History`] and [`Tree::HistoryScores`].
+
+mod archive;
+mod atomic;
+mod data_dir;
+mod keystore;
+mod memory;
+mod prune;
+mod recovery;
+#[cfg(feature = "sled")]
+mod sled_store;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+mod wal;
+
+pub use archive::{read_archive, write_archive, ArchiveInfo, ARCHIVE_VERSION};
+pub use atomic::{write_atomic, write_atomic_private};
+pub use data_dir::{DataDir, DATA_DIR_VERSION};
+pub use keystore::{decode_keypair, encode_keypair, Keystore, KEYSTORE_VERSION};
+pub use memory::MemoryStore;
+pub use prune::{prune, PruneStats, Retention};
+pub use recovery::{recover, RecoveryStats};
+#[cfg(feature = "sled")]
+pub use sled_store::SledStore;
+pub use snapshot::{read_snapshot, write_snapshot, SNAPSHOT_VERSION};
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::SqliteStore;
+pub use wal::{Wal, WalEntry};
+
+use crate::{
+	epoch::{Epoch, EpochConfig},
+	peer::{opinion::Opinion, NetworkConfig},
+	EigenError,
+};
+use libp2p::{Multiaddr, PeerId};
+use sha2::{Digest, Sha256};
+use std::ops::RangeInclusive;
+
+/// The trees of the store, each holding one kind of records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Tree {
+	/// The local scores towards the neighbors, keyed by the PeerId.
+	Scores,
+	/// The opinions sent to the neighbors, keyed by the epoch and the PeerId.
+	LocalOpinions,
+	/// The opinions received from the neighbors, keyed by the epoch and the
+	/// PeerId.
+	NeighborOpinions,
+	/// The results of the verification of the received opinions, keyed by the
+	/// PeerId and the hash of the opinion.
+	Verifications,
+	/// The last addresses the peers were dialed on, keyed by the PeerId.
+	Addresses,
+	/// The global trust scores logged at the start of the epochs, keyed by the
+	/// epoch.
+	Checkpoints,
+	/// The proofs of the opinions, with the number of the opinions referring
+	/// to them, keyed by the [`ProofId`].
+	Proofs,
+	/// The opinions between any peers, imported from the archives, keyed by
+	/// the epoch, the author and the subject. They are kept outside of the
+	/// retention.
+	History,
+	/// The global trust scores of any peers, imported from the archives, keyed
+	/// by the epoch and the PeerId.
+	HistoryScores,
+	/// The accepted announcements of the epoch intervals, keyed by their
+	/// activation epoch.
+	EpochConfigs,
+}
+
+impl Tree {
+	/// All the trees. The snapshots refer to the trees by their index here, so
+	/// the new trees go at the end.
+	pub const ALL: [Tree; 10] = [
+		Tree::Scores,
+		Tree::LocalOpinions,
+		Tree::NeighborOpinions,
+		Tree::Verifications,
+		Tree::Addresses,
+		Tree::Checkpoints,
+		Tree::Proofs,
+		Tree::History,
+		Tree::HistoryScores,
+		Tree::EpochConfigs,
+	];
+
+	/// The name of the tree in the backend.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Tree::Scores => "scores",
+			Tree::LocalOpinions => "local_opinions",
+			Tree::NeighborOpinions => "neighbor_opinions",
+			Tree::Verifications => "verifications",
+			Tree::Addresses => "addresses",
+			Tree::Checkpoints => "checkpoints",
+			Tree::Proofs => "proofs",
+			Tree::History => "history",
+			Tree::HistoryScores => "history_scores",
+			Tree::EpochConfigs => "epoch_configs",
+		}
+	}
+}
+
+/// The backends of the store in the data dir.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+	/// A [`MemoryStore`]. Nothing is persisted, only the write-ahead log is
+	/// kept in the data dir.
+	Memory,
+	/// A `SledStore`, with the `sled` feature.
+	#[cfg(feature = "sled")]
+	Sled,
+	/// A `SqliteStore`, that can be queried with SQL, with the `sqlite`
+	/// feature.
+	#[cfg(feature = "sqlite")]
+	Sqlite,
+}
+
+impl Default for Backend {
+	fn default() -> Self {
+		Backend::Memory
+	}
+}
+
+/// The id of a proof: the SHA-256 of its bytes.
+pub type ProofId = [u8; 32];
+
+/// The prefix of the binary CIDv1 of a proof: the version, the raw codec, and
+/// the multihash header of SHA-256.
+const CID_PREFIX: [u8; 4] = [0x01, 0x55, 0x12, 0x20];
+
+/// The id of the proof.
+pub fn proof_id(proof: &[u8]) -> ProofId {
+	Sha256::digest(proof).into()
+}
+
+/// The id of the proof as a CIDv1 of raw bytes in base58btc, the way the
+/// proofs are referred to outside of the store. It is the same CID that IPFS
+/// gives to the proof bytes.
+pub fn proof_cid(id: &ProofId) -> String {
+	let mut bytes = CID_PREFIX.to_vec();
+	bytes.extend(id);
+	format!("z{}", bs58::encode(bytes).into_string())
+}
+
+/// The first byte of the records of the opinions whose proof is in
+/// [`Tree::Proofs`]. The encoding of an opinion starts with its version, or a
+/// zero, instead.
+const PROOF_REF_TAG: u8 = 0xca;
+
+/// Split the record of an opinion into the id of its proof, if the proof is
+/// referenced, and the opinion. The opinions stored before the proofs were
+/// deduplicated have the proof in the record.
+fn split_opinion_record(
+	value: &[u8],
+) -> Result<(Option<ProofId>, Opinion<NetworkConfig>), EigenError> {
+	match value.split_first() {
+		Some((&PROOF_REF_TAG, rest)) => {
+			if rest.len() < 32 {
+				return Err(EigenError::StorageError);
+			}
+			let (id, opinion) = rest.split_at(32);
+			let id: ProofId = id.try_into().map_err(|_| EigenError::StorageError)?;
+			Ok((Some(id), Opinion::from_bytes(opinion)?))
+		},
+		_ => Ok((None, Opinion::from_bytes(value)?)),
+	}
+}
+
+/// The reference count and the bytes of a record of [`Tree::Proofs`].
+fn split_proof_record(value: &[u8]) -> Result<(u64, &[u8]), EigenError> {
+	if value.len() < 8 {
+		return Err(EigenError::StorageError);
+	}
+	let (refs, proof) = value.split_at(8);
+	Ok((
+		u64::from_be_bytes(refs.try_into().map_err(|_| EigenError::StorageError)?),
+		proof,
+	))
+}
+
+/// A write of a batch, see [`Store::write_batch`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WriteOp {
+	/// Set the value of the key.
+	Insert(Tree, Vec<u8>, Vec<u8>),
+	/// Remove the key.
+	Remove(Tree, Vec<u8>),
+}
+
+impl WriteOp {
+	/// The tree written to.
+	pub fn tree(&self) -> Tree {
+		match self {
+			WriteOp::Insert(tree, ..) | WriteOp::Remove(tree, _) => *tree,
+		}
+	}
+}
+
+/// The write of the record of a proof with the reference count.
+fn proof_record(id: &ProofId, refs: u64, proof: &[u8]) -> WriteOp {
+	let mut value = refs.to_be_bytes().to_vec();
+	value.extend(proof);
+	WriteOp::Insert(Tree::Proofs, id.to_vec(), value)
+}
+
+/// The write adding a reference to the proof, saving it if it isn't saved.
+fn retain_proof<S: Store + ?Sized>(store: &S, proof: &[u8]) -> Result<WriteOp, EigenError> {
+	let id = proof_id(proof);
+	let refs = match store.get(Tree::Proofs, &id)? {
+		Some(value) => split_proof_record(&value)?.0,
+		None => 0,
+	};
+	Ok(proof_record(&id, refs + 1, proof))
+}
+
+/// The write dropping a reference to the proof, removing it with the last
+/// one, and the number of the bytes it removes.
+fn release_proof<S: Store + ?Sized>(
+	store: &S,
+	id: &ProofId,
+) -> Result<Option<(WriteOp, u64)>, EigenError> {
+	let value = match store.get(Tree::Proofs, id)? {
+		Some(value) => value,
+		None => return Ok(None),
+	};
+	let (refs, proof) = split_proof_record(&value)?;
+	if refs > 1 {
+		return Ok(Some((proof_record(id, refs - 1, proof), 0)));
+	}
+	let removed = (id.len() + value.len()) as u64;
+	Ok(Some((WriteOp::Remove(Tree::Proofs, id.to_vec()), removed)))
+}
+
+/// The writes saving the opinion at the key of the tree, with the proof saved
+/// by its id, and dropping the reference to the proof of the opinion it
+/// replaces.
+fn opinion_writes<S: Store + ?Sized>(
+	store: &S,
+	tree: Tree,
+	key: Vec<u8>,
+	opinion: &Opinion<NetworkConfig>,
+) -> Result<Vec<WriteOp>, EigenError> {
+	let old_id = match store.get(tree, &key)? {
+		Some(old) => split_opinion_record(&old)?.0,
+		None => None,
+	};
+	let new_id = (!opinion.proof_bytes.is_empty()).then(|| proof_id(&opinion.proof_bytes));
+
+	// The references only change if the proof does.
+	let mut batch = Vec::new();
+	let value = match new_id {
+		Some(id) => {
+			if old_id != new_id {
+				batch.push(retain_proof(store, &opinion.proof_bytes)?);
+			}
+			let mut value = vec![PROOF_REF_TAG];
+			value.extend(id);
+			value.extend(opinion.without_proof().to_bytes());
+			value
+		},
+		None => opinion.to_bytes(),
+	};
+	batch.push(WriteOp::Insert(tree, key, value));
+	if let Some(id) = old_id.filter(|id| Some(*id) != new_id) {
+		batch.extend(release_proof(store, &id)?.map(|(op, _)| op));
+	}
+	Ok(batch)
+}
+
+/// The key of an opinion: the epoch first, so the records are ordered by it.
+fn opinion_key(peer_id: &PeerId, epoch: Epoch) -> Vec<u8> {
+	let mut key = epoch.0.to_be_bytes().to_vec();
+	key.extend(peer_id.to_bytes());
+	key
+}
+
+/// The key of the result of a verification: the PeerId, and the hash of the
+/// opinion.
+fn verification_key(peer_id: &PeerId, hash: &[u8; 32]) -> Vec<u8> {
+	let mut key = peer_id.to_bytes();
+	key.extend(hash);
+	key
+}
+
+fn decode_epoch(bytes: &[u8]) -> Result<Epoch, EigenError> {
+	let bytes: [u8; 8] = bytes.try_into().map_err(|_| EigenError::StorageError)?;
+	Ok(Epoch(u64::from_be_bytes(bytes)))
+}
+
+fn decode_peer_id(bytes: &[u8]) -> Result<PeerId, EigenError> {
+	PeerId::from_bytes(bytes).map_err(|_| EigenError::StorageError)
+}
+
+fn decode_opinion_key(key: &[u8]) -> Result<(PeerId, Epoch), EigenError> {
+	if key.len() < 8 {
+		return Err(EigenError::StorageError);
+	}
+	let (epoch, peer_id) = key.split_at(8);
+	Ok((decode_peer_id(peer_id)?, decode_epoch(epoch)?))
+}
+
+/// The key of an opinion of the history: the epoch, the length of the PeerId
+/// of the author, the author, and the subject.
+fn history_key(epoch: Epoch, author: &PeerId, subject: &PeerId) -> Vec<u8> {
+	let author = author.to_bytes();
+	let mut key = epoch.0.to_be_bytes().to_vec();
+	key.push(author.len() as u8);
+	key.extend(author);
+	key.extend(subject.to_bytes());
+	key
+}
+
+fn decode_history_key(key: &[u8]) -> Result<(Epoch, PeerId, PeerId), EigenError> {
+	if key.len() < 9 {
+		return Err(EigenError::StorageError);
+	}
+	let (epoch, rest) = key.split_at(8);
+	let (author_len, rest) = rest.split_at(1);
+	let author_len = usize::from(author_len[0]);
+	if rest.len() < author_len {
+		return Err(EigenError::StorageError);
+	}
+	let (author, subject) = rest.split_at(author_len);
+	Ok((
+		decode_epoch(epoch)?,
+		decode_peer_id(author)?,
+		decode_peer_id(subject)?,
+	))
+}
+
+/// The key of a global trust score of the history: the epoch, and the PeerId.
+fn history_score_key(epoch: Epoch, peer_id: &PeerId) -> Vec<u8> {
+	opinion_key(peer_id, epoch)
+}
+
+fn decode_history_score_key(key: &[u8]) -> Result<(Epoch, PeerId), EigenError> {
+	let (peer_id, epoch) = decode_opinion_key(key)?;
+	Ok((epoch, peer_id))
+}
+
+/// Take the next `len` bytes of the reader, or fail with the error of the
+/// file being read, like [`EigenError::InvalidArchive`].
+pub(crate) fn take<'a>(
+	reader: &mut &'a [u8],
+	len: usize,
+	err: fn() -> EigenError,
+) -> Result<&'a [u8], EigenError> {
+	if reader.len() < len {
+		return Err(err());
+	}
+	let (bytes, rest) = reader.split_at(len);
+	*reader = rest;
+	Ok(bytes)
+}
+
+/// Take the next `N` bytes of the reader, see [`take`].
+pub(crate) fn read_array<const N: usize>(
+	reader: &mut &[u8],
+	err: fn() -> EigenError,
+) -> Result<[u8; N], EigenError> {
+	take(reader, N, err)?.try_into().map_err(|_| err())
+}
+
+/// The backend of the persistence. The trees are ordered by the key.
+pub trait Store: Send + Sync {
+	/// Get the value of the key.
+	fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, EigenError>;
+	/// Set the value of the key.
+	fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), EigenError>;
+	/// Remove the key, if it is there.
+	fn remove(&self, tree: Tree, key: &[u8]) -> Result<(), EigenError>;
+	/// All the records of the tree, ordered by the key.
+	fn records(&self, tree: Tree) -> Result<Vec<(Vec<u8>, Vec<u8>)>, EigenError>;
+	/// Write the pending changes to the disk.
+	fn flush(&self) -> Result<(), EigenError>;
+	/// Apply the writes at once: after a crash, the store has either all or
+	/// none of them.
+	fn write_batch(&self, batch: &[WriteOp]) -> Result<(), EigenError>;
+
+	/// The size of the store on the disk, if the backend knows it.
+	fn size_on_disk(&self) -> Result<Option<u64>, EigenError> {
+		Ok(None)
+	}
+
+	/// Remove all the records of the tree.
+	fn clear(&self, tree: Tree) -> Result<(), EigenError> {
+		for (key, _) in self.records(tree)? {
+			self.remove(tree, &key)?;
+		}
+		Ok(())
+	}
+
+	/// Save the local score towards the neighbor.
+	fn save_score(&self, peer_id: &PeerId, score: u32) -> Result<(), EigenError> {
+		self.insert(Tree::Scores, &peer_id.to_bytes(), &score.to_be_bytes())
+	}
+
+	/// Load the local scores towards the neighbors.
+	fn load_scores(&self) -> Result<Vec<(PeerId, u32)>, EigenError> {
+		self.records(Tree::Scores)?
+			.into_iter()
+			.map(|(key, value)| {
+				let score: [u8; 4] = value
+					.as_slice()
+					.try_into()
+					.map_err(|_| EigenError::StorageError)?;
+				Ok((decode_peer_id(&key)?, u32::from_be_bytes(score)))
+			})
+			.collect()
+	}
+
+	/// Load the proof with the id.
+	fn load_proof(&self, id: &ProofId) -> Result<Option<Vec<u8>>, EigenError> {
+		match self.get(Tree::Proofs, id)? {
+			Some(value) => Ok(Some(split_proof_record(&value)?.1.to_vec())),
+			None => Ok(None),
+		}
+	}
+
+	/// Decode the record of an opinion, with its proof.
+	fn decode_opinion(&self, value: &[u8]) -> Result<Opinion<NetworkConfig>, EigenError> {
+		let (id, mut opinion) = split_opinion_record(value)?;
+		if let Some(id) = id {
+			opinion.proof_bytes = self.load_proof(&id)?.ok_or_else(|| {
+				log::error!("The proof {} is missing from the store", proof_cid(&id));
+				EigenError::StorageError
+			})?;
+		}
+		Ok(opinion)
+	}
+
+	/// Save the opinion exchanged with the peer in the epoch, into the tree of
+	/// the local or the neighbor opinions. The proof is saved by its id.
+	fn save_opinion(
+		&self,
+		tree: Tree,
+		key: &(PeerId, Epoch),
+		opinion: &Opinion<NetworkConfig>,
+	) -> Result<(), EigenError> {
+		let (peer_id, epoch) = key;
+		let key = opinion_key(peer_id, *epoch);
+		self.write_batch(&opinion_writes(self, tree, key, opinion)?)
+	}
+
+	/// Remove the opinion exchanged with the peer in the epoch.
+	fn remove_opinion(&self, tree: Tree, key: &(PeerId, Epoch)) -> Result<(), EigenError> {
+		let (peer_id, epoch) = key;
+		let key = opinion_key(peer_id, *epoch);
+		if let Some(old) = self.get(tree, &key)? {
+			let mut batch = Vec::new();
+			if let (Some(id), _) = split_opinion_record(&old)? {
+				batch.extend(release_proof(self, &id)?.map(|(op, _)| op));
+			}
+			batch.push(WriteOp::Remove(tree, key));
+			self.write_batch(&batch)?;
+		}
+		Ok(())
+	}
+
+	/// Load the opinions of the tree, ordered by the epoch.
+	#[allow(clippy::type_complexity)]
+	fn load_opinions(
+		&self,
+		tree: Tree,
+	) -> Result<Vec<((PeerId, Epoch), Opinion<NetworkConfig>)>, EigenError> {
+		self.records(tree)?
+			.into_iter()
+			.map(|(key, value)| Ok((decode_opinion_key(&key)?, self.decode_opinion(&value)?)))
+			.collect()
+	}
+
+	/// Save the result of the verification of the opinion with the hash.
+	fn save_verification(
+		&self,
+		peer_id: &PeerId,
+		hash: &[u8; 32],
+		valid: bool,
+	) -> Result<(), EigenError> {
+		let key = verification_key(peer_id, hash);
+		self.insert(Tree::Verifications, &key, &[u8::from(valid)])
+	}
+
+	/// Load the result of the verification of the opinion with the hash.
+	fn load_verification(
+		&self,
+		peer_id: &PeerId,
+		hash: &[u8; 32],
+	) -> Result<Option<bool>, EigenError> {
+		let value = self.get(Tree::Verifications, &verification_key(peer_id, hash))?;
+		Ok(value.map(|value| value == [1]))
+	}
+
+	/// Remove the result of the verification of the opinion with the hash.
+	fn remove_verification(&self, peer_id: &PeerId, hash: &[u8; 32]) -> Result<(), EigenError> {
+		self.remove(Tree::Verifications, &verification_key(peer_id, hash))
+	}
+
+	/// Load the results of the verifications.
+	fn load_verifications(&self) -> Result<Vec<(PeerId, [u8; 32], bool)>, EigenError> {
+		self.records(Tree::Verifications)?
+			.into_iter()
+			.map(|(key, value)| {
+				if key.len() < 32 {
+					return Err(EigenError::StorageError);
+				}
+				let (peer_id, hash) = key.split_at(key.len() - 32);
+				let hash: [u8; 32] = hash.try_into().map_err(|_| EigenError::StorageError)?;
+				Ok((decode_peer_id(peer_id)?, hash, value == [1]))
+			})
+			.collect()
+	}
+
+	/// Save the address the peer was dialed on.
+	fn save_address(&self, peer_id: &PeerId, addr: &Multiaddr) -> Result<(), EigenError> {
+		self.insert(Tree::Addresses, &peer_id.to_bytes(), &addr.to_vec())
+	}
+
+	/// Load the address book.
+	fn load_addresses(&self) -> Result<Vec<(PeerId, Multiaddr)>, EigenError> {
+		self.records(Tree::Addresses)?
+			.into_iter()
+			.map(|(key, value)| {
+				let addr = Multiaddr::try_from(value).map_err(|_| EigenError::StorageError)?;
+				Ok((decode_peer_id(&key)?, addr))
+			})
+			.collect()
+	}
+
+	/// Save the global trust score logged at the start of the epoch.
+	fn save_checkpoint(&self, epoch: Epoch, score: f64) -> Result<(), EigenError> {
+		self.insert(
+			Tree::Checkpoints,
+			&epoch.0.to_be_bytes(),
+			&score.to_be_bytes(),
+		)
+	}
+
+	/// Load the checkpoints, ordered by the epoch.
+	fn load_checkpoints(&self) -> Result<Vec<(Epoch, f64)>, EigenError> {
+		self.records(Tree::Checkpoints)?
+			.into_iter()
+			.map(|(key, value)| {
+				let score: [u8; 8] = value
+					.as_slice()
+					.try_into()
+					.map_err(|_| EigenError::StorageError)?;
+				Ok((decode_epoch(&key)?, f64::from_be_bytes(score)))
+			})
+			.collect()
+	}
+
+	/// Save the accepted epoch config.
+	fn save_epoch_config(&self, config: &EpochConfig) -> Result<(), EigenError> {
+		self.insert(
+			Tree::EpochConfigs,
+			&config.activation.to_be_bytes(),
+			&config.to_bytes()?,
+		)
+	}
+
+	/// Load the accepted epoch configs, ordered by the activation epoch.
+	fn load_epoch_configs(&self) -> Result<Vec<EpochConfig>, EigenError> {
+		self.records(Tree::EpochConfigs)?
+			.into_iter()
+			.map(|(_, value)| EpochConfig::from_bytes(&value).map_err(|_| EigenError::StorageError))
+			.collect()
+	}
+
+	/// Save the opinion of the author about the subject in the epoch, into the
+	/// history. The proof is saved by its id.
+	fn save_history_opinion(
+		&self,
+		epoch: Epoch,
+		author: &PeerId,
+		subject: &PeerId,
+		opinion: &Opinion<NetworkConfig>,
+	) -> Result<(), EigenError> {
+		let key = history_key(epoch, author, subject);
+		self.write_batch(&opinion_writes(self, Tree::History, key, opinion)?)
+	}
+
+	/// Load the opinions of the history in the epochs, as the epoch, the
+	/// author and the subject, ordered by the epoch.
+	#[allow(clippy::type_complexity)]
+	fn load_history(
+		&self,
+		epochs: RangeInclusive<Epoch>,
+	) -> Result<Vec<((Epoch, PeerId, PeerId), Opinion<NetworkConfig>)>, EigenError> {
+		let mut history = Vec::new();
+		for (key, value) in self.records(Tree::History)? {
+			let key = decode_history_key(&key)?;
+			if epochs.contains(&key.0) {
+				history.push((key, self.decode_opinion(&value)?));
+			}
+		}
+		Ok(history)
+	}
+
+	/// Save the global trust score of the peer in the epoch, into the history.
+	fn save_history_score(
+		&self,
+		epoch: Epoch,
+		peer_id: &PeerId,
+		score: f64,
+	) -> Result<(), EigenError> {
+		let key = history_score_key(epoch, peer_id);
+		self.insert(Tree::HistoryScores, &key, &score.to_be_bytes())
+	}
+
+	/// Load the global trust scores of the history in the epochs, ordered by
+	/// the epoch.
+	fn load_history_scores(
+		&self,
+		epochs: RangeInclusive<Epoch>,
+	) -> Result<Vec<(Epoch, PeerId, f64)>, EigenError> {
+		let mut scores = Vec::new();
+		for (key, value) in self.records(Tree::HistoryScores)? {
+			let (epoch, peer_id) = decode_history_score_key(&key)?;
+			let score: [u8; 8] = value
+				.as_slice()
+				.try_into()
+				.map_err(|_| EigenError::StorageError)?;
+			if epochs.contains(&epoch) {
+				scores.push((epoch, peer_id, f64::from_be_bytes(score)));
+			}
+		}
+		Ok(scores)
+	}
+}
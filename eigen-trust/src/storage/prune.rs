@@ -0,0 +1,173 @@
+//! The pruning of the records outside of the retention, so the store of a
+//! long-running node doesn't grow without a bound.
+
+use super::{
+	decode_epoch, decode_opinion_key, release_proof, split_opinion_record, verification_key, Store,
+	Tree, WriteOp,
+};
+use crate::{epoch::Epoch, EigenError};
+
+/// How many of the last epochs of the data are kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Retention {
+	/// The epochs of the opinions and the checkpoints.
+	pub opinion_epochs: u64,
+	/// The epochs of the proofs of the opinions. The older opinions are kept
+	/// without their proofs.
+	pub proof_epochs: u64,
+}
+
+impl Default for Retention {
+	fn default() -> Self {
+		Self {
+			opinion_epochs: 90,
+			proof_epochs: 10,
+		}
+	}
+}
+
+/// What a pruning run removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PruneStats {
+	/// The number of removed opinions.
+	pub opinions: usize,
+	/// The number of opinions whose proofs were removed.
+	pub proofs: usize,
+	/// The number of removed checkpoints.
+	pub checkpoints: usize,
+	/// The bytes of the removed records and proofs. A proof shared by several
+	/// opinions is only removed with the last of them.
+	pub reclaimed_bytes: u64,
+	/// The size of the store on the disk after the run, if the backend knows
+	/// it.
+	pub size_on_disk: Option<u64>,
+}
+
+/// Remove the opinions and the checkpoints of the epochs before
+/// `opinions_before`, and the proofs of the opinions before `proofs_before`.
+/// The results of the verification of the opinions follow them.
+pub fn prune(
+	store: &dyn Store,
+	opinions_before: Epoch,
+	proofs_before: Epoch,
+) -> Result<PruneStats, EigenError> {
+	let mut stats = PruneStats::default();
+
+	for tree in [Tree::LocalOpinions, Tree::NeighborOpinions] {
+		// The records are ordered by the epoch.
+		for (key, value) in store.records(tree)? {
+			let (peer_id, epoch) = decode_opinion_key(&key)?;
+			if epoch >= proofs_before && epoch >= opinions_before {
+				break;
+			}
+			let (proof_id, _) = split_opinion_record(&value)?;
+			let opinion = store.decode_opinion(&value)?;
+			// The changes of an opinion are written at once, so a crash doesn't
+			// leave a reference to a removed proof.
+			let mut batch = Vec::new();
+			let old_verification = verification_key(&peer_id, &opinion.hash());
+			if epoch < opinions_before {
+				batch.push(WriteOp::Remove(tree, key.clone()));
+				if tree == Tree::NeighborOpinions {
+					batch.push(WriteOp::Remove(Tree::Verifications, old_verification));
+				}
+				stats.opinions += 1;
+				stats.reclaimed_bytes += (key.len() + value.len()) as u64;
+			} else if !opinion.proof_bytes.is_empty() {
+				let stripped = opinion.without_proof();
+				batch.push(WriteOp::Insert(tree, key.clone(), stripped.to_bytes()));
+				if tree == Tree::NeighborOpinions {
+					// The hash covers the proof, so the result is moved to the new one.
+					if let Some(valid) = store.get(Tree::Verifications, &old_verification)? {
+						let new_verification = verification_key(&peer_id, &stripped.hash());
+						batch.push(WriteOp::Remove(Tree::Verifications, old_verification));
+						batch.push(WriteOp::Insert(
+							Tree::Verifications,
+							new_verification,
+							valid,
+						));
+					}
+				}
+				stats.proofs += 1;
+				if proof_id.is_none() {
+					stats.reclaimed_bytes += opinion.proof_bytes.len() as u64;
+				}
+			} else {
+				continue;
+			}
+			// The proof is removed with the last opinion referring to it.
+			if let Some(id) = proof_id {
+				if let Some((op, removed)) = release_proof(store, &id)? {
+					batch.push(op);
+					stats.reclaimed_bytes += removed;
+				}
+			}
+			store.write_batch(&batch)?;
+		}
+	}
+
+	for (key, value) in store.records(Tree::Checkpoints)? {
+		if decode_epoch(&key)? >= opinions_before {
+			break;
+		}
+		store.remove(Tree::Checkpoints, &key)?;
+		stats.checkpoints += 1;
+		stats.reclaimed_bytes += (key.len() + value.len()) as u64;
+	}
+
+	store.flush()?;
+	stats.size_on_disk = store.size_on_disk()?;
+	Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{peer::opinion::Opinion, storage::MemoryStore};
+	use eigen_trust_circuit::{ecdsa::SigData, halo2wrong::curves::secp256k1::Fq};
+	use libp2p::PeerId;
+
+	#[test]
+	fn should_prune_by_epoch() {
+		let store = MemoryStore::default();
+		let peer_id = PeerId::random();
+		let opinion = |k: u64| Opinion::new(Epoch(k), SigData::<Fq>::empty(), 0.5, vec![7; 100]);
+		for k in 1..=6 {
+			let op = opinion(k);
+			store
+				.save_opinion(Tree::NeighborOpinions, &(peer_id, Epoch(k)), &op)
+				.unwrap();
+			store.save_verification(&peer_id, &op.hash(), true).unwrap();
+			store
+				.save_opinion(Tree::LocalOpinions, &(peer_id, Epoch(k)), &op)
+				.unwrap();
+			store.save_checkpoint(Epoch(k), 0.5).unwrap();
+		}
+
+		let stats = prune(&store, Epoch(3), Epoch(5)).unwrap();
+		assert_eq!(stats.opinions, 4);
+		assert_eq!(stats.proofs, 4);
+		assert_eq!(stats.checkpoints, 2);
+		assert!(stats.reclaimed_bytes >= 4 * 100);
+		assert_eq!(stats.size_on_disk, None);
+
+		let neighbor_opinions = store.load_opinions(Tree::NeighborOpinions).unwrap();
+		let epochs: Vec<u64> = neighbor_opinions.iter().map(|((_, k), _)| k.0).collect();
+		assert_eq!(epochs, vec![3, 4, 5, 6]);
+		for ((_, k), op) in &neighbor_opinions {
+			assert_eq!(op.proof_bytes.is_empty(), k.0 < 5);
+			assert_eq!(
+				store.load_verification(&peer_id, &op.hash()).unwrap(),
+				Some(true)
+			);
+		}
+		assert_eq!(store.load_verifications().unwrap().len(), 4);
+		assert_eq!(store.load_checkpoints().unwrap().len(), 4);
+		// The proof is kept once, for the opinions of the last two epochs.
+		assert_eq!(store.records(Tree::Proofs).unwrap().len(), 1);
+
+		// Nothing is left to prune.
+		let stats = prune(&store, Epoch(3), Epoch(5)).unwrap();
+		assert_eq!(stats.reclaimed_bytes, 0);
+	}
+}
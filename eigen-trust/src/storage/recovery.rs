@@ -0,0 +1,352 @@
+//! The recovery pass run over the store at startup. The writes of the node
+//! are atomic, but a store written by an older version, or by a backend that
+//! lost its latest writes in a crash, can still have records that can't be
+//! decoded, opinions referring to missing proofs, or reference counts that
+//! don't match the opinions. They are discarded, or repaired, before the
+//! state is loaded.
+
+use super::{
+	decode_epoch, decode_history_key, decode_history_score_key, decode_opinion_key, decode_peer_id,
+	proof_cid, proof_id, proof_record, split_opinion_record, split_proof_record, ProofId, Store,
+	Tree, WriteOp,
+};
+use crate::{epoch::EpochConfig, EigenError};
+use libp2p::Multiaddr;
+use std::collections::HashMap;
+
+/// What the recovery pass discarded or repaired.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecoveryStats {
+	/// The number of the discarded records that couldn't be decoded, or
+	/// whose content didn't match their key.
+	pub torn_records: usize,
+	/// The number of the discarded opinions whose proof was missing.
+	pub dangling_opinions: usize,
+	/// The number of the proofs whose reference count was corrected, or that
+	/// were removed as no opinion referred to them.
+	pub repaired_proofs: usize,
+}
+
+impl RecoveryStats {
+	/// Returns true if nothing was discarded or repaired.
+	pub fn is_clean(&self) -> bool {
+		*self == Self::default()
+	}
+}
+
+/// Check that the record of the tree can be decoded. The opinions and the
+/// proofs are checked by [`recover`].
+fn check_record(tree: Tree, key: &[u8], value: &[u8]) -> Result<(), EigenError> {
+	match tree {
+		Tree::Scores => {
+			decode_peer_id(key)?;
+			if value.len() != 4 {
+				return Err(EigenError::StorageError);
+			}
+		},
+		Tree::Verifications => {
+			if key.len() < 32 || value.len() != 1 {
+				return Err(EigenError::StorageError);
+			}
+			decode_peer_id(&key[..key.len() - 32])?;
+		},
+		Tree::Addresses => {
+			decode_peer_id(key)?;
+			Multiaddr::try_from(value.to_vec()).map_err(|_| EigenError::StorageError)?;
+		},
+		Tree::Checkpoints => {
+			decode_epoch(key)?;
+			if value.len() != 8 {
+				return Err(EigenError::StorageError);
+			}
+		},
+		Tree::HistoryScores => {
+			decode_history_score_key(key)?;
+			if value.len() != 8 {
+				return Err(EigenError::StorageError);
+			}
+		},
+		Tree::EpochConfigs => {
+			decode_epoch(key)?;
+			EpochConfig::from_bytes(value).map_err(|_| EigenError::StorageError)?;
+		},
+		Tree::LocalOpinions | Tree::NeighborOpinions | Tree::History | Tree::Proofs => {},
+	}
+	Ok(())
+}
+
+fn discard(tree: Tree, key: Vec<u8>, batch: &mut Vec<WriteOp>) {
+	log::warn!("Discarding a torn record of the {} tree", tree.name());
+	batch.push(WriteOp::Remove(tree, key));
+}
+
+/// Discard the records of the store that can't be decoded, and the opinions
+/// whose proof is missing, and set the reference counts of the proofs to the
+/// number of the opinions referring to them. The changes are written at once.
+pub fn recover(store: &dyn Store) -> Result<RecoveryStats, EigenError> {
+	let mut stats = RecoveryStats::default();
+	let mut batch = Vec::new();
+
+	for tree in [
+		Tree::Scores,
+		Tree::Verifications,
+		Tree::Addresses,
+		Tree::Checkpoints,
+		Tree::HistoryScores,
+		Tree::EpochConfigs,
+	] {
+		for (key, value) in store.records(tree)? {
+			if check_record(tree, &key, &value).is_err() {
+				discard(tree, key, &mut batch);
+				stats.torn_records += 1;
+			}
+		}
+	}
+
+	// The proofs whose bytes match their id, with their reference counts.
+	let mut proofs: HashMap<ProofId, (u64, Vec<u8>)> = HashMap::new();
+	for (key, value) in store.records(Tree::Proofs)? {
+		let record = ProofId::try_from(key.as_slice())
+			.ok()
+			.zip(split_proof_record(&value).ok())
+			.filter(|(id, (_, proof))| proof_id(proof) == *id);
+		match record {
+			Some((id, (refs, proof))) => {
+				proofs.insert(id, (refs, proof.to_vec()));
+			},
+			None => {
+				discard(Tree::Proofs, key, &mut batch);
+				stats.torn_records += 1;
+			},
+		}
+	}
+
+	let mut refs: HashMap<ProofId, u64> = HashMap::new();
+	for tree in [Tree::LocalOpinions, Tree::NeighborOpinions, Tree::History] {
+		for (key, value) in store.records(tree)? {
+			let key_check = match tree {
+				Tree::History => decode_history_key(&key).map(|_| ()),
+				_ => decode_opinion_key(&key).map(|_| ()),
+			};
+			let record = key_check.and_then(|_| split_opinion_record(&value));
+			match record {
+				Ok((Some(id), _)) if !proofs.contains_key(&id) => {
+					log::warn!(
+						"Discarding an opinion of the {} tree, its proof {} is missing",
+						tree.name(),
+						proof_cid(&id)
+					);
+					batch.push(WriteOp::Remove(tree, key));
+					stats.dangling_opinions += 1;
+				},
+				Ok((Some(id), _)) => *refs.entry(id).or_default() += 1,
+				Ok((None, _)) => {},
+				Err(_) => {
+					discard(tree, key, &mut batch);
+					stats.torn_records += 1;
+				},
+			}
+		}
+	}
+
+	for (id, (stored_refs, proof)) in &proofs {
+		match refs.get(id) {
+			Some(refs) if refs == stored_refs => {},
+			Some(refs) => {
+				batch.push(proof_record(id, *refs, proof));
+				stats.repaired_proofs += 1;
+			},
+			None => {
+				batch.push(WriteOp::Remove(Tree::Proofs, id.to_vec()));
+				stats.repaired_proofs += 1;
+			},
+		}
+	}
+
+	if !batch.is_empty() {
+		store.write_batch(&batch)?;
+		store.flush()?;
+	}
+	Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		epoch::Epoch,
+		peer::{opinion::Opinion, NetworkConfig},
+		storage::{prune, MemoryStore},
+	};
+	use eigen_trust_circuit::{ecdsa::SigData, halo2wrong::curves::secp256k1::Fq};
+	use libp2p::PeerId;
+	use std::sync::Mutex;
+
+	/// A store that crashes after a number of writes: the writes after it
+	/// fail. Unless the batches are atomic, they are written one record at a
+	/// time, like by a backend without transactions, so a crash tears them.
+	struct FaultyStore {
+		inner: MemoryStore,
+		writes_left: Mutex<usize>,
+		atomic: bool,
+	}
+
+	impl FaultyStore {
+		fn new(writes: usize, atomic: bool) -> Self {
+			Self {
+				inner: MemoryStore::default(),
+				writes_left: Mutex::new(writes),
+				atomic,
+			}
+		}
+
+		/// Take `count` writes from the budget, or crash.
+		fn take_writes(&self, count: usize) -> Result<(), EigenError> {
+			let mut writes_left = self.writes_left.lock().unwrap();
+			if *writes_left < count {
+				*writes_left = 0;
+				return Err(EigenError::StorageError);
+			}
+			*writes_left -= count;
+			Ok(())
+		}
+	}
+
+	impl Store for FaultyStore {
+		fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, EigenError> {
+			self.inner.get(tree, key)
+		}
+
+		fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), EigenError> {
+			self.take_writes(1)?;
+			self.inner.insert(tree, key, value)
+		}
+
+		fn remove(&self, tree: Tree, key: &[u8]) -> Result<(), EigenError> {
+			self.take_writes(1)?;
+			self.inner.remove(tree, key)
+		}
+
+		fn records(&self, tree: Tree) -> Result<Vec<(Vec<u8>, Vec<u8>)>, EigenError> {
+			self.inner.records(tree)
+		}
+
+		fn flush(&self) -> Result<(), EigenError> {
+			Ok(())
+		}
+
+		fn write_batch(&self, batch: &[WriteOp]) -> Result<(), EigenError> {
+			if self.atomic {
+				self.take_writes(batch.len())?;
+				return self.inner.write_batch(batch);
+			}
+			for op in batch {
+				match op {
+					WriteOp::Insert(tree, key, value) => self.insert(*tree, key, value)?,
+					WriteOp::Remove(tree, key) => self.remove(*tree, key)?,
+				}
+			}
+			Ok(())
+		}
+	}
+
+	/// Save and replace opinions sharing a proof, and prune them, until the
+	/// store crashes.
+	fn run_until_crash(store: &dyn Store) -> Result<(), EigenError> {
+		let peer_1 = PeerId::random();
+		let peer_2 = PeerId::random();
+		let sig = SigData::<Fq>::empty();
+		let opinion =
+			|k, proof: &[u8]| Opinion::<NetworkConfig>::new(Epoch(k), sig, 0.5, proof.to_vec());
+		for k in 1..=4 {
+			let op = opinion(k, &[1; 32]);
+			store.save_opinion(Tree::NeighborOpinions, &(peer_1, Epoch(k)), &op)?;
+			store.save_verification(&peer_1, &op.hash(), true)?;
+			store.save_opinion(Tree::LocalOpinions, &(peer_2, Epoch(k)), &op)?;
+		}
+		store.save_opinion(
+			Tree::LocalOpinions,
+			&(peer_2, Epoch(4)),
+			&opinion(4, &[2; 32]),
+		)?;
+		store.remove_opinion(Tree::NeighborOpinions, &(peer_1, Epoch(4)))?;
+		prune(store, Epoch(2), Epoch(3))?;
+		Ok(())
+	}
+
+	/// Check that the state can be loaded, and nothing is left to recover.
+	fn assert_consistent(store: &dyn Store) {
+		store.load_opinions(Tree::LocalOpinions).unwrap();
+		store.load_opinions(Tree::NeighborOpinions).unwrap();
+		store.load_verifications().unwrap();
+		assert!(recover(store).unwrap().is_clean());
+	}
+
+	#[test]
+	fn should_discard_torn_records() {
+		let store = MemoryStore::default();
+		let peer_id = PeerId::random();
+		let sig = SigData::<Fq>::empty();
+		let op = Opinion::<NetworkConfig>::new(Epoch(1), sig, 0.5, vec![1; 32]);
+		store
+			.save_opinion(Tree::NeighborOpinions, &(peer_id, Epoch(1)), &op)
+			.unwrap();
+		store.save_checkpoint(Epoch(1), 0.5).unwrap();
+		assert!(recover(&store).unwrap().is_clean());
+
+		// A truncated opinion, a checkpoint, and a proof whose bytes don't
+		// match its id, which leaves the saved opinion dangling.
+		let mut key = Epoch(2).0.to_be_bytes().to_vec();
+		key.extend(peer_id.to_bytes());
+		let bytes = op.without_proof().to_bytes();
+		store
+			.insert(Tree::NeighborOpinions, &key, &bytes[..bytes.len() / 2])
+			.unwrap();
+		store
+			.insert(Tree::Checkpoints, &Epoch(2).0.to_be_bytes(), &[0; 3])
+			.unwrap();
+		let id = proof_id(&[1; 32]);
+		store
+			.insert(Tree::Proofs, &id, &[0, 0, 0, 0, 0, 0, 0, 1, 2])
+			.unwrap();
+
+		let stats = recover(&store).unwrap();
+		assert_eq!(stats, RecoveryStats {
+			torn_records: 3,
+			dangling_opinions: 1,
+			repaired_proofs: 0,
+		});
+		assert!(store
+			.load_opinions(Tree::NeighborOpinions)
+			.unwrap()
+			.is_empty());
+		assert_eq!(store.load_checkpoints().unwrap(), vec![(Epoch(1), 0.5)]);
+		assert_consistent(&store);
+	}
+
+	#[test]
+	fn should_recover_from_torn_batches() {
+		// Crashing after every number of writes, until the run completes.
+		for writes in 0.. {
+			let store = FaultyStore::new(writes, false);
+			let completed = run_until_crash(&store).is_ok();
+			recover(&store.inner).unwrap();
+			assert_consistent(&store.inner);
+			if completed {
+				break;
+			}
+		}
+	}
+
+	#[test]
+	fn should_not_tear_atomic_batches() {
+		for writes in 0.. {
+			let store = FaultyStore::new(writes, true);
+			let completed = run_until_crash(&store).is_ok();
+			assert_consistent(&store.inner);
+			if completed {
+				break;
+			}
+		}
+	}
+}
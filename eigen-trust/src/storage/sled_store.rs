@@ -0,0 +1,243 @@
+//! The [`Store`] backed by a sled database.
+
+use super::{Store, Tree, WriteOp};
+use crate::EigenError;
+use sled::{
+	transaction::{ConflictableTransactionResult, TransactionError},
+	Transactional,
+};
+use std::path::Path;
+
+/// The store in a sled database, with a sled tree for each [`Tree`].
+#[derive(Clone, Debug)]
+pub struct SledStore {
+	db: sled::Db,
+}
+
+impl SledStore {
+	/// Open the database in the directory, creating it if it doesn't exist.
+	pub fn open(path: &Path) -> Result<Self, EigenError> {
+		let db = sled::open(path).map_err(|e| {
+			log::error!("Failed to open the store at {:?}: {}", path, e);
+			EigenError::StorageError
+		})?;
+		Ok(Self { db })
+	}
+
+	/// Open a database that is removed once dropped.
+	pub fn temporary() -> Result<Self, EigenError> {
+		let db = sled::Config::new()
+			.temporary(true)
+			.open()
+			.map_err(|_| EigenError::StorageError)?;
+		Ok(Self { db })
+	}
+
+	fn tree(&self, tree: Tree) -> Result<sled::Tree, EigenError> {
+		self.db.open_tree(tree.name()).map_err(|e| {
+			log::error!("Failed to open the tree {}: {}", tree.name(), e);
+			EigenError::StorageError
+		})
+	}
+}
+
+fn storage_error(e: sled::Error) -> EigenError {
+	log::error!("Storage error: {}", e);
+	EigenError::StorageError
+}
+
+impl Store for SledStore {
+	fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, EigenError> {
+		let value = self.tree(tree)?.get(key).map_err(storage_error)?;
+		Ok(value.map(|v| v.to_vec()))
+	}
+
+	fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), EigenError> {
+		self.tree(tree)?.insert(key, value).map_err(storage_error)?;
+		Ok(())
+	}
+
+	fn remove(&self, tree: Tree, key: &[u8]) -> Result<(), EigenError> {
+		self.tree(tree)?.remove(key).map_err(storage_error)?;
+		Ok(())
+	}
+
+	fn records(&self, tree: Tree) -> Result<Vec<(Vec<u8>, Vec<u8>)>, EigenError> {
+		self.tree(tree)?
+			.iter()
+			.map(|res| {
+				let (key, value) = res.map_err(storage_error)?;
+				Ok((key.to_vec(), value.to_vec()))
+			})
+			.collect()
+	}
+
+	fn flush(&self) -> Result<(), EigenError> {
+		self.db.flush().map_err(storage_error)?;
+		Ok(())
+	}
+
+	fn size_on_disk(&self) -> Result<Option<u64>, EigenError> {
+		let size = self.db.size_on_disk().map_err(storage_error)?;
+		Ok(Some(size))
+	}
+
+	fn write_batch(&self, batch: &[WriteOp]) -> Result<(), EigenError> {
+		// A transaction over all the trees written to.
+		let mut trees: Vec<Tree> = Vec::new();
+		for op in batch {
+			if !trees.contains(&op.tree()) {
+				trees.push(op.tree());
+			}
+		}
+		let sled_trees = trees
+			.iter()
+			.map(|tree| self.tree(*tree))
+			.collect::<Result<Vec<_>, _>>()?;
+		let result =
+			sled_trees
+				.as_slice()
+				.transaction(|views| -> ConflictableTransactionResult<(), ()> {
+					for op in batch {
+						let index = trees.iter().position(|tree| *tree == op.tree());
+						let view = match index.and_then(|index| views.get(index)) {
+							Some(view) => view,
+							None => return sled::transaction::abort(()),
+						};
+						match op {
+							WriteOp::Insert(_, key, value) => {
+								view.insert(key.as_slice(), value.as_slice())?;
+							},
+							WriteOp::Remove(_, key) => {
+								view.remove(key.as_slice())?;
+							},
+						}
+					}
+					Ok(())
+				});
+		result.map_err(|e| match e {
+			TransactionError::Storage(e) => storage_error(e),
+			TransactionError::Abort(()) => EigenError::StorageError,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		epoch::Epoch,
+		peer::{opinion::Opinion, NetworkConfig},
+		storage::{proof_cid, proof_id},
+	};
+	use eigen_trust_circuit::{ecdsa::SigData, halo2wrong::curves::secp256k1::Fq};
+	use libp2p::{Multiaddr, PeerId};
+	use std::str::FromStr;
+
+	#[test]
+	fn should_store_records() {
+		let store = SledStore::temporary().unwrap();
+		let peer_1 = PeerId::random();
+		let peer_2 = PeerId::random();
+
+		store.save_score(&peer_1, 3).unwrap();
+		store.save_score(&peer_2, 5).unwrap();
+		store.save_score(&peer_1, 4).unwrap();
+		let mut scores = store.load_scores().unwrap();
+		scores.sort_by_key(|(_, score)| *score);
+		assert_eq!(scores, vec![(peer_1, 4), (peer_2, 5)]);
+
+		let addr = Multiaddr::from_str("/ip4/127.0.0.1/tcp/58584").unwrap();
+		store.save_address(&peer_1, &addr).unwrap();
+		assert_eq!(store.load_addresses().unwrap(), vec![(peer_1, addr)]);
+
+		store.save_checkpoint(Epoch(7), 0.5).unwrap();
+		store.save_checkpoint(Epoch(6), 0.25).unwrap();
+		assert_eq!(store.load_checkpoints().unwrap(), vec![
+			(Epoch(6), 0.25),
+			(Epoch(7), 0.5)
+		]);
+
+		let hash = [7; 32];
+		store.save_verification(&peer_2, &hash, true).unwrap();
+		assert_eq!(store.load_verifications().unwrap(), vec![(
+			peer_2, hash, true
+		)]);
+		store.remove_verification(&peer_2, &hash).unwrap();
+		assert!(store.load_verifications().unwrap().is_empty());
+	}
+
+	#[test]
+	fn should_store_opinions_by_epoch() {
+		let store = SledStore::temporary().unwrap();
+		let peer_id = PeerId::random();
+		let sig = SigData::<Fq>::empty();
+		let later = Opinion::<NetworkConfig>::new(Epoch(300), sig, 0.5, vec![1, 2, 3]);
+		let earlier = Opinion::<NetworkConfig>::new(Epoch(2), sig, 0.25, Vec::new());
+
+		let tree = Tree::NeighborOpinions;
+		store
+			.save_opinion(tree, &(peer_id, Epoch(300)), &later)
+			.unwrap();
+		store
+			.save_opinion(tree, &(peer_id, Epoch(2)), &earlier)
+			.unwrap();
+		assert_eq!(store.load_opinions(tree).unwrap(), vec![
+			((peer_id, Epoch(2)), earlier),
+			((peer_id, Epoch(300)), later.clone()),
+		]);
+		assert!(store.load_opinions(Tree::LocalOpinions).unwrap().is_empty());
+
+		store.remove_opinion(tree, &(peer_id, Epoch(2))).unwrap();
+		assert_eq!(store.load_opinions(tree).unwrap(), vec![(
+			(peer_id, Epoch(300)),
+			later
+		)]);
+	}
+
+	#[test]
+	fn should_deduplicate_proofs() {
+		let store = SledStore::temporary().unwrap();
+		let peer_1 = PeerId::random();
+		let peer_2 = PeerId::random();
+		let sig = SigData::<Fq>::empty();
+		let proof = vec![0; 64];
+		let opinion = |k| Opinion::<NetworkConfig>::new(Epoch(k), sig, 0.5, proof.clone());
+
+		store
+			.save_opinion(Tree::NeighborOpinions, &(peer_1, Epoch(1)), &opinion(1))
+			.unwrap();
+		store
+			.save_opinion(Tree::NeighborOpinions, &(peer_2, Epoch(1)), &opinion(1))
+			.unwrap();
+		store
+			.save_opinion(Tree::LocalOpinions, &(peer_1, Epoch(2)), &opinion(2))
+			.unwrap();
+		let id = proof_id(&proof);
+		assert_eq!(store.records(Tree::Proofs).unwrap().len(), 1);
+		assert_eq!(store.load_proof(&id).unwrap(), Some(proof.clone()));
+		assert!(proof_cid(&id).starts_with("zb2rh"));
+
+		// Saving the same opinion again doesn't add a reference.
+		store
+			.save_opinion(Tree::LocalOpinions, &(peer_1, Epoch(2)), &opinion(2))
+			.unwrap();
+		assert_eq!(store.load_opinions(Tree::LocalOpinions).unwrap(), vec![(
+			(peer_1, Epoch(2)),
+			opinion(2)
+		)]);
+
+		// The proof is removed with the last opinion referring to it.
+		store
+			.remove_opinion(Tree::NeighborOpinions, &(peer_1, Epoch(1)))
+			.unwrap();
+		store
+			.remove_opinion(Tree::NeighborOpinions, &(peer_2, Epoch(1)))
+			.unwrap();
+		assert_eq!(store.load_proof(&id).unwrap(), Some(proof));
+		store
+			.remove_opinion(Tree::LocalOpinions, &(peer_1, Epoch(2)))
+			.unwrap();
+		assert!(store.records(Tree::Proofs).unwrap().is_empty());
+	}
+}
@@ -0,0 +1,155 @@
+//! The snapshots of the node state: the records of all the trees of a
+//! [`Store`] in a single file, tied to the identity of the node.
+//!
+//! The layout is `magic || version || peer_id_len (u16 BE) || peer_id ||
+//! records || checksum`, where each record is `tree || key_len (u32 BE) ||
+//! key || value_len (u32 BE) || value`, with the tree as its index in
+//! [`Tree::ALL`], and the checksum is the SHA-256 of everything before it.
+
+use super::{read_array, take, write_atomic, MemoryStore, Store, Tree};
+use crate::EigenError;
+use libp2p::PeerId;
+use sha2::{Digest, Sha256};
+use std::{fs, path::Path};
+
+/// The first bytes of a snapshot.
+const SNAPSHOT_MAGIC: &[u8; 8] = b"EIGENSNP";
+/// The version of the snapshot layout.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+fn invalid() -> EigenError {
+	EigenError::InvalidSnapshot
+}
+
+fn snapshot_error(what: &str, path: &Path, e: std::io::Error) -> EigenError {
+	log::error!("Failed to {} the snapshot at {:?}: {}", what, path, e);
+	EigenError::InvalidSnapshot
+}
+
+/// Write the records of the store to the file, as the snapshot of the node
+/// with the PeerId. The file is replaced at once, so a failed write leaves
+/// the previous snapshot in place.
+pub fn write_snapshot(path: &Path, peer_id: &PeerId, store: &dyn Store) -> Result<(), EigenError> {
+	let mut bytes = SNAPSHOT_MAGIC.to_vec();
+	bytes.push(SNAPSHOT_VERSION);
+	let peer_id = peer_id.to_bytes();
+	bytes.extend((peer_id.len() as u16).to_be_bytes());
+	bytes.extend(peer_id);
+	for (index, tree) in Tree::ALL.iter().enumerate() {
+		for (key, value) in store.records(*tree)? {
+			bytes.push(index as u8);
+			bytes.extend((key.len() as u32).to_be_bytes());
+			bytes.extend(key);
+			bytes.extend((value.len() as u32).to_be_bytes());
+			bytes.extend(value);
+		}
+	}
+	let checksum = Sha256::digest(&bytes);
+	bytes.extend(checksum);
+
+	write_atomic(path, &bytes).map_err(|e| snapshot_error("write", path, e))
+}
+
+/// Read the snapshot of the node with the PeerId. Fails if the file is
+/// corrupted, of an unknown version, or of another node.
+pub fn read_snapshot(path: &Path, peer_id: &PeerId) -> Result<MemoryStore, EigenError> {
+	let bytes = fs::read(path).map_err(|e| snapshot_error("read", path, e))?;
+	if bytes.len() < 32 {
+		return Err(EigenError::InvalidSnapshot);
+	}
+	let (body, checksum) = bytes.split_at(bytes.len() - 32);
+	if Sha256::digest(body).as_slice() != checksum {
+		log::error!("The checksum of the snapshot at {:?} doesn't match", path);
+		return Err(EigenError::InvalidSnapshot);
+	}
+
+	let mut reader = body;
+	if take(&mut reader, SNAPSHOT_MAGIC.len(), invalid)? != SNAPSHOT_MAGIC {
+		log::error!("{:?} is not a snapshot", path);
+		return Err(EigenError::InvalidSnapshot);
+	}
+	let version = take(&mut reader, 1, invalid)?[0];
+	if version != SNAPSHOT_VERSION {
+		log::error!("Unknown snapshot version {}", version);
+		return Err(EigenError::InvalidSnapshot);
+	}
+	let peer_id_len = u16::from_be_bytes(read_array(&mut reader, invalid)?);
+	let owner = PeerId::from_bytes(take(&mut reader, peer_id_len.into(), invalid)?)
+		.map_err(|_| EigenError::InvalidSnapshot)?;
+	if owner != *peer_id {
+		log::error!("The snapshot belongs to {}, not {}", owner, peer_id);
+		return Err(EigenError::InvalidSnapshot);
+	}
+
+	let store = MemoryStore::default();
+	while !reader.is_empty() {
+		let index = take(&mut reader, 1, invalid)?[0];
+		let tree = Tree::ALL
+			.get(usize::from(index))
+			.ok_or(EigenError::InvalidSnapshot)?;
+		let key_len = u32::from_be_bytes(read_array(&mut reader, invalid)?);
+		let key = take(&mut reader, key_len as usize, invalid)?;
+		let value_len = u32::from_be_bytes(read_array(&mut reader, invalid)?);
+		let value = take(&mut reader, value_len as usize, invalid)?;
+		store.insert(*tree, key, value)?;
+	}
+	Ok(store)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::epoch::Epoch;
+
+	fn temp_path(name: &str) -> std::path::PathBuf {
+		let suffix: u64 = rand::random();
+		std::env::temp_dir().join(format!("eigen-trust-{}-{}.snapshot", name, suffix))
+	}
+
+	#[test]
+	fn should_write_and_read_snapshot() {
+		let peer_id = PeerId::random();
+		let neighbor_id = PeerId::random();
+		let store = MemoryStore::default();
+		store.save_score(&neighbor_id, 3).unwrap();
+		store.save_checkpoint(Epoch(4), 0.5).unwrap();
+
+		let path = temp_path("roundtrip");
+		write_snapshot(&path, &peer_id, &store).unwrap();
+		let restored = read_snapshot(&path, &peer_id).unwrap();
+		for tree in Tree::ALL {
+			assert_eq!(
+				restored.records(tree).unwrap(),
+				store.records(tree).unwrap()
+			);
+		}
+
+		assert!(matches!(
+			read_snapshot(&path, &neighbor_id),
+			Err(EigenError::InvalidSnapshot)
+		));
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn should_reject_corrupted_snapshot() {
+		let peer_id = PeerId::random();
+		let store = MemoryStore::default();
+		store.save_score(&PeerId::random(), 3).unwrap();
+
+		let path = temp_path("corrupted");
+		write_snapshot(&path, &peer_id, &store).unwrap();
+		let mut bytes = fs::read(&path).unwrap();
+		let middle = bytes.len() / 2;
+		bytes[middle] ^= 1;
+		fs::write(&path, &bytes).unwrap();
+		assert!(matches!(
+			read_snapshot(&path, &peer_id),
+			Err(EigenError::InvalidSnapshot)
+		));
+
+		fs::write(&path, b"EIGENSNP").unwrap();
+		assert!(read_snapshot(&path, &peer_id).is_err());
+		fs::remove_file(path).unwrap();
+	}
+}
@@ -0,0 +1,383 @@
+//! The [`Store`] backed by a SQLite database.
+//!
+//! Next to the encoded records, the tables have the fields of the records in
+//! their own columns, so the history of the node can be queried with SQL
+//! while it runs, e.g. the scores a neighbor gave over time:
+//!
+//! ```sql
+//! SELECT epoch, score FROM neighbor_opinions WHERE peer_id = '16Uiu2...' ORDER BY epoch;
+//! ```
+//!
+//! or the failed verifications by peer:
+//!
+//! ```sql
+//! SELECT peer_id, COUNT(*) FROM verifications WHERE NOT valid GROUP BY peer_id;
+//! ```
+//!
+//! The columns are only written by the store, the records are read from the
+//! `key` and `value` columns.
+
+use super::{
+	decode_epoch, decode_history_key, decode_history_score_key, decode_opinion_key, decode_peer_id,
+	proof_cid, split_opinion_record, split_proof_record, ProofId, Store, Tree, WriteOp,
+};
+use crate::{
+	epoch::{Epoch, EpochConfig},
+	EigenError,
+};
+use libp2p::Multiaddr;
+use rusqlite::{params_from_iter, types::Value, Connection, OptionalExtension};
+use std::{
+	path::Path,
+	sync::{Mutex, MutexGuard},
+};
+
+/// The columns of the fields of the records of the tree, with their types.
+fn columns(tree: Tree) -> &'static [(&'static str, &'static str)] {
+	match tree {
+		Tree::Scores => &[("peer_id", "TEXT"), ("score", "INTEGER")],
+		Tree::LocalOpinions | Tree::NeighborOpinions => &[
+			("peer_id", "TEXT"),
+			("epoch", "INTEGER"),
+			("score", "REAL"),
+			("has_proof", "INTEGER"),
+		],
+		Tree::Verifications => &[
+			("peer_id", "TEXT"),
+			("opinion_hash", "BLOB"),
+			("valid", "INTEGER"),
+		],
+		Tree::Addresses => &[("peer_id", "TEXT"), ("address", "TEXT")],
+		Tree::Checkpoints => &[("epoch", "INTEGER"), ("score", "REAL")],
+		Tree::Proofs => &[("cid", "TEXT"), ("refs", "INTEGER"), ("size", "INTEGER")],
+		Tree::History => &[
+			("epoch", "INTEGER"),
+			("author", "TEXT"),
+			("subject", "TEXT"),
+			("score", "REAL"),
+			("has_proof", "INTEGER"),
+		],
+		Tree::HistoryScores => &[("epoch", "INTEGER"), ("peer_id", "TEXT"), ("score", "REAL")],
+		Tree::EpochConfigs => &[
+			("activation", "INTEGER"),
+			("interval", "INTEGER"),
+			("signer", "TEXT"),
+		],
+	}
+}
+
+fn peer_id_field(bytes: &[u8]) -> Result<Value, EigenError> {
+	Ok(Value::Text(decode_peer_id(bytes)?.to_string()))
+}
+
+fn epoch_field(epoch: Epoch) -> Result<Value, EigenError> {
+	let epoch = i64::try_from(epoch.0).map_err(|_| EigenError::StorageError)?;
+	Ok(Value::Integer(epoch))
+}
+
+/// The fields of the record, in the order of the [`columns`] of the tree.
+fn fields(tree: Tree, key: &[u8], value: &[u8]) -> Result<Vec<Value>, EigenError> {
+	let fields = match tree {
+		Tree::Scores => {
+			let score: [u8; 4] = value.try_into().map_err(|_| EigenError::StorageError)?;
+			vec![
+				peer_id_field(key)?,
+				Value::Integer(u32::from_be_bytes(score).into()),
+			]
+		},
+		Tree::LocalOpinions | Tree::NeighborOpinions => {
+			let (peer_id, epoch) = decode_opinion_key(key)?;
+			let (proof_id, opinion) = split_opinion_record(value)?;
+			let has_proof = proof_id.is_some() || !opinion.proof_bytes.is_empty();
+			vec![
+				Value::Text(peer_id.to_string()),
+				epoch_field(epoch)?,
+				Value::Real(opinion.op),
+				Value::Integer(has_proof.into()),
+			]
+		},
+		Tree::Verifications => {
+			if key.len() < 32 {
+				return Err(EigenError::StorageError);
+			}
+			let (peer_id, hash) = key.split_at(key.len() - 32);
+			vec![
+				peer_id_field(peer_id)?,
+				Value::Blob(hash.to_vec()),
+				Value::Integer((value == [1]).into()),
+			]
+		},
+		Tree::Addresses => {
+			let addr = Multiaddr::try_from(value.to_vec()).map_err(|_| EigenError::StorageError)?;
+			vec![peer_id_field(key)?, Value::Text(addr.to_string())]
+		},
+		Tree::Checkpoints => {
+			let score: [u8; 8] = value.try_into().map_err(|_| EigenError::StorageError)?;
+			vec![
+				epoch_field(decode_epoch(key)?)?,
+				Value::Real(f64::from_be_bytes(score)),
+			]
+		},
+		Tree::Proofs => {
+			let id: ProofId = key.try_into().map_err(|_| EigenError::StorageError)?;
+			let (refs, proof) = split_proof_record(value)?;
+			let refs = i64::try_from(refs).map_err(|_| EigenError::StorageError)?;
+			let size = i64::try_from(proof.len()).map_err(|_| EigenError::StorageError)?;
+			vec![
+				Value::Text(proof_cid(&id)),
+				Value::Integer(refs),
+				Value::Integer(size),
+			]
+		},
+		Tree::History => {
+			let (epoch, author, subject) = decode_history_key(key)?;
+			let (proof_id, opinion) = split_opinion_record(value)?;
+			let has_proof = proof_id.is_some() || !opinion.proof_bytes.is_empty();
+			vec![
+				epoch_field(epoch)?,
+				Value::Text(author.to_string()),
+				Value::Text(subject.to_string()),
+				Value::Real(opinion.op),
+				Value::Integer(has_proof.into()),
+			]
+		},
+		Tree::HistoryScores => {
+			let (epoch, peer_id) = decode_history_score_key(key)?;
+			let score: [u8; 8] = value.try_into().map_err(|_| EigenError::StorageError)?;
+			vec![
+				epoch_field(epoch)?,
+				Value::Text(peer_id.to_string()),
+				Value::Real(f64::from_be_bytes(score)),
+			]
+		},
+		Tree::EpochConfigs => {
+			let config = EpochConfig::from_bytes(value).map_err(|_| EigenError::StorageError)?;
+			let interval = i64::try_from(config.interval).map_err(|_| EigenError::StorageError)?;
+			vec![
+				epoch_field(config.activation)?,
+				Value::Integer(interval),
+				Value::Text(config.signer().to_string()),
+			]
+		},
+	};
+	Ok(fields)
+}
+
+fn storage_error(e: rusqlite::Error) -> EigenError {
+	log::error!("Storage error: {}", e);
+	EigenError::StorageError
+}
+
+fn insert_into(conn: &Connection, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), EigenError> {
+	let columns = columns(tree);
+	let names: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+	let sql = format!(
+		"INSERT OR REPLACE INTO {} (key, value, {}) VALUES (?, ?{})",
+		tree.name(),
+		names.join(", "),
+		", ?".repeat(columns.len())
+	);
+	let mut values = vec![Value::Blob(key.to_vec()), Value::Blob(value.to_vec())];
+	values.extend(fields(tree, key, value)?);
+	conn.execute(&sql, params_from_iter(values))
+		.map_err(storage_error)?;
+	Ok(())
+}
+
+fn remove_from(conn: &Connection, tree: Tree, key: &[u8]) -> Result<(), EigenError> {
+	let sql = format!("DELETE FROM {} WHERE key = ?", tree.name());
+	conn.execute(&sql, [key]).map_err(storage_error)?;
+	Ok(())
+}
+
+/// The store in a SQLite database, with a table for each [`Tree`].
+#[derive(Debug)]
+pub struct SqliteStore {
+	conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+	/// Open the database in the file, creating it if it doesn't exist.
+	pub fn open(path: &Path) -> Result<Self, EigenError> {
+		let conn = Connection::open(path).map_err(|e| {
+			log::error!("Failed to open the store at {:?}: {}", path, e);
+			EigenError::StorageError
+		})?;
+		Self::init(conn)
+	}
+
+	/// Open a database in the memory, lost once dropped.
+	pub fn in_memory() -> Result<Self, EigenError> {
+		let conn = Connection::open_in_memory().map_err(storage_error)?;
+		Self::init(conn)
+	}
+
+	fn init(conn: Connection) -> Result<Self, EigenError> {
+		// With the write-ahead journal, the database can be read by the
+		// operators while the node writes to it.
+		conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))
+			.map_err(storage_error)?;
+		for tree in Tree::ALL {
+			let columns: Vec<String> = columns(tree)
+				.iter()
+				.map(|(name, ty)| format!(", {} {} NOT NULL", name, ty))
+				.collect();
+			let sql = format!(
+				"CREATE TABLE IF NOT EXISTS {} (key BLOB PRIMARY KEY, value BLOB NOT NULL{})",
+				tree.name(),
+				columns.concat()
+			);
+			conn.execute(&sql, []).map_err(storage_error)?;
+		}
+		Ok(Self {
+			conn: Mutex::new(conn),
+		})
+	}
+
+	fn conn(&self) -> Result<MutexGuard<Connection>, EigenError> {
+		self.conn.lock().map_err(|_| EigenError::StorageError)
+	}
+}
+
+impl Store for SqliteStore {
+	fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, EigenError> {
+		let sql = format!("SELECT value FROM {} WHERE key = ?", tree.name());
+		self.conn()?
+			.query_row(&sql, [key], |row| row.get(0))
+			.optional()
+			.map_err(storage_error)
+	}
+
+	fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), EigenError> {
+		insert_into(&self.conn()?, tree, key, value)
+	}
+
+	fn remove(&self, tree: Tree, key: &[u8]) -> Result<(), EigenError> {
+		remove_from(&self.conn()?, tree, key)
+	}
+
+	fn write_batch(&self, batch: &[WriteOp]) -> Result<(), EigenError> {
+		let mut conn = self.conn()?;
+		// Rolled back when dropped without the commit.
+		let tx = conn.transaction().map_err(storage_error)?;
+		for op in batch {
+			match op {
+				WriteOp::Insert(tree, key, value) => insert_into(&tx, *tree, key, value)?,
+				WriteOp::Remove(tree, key) => remove_from(&tx, *tree, key)?,
+			}
+		}
+		tx.commit().map_err(storage_error)
+	}
+
+	fn records(&self, tree: Tree) -> Result<Vec<(Vec<u8>, Vec<u8>)>, EigenError> {
+		// The blobs are compared byte by byte, like the keys of the other backends.
+		let sql = format!("SELECT key, value FROM {} ORDER BY key", tree.name());
+		let conn = self.conn()?;
+		let mut stmt = conn.prepare(&sql).map_err(storage_error)?;
+		let rows = stmt
+			.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+			.map_err(storage_error)?;
+		rows.map(|row| row.map_err(storage_error)).collect()
+	}
+
+	fn flush(&self) -> Result<(), EigenError> {
+		// Every write is committed on its own, so this only moves the journal
+		// into the database file.
+		self.conn()?
+			.query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |_| Ok(()))
+			.map_err(storage_error)
+	}
+
+	fn size_on_disk(&self) -> Result<Option<u64>, EigenError> {
+		let size: i64 = self
+			.conn()?
+			.query_row(
+				"SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+				[],
+				|row| row.get(0),
+			)
+			.map_err(storage_error)?;
+		Ok(u64::try_from(size).ok())
+	}
+
+	fn clear(&self, tree: Tree) -> Result<(), EigenError> {
+		let sql = format!("DELETE FROM {}", tree.name());
+		self.conn()?.execute(&sql, []).map_err(storage_error)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::peer::{opinion::Opinion, NetworkConfig};
+	use eigen_trust_circuit::{ecdsa::SigData, halo2wrong::curves::secp256k1::Fq};
+	use libp2p::PeerId;
+	use std::str::FromStr;
+
+	#[test]
+	fn should_store_records() {
+		let store = SqliteStore::in_memory().unwrap();
+		let peer_1 = PeerId::random();
+		let peer_2 = PeerId::random();
+
+		store.save_score(&peer_1, 3).unwrap();
+		store.save_score(&peer_2, 5).unwrap();
+		store.save_score(&peer_1, 4).unwrap();
+		let mut scores = store.load_scores().unwrap();
+		scores.sort_by_key(|(_, score)| *score);
+		assert_eq!(scores, vec![(peer_1, 4), (peer_2, 5)]);
+
+		let addr = Multiaddr::from_str("/ip4/127.0.0.1/tcp/58584").unwrap();
+		store.save_address(&peer_1, &addr).unwrap();
+		assert_eq!(store.load_addresses().unwrap(), vec![(peer_1, addr)]);
+
+		store.save_checkpoint(Epoch(300), 0.5).unwrap();
+		store.save_checkpoint(Epoch(6), 0.25).unwrap();
+		assert_eq!(store.load_checkpoints().unwrap(), vec![
+			(Epoch(6), 0.25),
+			(Epoch(300), 0.5)
+		]);
+
+		store.clear(Tree::Checkpoints).unwrap();
+		assert!(store.load_checkpoints().unwrap().is_empty());
+		assert!(store.size_on_disk().unwrap().is_some());
+	}
+
+	#[test]
+	fn should_query_history() {
+		let store = SqliteStore::in_memory().unwrap();
+		let peer_id = PeerId::random();
+		let sig = SigData::<Fq>::empty();
+		for (k, score) in [(1, 0.25), (2, 0.5), (3, 0.75)] {
+			let opinion = Opinion::<NetworkConfig>::new(Epoch(k), sig, score, Vec::new());
+			let key = (peer_id, Epoch(k));
+			store
+				.save_opinion(Tree::NeighborOpinions, &key, &opinion)
+				.unwrap();
+			store
+				.save_verification(&peer_id, &opinion.hash(), k != 2)
+				.unwrap();
+		}
+
+		let conn = store.conn().unwrap();
+		let mut stmt = conn
+			.prepare("SELECT epoch, score FROM neighbor_opinions WHERE peer_id = ? ORDER BY epoch")
+			.unwrap();
+		let history: Vec<(i64, f64)> = stmt
+			.query_map([peer_id.to_string()], |row| Ok((row.get(0)?, row.get(1)?)))
+			.unwrap()
+			.map(|row| row.unwrap())
+			.collect();
+		assert_eq!(history, vec![(1, 0.25), (2, 0.5), (3, 0.75)]);
+
+		let failures: (String, i64) = conn
+			.query_row(
+				"SELECT peer_id, COUNT(*) FROM verifications WHERE NOT valid GROUP BY peer_id",
+				[],
+				|row| Ok((row.get(0)?, row.get(1)?)),
+			)
+			.unwrap();
+		assert_eq!(failures, (peer_id.to_string(), 1));
+	}
+}
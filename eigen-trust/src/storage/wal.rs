@@ -0,0 +1,274 @@
+//! The write-ahead log of the accepted opinions. An entry is appended, and
+//! synced to the disk, before the opinion is recorded as verified, so after a
+//! crash the node knows exactly which opinions it had accepted, even if the
+//! store lost its latest writes.
+//!
+//! The log is a sequence of records: `len (u32 BE) || checksum || payload`,
+//! where the checksum is the first 4 bytes of the SHA-256 of the payload. A
+//! torn record at the end of the log is dropped when it is replayed.
+
+use super::write_atomic;
+use crate::{
+	epoch::Epoch,
+	peer::{opinion::Opinion, NetworkConfig},
+	EigenError,
+};
+use libp2p::PeerId;
+use sha2::{Digest, Sha256};
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{Read, Write},
+	path::{Path, PathBuf},
+};
+
+/// The size of the record header: the length and the checksum.
+const HEADER_LEN: usize = 8;
+
+/// An opinion accepted from a neighbor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WalEntry {
+	/// The neighbor the opinion came from.
+	pub peer_id: PeerId,
+	/// The epoch of the opinion.
+	pub epoch: Epoch,
+	/// The opinion of the neighbor about us.
+	pub op: f64,
+	/// The SHA-256 hash of the proof of the opinion.
+	pub proof_hash: [u8; 32],
+}
+
+impl WalEntry {
+	/// The entry of the opinion from the neighbor.
+	pub fn new(peer_id: PeerId, opinion: &Opinion<NetworkConfig>) -> Self {
+		Self {
+			peer_id,
+			epoch: opinion.k,
+			op: opinion.op,
+			proof_hash: Sha256::digest(&opinion.proof_bytes).into(),
+		}
+	}
+
+	/// Returns true if the opinion is the one the entry was written for.
+	pub fn matches(&self, opinion: &Opinion<NetworkConfig>) -> bool {
+		let proof_hash: [u8; 32] = Sha256::digest(&opinion.proof_bytes).into();
+		self.epoch == opinion.k && self.op == opinion.op && self.proof_hash == proof_hash
+	}
+
+	/// `epoch (u64 BE) || op (f64 BE) || proof_hash || peer_id`
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = self.epoch.0.to_be_bytes().to_vec();
+		bytes.extend(self.op.to_be_bytes());
+		bytes.extend(self.proof_hash);
+		bytes.extend(self.peer_id.to_bytes());
+		bytes
+	}
+
+	fn from_bytes(bytes: &[u8]) -> Result<Self, EigenError> {
+		if bytes.len() < 48 {
+			return Err(EigenError::StorageError);
+		}
+		let (epoch, rest) = bytes.split_at(8);
+		let (op, rest) = rest.split_at(8);
+		let (proof_hash, peer_id) = rest.split_at(32);
+		let to_array = |b: &[u8]| -> Result<[u8; 8], EigenError> {
+			b.try_into().map_err(|_| EigenError::StorageError)
+		};
+		Ok(Self {
+			peer_id: PeerId::from_bytes(peer_id).map_err(|_| EigenError::StorageError)?,
+			epoch: Epoch(u64::from_be_bytes(to_array(epoch)?)),
+			op: f64::from_be_bytes(to_array(op)?),
+			proof_hash: proof_hash
+				.try_into()
+				.map_err(|_| EigenError::StorageError)?,
+		})
+	}
+}
+
+fn checksum(payload: &[u8]) -> [u8; 4] {
+	let hash = Sha256::digest(payload);
+	[hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn encode_record(entry: &WalEntry) -> Vec<u8> {
+	let payload = entry.to_bytes();
+	let mut record = (payload.len() as u32).to_be_bytes().to_vec();
+	record.extend(checksum(&payload));
+	record.extend(payload);
+	record
+}
+
+/// Decode the records, up to the first one that is torn or corrupted.
+/// Returns the entries and the length of the valid part of the log.
+fn decode_records(bytes: &[u8]) -> (Vec<WalEntry>, usize) {
+	let mut entries = Vec::new();
+	let mut offset = 0;
+	while bytes.len() - offset >= HEADER_LEN {
+		let header = &bytes[offset..offset + HEADER_LEN];
+		let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+		let start = offset + HEADER_LEN;
+		let payload = match bytes.get(start..start + len) {
+			Some(payload) if checksum(payload) == header[4..] => payload,
+			_ => break,
+		};
+		match WalEntry::from_bytes(payload) {
+			Ok(entry) => entries.push(entry),
+			Err(_) => break,
+		}
+		offset = start + len;
+	}
+	(entries, offset)
+}
+
+fn wal_error(what: &str, path: &Path, e: std::io::Error) -> EigenError {
+	log::error!("Failed to {} the WAL at {:?}: {}", what, path, e);
+	EigenError::StorageError
+}
+
+/// The append-only log file.
+#[derive(Debug)]
+pub struct Wal {
+	path: PathBuf,
+	file: File,
+}
+
+impl Wal {
+	/// Open the log, creating it if it doesn't exist, and replay it. A torn
+	/// record at the end is cut off, so the next entries follow the valid
+	/// ones.
+	pub fn open(path: &Path) -> Result<(Self, Vec<WalEntry>), EigenError> {
+		let mut file = OpenOptions::new()
+			.read(true)
+			.append(true)
+			.create(true)
+			.open(path)
+			.map_err(|e| wal_error("open", path, e))?;
+		let mut bytes = Vec::new();
+		file.read_to_end(&mut bytes)
+			.map_err(|e| wal_error("read", path, e))?;
+
+		let (entries, valid_len) = decode_records(&bytes);
+		if valid_len < bytes.len() {
+			log::warn!(
+				"Dropping {} bytes of a torn record at the end of the WAL at {:?}",
+				bytes.len() - valid_len,
+				path
+			);
+			file.set_len(valid_len as u64)
+				.map_err(|e| wal_error("truncate", path, e))?;
+		}
+
+		let wal = Self {
+			path: path.to_path_buf(),
+			file,
+		};
+		Ok((wal, entries))
+	}
+
+	/// Append the entry, and wait until it is on the disk.
+	pub fn append(&mut self, entry: &WalEntry) -> Result<(), EigenError> {
+		self.file
+			.write_all(&encode_record(entry))
+			.and_then(|_| self.file.sync_data())
+			.map_err(|e| wal_error("append to", &self.path, e))
+	}
+
+	/// Keep only the entries for which the predicate returns true. The log is
+	/// rewritten to a temporary file, which then replaces it.
+	pub fn retain(&mut self, keep: impl Fn(&WalEntry) -> bool) -> Result<(), EigenError> {
+		let bytes = fs::read(&self.path).map_err(|e| wal_error("read", &self.path, e))?;
+		let (entries, _) = decode_records(&bytes);
+		let records: Vec<u8> = entries
+			.iter()
+			.filter(|entry| keep(entry))
+			.flat_map(encode_record)
+			.collect();
+
+		write_atomic(&self.path, &records).map_err(|e| wal_error("compact", &self.path, e))?;
+
+		self.file = OpenOptions::new()
+			.append(true)
+			.open(&self.path)
+			.map_err(|e| wal_error("open", &self.path, e))?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use eigen_trust_circuit::{ecdsa::SigData, halo2wrong::curves::secp256k1::Fq};
+
+	fn temp_path(name: &str) -> PathBuf {
+		let suffix: u64 = rand::random();
+		std::env::temp_dir().join(format!("eigen-trust-{}-{}.wal", name, suffix))
+	}
+
+	fn entry(epoch: u64, op: f64) -> WalEntry {
+		let opinion = Opinion::new(Epoch(epoch), SigData::<Fq>::empty(), op, vec![1, 2]);
+		WalEntry::new(PeerId::random(), &opinion)
+	}
+
+	#[test]
+	fn should_replay_appended_entries() {
+		let path = temp_path("replay");
+		let (mut wal, entries) = Wal::open(&path).unwrap();
+		assert!(entries.is_empty());
+		let first = entry(1, 0.25);
+		let second = entry(2, 0.5);
+		wal.append(&first).unwrap();
+		wal.append(&second).unwrap();
+		drop(wal);
+
+		let (_, entries) = Wal::open(&path).unwrap();
+		assert_eq!(entries, vec![first, second]);
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn should_drop_torn_record() {
+		let path = temp_path("torn");
+		let (mut wal, _) = Wal::open(&path).unwrap();
+		let first = entry(1, 0.25);
+		wal.append(&first).unwrap();
+		drop(wal);
+
+		// A crash in the middle of the second append.
+		let record = encode_record(&entry(2, 0.5));
+		let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+		file.write_all(&record[..record.len() - 3]).unwrap();
+		drop(file);
+
+		let (mut wal, entries) = Wal::open(&path).unwrap();
+		assert_eq!(entries, vec![first.clone()]);
+		let third = entry(3, 0.75);
+		wal.append(&third).unwrap();
+		drop(wal);
+
+		let (_, entries) = Wal::open(&path).unwrap();
+		assert_eq!(entries, vec![first, third]);
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn should_retain_entries() {
+		let path = temp_path("retain");
+		let (mut wal, _) = Wal::open(&path).unwrap();
+		for epoch in 1..=4 {
+			wal.append(&entry(epoch, 0.5)).unwrap();
+		}
+		wal.retain(|entry| entry.epoch.0 >= 3).unwrap();
+		let fifth = entry(5, 0.5);
+		wal.append(&fifth).unwrap();
+		drop(wal);
+
+		let (_, entries) = Wal::open(&path).unwrap();
+		let epochs: Vec<u64> = entries.iter().map(|entry| entry.epoch.0).collect();
+		assert_eq!(epochs, vec![3, 4, 5]);
+		assert!(
+			entries[2].matches(&Opinion::new(Epoch(5), SigData::<Fq>::empty(), 0.5, vec![
+				1, 2
+			]))
+		);
+		fs::remove_file(path).unwrap();
+	}
+}
@@ -1,3 +1,4 @@
-cargo run -p eigen-trust --release -- \
+cargo run -p eigen-trust --release --features cli -- run \
 --key 23jhTbXRXh1RPMwzN2B7GNXZDiDtrkdm943bVBfAQBJFUosggfSDVQzui7pEbuzBFf6x7C5SLWXvUGB1gPaTLTpwRxDYu \
---address /ip4/127.0.0.1/tcp/58584
\ No newline at end of file
+--address /ip4/127.0.0.1/tcp/58584 \
+--bootstrap-file "$(dirname "$0")/bootstrap-peers.txt"
\ No newline at end of file
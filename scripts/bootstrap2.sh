@@ -1,3 +1,4 @@
-cargo run -p eigen-trust --release -- \
+cargo run -p eigen-trust --release --features cli -- run \
 --key 23jhTfVepCSFrkYE8tATMUuxU3SErCYvrShcit6dQfaonM4QxF82wh4k917LJShErtKNNbaUjmqGVDLDQdVB9n7TGieQ1 \
---address /ip4/127.0.0.1/tcp/58601
\ No newline at end of file
+--address /ip4/127.0.0.1/tcp/58601 \
+--bootstrap-file "$(dirname "$0")/bootstrap-peers.txt"
\ No newline at end of file
@@ -1 +1,2 @@
 cargo clippy
+cargo clippy -p eigen-trust --features sled,sqlite
@@ -0,0 +1,104 @@
+#!/usr/bin/env python3
+"""Generates the Poseidon parameters for the scalar field of Bn254 and the
+x^5 S-box, with the Grain LFSR from the reference implementation
+(generate_parameters_grain.sage).
+
+The MDS matrix is the first Cauchy matrix sampled from the LFSR. The
+reference implementation additionally screens it against the invariant
+subspace trails (Algorithms 1-3 of the Poseidon paper), which has to be
+re-run in Sage when adding a new width.
+
+Usage: python3 scripts/poseidon_params.py <width> <full_rounds> <partial_rounds>
+"""
+
+import sys
+
+# The scalar field of Bn254.
+P = 0x30644E72E131A029B85045B68181585D2833E84879B9709143E1F593F0000001
+FIELD_SIZE = 254
+# Prime field, x^alpha S-box.
+FIELD = 1
+SBOX = 0
+
+
+def bits(value, width):
+    return [int(b) for b in bin(value)[2:].zfill(width)]
+
+
+def grain(t, r_f, r_p):
+    state = (
+        bits(FIELD, 2)
+        + bits(SBOX, 4)
+        + bits(FIELD_SIZE, 12)
+        + bits(t, 12)
+        + bits(r_f, 10)
+        + bits(r_p, 10)
+        + [1] * 30
+    )
+
+    def next_bit():
+        new_bit = state[62] ^ state[51] ^ state[38] ^ state[23] ^ state[13] ^ state[0]
+        state.pop(0)
+        state.append(new_bit)
+        return new_bit
+
+    for _ in range(160):
+        next_bit()
+
+    # Self-shrinking: the second bit of a pair is kept if the first one is set.
+    while True:
+        first = next_bit()
+        while first == 0:
+            next_bit()
+            first = next_bit()
+        yield next_bit()
+
+
+def random_bits(gen, num_bits):
+    value = 0
+    for _ in range(num_bits):
+        value = (value << 1) | next(gen)
+    return value
+
+
+def round_constants(gen, t, r_f, r_p):
+    constants = []
+    for _ in range((r_f + r_p) * t):
+        value = random_bits(gen, FIELD_SIZE)
+        while value >= P:
+            value = random_bits(gen, FIELD_SIZE)
+        constants.append(value)
+    return constants
+
+
+def mds_matrix(gen, t):
+    while True:
+        samples = [random_bits(gen, FIELD_SIZE) % P for _ in range(2 * t)]
+        while len(set(samples)) != len(samples):
+            samples = [random_bits(gen, FIELD_SIZE) % P for _ in range(2 * t)]
+        xs, ys = samples[:t], samples[t:]
+        if all((x + y) % P != 0 for x in xs for y in ys):
+            return [[pow(x + y, -1, P) for y in ys] for x in xs]
+
+
+def to_hex(x):
+    return '"0x%064x"' % x
+
+
+def main():
+    t, r_f, r_p = (int(arg) for arg in sys.argv[1:4])
+
+    gen = grain(t, r_f, r_p)
+    constants = round_constants(gen, t, r_f, r_p)
+    mds = mds_matrix(gen, t)
+
+    print("// round constants")
+    for x in constants:
+        print("%s," % to_hex(x))
+    print("// mds")
+    for row in mds:
+        print("[%s]," % ", ".join(to_hex(x) for x in row))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,118 @@
+#!/usr/bin/env python3
+"""Generates the Rescue-Prime parameters, following the reference
+implementation from the Rescue-Prime specification (Algorithms 5, 6 and 7).
+
+Usage: python3 scripts/rescue_prime_params.py <width> [capacity] [security_level]
+"""
+
+import sys
+from hashlib import shake_256
+from math import ceil, comb, gcd
+
+# The scalar field of Bn254.
+P = 0x30644E72E131A029B85045B68181585D2833E84879B9709143E1F593F0000001
+# The factorization of P - 1.
+P_MINUS_ONE_FACTORS = [
+    2,
+    3,
+    13,
+    29,
+    983,
+    11003,
+    237073,
+    405928799,
+    1670836401704629,
+    13818364434197438864469338081,
+]
+
+
+def get_alphas(p):
+    alpha = 3
+    while gcd(alpha, p - 1) != 1:
+        alpha += 1
+    return alpha, pow(alpha, -1, p - 1)
+
+
+def get_number_of_rounds(m, capacity, security_level, alpha):
+    rate = m - capacity
+
+    def dcon(n):
+        return (alpha - 1) * m * (n - 1) // 2 + 2
+
+    def v(n):
+        return m * (n - 1) + rate
+
+    target = 2**security_level
+    l1 = 1
+    while l1 < 25:
+        if comb(v(l1) + dcon(l1), v(l1)) ** 2 > target:
+            break
+        l1 += 1
+    # Set a minimum value for sanity and add 50%.
+    return ceil(1.5 * max(5, l1))
+
+
+def is_primitive(g, p):
+    return all(pow(g, (p - 1) // q, p) != 1 for q in P_MINUS_ONE_FACTORS)
+
+
+def get_mds_matrix(p, m):
+    g = 2
+    while not is_primitive(g, p):
+        g += 1
+
+    # The systematic generator matrix of the code, in reduced row echelon form.
+    v = [[pow(g, i * j, p) for j in range(2 * m)] for i in range(m)]
+    for col in range(m):
+        pivot = next(row for row in range(col, m) if v[row][col] != 0)
+        v[col], v[pivot] = v[pivot], v[col]
+        inv = pow(v[col][col], -1, p)
+        v[col] = [x * inv % p for x in v[col]]
+        for row in range(m):
+            if row != col and v[row][col] != 0:
+                factor = v[row][col]
+                v[row] = [(x - factor * y) % p for x, y in zip(v[row], v[col])]
+
+    # The MDS matrix is the transpose of the right half.
+    return [[v[j][m + i] for j in range(m)] for i in range(m)]
+
+
+def get_round_constants(p, m, capacity, security_level, n):
+    bytes_per_int = ceil(len(bin(p)[2:]) / 8) + 1
+    num_bytes = bytes_per_int * 2 * m * n
+    seed = "Rescue-XLIX(%i,%i,%i,%i)" % (p, m, capacity, security_level)
+    stream = shake_256(seed.encode("ascii")).digest(num_bytes)
+
+    constants = []
+    for i in range(2 * m * n):
+        chunk = stream[bytes_per_int * i : bytes_per_int * (i + 1)]
+        constants.append(int.from_bytes(chunk, "little") % p)
+    return constants
+
+
+def to_hex(x):
+    return '"0x%064x"' % x
+
+
+def main():
+    m = int(sys.argv[1])
+    capacity = int(sys.argv[2]) if len(sys.argv) > 2 else 1
+    security_level = int(sys.argv[3]) if len(sys.argv) > 3 else 128
+
+    alpha, alpha_inv = get_alphas(P)
+    n = get_number_of_rounds(m, capacity, security_level, alpha)
+    mds = get_mds_matrix(P, m)
+    constants = get_round_constants(P, m, capacity, security_level, n)
+
+    print("// alpha = %d, alpha_inv = %s" % (alpha, hex(alpha_inv)))
+    print("// rounds = %d" % n)
+    print("// mds")
+    for row in mds:
+        print("[%s]," % ", ".join(to_hex(x) for x in row))
+    print("// round constants")
+    for x in constants:
+        print("%s," % to_hex(x))
+
+
+if __name__ == "__main__":
+    main()
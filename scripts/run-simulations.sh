@@ -1 +1,2 @@
-cargo run -p bench --release
+# The bench flags are passed through, e.g. `--peers 10 --topology ring`.
+cargo run -p bench --release -- "$@"
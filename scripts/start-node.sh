@@ -1 +1,2 @@
-cargo run -p eigen-trust --release
\ No newline at end of file
+cargo run -p eigen-trust --release --features cli -- run \
+--bootstrap-file "$(dirname "$0")/bootstrap-peers.txt"
\ No newline at end of file
@@ -1,2 +1,2 @@
-cargo test -p eigen-trust --release -- --test-threads=1
+cargo test -p eigen-trust --release --features sled,sqlite -- --test-threads=1
 cargo test -p eigen-trust-circuit --release -- --test-threads=1